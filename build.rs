@@ -9,10 +9,15 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("cargo:rustc-link-arg-bins={}", script.display());
     println!("cargo:rerun-if-changed={}", script.display());
 
-    let mut asm = PathBuf::from(dir);
+    let mut asm = PathBuf::from(dir.clone());
     asm.push("src/boot/boot.S");
 
     println!("cargo:rerun-if-changed={}", asm.display());
 
+    let mut exception_asm = PathBuf::from(dir);
+    exception_asm.push("src/arch/aarch64/exception.S");
+
+    println!("cargo:rerun-if-changed={}", exception_asm.display());
+
     Ok(())
 }
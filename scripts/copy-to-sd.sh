@@ -20,6 +20,15 @@ pushd target/aarch64-unknown-none-softfloat/debug > /dev/null
 aarch64-elf-objcopy angeldust -O binary angeldust.img
 cp angeldust.img "${BOOT_DIRECTORY}/angeldust.img" 
 
+popd > /dev/null
+
+# The initrd is optional, it is mounted as the root filesystem if the firmware loads it.
+if [ -f initrd.cpio ]
+then
+    cp initrd.cpio "${BOOT_DIRECTORY}/initrd.cpio"
+    echo "+ Don't forget to add \`initramfs initrd.cpio followkernel\` to ${BOOT_DIRECTORY}/config.txt!"
+fi
+
 # TODO: Other platforms
 if [ "$(uname)" == "Darwin" ]
 then
@@ -27,6 +36,4 @@ then
     diskutil eject "${BOOT_DIRECTORY}" > /dev/null
 fi
 
-echo "+ Don't forget to add \`kernel=angeldust.img\` to ${BOOT_DIRECTORY}/config.txt!"
-
-popd > /dev/null
\ No newline at end of file
+echo "+ Don't forget to add \`kernel=angeldust.img\` to ${BOOT_DIRECTORY}/config.txt!"
\ No newline at end of file
@@ -0,0 +1,41 @@
+use core::arch::asm;
+
+/// Returns the size of the smallest data cache line in bytes.
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/CTR-EL0--Cache-Type-Register?lang=en
+pub fn data_line_size() -> usize {
+    let ctr: u64;
+    unsafe {
+        asm!("mrs {0}, ctr_el0", out(reg) ctr);
+    }
+
+    // DminLine is the log2 of the amount of words in a line.
+    4 << ((ctr >> 16) & 0xF)
+}
+
+/// Cleans and invalidates every data cache line that covers [length] bytes from [address], so
+/// that a device which accesses memory directly (DMA) sees what the CPU wrote, and the CPU then
+/// sees what the device wrote.
+///
+/// While the MMU is off every access is non-cacheable, which makes this a no-op, but it is needed
+/// once memory is mapped as normal, cacheable memory.
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Instructions/DC-CIVAC--Data-or-unified-Cache-line-Clean-and-Invalidate-by-VA-to-PoC?lang=en
+pub fn clean_and_invalidate(address: usize, length: usize) {
+    let line_size = data_line_size();
+    let start = address & !(line_size - 1);
+    let end = address + length;
+
+    for line in (start..end).step_by(line_size) {
+        unsafe { asm!("dc civac, {0}", in(reg) line) }
+    }
+
+    unsafe { asm!("dsb sy") }
+}
+
+/// Invalidates the instruction cache of every core, so that code that was just written through
+/// the data cache (e.g. a page of a program) is fetched from memory, wherever it runs.
+/// [clean_and_invalidate] must be called on the code first.
+pub fn invalidate_instructions() {
+    unsafe { asm!("ic ialluis", "dsb ish", "isb") }
+}
@@ -0,0 +1,74 @@
+use core::arch::global_asm;
+
+global_asm!(include_str!("context.S"));
+
+extern "C" {
+    fn switch_context(old: *mut Context, new: *const Context);
+    fn thread_trampoline();
+}
+
+/// The interrupt mask bits that a new thread starts with: everything is masked, until the entry
+/// point unmasks it.
+const DAIF_MASKED: u64 = 0b1111 << 6;
+
+/// The state of a thread that isn't running, which [switch] saves and restores.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct Context {
+    /// The callee-saved registers, x19 to x30.
+    registers: [u64; 12],
+    sp: u64,
+    daif: u64,
+
+    /// The address space that the thread was using, and its ASID (`TTBR0_EL1`).
+    ttbr0: u64,
+
+    /// The thread pointer of EL0 (`TPIDR_EL0`).
+    tpidr_el0: u64,
+}
+
+impl Context {
+    /// A context for a thread that is already running, which is filled in by the first [switch]
+    /// away from it.
+    pub const fn empty() -> Context {
+        Context {
+            registers: [0; 12],
+            sp: 0,
+            daif: DAIF_MASKED,
+            ttbr0: 0,
+            tpidr_el0: 0,
+        }
+    }
+
+    /// A context that calls [entry] with [argument], on the stack that ends at [stack_top], in the
+    /// address space at [ttbr0]. IRQs are masked when [entry] is called.
+    pub fn new(
+        entry: extern "C" fn(usize) -> !,
+        argument: usize,
+        stack_top: u64,
+        ttbr0: u64,
+    ) -> Context {
+        let mut registers = [0; 12];
+        registers[0] = entry as *const () as u64;
+        registers[1] = argument as u64;
+        registers[11] = thread_trampoline as *const () as u64;
+
+        Context {
+            registers,
+            sp: stack_top,
+            daif: DAIF_MASKED,
+            ttbr0,
+            tpidr_el0: 0,
+        }
+    }
+}
+
+/// Saves the running thread's state in [old], and resumes the thread that saved [new]. This
+/// returns once another thread switches back to [old].
+///
+/// # Safety
+/// - IRQs must be masked, and neither context may be used by anything else until this returns.
+/// - [new] must have been saved by [switch], or created by [Context::new] with a valid stack.
+pub unsafe fn switch(old: *mut Context, new: *const Context) {
+    switch_context(old, new)
+}
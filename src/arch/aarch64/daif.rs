@@ -0,0 +1,77 @@
+#[cfg(feature = "lockdep")]
+use crate::lockdep;
+use core::arch::asm;
+#[cfg(feature = "lockdep")]
+use core::panic::Location;
+
+/// The `I` bit, which masks IRQs.
+#[cfg(feature = "lockdep")]
+const IRQ_MASK: u64 = 1 << 7;
+
+/// Holds the interrupt mask bits for the current exception level.
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/DAIF--Interrupt-Mask-Bits?lang=en
+#[derive(Clone, Copy, Debug)]
+pub struct DaifRegister {
+    bits: u64,
+}
+
+impl DaifRegister {
+    pub fn read() -> DaifRegister {
+        let bits: u64;
+        unsafe {
+            asm!("mrs {0}, daif", out(reg) bits);
+        }
+
+        DaifRegister { bits }
+    }
+
+    /// Restores the interrupt mask bits to the ones held by this [DaifRegister].
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub fn write(self) {
+        #[cfg(feature = "lockdep")]
+        if self.bits & IRQ_MASK == 0 && are_irqs_masked() {
+            lockdep::irqs_unmasked(Location::caller());
+        }
+
+        unsafe {
+            asm!("msr daif, {0}", in(reg) self.bits);
+        }
+    }
+}
+
+/// Stops IRQs from being delivered to the current core.
+pub fn mask_irqs() {
+    unsafe { asm!("msr daifset, #2") }
+}
+
+/// Allows IRQs to be delivered to the current core.
+#[cfg_attr(feature = "lockdep", track_caller)]
+pub fn unmask_irqs() {
+    #[cfg(feature = "lockdep")]
+    if are_irqs_masked() {
+        lockdep::irqs_unmasked(Location::caller());
+    }
+
+    unsafe { asm!("msr daifclr, #2") }
+}
+
+/// Whether IRQs are masked on the current core.
+#[cfg(feature = "lockdep")]
+fn are_irqs_masked() -> bool {
+    DaifRegister::read().bits & IRQ_MASK != 0
+}
+
+/// Runs [f] with IRQs masked, restoring the previous mask afterwards.
+///
+/// This is safe to nest, and can be called from within an IRQ handler.
+#[cfg_attr(feature = "lockdep", track_caller)]
+pub fn without_irqs<R>(f: impl FnOnce() -> R) -> R {
+    let saved = DaifRegister::read();
+    mask_irqs();
+
+    let result = f();
+
+    saved.write();
+    result
+}
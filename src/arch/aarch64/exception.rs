@@ -0,0 +1,381 @@
+use crate::{
+    cpu::percpu::{self, MAX_CORES},
+    io::interrupts,
+    memory::paging::{self, KernelSection},
+};
+use core::{
+    arch::{asm, global_asm},
+    sync::atomic::Ordering,
+};
+
+global_asm!(include_str!("exception.S"));
+
+extern "C" {
+    /// The exception vector table, defined in `exception.S`.
+    static exception_vectors: u8;
+}
+
+/// The size of each core's [OVERFLOW_STACKS], which must match `OVERFLOW_STACK_SIZE` in
+/// `exception.S`. It only has to be large enough to report the overflow.
+const OVERFLOW_STACK_SIZE: usize = 16 * 1024;
+
+#[repr(C, align(16))]
+struct OverflowStack([u8; OVERFLOW_STACK_SIZE]);
+
+/// The stacks that `exception.S` switches to when an exception from EL1 has no room for its
+/// [TrapFrame], e.g. because its stack overflowed into the guard page under it, one for each core.
+#[no_mangle]
+static mut OVERFLOW_STACKS: [OverflowStack; MAX_CORES] =
+    [const { OverflowStack([0; OVERFLOW_STACK_SIZE]) }; MAX_CORES];
+
+/// The state of the interrupted code, saved by `exception.S` when an exception is taken.
+///
+/// Any changes made to this frame by an exception handler will be restored when returning from
+/// the exception.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct TrapFrame {
+    /// The general purpose registers, x0 to x30.
+    pub registers: [u64; 31],
+
+    /// The stack pointer used by EL0.
+    pub sp_el0: u64,
+
+    /// The address that will be returned to (`ELR_EL1`).
+    pub elr: u64,
+
+    /// The program status that will be restored (`SPSR_EL1`).
+    pub spsr: u64,
+}
+
+/// Represents the four types of exception, each of which has its own vector.
+///
+/// https://developer.arm.com/documentation/100933/0100/AArch64-exception-vector-table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionKind {
+    Synchronous,
+    Irq,
+    Fiq,
+    SError,
+}
+
+/// Represents where an exception was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionOrigin {
+    /// The current exception level, while using `SP_EL0`.
+    CurrentElSp0,
+
+    /// The current exception level, while using `SP_EL1`.
+    CurrentElSpx,
+
+    /// A lower exception level running in AArch64.
+    LowerElAarch64,
+
+    /// A lower exception level running in AArch32.
+    LowerElAarch32,
+}
+
+/// Represents the reason for a synchronous exception.
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/ESR-EL1--Exception-Syndrome-Register--EL1-?lang=en#fieldset_0-31_26
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExceptionClass {
+    Unknown,
+    TrappedWfiWfe,
+    TrappedFloatingPoint,
+    IllegalExecutionState,
+    SupervisorCall,
+    TrappedSystemRegister,
+    InstructionAbortLowerEl,
+    InstructionAbortSameEl,
+    PcAlignmentFault,
+    DataAbortLowerEl,
+    DataAbortSameEl,
+    SpAlignmentFault,
+    SError,
+    Breakpoint,
+    Other(u32),
+}
+
+/// Holds the syndrome information for a synchronous exception or SError.
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/ESR-EL1--Exception-Syndrome-Register--EL1-?lang=en
+#[derive(Debug, Clone, Copy)]
+pub struct ExceptionSyndromeRegister {
+    pub class: ExceptionClass,
+
+    /// The raw value of the register.
+    pub value: u64,
+}
+
+/// Represents why a data or instruction abort happened, from its fault status code.
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/ESR-EL1--Exception-Syndrome-Register--EL1-?lang=en#fieldset_0-5_0_1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultKind {
+    /// The address is too large for the translation tables.
+    AddressSize,
+
+    /// Nothing is mapped at the address.
+    Translation,
+
+    /// The page's access flag is clear.
+    AccessFlag,
+
+    /// The page is mapped, but not for this kind of access.
+    Permission,
+
+    /// The address isn't aligned for the access.
+    Alignment,
+
+    Other(u8),
+}
+
+/// Describes a data or instruction abort, see [ExceptionSyndromeRegister::abort].
+#[derive(Debug, Clone, Copy)]
+pub struct Abort {
+    pub kind: FaultKind,
+
+    /// The address that was accessed (`FAR_EL1`).
+    pub address: u64,
+
+    /// Whether the access was a write, which is never the case for instruction fetches.
+    pub is_write: bool,
+
+    /// Whether the abort was caused by fetching an instruction.
+    pub is_instruction: bool,
+}
+
+/// Points `VBAR_EL1` at our exception vector table.
+/// This must be called before any interrupts are unmasked.
+pub fn initialize() {
+    unsafe {
+        let vectors = &exception_vectors as *const u8 as u64;
+        asm!("msr vbar_el1, {0}", "isb", in(reg) vectors);
+    }
+}
+
+/// Called by `exception.S` whenever an exception is taken to EL1.
+#[no_mangle]
+extern "C" fn handle_exception(frame: &mut TrapFrame, kind: u64) {
+    let origin = ExceptionOrigin::from(kind >> 2);
+
+    match ExceptionKind::from(kind & 0b11) {
+        ExceptionKind::Irq => {
+            let cpu = percpu::this();
+            cpu.irq_depth.fetch_add(1, Ordering::Relaxed);
+            interrupts::handle_irq();
+            cpu.irq_depth.fetch_sub(1, Ordering::Relaxed);
+
+            // The interrupt has been acknowledged, so another thread can run before returning.
+            crate::scheduler::preempt();
+        }
+
+        ExceptionKind::Synchronous if origin == ExceptionOrigin::LowerElAarch64 => {
+            let syndrome = ExceptionSyndromeRegister::read();
+            if syndrome.class == ExceptionClass::SupervisorCall {
+                crate::process::syscall::handle(frame)
+            } else if let Some(abort) = syndrome.abort() {
+                crate::process::fault::handle_abort(frame, abort)
+            } else {
+                // Whatever else a program does wrong only ends the program.
+                crate::process::fault::handle_exception(frame, syndrome)
+            }
+        }
+
+        kind => {
+            let syndrome = ExceptionSyndromeRegister::read();
+            if let Some(abort) = syndrome.abort() {
+                explain_kernel_abort(frame, abort);
+            }
+
+            panic!(
+                "unhandled {:?} exception from {:?}: {:?} (esr: {:#0x}, elr: {:#0x}, far: {:#0x})",
+                kind,
+                origin,
+                syndrome.class,
+                syndrome.value,
+                frame.elr,
+                read_fault_address()
+            );
+        }
+    }
+}
+
+/// Called by `exception.S` on the current core's overflow stack, rather than [handle_exception],
+/// when an exception from EL1 was taken with a stack pointer ([sp]) that leaves no room for its
+/// [TrapFrame] in mapped memory.
+#[no_mangle]
+extern "C" fn handle_stack_overflow(sp: u64) -> ! {
+    let syndrome = ExceptionSyndromeRegister::read();
+    let elr: u64;
+    unsafe { asm!("mrs {0}, elr_el1", out(reg) elr) };
+
+    let frame = sp.wrapping_sub(core::mem::size_of::<TrapFrame>() as u64);
+    if paging::kernel_section(frame) == Some(KernelSection::Guard) {
+        panic!(
+            "kernel stack overflow: the stack pointer {:#x} reaches into the guard page under a stack ({:?}, elr: {:#x}, far: {:#x})",
+            sp,
+            syndrome.class,
+            elr,
+            read_fault_address()
+        );
+    }
+
+    panic!(
+        "the kernel's stack pointer {:#x} leaves no room for an exception ({:?}, elr: {:#x}, far: {:#x})",
+        sp,
+        syndrome.class,
+        elr,
+        read_fault_address()
+    )
+}
+
+/// Panics with a clearer message if [abort] hit one of the protections of the kernel's own image:
+/// writing to its code or read-only data, executing its data, or overflowing a stack into the
+/// guard page below it.
+fn explain_kernel_abort(frame: &TrapFrame, abort: Abort) {
+    let Some(section) = paging::kernel_section(abort.address) else {
+        return;
+    };
+
+    let permission = abort.kind == FaultKind::Permission;
+    match section {
+        KernelSection::Guard => panic!(
+            "kernel stack overflow: {:#x} is in the guard page under a stack (elr: {:#x})",
+            abort.address, frame.elr
+        ),
+        KernelSection::Text | KernelSection::ReadOnlyData if permission && abort.is_write => {
+            panic!(
+                "the kernel wrote to {:#x}, which is read-only {:?} (elr: {:#x})",
+                abort.address, section, frame.elr
+            )
+        }
+        KernelSection::ReadOnlyData | KernelSection::Data if permission && abort.is_instruction => {
+            panic!(
+                "the kernel jumped to {:#x}, which is {:?} that can't be executed (elr: {:#x})",
+                abort.address, section, frame.elr
+            )
+        }
+        _ => {}
+    }
+}
+
+/// Returns the faulting virtual address for an abort (`FAR_EL1`).
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/FAR-EL1--Fault-Address-Register--EL1-?lang=en
+pub fn read_fault_address() -> u64 {
+    let address: u64;
+    unsafe {
+        asm!("mrs {0}, far_el1", out(reg) address);
+    }
+
+    address
+}
+
+impl ExceptionKind {
+    pub const fn from(value: u64) -> ExceptionKind {
+        match value {
+            0 => ExceptionKind::Synchronous,
+            1 => ExceptionKind::Irq,
+            2 => ExceptionKind::Fiq,
+            _ => ExceptionKind::SError,
+        }
+    }
+}
+
+impl ExceptionOrigin {
+    pub const fn from(value: u64) -> ExceptionOrigin {
+        match value {
+            0 => ExceptionOrigin::CurrentElSp0,
+            1 => ExceptionOrigin::CurrentElSpx,
+            2 => ExceptionOrigin::LowerElAarch64,
+            _ => ExceptionOrigin::LowerElAarch32,
+        }
+    }
+}
+
+impl ExceptionClass {
+    pub const fn from(value: u32) -> ExceptionClass {
+        match value {
+            0x00 => ExceptionClass::Unknown,
+            0x01 => ExceptionClass::TrappedWfiWfe,
+            0x07 => ExceptionClass::TrappedFloatingPoint,
+            0x0E => ExceptionClass::IllegalExecutionState,
+            0x15 => ExceptionClass::SupervisorCall,
+            0x18 => ExceptionClass::TrappedSystemRegister,
+            0x20 => ExceptionClass::InstructionAbortLowerEl,
+            0x21 => ExceptionClass::InstructionAbortSameEl,
+            0x22 => ExceptionClass::PcAlignmentFault,
+            0x24 => ExceptionClass::DataAbortLowerEl,
+            0x25 => ExceptionClass::DataAbortSameEl,
+            0x26 => ExceptionClass::SpAlignmentFault,
+            0x2F => ExceptionClass::SError,
+            0x3C => ExceptionClass::Breakpoint,
+            _ => ExceptionClass::Other(value),
+        }
+    }
+}
+
+impl FaultKind {
+    pub const fn from(status: u8) -> FaultKind {
+        // The bottom two bits of the first four kinds are the level of the table that faulted.
+        match status {
+            0b00_0000..=0b00_0011 => FaultKind::AddressSize,
+            0b00_0100..=0b00_0111 => FaultKind::Translation,
+            0b00_1000..=0b00_1011 => FaultKind::AccessFlag,
+            0b00_1100..=0b00_1111 => FaultKind::Permission,
+            0b10_0001 => FaultKind::Alignment,
+            _ => FaultKind::Other(status),
+        }
+    }
+}
+
+impl ExceptionSyndromeRegister {
+    /// Whether the write-not-read bit of a data abort is set.
+    const WRITE_NOT_READ: u64 = 1 << 6;
+
+    /// Whether a data abort was caused by a cache maintenance instruction, which also sets
+    /// [Self::WRITE_NOT_READ].
+    const CACHE_MAINTENANCE: u64 = 1 << 8;
+
+    /// Decodes a data or instruction abort, or a misaligned program counter or stack pointer,
+    /// along with the address that caused it (which isn't known for a misaligned stack pointer).
+    /// Returns [None] for every other exception class.
+    pub fn abort(&self) -> Option<Abort> {
+        let (kind, is_instruction) = match self.class {
+            ExceptionClass::DataAbortLowerEl | ExceptionClass::DataAbortSameEl => {
+                (FaultKind::from((self.value & 0x3F) as u8), false)
+            }
+            ExceptionClass::InstructionAbortLowerEl | ExceptionClass::InstructionAbortSameEl => {
+                (FaultKind::from((self.value & 0x3F) as u8), true)
+            }
+            ExceptionClass::PcAlignmentFault => (FaultKind::Alignment, true),
+            ExceptionClass::SpAlignmentFault => (FaultKind::Alignment, false),
+            _ => return None,
+        };
+
+        let is_write = !is_instruction
+            && self.value & Self::WRITE_NOT_READ != 0
+            && self.value & Self::CACHE_MAINTENANCE == 0;
+
+        Some(Abort {
+            kind,
+            address: read_fault_address(),
+            is_write,
+            is_instruction,
+        })
+    }
+
+    pub fn read() -> ExceptionSyndromeRegister {
+        let value: u64;
+        unsafe {
+            asm!("mrs {0}, esr_el1", out(reg) value);
+        }
+
+        ExceptionSyndromeRegister {
+            class: ExceptionClass::from(((value >> 26) & 0x3F) as u32),
+            value,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use core::arch::asm;
+
+/// The ARM generic timer's virtual counter and timer for the current core.
+///
+/// The virtual timer is used (rather than the physical one) as EL1 is always allowed to access it,
+/// regardless of how the firmware configured `CNTHCTL_EL2`. `boot.S` zeroes `CNTVOFF_EL2`, so the
+/// virtual count matches the physical count.
+///
+/// https://developer.arm.com/documentation/102379/0101/The-processor-timers
+pub struct GenericTimer;
+
+impl GenericTimer {
+    /// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/CNTV-CTL-EL0--Counter-timer-Virtual-Timer-Control-register?lang=en
+    const ENABLE: u64 = 1 << 0;
+
+    /// Returns the frequency of the system counter in Hz.
+    ///
+    /// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/CNTFRQ-EL0--Counter-timer-Frequency-register?lang=en
+    pub fn frequency() -> u64 {
+        let frequency: u64;
+        unsafe {
+            asm!("mrs {0}, cntfrq_el0", out(reg) frequency);
+        }
+
+        frequency
+    }
+
+    /// Returns the current value of the virtual counter.
+    ///
+    /// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/CNTVCT-EL0--Counter-timer-Virtual-Count-register?lang=en
+    pub fn counter() -> u64 {
+        let counter: u64;
+        unsafe {
+            // The ISB prevents the counter from being read early.
+            asm!("isb", "mrs {0}, cntvct_el0", out(reg) counter);
+        }
+
+        counter
+    }
+
+    /// Arms the virtual timer to fire an interrupt after [ticks] counter ticks.
+    /// This also clears any pending timer interrupt.
+    ///
+    /// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/CNTV-TVAL-EL0--Counter-timer-Virtual-Timer-TimerValue-register?lang=en
+    pub fn arm(ticks: u64) {
+        unsafe {
+            asm!(
+                "msr cntv_tval_el0, {0}",
+                "msr cntv_ctl_el0, {1}",
+                "isb",
+                in(reg) ticks,
+                in(reg) Self::ENABLE,
+            );
+        }
+    }
+}
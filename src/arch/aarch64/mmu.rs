@@ -0,0 +1,145 @@
+use core::arch::asm;
+
+/// The memory types that page table entries refer to by index.
+/// Attribute 0 is normal, write-back cacheable memory, attribute 1 is device-nGnRnE memory.
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/MAIR-EL1--Memory-Attribute-Indirection-Register--EL1-?lang=en
+const MAIR: u64 = 0xFF;
+
+// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/TCR-EL1--Translation-Control-Register--EL1-?lang=en
+const TCR_T0SZ_SHIFT: u64 = 0;
+const TCR_IRGN0_WRITE_BACK: u64 = 0b01 << 8;
+const TCR_ORGN0_WRITE_BACK: u64 = 0b01 << 10;
+const TCR_SH0_INNER_SHAREABLE: u64 = 0b11 << 12;
+const TCR_TG0_4K: u64 = 0b00 << 14;
+const TCR_T1SZ_SHIFT: u64 = 16;
+const TCR_IRGN1_WRITE_BACK: u64 = 0b01 << 24;
+const TCR_ORGN1_WRITE_BACK: u64 = 0b01 << 26;
+const TCR_SH1_INNER_SHAREABLE: u64 = 0b11 << 28;
+const TCR_TG1_4K: u64 = 0b10 << 30;
+const TCR_IPS_SHIFT: u64 = 32;
+
+// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/SCTLR-EL1--System-Control-Register--EL1-?lang=en
+const SCTLR_M: u64 = 1 << 0;
+const SCTLR_C: u64 = 1 << 2;
+const SCTLR_I: u64 = 1 << 12;
+
+/// Where the ASID goes in `TTBR0_EL1`, and in the operand of the TLB maintenance instructions.
+const ASID_SHIFT: u64 = 48;
+
+/// Translates the upper half of the address space with the tables at [kernel_table], and the
+/// lower half with [user_table] (from [ttbr0]), for virtual addresses of [address_bits] bits in
+/// each half. The MMU and the caches are turned on if they weren't already.
+///
+/// `boot.S` already turned the MMU on with tables that map the same memory, this swaps them for
+/// the kernel's own.
+///
+/// # Safety
+/// - [kernel_table] must map the code that is running, its stack, and everything else that the
+///   kernel uses, at the same addresses as the current tables, or the next access will fault.
+pub unsafe fn enable(kernel_table: u64, user_table: u64, address_bits: u32) {
+    let size_offset = 64 - address_bits as u64;
+
+    // The physical address size is whatever the CPU supports.
+    let features: u64;
+    asm!("mrs {0}, id_aa64mmfr0_el1", out(reg) features);
+
+    let tcr = (size_offset << TCR_T0SZ_SHIFT)
+        | TCR_IRGN0_WRITE_BACK
+        | TCR_ORGN0_WRITE_BACK
+        | TCR_SH0_INNER_SHAREABLE
+        | TCR_TG0_4K
+        | (size_offset << TCR_T1SZ_SHIFT)
+        | TCR_IRGN1_WRITE_BACK
+        | TCR_ORGN1_WRITE_BACK
+        | TCR_SH1_INNER_SHAREABLE
+        | TCR_TG1_4K
+        | ((features & 0b111) << TCR_IPS_SHIFT);
+
+    asm!(
+        "msr mair_el1, {mair}",
+        "msr tcr_el1, {tcr}",
+        "msr ttbr1_el1, {kernel_table}",
+        "msr ttbr0_el1, {user_table}",
+        "dsb ish",
+        "tlbi vmalle1",
+        "dsb ish",
+        "isb",
+        mair = in(reg) MAIR,
+        tcr = in(reg) tcr,
+        kernel_table = in(reg) kernel_table,
+        user_table = in(reg) user_table,
+    );
+
+    let mut sctlr: u64;
+    asm!("mrs {0}, sctlr_el1", out(reg) sctlr);
+    sctlr |= SCTLR_M | SCTLR_C | SCTLR_I;
+    asm!("msr sctlr_el1, {0}", "isb", in(reg) sctlr);
+}
+
+/// The tables that translate the lower half of the address space, and their ASID
+/// (`TTBR0_EL1`).
+pub fn current_table() -> u64 {
+    let table: u64;
+    unsafe { asm!("mrs {0}, ttbr0_el1", out(reg) table) };
+
+    table
+}
+
+/// The value of `TTBR0_EL1` that translates the lower half with the tables at [table], tagging
+/// its translations with [asid].
+pub const fn ttbr0(table: u64, asid: u16) -> u64 {
+    table | ((asid as u64) << ASID_SHIFT)
+}
+
+/// The ASID that the value of `TTBR0_EL1` [table] (from [ttbr0]) tags translations with.
+pub const fn asid_of(table: u64) -> u16 {
+    (table >> ASID_SHIFT) as u16
+}
+
+/// Switches the lower half of the address space to the tables at [table] (from [ttbr0]).
+///
+/// Translations are tagged with their ASID, so the old tables' ones don't have to be forgotten.
+///
+/// # Safety
+/// - [table] must only map the lower half, and its ASID mustn't be used by any other tables.
+pub unsafe fn switch(table: u64) {
+    asm!("dsb ish", "msr ttbr0_el1, {0}", "isb", in(reg) table);
+}
+
+/// Makes the page table entries that were written visible to the table walker.
+pub fn synchronize() {
+    unsafe { asm!("dsb ishst", "isb") }
+}
+
+/// Forgets any translation of the page at [address] on the current core only, e.g. when asked to
+/// by another core.
+pub fn invalidate_local_page(address: u64) {
+    unsafe { asm!("dsb nshst", "tlbi vaae1, {0}", "dsb nsh", "isb", in(reg) address >> 12) }
+}
+
+/// Forgets the translation of the page at [address] in the address space of [asid] on the
+/// current core only, after its entry was changed.
+pub fn invalidate_local_asid_page(asid: u16, address: u64) {
+    let operand = ((asid as u64) << ASID_SHIFT) | ((address >> 12) & 0xFFF_FFFF_FFFF);
+    unsafe { asm!("dsb nshst", "tlbi vae1, {0}", "dsb nsh", "isb", in(reg) operand) }
+}
+
+/// Forgets every translation on the current core only.
+pub fn invalidate_local() {
+    unsafe { asm!("dsb nshst", "tlbi vmalle1", "dsb nsh", "isb") }
+}
+
+/// Forgets the translation of the kernel's page at [address] on every core, which is global, so
+/// it isn't tagged with an ASID.
+pub fn invalidate_kernel_page(address: u64) {
+    let operand = (address >> 12) & 0xFFF_FFFF_FFFF;
+    unsafe { asm!("dsb ishst", "tlbi vaae1is, {0}", "dsb ish", "isb", in(reg) operand) }
+}
+
+/// Forgets every translation of the address space of [asid] on every core, e.g. before the ASID
+/// is used for another one.
+pub fn invalidate_asid(asid: u16) {
+    let operand = (asid as u64) << ASID_SHIFT;
+    unsafe { asm!("dsb ishst", "tlbi aside1is, {0}", "dsb ish", "isb", in(reg) operand) }
+}
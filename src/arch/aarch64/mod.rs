@@ -1,2 +1,10 @@
+pub mod cache;
+pub mod context;
 pub mod currentel;
+pub mod daif;
+pub mod exception;
+pub mod generic_timer;
 pub mod midr_el1;
+pub mod mmu;
+pub mod mpidr_el1;
+pub mod user;
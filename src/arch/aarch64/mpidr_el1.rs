@@ -0,0 +1,23 @@
+use core::arch::asm;
+
+/// Stores the affinity of the current processor core.
+///
+/// https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/MPIDR-EL1--Multiprocessor-Affinity-Register?lang=en
+#[derive(Clone, Copy, Debug)]
+pub struct MultiprocessorAffinityRegister {
+    /// The index of this core within its cluster (0 to 3 on the Raspberry Pi).
+    pub core_id: u32,
+}
+
+impl MultiprocessorAffinityRegister {
+    pub fn read() -> MultiprocessorAffinityRegister {
+        let data: u64;
+        unsafe {
+            asm!("mrs {0}, mpidr_el1", out(reg) data);
+        }
+
+        // https://developer.arm.com/documentation/ddi0601/2023-03/AArch64-Registers/MPIDR-EL1--Multiprocessor-Affinity-Register?lang=en#fieldset_0-7_0
+        let core_id = (data & 0xFF) as u32;
+        MultiprocessorAffinityRegister { core_id }
+    }
+}
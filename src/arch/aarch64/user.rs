@@ -0,0 +1,59 @@
+use super::exception::TrapFrame;
+use core::arch::{asm, global_asm};
+
+global_asm!(include_str!("user.S"));
+
+extern "C" {
+    fn enter_user(frame: *const TrapFrame, context: *mut KernelContext) -> u64;
+    fn leave_user(context: *const KernelContext, value: u64) -> !;
+}
+
+/// The registers that [enter] saves, so that [leave] can return from it.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct KernelContext {
+    /// The callee-saved registers, x19 to x30.
+    registers: [u64; 12],
+    sp: u64,
+}
+
+impl KernelContext {
+    pub const fn new() -> KernelContext {
+        KernelContext {
+            registers: [0; 12],
+            sp: 0,
+        }
+    }
+}
+
+/// Drops to EL0 with the registers in [frame], and returns the value that is passed to [leave]
+/// once the code running there is done.
+///
+/// # Safety
+/// - [frame] must return to EL0, at code that is mapped in the current address space.
+/// - [context] must stay valid until [leave] is called with it.
+pub unsafe fn enter(frame: &TrapFrame, context: *mut KernelContext) -> u64 {
+    enter_user(frame, context)
+}
+
+/// Returns [value] from the [enter] call that saved [context], abandoning the exception handler
+/// that this is called from.
+///
+/// # Safety
+/// - [context] must have been saved by an [enter] call that hasn't returned yet.
+pub unsafe fn leave(context: *const KernelContext, value: u64) -> ! {
+    leave_user(context, value)
+}
+
+/// The thread pointer of the code running at EL0 (`TPIDR_EL0`), which the C library points at its
+/// thread-local storage.
+pub fn thread_pointer() -> u64 {
+    let value: u64;
+    unsafe { asm!("mrs {0}, tpidr_el0", out(reg) value) };
+    value
+}
+
+/// Changes the thread pointer of the code running at EL0, see [thread_pointer].
+pub fn set_thread_pointer(value: u64) {
+    unsafe { asm!("msr tpidr_el0, {0}", in(reg) value) };
+}
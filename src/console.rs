@@ -1,52 +1,247 @@
-use crate::{io::uart::Uart, mutex::Mutex};
+use crate::{
+    arch::aarch64::daif,
+    io::{
+        clocks::{self, ClockId},
+        mini_uart::MiniUart,
+        power,
+        uart::Uart,
+    },
+    mutex::Mutex,
+    params::{Parameter, ParameterError},
+    println,
+};
 use core::fmt::{self, Write};
 
-static UART: Mutex<Option<Uart>> = Mutex::new(None);
+/// The UART that the console uses. It is locked with IRQs masked, as interrupt handlers print
+/// too, and is held for a whole message, so that messages from different cores don't interleave.
+static OUTPUT: Mutex<Option<Output>> = Mutex::new(None);
 
+/// The parameters accepted by the console.
+pub const PARAMETERS: &[Parameter] = &[Parameter {
+    key: "console.uart",
+    default: "uart0",
+    description: "the uart to write the console to (uart0, uart1 or none)",
+    apply: |params, value| {
+        params.console.uart = match value {
+            "uart0" => ConsoleUart::Uart0,
+            "uart1" => ConsoleUart::Uart1,
+            "none" => ConsoleUart::None,
+            _ => {
+                return Err(ParameterError::UnknownValue {
+                    expected: "uart0, uart1 or none",
+                })
+            }
+        };
+
+        Ok(())
+    },
+}];
+
+/// The console's [crate::params::BootParams].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConsoleParams {
+    pub uart: ConsoleUart,
+}
+
+/// The UARTs that the console can be written to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConsoleUart {
+    /// The PL011 [Uart].
+    #[default]
+    Uart0,
+
+    /// The auxiliary [MiniUart].
+    Uart1,
+
+    /// Disables the console.
+    None,
+}
+
+/// The device that the console is currently being written to.
+#[derive(Clone, Copy)]
+enum Output {
+    Uart0(Uart),
+    Uart1(MiniUart),
+}
+
+/// Initializes the console on [ConsoleUart::Uart0], which the firmware leaves enabled.
+/// This must be done before the mailbox is available, so the [crate::params::BootParams] can't be used yet.
 pub fn initialize() {
     let mut uart = Uart::new();
     uart.initialize();
 
-    let mut uart_mut = UART.lock();
-    *uart_mut = Some(uart);
+    daif::without_irqs(|| *OUTPUT.lock() = Some(Output::Uart0(uart)));
+}
+
+/// Moves the console to [uart], enabling its power domains first, and powering off the UART that
+/// it was on. If the [ConsoleUart] can't be enabled, the console stays where it was.
+///
+/// [clocks::initialize] should be called before this, as the mini UART's baud rate depends on
+/// the core clock.
+pub fn select(uart: ConsoleUart) {
+    let current = daif::without_irqs(|| OUTPUT.lock().map_or(ConsoleUart::None, |it| it.uart()));
+
+    let output = match uart {
+        ConsoleUart::Uart0 => {
+            if let Err(error) = power::enable_domains("uart0", Uart::POWER_DOMAINS) {
+                println!(
+                    "[angeldust::console] failed to enable uart0's power domains: {:?}",
+                    error
+                );
+                return;
+            }
+
+            // The PL011 is the UART that we started on, so there's nothing else to do.
+            if current == ConsoleUart::Uart0 {
+                return;
+            }
+
+            let mut uart = Uart::new();
+            uart.initialize();
+            Some(Output::Uart0(uart))
+        }
+
+        ConsoleUart::Uart1 => {
+            if let Err(error) = power::enable_domains("uart1", MiniUart::POWER_DOMAINS) {
+                println!(
+                    "[angeldust::console] failed to enable uart1's power domains: {:?}",
+                    error
+                );
+                return;
+            }
+
+            let core_clock_rate = match clocks::instance().rate(ClockId::Core) {
+                Ok(rate) => rate,
+                Err(error) => {
+                    println!(
+                        "[angeldust::console] failed to read the core clock for uart1: {:?}",
+                        error
+                    );
+                    return;
+                }
+            };
+
+            let mut uart = MiniUart::new();
+            uart.initialize(core_clock_rate);
+            Some(Output::Uart1(uart))
+        }
+
+        ConsoleUart::None => None,
+    };
+
+    println!("[angeldust::console] switching the console to {:?}", uart);
+    daif::without_irqs(|| *OUTPUT.lock() = output);
+    println!("[angeldust::console] console is now on {:?}", uart);
+
+    // Nothing but the console uses the UARTs, so the previous one can be powered off.
+    let previous = match current {
+        ConsoleUart::Uart0 => Some(("uart0", Uart::POWER_DOMAINS)),
+        ConsoleUart::Uart1 => Some(("uart1", MiniUart::POWER_DOMAINS)),
+        ConsoleUart::None => None,
+    };
+
+    if let Some((name, domains)) = previous.filter(|_| current != uart) {
+        if let Err(error) = power::disable_domains(name, domains) {
+            println!(
+                "[angeldust::console] failed to power off {}'s power domains: {:?}",
+                name, error
+            );
+        }
+    }
+}
+
+/// Writes [bytes] to the console as they are, apart from line endings, e.g. for user programs
+/// whose output may not be UTF-8.
+pub fn write_bytes(bytes: &[u8]) {
+    daif::without_irqs(|| {
+        let output = OUTPUT.lock();
+        if let Some(output) = *output {
+            for byte in bytes {
+                if *byte == b'\n' {
+                    output.write(b'\r');
+                }
+
+                output.write(*byte);
+            }
+        }
+    })
+}
+
+/// Reads a byte that was received by the console's UART, or returns [None] if there is nothing
+/// to read (or no console).
+pub fn read_byte() -> Option<u8> {
+    daif::without_irqs(|| (*OUTPUT.lock())?.read())
+}
+
+/// Unlocks the console, so that a panic can be reported even if it happened while printing.
+///
+/// # Safety
+/// - Whatever was printing must never continue, as its output would be mixed with the panic's.
+pub unsafe fn force_unlock() {
+    OUTPUT.force_unlock();
 }
 
+#[allow(dead_code)]
 pub fn clear() {
-    write!(UartWriter, "{}[2J", 27 as char).ok();
+    _print(format_args!("{}[2J", 27 as char));
 }
 
-struct UartWriter;
+struct ConsoleWriter(Output);
 
-impl fmt::Write for UartWriter {
+impl fmt::Write for ConsoleWriter {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        if let Some(uart) = *UART.lock() {
-            for c in s.chars() {
-                if c == '\n' {
-                    uart.write(b'\r')
-                }
-
-                uart.write(c as u8);
+        for c in s.chars() {
+            if c == '\n' {
+                self.0.write(b'\r')
             }
+
+            self.0.write(c as u8);
         }
 
-        // We don't want to return an error if the UART hasn't been initialized yet,
-        // as that may cause a panic, which would end up being useless.
         Ok(())
     }
 }
 
+impl Output {
+    const fn uart(&self) -> ConsoleUart {
+        match self {
+            Output::Uart0(_) => ConsoleUart::Uart0,
+            Output::Uart1(_) => ConsoleUart::Uart1,
+        }
+    }
+
+    fn write(&self, byte: u8) {
+        match self {
+            Output::Uart0(uart) => uart.write(byte),
+            Output::Uart1(uart) => uart.write(byte),
+        }
+    }
+
+    fn read(&self) -> Option<u8> {
+        match self {
+            Output::Uart0(uart) => uart.read(),
+            Output::Uart1(uart) => uart.read(),
+        }
+    }
+}
+
 #[doc(hidden)]
 pub fn _print(args: core::fmt::Arguments) {
-    write!(UartWriter, "{}", args).ok();
+    daif::without_irqs(|| {
+        let output = OUTPUT.lock();
+        if let Some(output) = *output {
+            write!(ConsoleWriter(output), "{}", args).ok();
+        }
+    })
 }
 
 #[macro_export]
 macro_rules! print {
-    ($($arg:tt)*) => (crate::console::_print(format_args!($($arg)*)));
+    ($($arg:tt)*) => ($crate::console::_print(format_args!($($arg)*)));
 }
 
 #[macro_export]
 macro_rules! println {
-    () => (print!("\n"));
-    ($($arg:tt)*) => (print!("{}\n", format_args!($($arg)*)));
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
 }
@@ -0,0 +1,266 @@
+use super::percpu::{self, MAX_CORES};
+use crate::{
+    arch::aarch64::{daif, mmu},
+    io::interrupts::{self, Interrupt},
+    mutex::Mutex,
+    println, timer,
+};
+use bitflags::bitflags;
+use core::{
+    hint,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+};
+
+bitflags! {
+    /// What one core can ask another to do. Several messages to the same core may be handled by
+    /// a single interrupt.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Message: u32 {
+        /// Switch threads once the interrupt is handled, e.g. because a thread of a higher
+        /// priority was queued on the core.
+        const Reschedule = 1 << 0;
+
+        /// Run the function that was passed to [call_on].
+        const Call = 1 << 1;
+
+        /// Forget the translations that were passed to [shootdown].
+        const TlbShootdown = 1 << 2;
+
+        /// Stop the core forever, see [stop_others].
+        const Stop = 1 << 3;
+    }
+}
+
+/// Represents an error that can occur when sending a message to another core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpiError {
+    /// Occurs when the core isn't online.
+    Offline(usize),
+}
+
+/// A function that another core was asked to run with [call_on].
+#[derive(Clone, Copy)]
+struct Call {
+    function: fn(usize),
+    argument: usize,
+}
+
+/// What each core was asked to run by [call_on]. Only one core may use a core's slot at a time,
+/// which it claims with [CallSlot::busy].
+struct CallSlot {
+    busy: AtomicBool,
+    call: Mutex<Option<Call>>,
+    done: AtomicBool,
+}
+
+/// The value of [SHOOTDOWN_ADDRESS] that means every translation.
+const SHOOTDOWN_ALL: u64 = u64::MAX;
+
+/// Whether [initialize] has been called, so that a panic early on doesn't touch the interrupt
+/// controller.
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+static CALLS: [CallSlot; MAX_CORES] = [const { CallSlot::new() }; MAX_CORES];
+
+/// Claimed by the core that is doing a [shootdown], as there is only one request at a time.
+static SHOOTDOWN_BUSY: AtomicBool = AtomicBool::new(false);
+
+/// The page that the cores have to forget, or [SHOOTDOWN_ALL].
+static SHOOTDOWN_ADDRESS: AtomicU64 = AtomicU64::new(SHOOTDOWN_ALL);
+
+/// A bit for each core that still has to handle the [shootdown].
+static SHOOTDOWN_PENDING: AtomicU32 = AtomicU32::new(0);
+
+/// Starts handling the messages that other cores send to this one. Other cores pick the handler
+/// up with [interrupts::initialize_core].
+///
+/// [interrupts::initialize] must be called before this.
+pub fn initialize() {
+    interrupts::register(Interrupt::Ipi, handle);
+    INITIALIZED.store(true, Ordering::Release);
+}
+
+/// Sends [message] to [core], which handles it the next time that it has IRQs unmasked, or
+/// waits for another core.
+pub fn send(core: usize, message: Message) -> Result<(), IpiError> {
+    let cpu = percpu::get(core);
+    if !cpu.is_online() {
+        return Err(IpiError::Offline(core));
+    }
+
+    // The message has to be there before the interrupt is, as the interrupt may be handled
+    // straight away.
+    cpu.ipi_pending.fetch_or(message.bits(), Ordering::AcqRel);
+    interrupts::send_ipi(core);
+    Ok(())
+}
+
+/// Runs [function] with [argument] on [core], with IRQs masked, and waits for it to return.
+///
+/// This may be called with IRQs masked, as messages sent to this core are handled while it
+/// waits.
+pub fn call_on(core: usize, function: fn(usize), argument: usize) -> Result<(), IpiError> {
+    daif::without_irqs(|| {
+        if core == percpu::this().core {
+            function(argument);
+            return Ok(());
+        }
+
+        let slot = &CALLS[core];
+        while slot
+            .busy
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            poll();
+        }
+
+        slot.done.store(false, Ordering::Relaxed);
+        *slot.call.lock() = Some(Call { function, argument });
+
+        let result = send(core, Message::Call);
+        if result.is_ok() {
+            while !slot.done.load(Ordering::Acquire) {
+                poll();
+            }
+        } else {
+            slot.call.lock().take();
+        }
+
+        slot.busy.store(false, Ordering::Release);
+        result
+    })
+}
+
+/// Forgets the translation of the page at [address] on every core, or every translation if it
+/// is [None], and waits for every other core to do the same.
+///
+/// Broadcast TLB maintenance already reaches every core in the inner shareable domain, this is
+/// for translations that only some cores may have cached, which is cheaper to do locally.
+pub fn shootdown(address: Option<u64>) {
+    daif::without_irqs(|| {
+        while SHOOTDOWN_BUSY
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            poll();
+        }
+
+        let this = percpu::this().core;
+        let targets = percpu::all()
+            .iter()
+            .filter(|it| it.core != this && it.is_online())
+            .fold(0, |targets, it| targets | (1 << it.core));
+
+        SHOOTDOWN_ADDRESS.store(address.unwrap_or(SHOOTDOWN_ALL), Ordering::Relaxed);
+        SHOOTDOWN_PENDING.store(targets, Ordering::Release);
+
+        for core in 0..MAX_CORES {
+            // A core that went offline since doesn't have anything to forget.
+            if targets & (1 << core) != 0 && send(core, Message::TlbShootdown).is_err() {
+                SHOOTDOWN_PENDING.fetch_and(!(1 << core), Ordering::AcqRel);
+            }
+        }
+
+        invalidate(address.unwrap_or(SHOOTDOWN_ALL));
+        while SHOOTDOWN_PENDING.load(Ordering::Acquire) != 0 {
+            poll();
+        }
+
+        SHOOTDOWN_BUSY.store(false, Ordering::Release);
+    });
+}
+
+/// Prints how long each of the other cores takes to answer a [call_on], which also makes sure
+/// that they all handle IPIs.
+pub fn report() {
+    daif::without_irqs(|| {
+        let this = percpu::this().core;
+        for core in (0..MAX_CORES).filter(|it| *it != this) {
+            let start = timer::uptime();
+            match call_on(core, |_| {}, 0) {
+                Ok(()) => println!(
+                    "[angeldust::ipi] core {} answered in {}us",
+                    core,
+                    (timer::uptime() - start).as_micros()
+                ),
+                Err(error) => println!("[angeldust::ipi] core {} didn't answer: {:?}", core, error),
+            }
+        }
+    });
+}
+
+/// Stops every other core, e.g. because this one panicked. This doesn't wait for them, as a core
+/// only stops once it has IRQs unmasked.
+pub fn stop_others() {
+    if !INITIALIZED.load(Ordering::Acquire) {
+        return;
+    }
+
+    let this = percpu::this().core;
+    for core in (0..MAX_CORES).filter(|it| *it != this) {
+        // A core that isn't online has nothing to stop.
+        let _ = send(core, Message::Stop);
+    }
+}
+
+/// Handles the messages that were sent to this core, from the IPI handler or while waiting for
+/// another core with IRQs masked.
+fn handle() {
+    let cpu = percpu::this();
+    let messages = Message::from_bits_truncate(cpu.ipi_pending.swap(0, Ordering::AcqRel));
+
+    if messages.contains(Message::Stop) {
+        cpu.online.store(false, Ordering::Release);
+        super::halt();
+    }
+
+    if messages.contains(Message::TlbShootdown) {
+        invalidate(SHOOTDOWN_ADDRESS.load(Ordering::Relaxed));
+        SHOOTDOWN_PENDING.fetch_and(!(1 << cpu.core), Ordering::AcqRel);
+    }
+
+    if messages.contains(Message::Call) {
+        let slot = &CALLS[cpu.core];
+        let call = slot.call.lock().take();
+        if let Some(call) = call {
+            (call.function)(call.argument);
+            slot.done.store(true, Ordering::Release);
+        }
+    }
+
+    if messages.contains(Message::Reschedule) {
+        // The exception handler switches threads once every interrupt has been handled.
+        cpu.need_reschedule.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Handles the messages that were sent to this core while it waits, so that two cores waiting
+/// on each other with IRQs masked don't wait forever.
+fn poll() {
+    if percpu::this().ipi_pending.load(Ordering::Relaxed) != 0 {
+        handle();
+    }
+
+    hint::spin_loop();
+}
+
+/// Forgets the translation of [address] on this core, or every translation if it is
+/// [SHOOTDOWN_ALL].
+fn invalidate(address: u64) {
+    if address == SHOOTDOWN_ALL {
+        mmu::invalidate_local();
+    } else {
+        mmu::invalidate_local_page(address);
+    }
+}
+
+impl CallSlot {
+    const fn new() -> CallSlot {
+        CallSlot {
+            busy: AtomicBool::new(false),
+            call: Mutex::new(None),
+            done: AtomicBool::new(false),
+        }
+    }
+}
@@ -1,2 +1,20 @@
+pub mod ipi;
+pub mod percpu;
 pub mod raspberry_pi;
+pub mod revision;
+pub mod smp;
+pub mod system_info;
 pub use raspberry_pi::*;
+
+use crate::arch::aarch64::daif;
+use core::arch::asm;
+
+/// Stops the current core from doing anything else, forever.
+/// IRQs are masked first, so interrupt handlers won't run either.
+pub fn halt() -> ! {
+    daif::mask_irqs();
+
+    loop {
+        unsafe { asm!("wfe") }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    arch::aarch64::mpidr_el1::MultiprocessorAffinityRegister, mutex::Mutex,
+    scheduler::run_queue::RunQueue,
+};
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+};
+
+/// The amount of cores on every supported Raspberry Pi.
+pub const MAX_CORES: usize = 4;
+
+/// The data that belongs to a single core, which it finds through `TPIDR_EL1`.
+///
+/// Other cores may look at it too (e.g. to steal work from its run queue), so everything in it
+/// is atomic or behind a lock.
+pub struct PerCpu {
+    pub core: usize,
+
+    /// Whether the core has started, and is running threads.
+    pub online: AtomicBool,
+
+    /// The slot of the thread that the core is running.
+    pub current: AtomicUsize,
+
+    /// The slot of the core's idle thread, which runs when there is nothing else to do.
+    pub idle_thread: AtomicUsize,
+
+    /// The slot of the thread that the core just switched away from, which can't run anywhere
+    /// else until the switch is done.
+    pub previous: AtomicUsize,
+
+    /// The threads that are waiting to run on this core.
+    pub run_queue: Mutex<RunQueue>,
+
+    /// Set when the running thread should be switched out once the current interrupt is handled.
+    pub need_reschedule: AtomicBool,
+
+    /// The amount of ticks that are left of the running thread's time slice.
+    pub slice: AtomicU64,
+
+    /// The amount of ticks that the core spent in its idle thread, and in every other thread.
+    pub idle_ticks: AtomicU64,
+    pub busy_ticks: AtomicU64,
+
+    /// How many interrupts the core is handling, including ones that interrupted others.
+    pub irq_depth: AtomicU32,
+
+    /// The bits of the [super::ipi::Message]s that other cores sent, which haven't been handled.
+    pub ipi_pending: AtomicU32,
+}
+
+static CPUS: [PerCpu; MAX_CORES] = [
+    PerCpu::new(0),
+    PerCpu::new(1),
+    PerCpu::new(2),
+    PerCpu::new(3),
+];
+
+/// Points `TPIDR_EL1` at the current core's [PerCpu]. Each core must do this before anything
+/// else, as the exception handler uses it.
+pub fn initialize() {
+    let core = MultiprocessorAffinityRegister::read().core_id as usize;
+    let cpu = &CPUS[core] as *const PerCpu as u64;
+
+    unsafe { asm!("msr tpidr_el1, {0}", in(reg) cpu) };
+}
+
+/// Returns the current core's [PerCpu].
+///
+/// A thread may be moved to another core whenever IRQs are unmasked, so this should only be used
+/// with IRQs masked, unless any core's data will do.
+pub fn this() -> &'static PerCpu {
+    let cpu: u64;
+    unsafe { asm!("mrs {0}, tpidr_el1", out(reg) cpu) };
+
+    // Safety: [initialize] pointed `TPIDR_EL1` at an entry of [CPUS].
+    unsafe { &*(cpu as *const PerCpu) }
+}
+
+/// Returns the [PerCpu] of [core].
+pub fn get(core: usize) -> &'static PerCpu {
+    &CPUS[core]
+}
+
+/// Returns every core's [PerCpu], including cores that aren't online.
+pub fn all() -> &'static [PerCpu; MAX_CORES] {
+    &CPUS
+}
+
+/// Whether the current core is handling an interrupt.
+pub fn in_interrupt() -> bool {
+    this().irq_depth.load(Ordering::Relaxed) > 0
+}
+
+impl PerCpu {
+    const fn new(core: usize) -> PerCpu {
+        PerCpu {
+            core,
+            online: AtomicBool::new(false),
+            current: AtomicUsize::new(0),
+            idle_thread: AtomicUsize::new(0),
+            previous: AtomicUsize::new(0),
+            run_queue: Mutex::new(RunQueue::new()),
+            need_reschedule: AtomicBool::new(false),
+            slice: AtomicU64::new(0),
+            idle_ticks: AtomicU64::new(0),
+            busy_ticks: AtomicU64::new(0),
+            irq_depth: AtomicU32::new(0),
+            ipi_pending: AtomicU32::new(0),
+        }
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Acquire)
+    }
+
+    pub fn current(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub fn idle_thread(&self) -> usize {
+        self.idle_thread.load(Ordering::Relaxed)
+    }
+}
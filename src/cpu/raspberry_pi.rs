@@ -1,11 +1,22 @@
-use crate::arch::aarch64::midr_el1::MainIdRegister;
+use super::{revision::BoardRevision, system_info};
+use crate::{arch::aarch64::midr_el1::MainIdRegister, fdt, memory, sync::Once};
 
-static mut INSTANCE: Option<RaspberryPi> = None;
+static INSTANCE: Once<RaspberryPi> = Once::new();
+
+/// The decoded revision code, once [detect_revision] has read it.
+static REVISION: Once<Option<BoardRevision>> = Once::new();
 
 pub fn initialize() {
-    unsafe {
-        INSTANCE = Some(RaspberryPi::new());
-    }
+    INSTANCE.call_once(RaspberryPi::new);
+}
+
+/// Takes the board revision code from the [system_info::SystemInfo], which is more accurate than
+/// the board type inferred from the `midr_el1` register.
+///
+/// [initialize] and [system_info::initialize] must be called before this.
+pub fn detect_revision() {
+    let code = system_info::instance().board_revision;
+    REVISION.set(BoardRevision::decode(code));
 }
 
 /// Represents information about this Raspberry Pi.
@@ -15,6 +26,15 @@ pub fn initialize() {
 #[derive(Clone, Copy, Debug)]
 pub struct RaspberryPi {
     board_type: BoardType,
+
+    /// The decoded revision code, if [detect_revision] has been called and the code is new-style.
+    revision: Option<BoardRevision>,
+
+    peripheral_base_address: usize,
+    local_peripheral_base_address: usize,
+
+    /// Whether or not the peripheral base addresses were read from the device tree.
+    addresses_from_device_tree: bool,
 }
 
 /// Represents the different Rasberry Pi board types.
@@ -31,15 +51,32 @@ impl RaspberryPi {
     /// Retrieves the global instance of [RaspberryPi].
     /// You must call [initialize] before running this.
     pub fn instance() -> RaspberryPi {
-        unsafe { INSTANCE }.expect("To be Some(T)")
+        let instance = INSTANCE
+            .get()
+            .expect("raspberry_pi::initialize() should be called before RaspberryPi::instance()");
+
+        RaspberryPi {
+            revision: REVISION.get().copied().flatten(),
+            ..*instance
+        }
     }
 
     /// Whether or not the [RaspberryPi::board_type] is supported by the kernel.
     /// At the moment, only the Pi3 and Pi4 are supported.
+    ///
+    /// Once the [BoardRevision] is known, its processor must also agree with the board type
+    /// inferred from the `midr_el1` register.
     pub const fn is_supported(&self) -> bool {
-        match self.board_type() {
-            BoardType::Pi3 | BoardType::Pi4 => true,
-            _ => false,
+        if !matches!(self.board_type(), BoardType::Pi3 | BoardType::Pi4) {
+            return false;
+        }
+
+        match self.revision {
+            Some(revision) => matches!(
+                (revision.processor.board_type(), self.board_type()),
+                (Some(BoardType::Pi3), BoardType::Pi3) | (Some(BoardType::Pi4), BoardType::Pi4)
+            ),
+            None => true,
         }
     }
 
@@ -48,29 +85,70 @@ impl RaspberryPi {
         self.board_type
     }
 
-    /// Returns the inferred peripheral base address for this Raspberry Pi.
+    /// Returns the decoded [BoardRevision].
     ///
-    /// Since only the Raspberry Pi 3 and 4 are supported right now, anything else
-    /// will return the Pi 3's base address in order to try and get a working UART.
+    /// This is [None] until [detect_revision] has been called, or if the board uses an old-style
+    /// revision code.
+    pub const fn revision(&self) -> Option<BoardRevision> {
+        self.revision
+    }
+
+    /// Returns the peripheral base address for this Raspberry Pi.
+    ///
+    /// This comes from the `/soc` ranges in the device tree when one is present, otherwise it is
+    /// inferred from the [BoardType]. It is where the kernel maps the peripherals, in the upper
+    /// half.
     pub const fn peripheral_base_address(&self) -> *mut u8 {
-        let address: u32 = match self.board_type() {
-            BoardType::Pi4 => 0xFE00_0000,
+        memory::virtual_address(self.peripheral_base_address as u64) as *mut u8
+    }
 
-            // Let's just fall back to the Pi3 peripheral base address for any other value.
-            _ => 0x3F00_0000,
-        };
+    /// Returns the base address of the ARM local peripherals for this Raspberry Pi.
+    ///
+    /// These are the per-core timers, mailboxes and interrupt routing registers (BCM2836 and
+    /// later), along with the GIC-400 on the Raspberry Pi 4.
+    pub const fn local_peripheral_base_address(&self) -> *mut u8 {
+        memory::virtual_address(self.local_peripheral_base_address as u64) as *mut u8
+    }
 
-        address as *mut u8
+    /// Whether or not the peripheral base addresses were read from the device tree, rather than
+    /// being inferred from the [BoardType].
+    pub const fn addresses_from_device_tree(&self) -> bool {
+        self.addresses_from_device_tree
     }
 
     /// Creates a new instance of [RaspberryPi].
     /// This should only be called once, as the data will not change.
+    ///
+    /// [fdt::initialize] should be called before this, so that the peripheral addresses can be
+    /// read from the device tree.
     fn new() -> RaspberryPi {
         let midr = MainIdRegister::read();
+        let board_type = BoardType::from(midr.part_number);
+
+        let from_device_tree = fdt::instance().and_then(|it| {
+            Some((
+                it.translate_soc_address(Self::PERIPHERAL_BUS_ADDRESS)? as usize,
+                it.translate_soc_address(Self::LOCAL_PERIPHERAL_BUS_ADDRESS)? as usize,
+            ))
+        });
+
+        let (peripheral_base_address, local_peripheral_base_address) =
+            from_device_tree.unwrap_or(board_type.peripheral_base_addresses());
+
         RaspberryPi {
-            board_type: BoardType::from(midr.part_number),
+            board_type,
+            revision: None,
+            peripheral_base_address,
+            local_peripheral_base_address,
+            addresses_from_device_tree: from_device_tree.is_some(),
         }
     }
+
+    /// The address of the peripherals on the VideoCore's bus, which is the same on every board.
+    const PERIPHERAL_BUS_ADDRESS: u64 = 0x7E00_0000;
+
+    /// The address of the ARM local peripherals in the `/soc` ranges.
+    const LOCAL_PERIPHERAL_BUS_ADDRESS: u64 = 0x4000_0000;
 }
 
 impl BoardType {
@@ -84,4 +162,18 @@ impl BoardType {
             _ => BoardType::Unknown(part_number),
         }
     }
+
+    /// Returns the peripheral and local peripheral base addresses for this [BoardType], for when
+    /// there is no device tree to read them from.
+    ///
+    /// Since only the Raspberry Pi 3 and 4 are supported right now, anything else
+    /// will return the Pi 3's addresses in order to try and get a working UART.
+    const fn peripheral_base_addresses(&self) -> (usize, usize) {
+        match self {
+            BoardType::Pi4 => (0xFE00_0000, 0xFF80_0000),
+
+            // Let's just fall back to the Pi3 peripheral base address for any other value.
+            _ => (0x3F00_0000, 0x4000_0000),
+        }
+    }
 }
@@ -0,0 +1,172 @@
+use super::BoardType;
+
+/// A decoded new-style board revision code, as returned by the `GetBoardRevision` mailbox tag.
+///
+/// https://www.raspberrypi.com/documentation/computers/raspberry-pi.html#new-style-revision-codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardRevision {
+    /// The raw revision code.
+    pub code: u32,
+    pub model: BoardModel,
+    pub processor: Processor,
+    pub manufacturer: Manufacturer,
+
+    /// The amount of RAM on the board in megabytes.
+    pub memory_size_mb: u32,
+
+    /// The minor revision of the PCB, e.g. the `2` in `1.2`.
+    pub pcb_revision: u32,
+}
+
+/// Represents the different Raspberry Pi models.
+/// https://www.raspberrypi.com/documentation/computers/raspberry-pi.html#new-style-revision-codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BoardModel {
+    A,
+    B,
+    APlus,
+    BPlus,
+    Pi2B,
+    Alpha,
+    Cm1,
+    Pi3B,
+    Zero,
+    Cm3,
+    ZeroW,
+    Pi3BPlus,
+    Pi3APlus,
+    Internal,
+    Cm3Plus,
+    Pi4B,
+    Zero2W,
+    Pi400,
+    Cm4,
+    Cm4S,
+    Pi5,
+    Cm5,
+    Pi500,
+    Cm5Lite,
+    Unknown(u32),
+}
+
+/// Represents the SoC used by a Raspberry Pi.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Processor {
+    Bcm2835,
+    Bcm2836,
+    Bcm2837,
+    Bcm2711,
+    Bcm2712,
+    Unknown(u32),
+}
+
+/// Represents the company that manufactured a Raspberry Pi.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Manufacturer {
+    SonyUk,
+    Egoman,
+    Embest,
+    SonyJapan,
+    Stadium,
+    Unknown(u32),
+}
+
+impl BoardRevision {
+    /// Set in new-style revision codes.
+    const NEW_STYLE: u32 = 1 << 23;
+
+    /// Decodes a new-style revision code.
+    /// Returns [None] for old-style codes, which are only used by the original Raspberry Pi 1.
+    pub const fn decode(code: u32) -> Option<BoardRevision> {
+        if code & Self::NEW_STYLE == 0 {
+            return None;
+        }
+
+        // NOQuuuWuFMMMCCCCPPPPTTTTTTTTRRRR
+        let memory_size_mb = match (code >> 20) & 0x7 {
+            size @ 0..=6 => 256 << size,
+            _ => 0,
+        };
+
+        Some(BoardRevision {
+            code,
+            model: BoardModel::from((code >> 4) & 0xFF),
+            processor: Processor::from((code >> 12) & 0xF),
+            manufacturer: Manufacturer::from((code >> 16) & 0xF),
+            memory_size_mb,
+            pcb_revision: code & 0xF,
+        })
+    }
+}
+
+impl BoardModel {
+    pub const fn from(value: u32) -> BoardModel {
+        match value {
+            0x00 => BoardModel::A,
+            0x01 => BoardModel::B,
+            0x02 => BoardModel::APlus,
+            0x03 => BoardModel::BPlus,
+            0x04 => BoardModel::Pi2B,
+            0x05 => BoardModel::Alpha,
+            0x06 => BoardModel::Cm1,
+            0x08 => BoardModel::Pi3B,
+            0x09 => BoardModel::Zero,
+            0x0A => BoardModel::Cm3,
+            0x0C => BoardModel::ZeroW,
+            0x0D => BoardModel::Pi3BPlus,
+            0x0E => BoardModel::Pi3APlus,
+            0x0F => BoardModel::Internal,
+            0x10 => BoardModel::Cm3Plus,
+            0x11 => BoardModel::Pi4B,
+            0x12 => BoardModel::Zero2W,
+            0x13 => BoardModel::Pi400,
+            0x14 => BoardModel::Cm4,
+            0x15 => BoardModel::Cm4S,
+            0x17 => BoardModel::Pi5,
+            0x18 => BoardModel::Cm5,
+            0x19 => BoardModel::Pi500,
+            0x1A => BoardModel::Cm5Lite,
+            _ => BoardModel::Unknown(value),
+        }
+    }
+}
+
+impl Processor {
+    pub const fn from(value: u32) -> Processor {
+        match value {
+            0 => Processor::Bcm2835,
+            1 => Processor::Bcm2836,
+            2 => Processor::Bcm2837,
+            3 => Processor::Bcm2711,
+            4 => Processor::Bcm2712,
+            _ => Processor::Unknown(value),
+        }
+    }
+
+    /// Returns the [BoardType] that uses this processor.
+    ///
+    /// The Zero 2 W and the Compute Module 3 report a BCM2837, so they are treated as a
+    /// [BoardType::Pi3], and the Pi 400 and Compute Module 4 are treated as a [BoardType::Pi4].
+    pub const fn board_type(&self) -> Option<BoardType> {
+        match self {
+            Processor::Bcm2835 => Some(BoardType::Pi1),
+            Processor::Bcm2836 => Some(BoardType::Pi2),
+            Processor::Bcm2837 => Some(BoardType::Pi3),
+            Processor::Bcm2711 => Some(BoardType::Pi4),
+            _ => None,
+        }
+    }
+}
+
+impl Manufacturer {
+    pub const fn from(value: u32) -> Manufacturer {
+        match value {
+            0 => Manufacturer::SonyUk,
+            1 => Manufacturer::Egoman,
+            2 | 4 => Manufacturer::Embest,
+            3 => Manufacturer::SonyJapan,
+            5 => Manufacturer::Stadium,
+            _ => Manufacturer::Unknown(value),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use super::percpu::{self, MAX_CORES};
+use crate::{
+    arch::aarch64::{cache, exception},
+    fdt,
+    io::interrupts,
+    memory::{self, paging},
+    println,
+    scheduler::{self, SchedulerError},
+    timer,
+};
+use core::{
+    arch::asm,
+    hint,
+    ptr::{addr_of_mut, write_volatile},
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+/// How long a secondary core may take to come online before it is given up on.
+const START_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Where the firmware's spin table has each core wait for an address to jump to, if the device
+/// tree doesn't say.
+/// https://github.com/raspberrypi/tools/blob/master/armstubs/armstub8.S
+const DEFAULT_RELEASE_ADDRESS: u64 = 0xD8;
+
+extern "C" {
+    /// Read by the secondary cores once their MMU is on, see `boot.S`.
+    static mut secondary_boot: SecondaryBoot;
+
+    /// Where the secondary cores start, in `boot.S`.
+    fn secondary_entry();
+}
+
+/// What a secondary core needs to start, which must match `secondary_boot` in `boot.S`.
+#[repr(C)]
+struct SecondaryBoot {
+    stack_top: u64,
+}
+
+/// Represents an error that can occur while starting the secondary cores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmpError {
+    /// Occurs when the core's idle thread couldn't be created.
+    Scheduler(SchedulerError),
+
+    /// Occurs when the core didn't come online within [START_TIMEOUT].
+    Timeout,
+}
+
+/// The amount of cores that are online.
+static ONLINE: AtomicU32 = AtomicU32::new(1);
+
+/// Releases cores 1 to 3 from the firmware's spin table, and waits for each of them to come
+/// online and start scheduling threads.
+///
+/// [scheduler::initialize] must be called before this, on core 0.
+pub fn start_secondary_cores() {
+    for core in 1..MAX_CORES {
+        match start_core(core) {
+            Ok(()) => {
+                ONLINE.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(error) => println!(
+                "[angeldust::smp] failed to start core {}: {:?}",
+                core, error
+            ),
+        }
+    }
+
+    println!("[angeldust::smp] {} cores online", online());
+}
+
+/// The amount of cores that are online.
+pub fn online() -> u32 {
+    ONLINE.load(Ordering::Relaxed)
+}
+
+fn start_core(core: usize) -> Result<(), SmpError> {
+    let stack_top = scheduler::add_core(core).map_err(SmpError::Scheduler)?;
+
+    // Safety: Only core 0 writes this, and the core that reads it isn't running yet.
+    unsafe { write_volatile(addr_of_mut!(secondary_boot), SecondaryBoot { stack_top }) };
+
+    // The core starts with its MMU off, so it needs the physical address of its entry point.
+    let entry = memory::physical_address(secondary_entry as *const () as u64)
+        .expect("the kernel is linked in the upper half");
+    let release_address = memory::virtual_address(release_address(core));
+
+    // Safety: The spin table is below the kernel, which is reserved and mapped in the upper half.
+    unsafe {
+        write_volatile(release_address as *mut u64, entry);
+
+        // The core reads this with its MMU, and so its caches, off.
+        cache::clean_and_invalidate(release_address as usize, size_of::<u64>());
+        asm!("sev");
+    }
+
+    let deadline = timer::uptime() + START_TIMEOUT;
+    while !percpu::get(core).is_online() {
+        if timer::uptime() > deadline {
+            return Err(SmpError::Timeout);
+        }
+
+        hint::spin_loop();
+    }
+
+    Ok(())
+}
+
+/// Where [core] waits for the address to jump to, from its `cpu-release-addr` in the device tree.
+fn release_address(core: usize) -> u64 {
+    fdt::instance()
+        .and_then(|tree| tree.find_node("/cpus"))
+        .and_then(|cpus| {
+            cpus.children().find(|it| {
+                it.base_name() == "cpu"
+                    && it.property("reg").and_then(|it| it.as_u32()) == Some(core as u32)
+            })
+        })
+        .and_then(|cpu| cpu.property("cpu-release-addr"))
+        .and_then(|it| it.as_u64())
+        .unwrap_or(DEFAULT_RELEASE_ADDRESS + 8 * core as u64)
+}
+
+/// Called by `boot.S` on cores 1 to 3, with the MMU on and the stack of their idle thread.
+#[no_mangle]
+extern "C" fn secondary_init(core: u64) -> ! {
+    // The core is still on the boot tables, which map the lower half too.
+    paging::enable_mmu_on_core();
+
+    percpu::initialize();
+    exception::initialize();
+    interrupts::initialize_core();
+    timer::initialize_core();
+
+    println!("[angeldust::smp] core {} is up", core);
+    scheduler::start_core()
+}
@@ -0,0 +1,197 @@
+use super::revision::BoardRevision;
+use crate::{
+    io::{
+        mac::MacAddress,
+        mailbox::{
+            self, types::Message, types::MessageTag, Channel, GetArmMemory, GetBoardMacAddress,
+            GetBoardModel, GetBoardRevision, GetBoardSerial, GetCommandLine, GetFirmwareHash,
+            GetFirmwareVersionMessage, GetVcMemory, MailboxError, COMMAND_LINE_SIZE,
+        },
+    },
+    println,
+    sync::Once,
+};
+use core::fmt::Display;
+
+static SYSTEM_INFO: Once<SystemInfo> = Once::new();
+
+/// Holds all of the tags sent to collect the [SystemInfo].
+///
+/// These are sent in a single message, as each transaction with the VideoCore is fairly slow.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SystemInfoMessage {
+    firmware_version: MessageTag<GetFirmwareVersionMessage>,
+    firmware_hash: MessageTag<GetFirmwareHash>,
+    board_model: MessageTag<GetBoardModel>,
+    board_revision: MessageTag<GetBoardRevision>,
+    board_serial: MessageTag<GetBoardSerial>,
+    mac_address: MessageTag<GetBoardMacAddress>,
+    arm_memory: MessageTag<GetArmMemory>,
+    vc_memory: MessageTag<GetVcMemory>,
+    command_line: MessageTag<GetCommandLine>,
+}
+
+/// A region of physical memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub base_address: u32,
+    pub size: u32,
+}
+
+/// The SHA-1 hash of the firmware's source revision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FirmwareHash(pub [u8; 20]);
+
+/// Information about the board and its firmware, collected once at boot.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemInfo {
+    /// The firmware's build time, as a unix timestamp.
+    pub firmware_version: u32,
+    pub firmware_hash: FirmwareHash,
+
+    /// The board model reported by the firmware, this is 0 on most boards.
+    /// [SystemInfo::revision] is much more useful.
+    pub board_model: u32,
+    pub board_revision: u32,
+    pub serial_number: u64,
+    pub mac_address: MacAddress,
+
+    /// The memory available to the ARM cores.
+    pub arm_memory: MemoryRegion,
+
+    /// The memory reserved by the VideoCore.
+    pub vc_memory: MemoryRegion,
+
+    command_line: [u8; COMMAND_LINE_SIZE],
+    command_line_length: usize,
+}
+
+/// Collects the [SystemInfo] from the firmware.
+/// [mailbox::initialize] must be called before this.
+pub fn initialize() -> Result<(), MailboxError> {
+    let request = SystemInfoMessage {
+        firmware_version: GetFirmwareVersionMessage::new(),
+        firmware_hash: GetFirmwareHash::new(),
+        board_model: GetBoardModel::new(),
+        board_revision: GetBoardRevision::new(),
+        board_serial: GetBoardSerial::new(),
+        mac_address: GetBoardMacAddress::new(),
+        arm_memory: GetArmMemory::new(),
+        vc_memory: GetVcMemory::new(),
+        command_line: GetCommandLine::new(),
+    };
+
+    let response: SystemInfoMessage =
+        mailbox::instance().send(Channel::PropertyTags, Message::new(request))?;
+
+    // The length of the command line is in the response code, but the firmware reports the length
+    // it wanted to write if our buffer was too small.
+    let command_line_length =
+        ((response.command_line.codes & !(1 << 31)) as usize).min(COMMAND_LINE_SIZE);
+
+    let serial = response.board_serial.data;
+    SYSTEM_INFO.set(SystemInfo {
+        firmware_version: response.firmware_version.data.firmware_version,
+        firmware_hash: FirmwareHash(response.firmware_hash.data.hash),
+        board_model: response.board_model.data.board_model,
+        board_revision: response.board_revision.data.board_revision,
+        serial_number: ((serial.serial_high as u64) << 32) | serial.serial_low as u64,
+        mac_address: response.mac_address.data.address,
+        arm_memory: MemoryRegion {
+            base_address: response.arm_memory.data.base_address,
+            size: response.arm_memory.data.size,
+        },
+        vc_memory: MemoryRegion {
+            base_address: response.vc_memory.data.base_address,
+            size: response.vc_memory.data.size,
+        },
+        command_line: response.command_line.data.command_line,
+        command_line_length,
+    });
+
+    Ok(())
+}
+
+/// Retrieves the [SystemInfo] collected at boot.
+/// You must call [initialize] before running this.
+pub fn instance() -> &'static SystemInfo {
+    SYSTEM_INFO
+        .get()
+        .expect("system_info::initialize() should be called before system_info::instance()")
+}
+
+impl SystemInfo {
+    /// Returns the decoded [BoardRevision], or [None] if the board uses an old-style revision code.
+    pub const fn revision(&self) -> Option<BoardRevision> {
+        BoardRevision::decode(self.board_revision)
+    }
+
+    /// Returns the kernel command line, with any trailing null bytes removed.
+    /// If the firmware passed something that isn't UTF-8, an empty string is returned.
+    pub fn command_line(&self) -> &str {
+        let bytes = &self.command_line[..self.command_line_length];
+        let end = bytes.iter().position(|it| *it == 0).unwrap_or(bytes.len());
+
+        core::str::from_utf8(&bytes[..end]).unwrap_or("")
+    }
+
+    /// Prints the [SystemInfo] as a boot banner.
+    pub fn print_banner(&self) {
+        println!("[angeldust::system_info] ----------------------------------------");
+
+        match self.revision() {
+            Some(revision) => println!(
+                "[angeldust::system_info] board:       {:?} rev 1.{} ({:?}, {} MB, {:?})",
+                revision.model,
+                revision.pcb_revision,
+                revision.processor,
+                revision.memory_size_mb,
+                revision.manufacturer
+            ),
+            None => println!(
+                "[angeldust::system_info] board:       model {:#0x}, revision {:#0x}",
+                self.board_model, self.board_revision
+            ),
+        }
+
+        println!(
+            "[angeldust::system_info] serial:      {:016x}",
+            self.serial_number
+        );
+        println!("[angeldust::system_info] mac:         {}", self.mac_address);
+        println!(
+            "[angeldust::system_info] firmware:    {} ({})",
+            self.firmware_version, self.firmware_hash
+        );
+        println!("[angeldust::system_info] arm memory:  {}", self.arm_memory);
+        println!("[angeldust::system_info] vc memory:   {}", self.vc_memory);
+        println!(
+            "[angeldust::system_info] cmdline:     {}",
+            self.command_line()
+        );
+        println!("[angeldust::system_info] ----------------------------------------");
+    }
+}
+
+impl Display for MemoryRegion {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:#010x} - {:#010x} ({} MB)",
+            self.base_address,
+            self.base_address as u64 + self.size as u64,
+            self.size / (1024 * 1024)
+        )
+    }
+}
+
+impl Display for FirmwareHash {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
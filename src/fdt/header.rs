@@ -0,0 +1,62 @@
+use super::implementation::read_u32;
+
+/// The header at the start of every flattened device tree blob.
+/// All of the fields are stored as big-endian in the blob, they are converted when parsed.
+///
+/// https://devicetree-specification.readthedocs.io/en/stable/flattened-format.html#header
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub magic: u32,
+
+    /// The size of the whole blob in bytes, including this header.
+    pub total_size: u32,
+
+    /// The offset of the structure block from the start of the blob.
+    pub structure_offset: u32,
+
+    /// The offset of the strings block from the start of the blob.
+    pub strings_offset: u32,
+
+    /// The offset of the memory reservation map from the start of the blob.
+    pub memory_reservation_offset: u32,
+
+    pub version: u32,
+
+    /// The lowest version that this blob is backwards compatible with.
+    pub last_compatible_version: u32,
+
+    /// The physical id of the boot CPU, this matches the `reg` property of its CPU node.
+    pub boot_cpu_id: u32,
+
+    pub strings_size: u32,
+    pub structure_size: u32,
+}
+
+impl Header {
+    /// The value of [Header::magic] in a valid blob.
+    pub const MAGIC: u32 = 0xD00D_FEED;
+
+    /// The size of the header in bytes.
+    pub const SIZE: usize = 40;
+
+    /// The newest version that we know how to parse, and the oldest version that we accept.
+    pub const VERSION: u32 = 17;
+    pub const MIN_VERSION: u32 = 16;
+
+    /// Parses a [Header] from the start of [bytes].
+    /// Returns [None] if [bytes] is shorter than [Header::SIZE].
+    pub fn parse(bytes: &[u8]) -> Option<Header> {
+        Some(Header {
+            magic: read_u32(bytes, 0)?,
+            total_size: read_u32(bytes, 4)?,
+            structure_offset: read_u32(bytes, 8)?,
+            strings_offset: read_u32(bytes, 12)?,
+            memory_reservation_offset: read_u32(bytes, 16)?,
+            version: read_u32(bytes, 20)?,
+            last_compatible_version: read_u32(bytes, 24)?,
+            boot_cpu_id: read_u32(bytes, 28)?,
+            strings_size: read_u32(bytes, 32)?,
+            structure_size: read_u32(bytes, 36)?,
+        })
+    }
+}
@@ -0,0 +1,291 @@
+use super::{
+    header::Header,
+    node::{read_string, Node, Nodes, Range, Region, Token},
+};
+use crate::memory;
+
+/// Represents an error that can occur while validating a [DeviceTree].
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum FdtError {
+    /// Occurs when the firmware did not pass a device tree to the kernel.
+    NotPresent,
+
+    /// Occurs when the blob is not aligned to an 8-byte boundary.
+    Misaligned(usize),
+
+    /// Occurs when the blob does not start with [Header::MAGIC].
+    InvalidMagic(u32),
+
+    /// Occurs when the blob's format is too old, or too new to be read by this parser.
+    UnsupportedVersion {
+        version: u32,
+        last_compatible_version: u32,
+    },
+
+    /// Occurs when the blocks described by the [Header] do not fit inside of the blob.
+    InvalidHeader(Header),
+
+    /// Occurs when an unknown token is found in the structure block.
+    InvalidToken { offset: usize, token: u32 },
+
+    /// Occurs when a node is closed that was never opened, or a token appears where it shouldn't.
+    UnexpectedToken(usize),
+
+    /// Occurs when a string is not null-terminated, or is not valid UTF-8.
+    InvalidString(usize),
+
+    /// Occurs when a block ends in the middle of a token, or before all nodes are closed.
+    UnexpectedEnd,
+}
+
+/// A validated flattened device tree blob.
+/// https://devicetree-specification.readthedocs.io/en/stable/flattened-format.html
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceTree {
+    address: usize,
+    header: Header,
+
+    structure: &'static [u8],
+    strings: &'static [u8],
+    memory_reservations: &'static [u8],
+}
+
+impl DeviceTree {
+    /// The largest blob that we are willing to accept, this protects us from treating a garbage
+    /// `total_size` as valid memory.
+    const MAX_SIZE: u32 = 2 * 1024 * 1024;
+
+    /// Validates the blob at the physical [address], walking its structure block to make sure that
+    /// every node is well-formed.
+    ///
+    /// ## Safety
+    /// - [address] must either be 0, or point to memory which is readable for as long as the
+    ///   kernel runs. It is only trusted once the header has been validated.
+    pub unsafe fn from_address(address: usize) -> Result<DeviceTree, FdtError> {
+        if address == 0 {
+            return Err(FdtError::NotPresent);
+        }
+
+        if !address.is_multiple_of(8) {
+            return Err(FdtError::Misaligned(address));
+        }
+
+        // The blob is read through the kernel's map of physical memory.
+        let pointer = memory::virtual_address(address as u64) as *const u8;
+        let header_bytes = core::slice::from_raw_parts(pointer, Header::SIZE);
+        let header = Header::parse(header_bytes).ok_or(FdtError::UnexpectedEnd)?;
+
+        if header.magic != Header::MAGIC {
+            return Err(FdtError::InvalidMagic(header.magic));
+        }
+
+        if header.version < Header::MIN_VERSION || header.last_compatible_version > Header::VERSION
+        {
+            return Err(FdtError::UnsupportedVersion {
+                version: header.version,
+                last_compatible_version: header.last_compatible_version,
+            });
+        }
+
+        if (header.total_size as usize) < Header::SIZE || header.total_size > Self::MAX_SIZE {
+            return Err(FdtError::InvalidHeader(header));
+        }
+
+        let blob = core::slice::from_raw_parts(pointer, header.total_size as usize);
+        let block = |offset: u32, size: u32, alignment: u32| {
+            if !offset.is_multiple_of(alignment) {
+                return None;
+            }
+
+            blob.get(offset as usize..offset.checked_add(size)? as usize)
+        };
+
+        let (Some(structure), Some(strings), Some(memory_reservations)) = (
+            block(header.structure_offset, header.structure_size, 4),
+            block(header.strings_offset, header.strings_size, 1),
+            block(
+                header.memory_reservation_offset,
+                header.total_size - header.memory_reservation_offset.min(header.total_size),
+                8,
+            ),
+        ) else {
+            return Err(FdtError::InvalidHeader(header));
+        };
+
+        let device_tree = DeviceTree {
+            address,
+            header,
+            structure,
+            strings,
+            memory_reservations,
+        };
+
+        device_tree.validate()?;
+        Ok(device_tree)
+    }
+
+    /// The physical address of the blob.
+    pub const fn address(&self) -> usize {
+        self.address
+    }
+
+    pub const fn header(&self) -> Header {
+        self.header
+    }
+
+    /// The root node, whose name is empty.
+    pub fn root(&self) -> Node {
+        // The root node is always the first token, this is checked by [DeviceTree::validate].
+        match Token::read(self, 0) {
+            Ok((Token::BeginNode(name), offset)) => Node::new(*self, name, offset),
+            _ => unreachable!("the device tree should start with the root node"),
+        }
+    }
+
+    /// Finds the node at [path], e.g. `/soc/gpio`.
+    /// See [Node::is_named] for how each component of the path is matched.
+    pub fn find_node(&self, path: &str) -> Option<Node> {
+        path.split('/')
+            .filter(|it| !it.is_empty())
+            .try_fold(self.root(), |node, name| node.child(name))
+    }
+
+    /// Iterates over every node in the tree.
+    pub fn nodes(&self) -> Nodes {
+        Nodes {
+            tree: *self,
+            offset: Some(0),
+        }
+    }
+
+    /// Finds the first node which is compatible with [compatible].
+    pub fn find_compatible(&self, compatible: &str) -> Option<Node> {
+        self.nodes().find(|it| it.is_compatible(compatible))
+    }
+
+    /// Iterates over the regions described by the memory nodes, which is all of the RAM
+    /// available to the ARM cores.
+    pub fn memory(&self) -> impl Iterator<Item = Region> {
+        let root = self.root();
+
+        root.children()
+            .filter(|it| {
+                it.property("device_type").and_then(|it| it.as_str()) == Some("memory")
+                    || it.is_named("memory")
+            })
+            .flat_map(move |it| it.reg(&root))
+    }
+
+    /// Iterates over the memory reservation map.
+    /// These regions must not be overwritten by the kernel (e.g. the spin tables on the Pi 3).
+    pub fn reserved_memory(&self) -> impl Iterator<Item = Region> {
+        self.memory_reservations
+            .chunks_exact(16)
+            .map(|it| Region {
+                address: read_u64(it, 0).unwrap_or(0),
+                size: read_u64(it, 8).unwrap_or(0),
+            })
+            .take_while(|it| it.address != 0 || it.size != 0)
+    }
+
+    /// The board's model name, e.g. `Raspberry Pi 3 Model B Rev 1.2`.
+    pub fn model(&self) -> Option<&'static str> {
+        self.root().property("model")?.as_str()
+    }
+
+    /// The kernel command line from `/chosen/bootargs`.
+    pub fn bootargs(&self) -> Option<&'static str> {
+        self.find_node("/chosen")?.property("bootargs")?.as_str()
+    }
+
+    /// Where the firmware (or QEMU) loaded the initrd, from `/chosen/linux,initrd-start` and
+    /// `/chosen/linux,initrd-end`, as its start and end addresses.
+    pub fn initrd(&self) -> Option<(u64, u64)> {
+        let chosen = self.find_node("/chosen")?;
+        let start = chosen.property("linux,initrd-start")?.as_u64()?;
+        let end = chosen.property("linux,initrd-end")?.as_u64()?;
+
+        Some((start, end))
+    }
+
+    /// Iterates over the `ranges` of the `/soc` node, which map VideoCore bus addresses to ARM
+    /// physical addresses.
+    pub fn soc_ranges(&self) -> impl Iterator<Item = Range> {
+        let root = self.root();
+        let soc = self.find_node("/soc");
+
+        soc.into_iter().flat_map(move |it| it.ranges(&root))
+    }
+
+    /// Translates an address on the `/soc` bus into an ARM physical address.
+    pub fn translate_soc_address(&self, address: u64) -> Option<u64> {
+        self.soc_ranges()
+            .find(|it| address >= it.child_address && address - it.child_address < it.size)
+            .map(|it| it.parent_address + (address - it.child_address))
+    }
+
+    pub(super) const fn structure(&self) -> &'static [u8] {
+        self.structure
+    }
+
+    /// Reads the string at [offset] in the strings block.
+    pub(super) fn string(&self, offset: usize) -> Result<&'static str, FdtError> {
+        read_string(self.strings, offset)
+    }
+
+    /// Walks the whole structure block, making sure that there is a single root node, that every
+    /// node is closed, and that the block is terminated.
+    fn validate(&self) -> Result<(), FdtError> {
+        let mut offset = 0;
+        let mut depth = 0usize;
+        let mut seen_root = false;
+
+        loop {
+            let (token, next) = Token::read(self, offset)?;
+
+            match token {
+                Token::BeginNode(_) if depth == 0 && seen_root => {
+                    return Err(FdtError::UnexpectedToken(offset))
+                }
+
+                Token::BeginNode(_) => {
+                    depth += 1;
+                    seen_root = true;
+                }
+
+                Token::EndNode if depth == 0 => return Err(FdtError::UnexpectedToken(offset)),
+                Token::EndNode => depth -= 1,
+
+                Token::Property(_) if depth == 0 => return Err(FdtError::UnexpectedToken(offset)),
+                Token::Property(_) => {}
+
+                Token::End if depth == 0 && seen_root => break,
+                Token::End => return Err(FdtError::UnexpectedEnd),
+            }
+
+            offset = next;
+        }
+
+        // The reservation map must be terminated by an empty entry within the blob.
+        self.memory_reservations
+            .chunks_exact(16)
+            .any(|it| read_u64(it, 0) == Some(0) && read_u64(it, 8) == Some(0))
+            .then_some(())
+            .ok_or(FdtError::UnexpectedEnd)
+    }
+}
+
+/// Reads the big-endian u32 at [offset] in [bytes].
+pub(super) fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads the big-endian u64 at [offset] in [bytes].
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let high = read_u32(bytes, offset)? as u64;
+    let low = read_u32(bytes, offset + 4)? as u64;
+
+    Some((high << 32) | low)
+}
@@ -0,0 +1,74 @@
+pub mod header;
+pub mod implementation;
+pub mod node;
+
+pub use implementation::*;
+
+use crate::{mutex::Mutex, println};
+
+static DEVICE_TREE: Mutex<Option<DeviceTree>> = Mutex::new(None);
+
+/// Validates the device tree blob at [address] and makes it available through [instance].
+///
+/// The firmware passes this address to the kernel in `x0`, which is 0 if it didn't load one
+/// (e.g. QEMU without `-dtb`).
+pub fn initialize(address: usize) -> Result<(), FdtError> {
+    // Safety: The address came from the firmware, and is only trusted once the header is valid.
+    let device_tree = unsafe { DeviceTree::from_address(address)? };
+    *DEVICE_TREE.lock() = Some(device_tree);
+
+    Ok(())
+}
+
+/// Retrieves the [DeviceTree] passed to the kernel by the firmware.
+/// This is [None] if [initialize] hasn't been called, or if there was no valid device tree.
+pub fn instance() -> Option<DeviceTree> {
+    *DEVICE_TREE.lock()
+}
+
+/// Prints a summary of the [DeviceTree].
+pub fn report(device_tree: &DeviceTree) {
+    let header = device_tree.header();
+    println!(
+        "[angeldust::fdt] device tree at {:#010x} ({} bytes, version {}, boot cpu {})",
+        device_tree.address(),
+        header.total_size,
+        header.version,
+        header.boot_cpu_id
+    );
+
+    if let Some(model) = device_tree.model() {
+        println!("[angeldust::fdt] model: {}", model);
+    }
+
+    for compatible in device_tree.root().compatible() {
+        println!("[angeldust::fdt] compatible: {}", compatible);
+    }
+
+    for region in device_tree.memory() {
+        println!(
+            "[angeldust::fdt] memory: {:#010x} - {:#010x}",
+            region.address,
+            region.address + region.size
+        );
+    }
+
+    for region in device_tree.reserved_memory() {
+        println!(
+            "[angeldust::fdt] reserved: {:#010x} - {:#010x}",
+            region.address,
+            region.address + region.size
+        );
+    }
+
+    for range in device_tree.soc_ranges() {
+        println!(
+            "[angeldust::fdt] soc range: {:#010x} -> {:#010x} ({:#x} bytes)",
+            range.child_address, range.parent_address, range.size
+        );
+    }
+
+    if let Some(bootargs) = device_tree.bootargs() {
+        println!("[angeldust::fdt] bootargs: {}", bootargs);
+    }
+}
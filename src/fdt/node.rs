@@ -0,0 +1,446 @@
+use super::implementation::{read_u32, DeviceTree, FdtError};
+
+/// The tokens that make up the structure block.
+/// https://devicetree-specification.readthedocs.io/en/stable/flattened-format.html#lexical-structure
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+/// A single token from the structure block, with any [FDT_NOP] tokens skipped.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Token {
+    BeginNode(&'static str),
+    EndNode,
+    Property(Property),
+    End,
+}
+
+impl Token {
+    /// Reads the token at [offset] in the structure block of [tree].
+    /// Returns the token and the offset of the token after it.
+    pub(super) fn read(tree: &DeviceTree, mut offset: usize) -> Result<(Token, usize), FdtError> {
+        let structure = tree.structure();
+
+        loop {
+            let token = read_u32(structure, offset).ok_or(FdtError::UnexpectedEnd)?;
+
+            return match token {
+                FDT_BEGIN_NODE => {
+                    let name = read_string(structure, offset + 4)?;
+                    Ok((Token::BeginNode(name), align(offset + 4 + name.len() + 1)))
+                }
+
+                FDT_END_NODE => Ok((Token::EndNode, offset + 4)),
+
+                FDT_PROP => {
+                    let length = read_u32(structure, offset + 4).ok_or(FdtError::UnexpectedEnd)?;
+                    let name_offset =
+                        read_u32(structure, offset + 8).ok_or(FdtError::UnexpectedEnd)?;
+
+                    let start = offset + 12;
+                    let end = start + length as usize;
+                    let value = structure.get(start..end).ok_or(FdtError::UnexpectedEnd)?;
+
+                    let property = Property {
+                        name: tree.string(name_offset as usize)?,
+                        value,
+                    };
+
+                    Ok((Token::Property(property), align(end)))
+                }
+
+                FDT_NOP => {
+                    offset += 4;
+                    continue;
+                }
+
+                FDT_END => Ok((Token::End, offset + 4)),
+
+                _ => Err(FdtError::InvalidToken { offset, token }),
+            };
+        }
+    }
+}
+
+/// A node in the [DeviceTree].
+#[derive(Debug, Clone, Copy)]
+pub struct Node {
+    tree: DeviceTree,
+    name: &'static str,
+
+    /// The offset of the first token after this node's name in the structure block.
+    offset: usize,
+}
+
+/// A property of a [Node].
+#[derive(Debug, Clone, Copy)]
+pub struct Property {
+    pub name: &'static str,
+    pub value: &'static [u8],
+}
+
+/// A `reg` entry, or a memory reservation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub address: u64,
+    pub size: u64,
+}
+
+/// A `ranges` entry, which maps addresses on a child bus to addresses on its parent bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub child_address: u64,
+    pub parent_address: u64,
+    pub size: u64,
+}
+
+impl Node {
+    /// The `#address-cells` and `#size-cells` to use if a node doesn't specify them.
+    pub const DEFAULT_ADDRESS_CELLS: u32 = 2;
+    pub const DEFAULT_SIZE_CELLS: u32 = 1;
+
+    pub(super) const fn new(tree: DeviceTree, name: &'static str, offset: usize) -> Node {
+        Node { tree, name, offset }
+    }
+
+    /// The full name of this node, including its unit address (e.g. `memory@0`).
+    /// The root node's name is empty.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The name of this node without its unit address (e.g. `memory`).
+    pub fn base_name(&self) -> &'static str {
+        self.name.split('@').next().unwrap_or(self.name)
+    }
+
+    /// Whether or not this node is called [name].
+    /// If [name] has no unit address, any unit address will match.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name == name || (!name.contains('@') && self.base_name() == name)
+    }
+
+    pub fn properties(&self) -> Properties {
+        Properties {
+            tree: self.tree,
+            offset: Some(self.offset),
+        }
+    }
+
+    pub fn property(&self, name: &str) -> Option<Property> {
+        self.properties().find(|it| it.name == name)
+    }
+
+    /// Iterates over the direct children of this node.
+    pub fn children(&self) -> Children {
+        Children {
+            tree: self.tree,
+            offset: Some(self.offset),
+        }
+    }
+
+    /// Finds a direct child of this node, see [Node::is_named].
+    pub fn child(&self, name: &str) -> Option<Node> {
+        self.children().find(|it| it.is_named(name))
+    }
+
+    /// Iterates over the strings in this node's `compatible` property, from most to least
+    /// specific.
+    pub fn compatible(&self) -> StringList {
+        StringList::new(self.property("compatible").map_or(&[], |it| it.value))
+    }
+
+    /// Whether or not [compatible] is one of the strings in this node's `compatible` property.
+    pub fn is_compatible(&self, compatible: &str) -> bool {
+        self.compatible().any(|it| it == compatible)
+    }
+
+    /// The number of cells used to encode an address in this node's children.
+    pub fn address_cells(&self) -> u32 {
+        self.property("#address-cells")
+            .and_then(|it| it.as_u32())
+            .unwrap_or(Self::DEFAULT_ADDRESS_CELLS)
+    }
+
+    /// The number of cells used to encode a size in this node's children.
+    pub fn size_cells(&self) -> u32 {
+        self.property("#size-cells")
+            .and_then(|it| it.as_u32())
+            .unwrap_or(Self::DEFAULT_SIZE_CELLS)
+    }
+
+    /// Iterates over this node's `reg` property, which is encoded using its [parent]'s cells.
+    pub fn reg(&self, parent: &Node) -> Regions {
+        Regions {
+            value: self.property("reg").map_or(&[], |it| it.value),
+            address_cells: parent.address_cells(),
+            size_cells: parent.size_cells(),
+        }
+    }
+
+    /// Iterates over this node's `ranges` property.
+    /// An empty `ranges` property means that addresses are identity mapped, this yields nothing.
+    pub fn ranges(&self, parent: &Node) -> Ranges {
+        Ranges {
+            value: self.property("ranges").map_or(&[], |it| it.value),
+            child_address_cells: self.address_cells(),
+            parent_address_cells: parent.address_cells(),
+            size_cells: self.size_cells(),
+        }
+    }
+}
+
+impl Property {
+    /// Interprets this property as a single big-endian u32.
+    pub fn as_u32(&self) -> Option<u32> {
+        match self.value.len() {
+            4 => read_u32(self.value, 0),
+            _ => None,
+        }
+    }
+
+    /// Interprets this property as one or two cells, as used by `linux,initrd-start`.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.value.len() {
+            4 | 8 => read_cells(self.value, 0, (self.value.len() / 4) as u32),
+            _ => None,
+        }
+    }
+
+    /// Interprets this property as a single null-terminated string.
+    pub fn as_str(&self) -> Option<&'static str> {
+        StringList::new(self.value).next()
+    }
+}
+
+/// Iterates over the properties of a [Node].
+pub struct Properties {
+    tree: DeviceTree,
+    offset: Option<usize>,
+}
+
+impl Iterator for Properties {
+    type Item = Property;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (token, next) = Token::read(&self.tree, self.offset?).ok()?;
+
+        match token {
+            Token::Property(property) => {
+                self.offset = Some(next);
+                Some(property)
+            }
+
+            // Properties always come before child nodes.
+            _ => {
+                self.offset = None;
+                None
+            }
+        }
+    }
+}
+
+/// Iterates over the direct children of a [Node].
+pub struct Children {
+    tree: DeviceTree,
+    offset: Option<usize>,
+}
+
+impl Iterator for Children {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut offset = self.offset?;
+
+        loop {
+            let Ok((token, next)) = Token::read(&self.tree, offset) else {
+                self.offset = None;
+                return None;
+            };
+
+            match token {
+                Token::Property(_) => offset = next,
+
+                Token::BeginNode(name) => {
+                    self.offset = skip_node(&self.tree, next);
+                    return Some(Node::new(self.tree, name, next));
+                }
+
+                Token::EndNode | Token::End => {
+                    self.offset = None;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Iterates over every node in a [DeviceTree], in the order that they appear in the blob.
+pub struct Nodes {
+    pub(super) tree: DeviceTree,
+    pub(super) offset: Option<usize>,
+}
+
+impl Iterator for Nodes {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut offset = self.offset?;
+
+        loop {
+            let Ok((token, next)) = Token::read(&self.tree, offset) else {
+                self.offset = None;
+                return None;
+            };
+
+            match token {
+                Token::BeginNode(name) => {
+                    self.offset = Some(next);
+                    return Some(Node::new(self.tree, name, next));
+                }
+
+                Token::Property(_) | Token::EndNode => offset = next,
+
+                Token::End => {
+                    self.offset = None;
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Iterates over the regions in a `reg` property.
+pub struct Regions {
+    value: &'static [u8],
+    address_cells: u32,
+    size_cells: u32,
+}
+
+impl Iterator for Regions {
+    type Item = Region;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let length = (self.address_cells + self.size_cells) as usize * 4;
+        if length == 0 {
+            return None;
+        }
+
+        let address = read_cells(self.value, 0, self.address_cells)?;
+        let size = read_cells(self.value, self.address_cells, self.size_cells)?;
+        self.value = self.value.get(length..)?;
+
+        Some(Region { address, size })
+    }
+}
+
+/// Iterates over the entries in a `ranges` property.
+pub struct Ranges {
+    value: &'static [u8],
+    child_address_cells: u32,
+    parent_address_cells: u32,
+    size_cells: u32,
+}
+
+impl Iterator for Ranges {
+    type Item = Range;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let length =
+            (self.child_address_cells + self.parent_address_cells + self.size_cells) as usize * 4;
+        if length == 0 {
+            return None;
+        }
+
+        let child_address = read_cells(self.value, 0, self.child_address_cells)?;
+        let parent_address = read_cells(
+            self.value,
+            self.child_address_cells,
+            self.parent_address_cells,
+        )?;
+        let size = read_cells(
+            self.value,
+            self.child_address_cells + self.parent_address_cells,
+            self.size_cells,
+        )?;
+        self.value = self.value.get(length..)?;
+
+        Some(Range {
+            child_address,
+            parent_address,
+            size,
+        })
+    }
+}
+
+/// Iterates over a list of null-terminated strings, like the `compatible` property.
+pub struct StringList {
+    value: &'static [u8],
+}
+
+impl StringList {
+    pub const fn new(value: &'static [u8]) -> StringList {
+        StringList { value }
+    }
+}
+
+impl Iterator for StringList {
+    type Item = &'static str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.value.iter().position(|it| *it == 0)?;
+        let string = core::str::from_utf8(&self.value[..end]).ok();
+        self.value = &self.value[end + 1..];
+
+        string
+    }
+}
+
+/// Skips over the rest of a node, returning the offset of the token after its [FDT_END_NODE].
+/// [offset] must be the offset of the first token after the node's name.
+fn skip_node(tree: &DeviceTree, mut offset: usize) -> Option<usize> {
+    let mut depth = 0;
+
+    loop {
+        let (token, next) = Token::read(tree, offset).ok()?;
+        offset = next;
+
+        match token {
+            Token::BeginNode(_) => depth += 1,
+            Token::EndNode if depth == 0 => return Some(offset),
+            Token::EndNode => depth -= 1,
+            Token::Property(_) => {}
+            Token::End => return None,
+        }
+    }
+}
+
+/// Reads a number which is encoded as [count] big-endian cells, starting at cell [index].
+/// Numbers larger than two cells are truncated to their lowest 64 bits.
+fn read_cells(value: &[u8], index: u32, count: u32) -> Option<u64> {
+    let mut result = 0u64;
+
+    for cell in index..index + count {
+        result = (result << 32) | read_u32(value, cell as usize * 4)? as u64;
+    }
+
+    Some(result)
+}
+
+/// Reads the null-terminated string at [offset] in [bytes].
+pub(super) fn read_string(bytes: &'static [u8], offset: usize) -> Result<&'static str, FdtError> {
+    let bytes = bytes.get(offset..).ok_or(FdtError::UnexpectedEnd)?;
+    let end = bytes
+        .iter()
+        .position(|it| *it == 0)
+        .ok_or(FdtError::InvalidString(offset))?;
+
+    core::str::from_utf8(&bytes[..end]).map_err(|_| FdtError::InvalidString(offset))
+}
+
+/// Rounds [offset] up to the next token boundary.
+const fn align(offset: usize) -> usize {
+    (offset + 3) & !3
+}
@@ -0,0 +1,186 @@
+use super::{read_u16, read_u32, FatError};
+
+/// The FAT variant, which is decided by the amount of clusters alone.
+/// 3.5. Determination of FAT type: https://academy.cba.mit.edu/classes/networking_communications/SD/FAT.pdf
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatKind {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+/// The volume's layout, from the BIOS parameter block in its first sector.
+/// 3.1. Boot Sector and BPB: https://academy.cba.mit.edu/classes/networking_communications/SD/FAT.pdf
+#[derive(Debug, Clone, Copy)]
+pub struct BootSector {
+    pub kind: FatKind,
+    pub bytes_per_sector: u32,
+    pub sectors_per_cluster: u32,
+    pub reserved_sectors: u32,
+    pub fat_count: u32,
+
+    /// The size of each FAT in sectors.
+    pub fat_size: u32,
+
+    /// The amount of entries in the fixed root directory of FAT12 and FAT16 volumes.
+    pub root_entry_count: u32,
+
+    /// The first cluster of the root directory on FAT32 volumes.
+    pub root_cluster: u32,
+
+    /// The sector that holds the FSInfo structure on FAT32 volumes, or 0 if there isn't one.
+    pub fs_info_sector: u32,
+
+    /// The only FAT that is used if mirroring is disabled on a FAT32 volume.
+    pub active_fat: Option<u32>,
+
+    pub label: [u8; 11],
+
+    pub first_data_sector: u32,
+    pub cluster_count: u32,
+}
+
+impl BootSector {
+    /// The smallest and largest sector sizes that FAT allows.
+    pub const MIN_SECTOR_SIZE: u32 = 512;
+    pub const MAX_SECTOR_SIZE: u32 = 4096;
+
+    /// The first cluster that holds data, clusters 0 and 1 are reserved.
+    pub const FIRST_CLUSTER: u32 = 2;
+
+    const SIGNATURE: [u8; 2] = [0x55, 0xAA];
+
+    /// BS_BootSig: Set if the volume ID and label follow it.
+    const EXTENDED_BOOT_SIGNATURE: u8 = 0x29;
+
+    /// BPB_ExtFlags: Set if only [BootSector::active_fat] is used.
+    const MIRRORING_DISABLED: u16 = 1 << 7;
+
+    /// Parses and validates the boot sector in [sector].
+    pub fn parse(sector: &[u8]) -> Result<BootSector, FatError> {
+        if sector[510..512] != Self::SIGNATURE || !matches!(sector[0], 0xEB | 0xE9) {
+            return Err(FatError::InvalidBootSector);
+        }
+
+        let bytes_per_sector = read_u16(sector, 11) as u32;
+        let sectors_per_cluster = sector[13] as u32;
+        let reserved_sectors = read_u16(sector, 14) as u32;
+        let fat_count = sector[16] as u32;
+        let root_entry_count = read_u16(sector, 17) as u32;
+
+        let total_sectors = match read_u16(sector, 19) {
+            0 => read_u32(sector, 32),
+            total => total as u32,
+        };
+
+        let fat_size = match read_u16(sector, 22) {
+            0 => read_u32(sector, 36),
+            size => size as u32,
+        };
+
+        if !bytes_per_sector.is_power_of_two()
+            || !(Self::MIN_SECTOR_SIZE..=Self::MAX_SECTOR_SIZE).contains(&bytes_per_sector)
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved_sectors == 0
+            || fat_count == 0
+            || fat_size == 0
+        {
+            return Err(FatError::InvalidBootSector);
+        }
+
+        let root_directory_sectors = (root_entry_count * 32).div_ceil(bytes_per_sector);
+        let first_data_sector = reserved_sectors + fat_count * fat_size + root_directory_sectors;
+        let data_sectors = total_sectors
+            .checked_sub(first_data_sector)
+            .ok_or(FatError::InvalidBootSector)?;
+
+        let cluster_count = data_sectors / sectors_per_cluster;
+        let kind = match cluster_count {
+            0..4085 => FatKind::Fat12,
+            4085..65525 => FatKind::Fat16,
+            _ => FatKind::Fat32,
+        };
+
+        // FAT32 volumes keep their root directory in a cluster chain.
+        if (kind == FatKind::Fat32) != (root_entry_count == 0) {
+            return Err(FatError::InvalidBootSector);
+        }
+
+        let (root_cluster, fs_info_sector, active_fat, label_offset) = match kind {
+            FatKind::Fat32 => {
+                let flags = read_u16(sector, 40);
+                let active_fat = (flags & Self::MIRRORING_DISABLED != 0)
+                    .then_some((flags & 0xF) as u32)
+                    .filter(|it| *it < fat_count);
+
+                (
+                    read_u32(sector, 44),
+                    read_u16(sector, 48) as u32,
+                    active_fat,
+                    66,
+                )
+            }
+            _ => (0, 0, None, 38),
+        };
+
+        // The label is only present in the extended boot record.
+        let mut label = *b"NO NAME    ";
+        if sector[label_offset] == Self::EXTENDED_BOOT_SIGNATURE {
+            label.copy_from_slice(&sector[label_offset + 5..label_offset + 16]);
+        }
+
+        Ok(BootSector {
+            kind,
+            bytes_per_sector,
+            sectors_per_cluster,
+            reserved_sectors,
+            fat_count,
+            fat_size,
+            root_entry_count,
+            root_cluster,
+            fs_info_sector,
+            active_fat,
+            label,
+            first_data_sector,
+            cluster_count,
+        })
+    }
+
+    /// The size of a cluster in bytes.
+    pub fn cluster_size(&self) -> u64 {
+        (self.bytes_per_sector * self.sectors_per_cluster) as u64
+    }
+
+    /// The last cluster that holds data.
+    pub fn last_cluster(&self) -> u32 {
+        self.cluster_count + Self::FIRST_CLUSTER - 1
+    }
+
+    /// Whether [cluster] is one that holds data.
+    pub fn is_valid_cluster(&self, cluster: u32) -> bool {
+        (Self::FIRST_CLUSTER..=self.last_cluster()).contains(&cluster)
+    }
+
+    /// The byte address of [cluster] within the volume.
+    pub fn cluster_address(&self, cluster: u32) -> u64 {
+        let sector = self.first_data_sector as u64
+            + (cluster - Self::FIRST_CLUSTER) as u64 * self.sectors_per_cluster as u64;
+
+        sector * self.bytes_per_sector as u64
+    }
+
+    /// The byte address of FAT number [index] within the volume.
+    pub fn fat_address(&self, index: u32) -> u64 {
+        (self.reserved_sectors + index * self.fat_size) as u64 * self.bytes_per_sector as u64
+    }
+
+    /// The byte address of the fixed root directory of FAT12 and FAT16 volumes.
+    pub fn root_directory_address(&self) -> u64 {
+        self.fat_address(self.fat_count)
+    }
+
+    /// The label, without its padding.
+    pub fn label(&self) -> &str {
+        core::str::from_utf8(&self.label).unwrap_or("?").trim_end()
+    }
+}
@@ -0,0 +1,1033 @@
+use super::{read_u16, read_u32, FatError, FatFilesystem, FatKind};
+use crate::io::block::BlockDevice;
+use bitflags::bitflags;
+use core::fmt::{self, Display, Formatter};
+
+/// The size of a single directory entry, long name entries included.
+const ENTRY_SIZE: u64 = 32;
+
+/// The most entries that a directory may hold, as `.` and `..` are found by their slot.
+const MAX_SLOTS: u32 = 65536;
+
+/// The first byte of an entry that is free, and of the entry after the last one that is used.
+const DELETED: u8 = 0xE5;
+const END_OF_DIRECTORY: u8 = 0x00;
+
+/// The first byte of a short name that starts with 0xE5, which would otherwise mark it deleted.
+const ESCAPED_DELETED: u8 = 0x05;
+
+/// The longest name that a long name entry sequence can hold, in UTF-16 units.
+pub const MAX_NAME_LENGTH: usize = 255;
+
+/// The amount of UTF-16 units in a single long name entry.
+const UNITS_PER_ENTRY: usize = 13;
+
+/// The offsets of the three runs of UTF-16 units in a long name entry.
+const LONG_NAME_RUNS: [(usize, usize); 3] = [(1, 5), (14, 6), (28, 2)];
+
+/// Long name entries: Set in the order field of the last entry, which is stored first.
+const LAST_LONG_ENTRY: u8 = 0x40;
+
+/// DIR_NTRes: Set if the base or extension of the short name should be shown in lowercase.
+const LOWERCASE_BASE: u8 = 0x08;
+const LOWERCASE_EXTENSION: u8 = 0x10;
+
+/// The characters that aren't allowed in a short name, besides control characters.
+const INVALID_SHORT_CHARACTERS: &[u8] = b"\"*+,./:;<=>?[\\]|";
+
+/// The characters that aren't allowed in a long name, besides control characters.
+const INVALID_LONG_CHARACTERS: &[u16] = &[
+    b'"' as u16,
+    b'*' as u16,
+    b'/' as u16,
+    b':' as u16,
+    b'<' as u16,
+    b'>' as u16,
+    b'?' as u16,
+    b'\\' as u16,
+    b'|' as u16,
+];
+
+/// New entries are stamped with 1980-01-01 00:00:00, as there is no real time clock.
+const EPOCH: Timestamp = Timestamp {
+    date: (1 << 5) | 1,
+    time: 0,
+};
+
+bitflags! {
+    /// DIR_Attr: The attributes of a directory entry.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Attributes: u8 {
+        const ReadOnly = 1 << 0;
+        const Hidden = 1 << 1;
+        const System = 1 << 2;
+        const VolumeId = 1 << 3;
+        const Directory = 1 << 4;
+
+        /// Set whenever the file is changed, so backup tools know to copy it.
+        const Archive = 1 << 5;
+
+        /// The combination that marks a long name entry, which no short entry can have.
+        const LongName = 0x0F;
+    }
+}
+
+/// A directory, which is either the fixed root directory of a FAT12 or FAT16 volume, or a chain
+/// of clusters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Directory {
+    FixedRoot,
+    Cluster(u32),
+}
+
+/// The date and time of a directory entry, as stored on the volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp {
+    pub date: u16,
+    pub time: u16,
+}
+
+/// The name of a directory entry, which is its long name if it has one.
+#[derive(Clone, Copy)]
+pub struct FileName {
+    units: [u16; MAX_NAME_LENGTH],
+    length: usize,
+}
+
+/// Where a [DirEntry] is stored, which is needed to change or remove it.
+#[derive(Debug, Clone, Copy)]
+pub struct EntryLocation {
+    pub directory: Directory,
+
+    /// The slot of the short entry within [EntryLocation::directory].
+    pub slot: u32,
+
+    /// The amount of slots used by the entry, including its long name entries.
+    pub slots: u32,
+
+    /// The byte address of the short entry within the volume.
+    pub address: u64,
+}
+
+/// A single file or directory.
+#[derive(Debug, Clone, Copy)]
+pub struct DirEntry {
+    pub name: FileName,
+    pub short_name: [u8; 11],
+    pub attributes: Attributes,
+
+    /// The first cluster of the entry's data, or 0 if it doesn't have any.
+    pub first_cluster: u32,
+
+    pub size: u32,
+
+    pub modified: Timestamp,
+
+    /// Where the entry is stored, or [None] for the root directory which has no entry.
+    pub location: Option<EntryLocation>,
+}
+
+/// Walks the slots of a [Directory], following its cluster chain.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct SlotCursor {
+    directory: Directory,
+    slot: u32,
+
+    /// The cluster that was reached last, and its index within the chain.
+    cluster: u32,
+    cluster_index: u32,
+}
+
+/// Iterates over the entries in a directory, except for the `.` and `..` entries.
+pub struct Entries<'a, D: BlockDevice> {
+    filesystem: &'a FatFilesystem<D>,
+    cursor: SlotCursor,
+    finished: bool,
+}
+
+impl<D: BlockDevice> FatFilesystem<D> {
+    /// Returns the root directory.
+    pub fn root(&self) -> Directory {
+        match self.boot_sector.kind {
+            FatKind::Fat32 => Directory::Cluster(self.boot_sector.root_cluster),
+            _ => Directory::FixedRoot,
+        }
+    }
+
+    /// Returns an iterator over the entries in [directory].
+    pub fn entries(&self, directory: Directory) -> Entries<'_, D> {
+        Entries {
+            filesystem: self,
+            cursor: SlotCursor::new(directory),
+            finished: false,
+        }
+    }
+
+    /// Returns an iterator over the entries in [directory], starting at [slot].
+    pub(super) fn entries_from(&self, directory: Directory, slot: u32) -> Entries<'_, D> {
+        let mut cursor = SlotCursor::new(directory);
+        cursor.slot = slot;
+
+        Entries {
+            filesystem: self,
+            cursor,
+            finished: false,
+        }
+    }
+
+    /// Returns the entry whose short entry is in [slot] of [directory].
+    /// Its long name isn't read, so it is called by its short name, and its location only
+    /// covers the short entry.
+    pub(super) fn entry_at(&self, directory: Directory, slot: u32) -> Result<DirEntry, FatError> {
+        let mut cursor = SlotCursor::new(directory);
+        cursor.slot = slot;
+        let address = self.slot_address(&mut cursor)?.ok_or(FatError::NotFound)?;
+
+        let mut raw = [0u8; ENTRY_SIZE as usize];
+        self.read_at(address, &mut raw)?;
+
+        let attributes = Attributes::from_bits_retain(raw[11]);
+        if matches!(raw[0], END_OF_DIRECTORY | DELETED) || attributes.contains(Attributes::VolumeId)
+        {
+            return Err(FatError::NotFound);
+        }
+
+        let location = EntryLocation {
+            directory,
+            slot,
+            slots: 1,
+            address,
+        };
+
+        Ok(parse_short_entry(
+            self.boot_sector.kind,
+            &raw,
+            None,
+            location,
+        ))
+    }
+
+    /// Returns the directory that [entry] refers to.
+    pub fn directory(&self, entry: &DirEntry) -> Result<Directory, FatError> {
+        if !entry.is_directory() {
+            return Err(FatError::NotADirectory);
+        }
+
+        // `..` entries that refer to the root directory use cluster 0, even on FAT32.
+        Ok(match entry.first_cluster {
+            0 => self.root(),
+            cluster => Directory::Cluster(cluster),
+        })
+    }
+
+    /// Finds the entry at [path], which is relative to the root directory and separated by `/`.
+    /// Names are compared without regard to ASCII case, against both the long and short name.
+    pub fn find(&self, path: &str) -> Result<DirEntry, FatError> {
+        let mut entry = self.root_entry();
+
+        for name in path.split('/').filter(|it| !it.is_empty()) {
+            let directory = self.directory(&entry)?;
+            entry = self.find_in(directory, name)?.ok_or(FatError::NotFound)?;
+        }
+
+        Ok(entry)
+    }
+
+    /// Creates an empty file at [path], whose parent directory must already exist.
+    pub fn create_file(&mut self, path: &str) -> Result<DirEntry, FatError> {
+        let (parent, name) = self.split_path(path)?;
+        self.create_entry(parent, name, Attributes::Archive, 0)
+    }
+
+    /// Creates an empty directory at [path], whose parent directory must already exist.
+    pub fn create_directory(&mut self, path: &str) -> Result<DirEntry, FatError> {
+        let (parent, name) = self.split_path(path)?;
+        self.create_directory_in(parent, name)
+    }
+
+    /// Creates an empty directory called [name] in [parent].
+    pub(super) fn create_directory_in(
+        &mut self,
+        parent: Directory,
+        name: &str,
+    ) -> Result<DirEntry, FatError> {
+        FileName::new(name)?;
+        if self.find_in(parent, name)?.is_some() {
+            return Err(FatError::AlreadyExists);
+        }
+
+        // The directory's cluster is filled in before it is linked into its parent, so a crash
+        // in between only leaks the cluster.
+        let cluster = self.allocate_cluster(None)?;
+        let address = self.boot_sector.cluster_address(cluster);
+        self.zero_at(address, self.boot_sector.cluster_size())?;
+
+        let parent_cluster = match parent {
+            Directory::FixedRoot => 0,
+            Directory::Cluster(cluster) if cluster == self.boot_sector.root_cluster => 0,
+            Directory::Cluster(cluster) => cluster,
+        };
+
+        let mut dot = [b' '; 11];
+        dot[0] = b'.';
+        self.write_at(
+            address,
+            &short_entry(&dot, 0, Attributes::Directory, cluster, 0),
+        )?;
+
+        let mut dot_dot = dot;
+        dot_dot[1] = b'.';
+        let entry = short_entry(&dot_dot, 0, Attributes::Directory, parent_cluster, 0);
+        self.write_at(address + ENTRY_SIZE, &entry)?;
+
+        self.create_entry(parent, name, Attributes::Directory, cluster)
+    }
+
+    /// Removes the file or empty directory at [path].
+    ///
+    /// The entry is removed before its clusters are freed, so a crash in between only leaks
+    /// them, rather than leaving them in use by both the entry and a new file.
+    pub fn remove(&mut self, path: &str) -> Result<(), FatError> {
+        let entry = self.find(path)?;
+        self.remove_entry(&entry)
+    }
+
+    /// Removes [entry], which must have been found by its full name so that its long name
+    /// entries are removed along with it.
+    pub(super) fn remove_entry(&mut self, entry: &DirEntry) -> Result<(), FatError> {
+        let location = entry.location.ok_or(FatError::InvalidName)?;
+
+        if entry.is_directory() {
+            let directory = self.directory(entry)?;
+            if self.entries(directory).next().transpose()?.is_some() {
+                return Err(FatError::DirectoryNotEmpty);
+            }
+        }
+
+        self.mark_dirty()?;
+
+        let mut cursor = SlotCursor::new(location.directory);
+        for slot in location.slot + 1 - location.slots..=location.slot {
+            cursor.slot = slot;
+            let address = self
+                .slot_address(&mut cursor)?
+                .ok_or(FatError::InvalidChain(cursor.cluster))?;
+
+            self.write_at(address, &[DELETED])?;
+        }
+
+        if entry.first_cluster != 0 {
+            self.free_chain(entry.first_cluster)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the size, first cluster, attributes and modification time of [entry] back to the
+    /// volume.
+    pub(super) fn update_entry(&mut self, entry: &DirEntry) -> Result<(), FatError> {
+        let location = entry.location.ok_or(FatError::IsADirectory)?;
+        self.mark_dirty()?;
+
+        let mut raw = [0u8; ENTRY_SIZE as usize];
+        self.read_at(location.address, &mut raw)?;
+        raw[11] = entry.attributes.bits();
+        raw[22..24].copy_from_slice(&entry.modified.time.to_le_bytes());
+        raw[24..26].copy_from_slice(&entry.modified.date.to_le_bytes());
+        raw[20..22].copy_from_slice(&((entry.first_cluster >> 16) as u16).to_le_bytes());
+        raw[26..28].copy_from_slice(&(entry.first_cluster as u16).to_le_bytes());
+        raw[28..32].copy_from_slice(&entry.size.to_le_bytes());
+
+        self.write_at(location.address, &raw)
+    }
+
+    /// Returns the byte address of the cursor's slot, or [None] if it is past the end of the
+    /// directory.
+    pub(super) fn slot_address(&self, cursor: &mut SlotCursor) -> Result<Option<u64>, FatError> {
+        let first = match cursor.directory {
+            Directory::FixedRoot => {
+                if cursor.slot >= self.boot_sector.root_entry_count {
+                    return Ok(None);
+                }
+
+                let offset = cursor.slot as u64 * ENTRY_SIZE;
+                return Ok(Some(self.boot_sector.root_directory_address() + offset));
+            }
+            Directory::Cluster(first) => first,
+        };
+
+        if cursor.slot >= MAX_SLOTS {
+            return Ok(None);
+        }
+
+        let slots_per_cluster = (self.boot_sector.cluster_size() / ENTRY_SIZE) as u32;
+        let index = cursor.slot / slots_per_cluster;
+        if index < cursor.cluster_index {
+            cursor.cluster = first;
+            cursor.cluster_index = 0;
+        }
+
+        while cursor.cluster_index < index {
+            match self.next_cluster(cursor.cluster)? {
+                Some(next) => cursor.cluster = next,
+                None => return Ok(None),
+            }
+
+            cursor.cluster_index += 1;
+        }
+
+        let offset = (cursor.slot % slots_per_cluster) as u64 * ENTRY_SIZE;
+        Ok(Some(
+            self.boot_sector.cluster_address(cursor.cluster) + offset,
+        ))
+    }
+
+    /// Returns the entry for the root directory, which isn't stored anywhere.
+    pub(super) fn root_entry(&self) -> DirEntry {
+        let first_cluster = match self.root() {
+            Directory::FixedRoot => 0,
+            Directory::Cluster(cluster) => cluster,
+        };
+
+        DirEntry {
+            name: FileName::EMPTY,
+            short_name: [b' '; 11],
+            attributes: Attributes::Directory,
+            first_cluster,
+            size: 0,
+            modified: EPOCH,
+            location: None,
+        }
+    }
+
+    /// Finds the entry called [name] in [directory].
+    pub(super) fn find_in(
+        &self,
+        directory: Directory,
+        name: &str,
+    ) -> Result<Option<DirEntry>, FatError> {
+        for entry in self.entries(directory) {
+            let entry = entry?;
+            if entry.name.eq_ignore_case(name) || short_name_matches(&entry.short_name, name) {
+                return Ok(Some(entry));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Splits [path] into its parent directory, which must exist, and its last name.
+    fn split_path<'a>(&self, path: &'a str) -> Result<(Directory, &'a str), FatError> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+
+        let parent = self.find(parent)?;
+        Ok((self.directory(&parent)?, name))
+    }
+
+    /// Adds an entry called [name] to [directory], with a long name if it needs one.
+    pub(super) fn create_entry(
+        &mut self,
+        directory: Directory,
+        name: &str,
+        attributes: Attributes,
+        first_cluster: u32,
+    ) -> Result<DirEntry, FatError> {
+        let long_name = FileName::new(name)?;
+        if self.find_in(directory, name)?.is_some() {
+            return Err(FatError::AlreadyExists);
+        }
+
+        // Names that fit in 8.3 don't need a long name, as long as each part is in a single case.
+        let (short_name, case, long_slots) = match lowercase_short_name(name) {
+            Some((short_name, case)) => (short_name, case, 0),
+            None => (
+                self.generate_short_name(directory, name)?,
+                0,
+                long_name.length.div_ceil(UNITS_PER_ENTRY) as u32,
+            ),
+        };
+
+        let slots = long_slots + 1;
+        let first_slot = self.allocate_slots(directory, slots)?;
+        let checksum = checksum(&short_name);
+
+        // The long name entries are stored before the short entry, last part first.
+        let mut cursor = SlotCursor::new(directory);
+        for index in 0..long_slots {
+            let order = long_slots - index;
+            let mut raw = [0u8; ENTRY_SIZE as usize];
+            raw[0] = order as u8 | if index == 0 { LAST_LONG_ENTRY } else { 0 };
+            raw[11] = Attributes::LongName.bits();
+            raw[13] = checksum;
+
+            let start = (order as usize - 1) * UNITS_PER_ENTRY;
+            let mut units =
+                (start..start + UNITS_PER_ENTRY).map(|it| match it.cmp(&long_name.length) {
+                    core::cmp::Ordering::Less => long_name.units[it],
+                    core::cmp::Ordering::Equal => 0x0000,
+                    core::cmp::Ordering::Greater => 0xFFFF,
+                });
+
+            for (offset, count) in LONG_NAME_RUNS {
+                for unit in 0..count {
+                    let value = units.next().unwrap_or(0xFFFF);
+                    raw[offset + unit * 2..][..2].copy_from_slice(&value.to_le_bytes());
+                }
+            }
+
+            cursor.slot = first_slot + index;
+            let address = self
+                .slot_address(&mut cursor)?
+                .ok_or(FatError::DirectoryFull)?;
+            self.write_at(address, &raw)?;
+        }
+
+        cursor.slot = first_slot + long_slots;
+        let address = self
+            .slot_address(&mut cursor)?
+            .ok_or(FatError::DirectoryFull)?;
+        let raw = short_entry(&short_name, case, attributes, first_cluster, 0);
+        self.write_at(address, &raw)?;
+
+        Ok(DirEntry {
+            name: long_name,
+            short_name,
+            attributes,
+            first_cluster,
+            size: 0,
+            modified: EPOCH,
+            location: Some(EntryLocation {
+                directory,
+                slot: cursor.slot,
+                slots,
+                address,
+            }),
+        })
+    }
+
+    /// Finds [count] consecutive free slots in [directory], growing it if needed, and returns
+    /// the first of them.
+    fn allocate_slots(&mut self, directory: Directory, count: u32) -> Result<u32, FatError> {
+        self.mark_dirty()?;
+
+        let mut cursor = SlotCursor::new(directory);
+        let mut run_start = 0;
+        let mut run_length = 0;
+
+        loop {
+            let address = match self.slot_address(&mut cursor)? {
+                Some(address) => address,
+                None => {
+                    if matches!(directory, Directory::FixedRoot) || cursor.slot >= MAX_SLOTS {
+                        return Err(FatError::DirectoryFull);
+                    }
+
+                    // The new cluster has to be zeroed, as a zero byte marks the end of the
+                    // directory.
+                    let cluster = self.allocate_cluster(Some(cursor.cluster))?;
+                    let address = self.boot_sector.cluster_address(cluster);
+                    self.zero_at(address, self.boot_sector.cluster_size())?;
+                    continue;
+                }
+            };
+
+            let mut first = [0u8];
+            self.read_at(address, &mut first)?;
+
+            if first[0] == DELETED || first[0] == END_OF_DIRECTORY {
+                if run_length == 0 {
+                    run_start = cursor.slot;
+                }
+
+                run_length += 1;
+                if run_length == count {
+                    return Ok(run_start);
+                }
+            } else {
+                run_length = 0;
+            }
+
+            cursor.slot += 1;
+        }
+    }
+
+    /// Generates a unique `BASIS~N` short name for [name] in [directory].
+    fn generate_short_name(&self, directory: Directory, name: &str) -> Result<[u8; 11], FatError> {
+        let (base, extension) = match name.rsplit_once('.') {
+            Some((base, extension)) if !base.trim_start_matches('.').is_empty() => {
+                (base, extension)
+            }
+            _ => (name, ""),
+        };
+
+        let mut basis = [b' '; 11];
+        let mut base_length = 0;
+        for byte in base.bytes().filter_map(short_character) {
+            if base_length == 8 {
+                break;
+            }
+
+            basis[base_length] = byte;
+            base_length += 1;
+        }
+
+        for (slot, byte) in basis[8..]
+            .iter_mut()
+            .zip(extension.bytes().filter_map(short_character))
+        {
+            *slot = byte;
+        }
+
+        for number in 1..1_000_000u32 {
+            let mut suffix = [0u8; 7];
+            let digits = write_decimal(&mut suffix[1..], number);
+            suffix[0] = b'~';
+            let suffix = &suffix[..digits + 1];
+
+            let mut candidate = basis;
+            let start = base_length.min(8 - suffix.len());
+            candidate[start..start + suffix.len()].copy_from_slice(suffix);
+            candidate[start + suffix.len()..8].fill(b' ');
+
+            let mut taken = false;
+            for entry in self.entries(directory) {
+                if entry?.short_name == candidate {
+                    taken = true;
+                    break;
+                }
+            }
+
+            if !taken {
+                return Ok(candidate);
+            }
+        }
+
+        Err(FatError::DirectoryFull)
+    }
+}
+
+impl SlotCursor {
+    pub(super) fn new(directory: Directory) -> SlotCursor {
+        let cluster = match directory {
+            Directory::FixedRoot => 0,
+            Directory::Cluster(cluster) => cluster,
+        };
+
+        SlotCursor {
+            directory,
+            slot: 0,
+            cluster,
+            cluster_index: 0,
+        }
+    }
+}
+
+impl<D: BlockDevice> Iterator for Entries<'_, D> {
+    type Item = Result<DirEntry, FatError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let result = self.next_entry();
+        if !matches!(result, Ok(Some(_))) {
+            self.finished = true;
+        }
+
+        result.transpose()
+    }
+}
+
+impl<D: BlockDevice> Entries<'_, D> {
+    /// Reads slots until a short entry is found, assembling its long name on the way.
+    fn next_entry(&mut self) -> Result<Option<DirEntry>, FatError> {
+        let mut long_name = FileName::EMPTY;
+
+        // The checksum and order of the long name entry that was read last, if the sequence is
+        // still intact.
+        let mut long_state: Option<(u8, u8)> = None;
+
+        loop {
+            let Some(address) = self.filesystem.slot_address(&mut self.cursor)? else {
+                return Ok(None);
+            };
+
+            let slot = self.cursor.slot;
+            self.cursor.slot += 1;
+
+            let mut raw = [0u8; ENTRY_SIZE as usize];
+            self.filesystem.read_at(address, &mut raw)?;
+
+            match raw[0] {
+                END_OF_DIRECTORY => return Ok(None),
+                DELETED => {
+                    long_state = None;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let attributes = Attributes::from_bits_retain(raw[11]);
+            if attributes & Attributes::LongName == Attributes::LongName {
+                long_state = read_long_entry(&raw, &mut long_name, long_state);
+                continue;
+            }
+
+            // Volume labels and the `.` and `..` entries aren't files.
+            if attributes.contains(Attributes::VolumeId) || raw[0] == b'.' {
+                long_state = None;
+                continue;
+            }
+
+            // The long name only belongs to this entry if every part of it was found, and its
+            // checksum matches.
+            let short_name = short_name(&raw);
+            let long_name = (long_state == Some((checksum(&short_name), 1))).then_some(long_name);
+            let slots = long_name.map_or(0, |it| it.length.div_ceil(UNITS_PER_ENTRY) as u32) + 1;
+
+            let location = EntryLocation {
+                directory: self.cursor.directory,
+                slot,
+                slots,
+                address,
+            };
+
+            let kind = self.filesystem.boot_sector.kind;
+            return Ok(Some(parse_short_entry(kind, &raw, long_name, location)));
+        }
+    }
+}
+
+/// Adds the long name entry in [raw] to [name], returning its checksum and order if it
+/// continues the sequence in [state].
+fn read_long_entry(raw: &[u8], name: &mut FileName, state: Option<(u8, u8)>) -> Option<(u8, u8)> {
+    let order = raw[0] & !LAST_LONG_ENTRY;
+    let checksum = raw[13];
+
+    let continues = if raw[0] & LAST_LONG_ENTRY != 0 {
+        *name = FileName::EMPTY;
+        true
+    } else {
+        state == Some((checksum, order + 1))
+    };
+
+    if !continues || order == 0 || order as usize * UNITS_PER_ENTRY > MAX_NAME_LENGTH + 12 {
+        return None;
+    }
+
+    let start = (order as usize - 1) * UNITS_PER_ENTRY;
+    let units = LONG_NAME_RUNS
+        .iter()
+        .flat_map(|(offset, count)| (0..*count).map(move |it| read_u16(raw, offset + it * 2)));
+
+    for (index, unit) in units.enumerate() {
+        let position = start + index;
+        if unit == 0x0000 || unit == 0xFFFF || position >= MAX_NAME_LENGTH {
+            break;
+        }
+
+        name.units[position] = unit;
+        name.length = name.length.max(position + 1);
+    }
+
+    Some((checksum, order))
+}
+
+/// Returns the short name of the short entry in [raw].
+fn short_name(raw: &[u8]) -> [u8; 11] {
+    let mut short_name = [0u8; 11];
+    short_name.copy_from_slice(&raw[..11]);
+    if short_name[0] == ESCAPED_DELETED {
+        short_name[0] = DELETED;
+    }
+
+    short_name
+}
+
+/// Parses the short entry in [raw], which is called [long_name] if it has a long name.
+fn parse_short_entry(
+    kind: FatKind,
+    raw: &[u8],
+    long_name: Option<FileName>,
+    location: EntryLocation,
+) -> DirEntry {
+    let short_name = short_name(raw);
+    let name = long_name.unwrap_or_else(|| FileName::from_short_name(&short_name, raw[12]));
+
+    // The high half of the first cluster is reserved on FAT12 and FAT16.
+    let first_cluster = match kind {
+        FatKind::Fat32 => ((read_u16(raw, 20) as u32) << 16) | read_u16(raw, 26) as u32,
+        _ => read_u16(raw, 26) as u32,
+    };
+
+    DirEntry {
+        name,
+        short_name,
+        attributes: Attributes::from_bits_retain(raw[11]),
+        first_cluster,
+        size: read_u32(raw, 28),
+        modified: Timestamp {
+            date: read_u16(raw, 24),
+            time: read_u16(raw, 22),
+        },
+        location: Some(location),
+    }
+}
+
+/// Builds a short entry for [name].
+fn short_entry(
+    name: &[u8; 11],
+    case: u8,
+    attributes: Attributes,
+    first_cluster: u32,
+    size: u32,
+) -> [u8; ENTRY_SIZE as usize] {
+    let mut raw = [0u8; ENTRY_SIZE as usize];
+    raw[..11].copy_from_slice(name);
+    if raw[0] == DELETED {
+        raw[0] = ESCAPED_DELETED;
+    }
+
+    raw[11] = attributes.bits();
+    raw[12] = case;
+
+    // The creation, access and modification dates.
+    for offset in [14, 22] {
+        raw[offset..offset + 2].copy_from_slice(&EPOCH.time.to_le_bytes());
+    }
+
+    for offset in [16, 18, 24] {
+        raw[offset..offset + 2].copy_from_slice(&EPOCH.date.to_le_bytes());
+    }
+
+    raw[20..22].copy_from_slice(&((first_cluster >> 16) as u16).to_le_bytes());
+    raw[26..28].copy_from_slice(&(first_cluster as u16).to_le_bytes());
+    raw[28..32].copy_from_slice(&size.to_le_bytes());
+    raw
+}
+
+/// The checksum of a short name, which is stored in each of its long name entries.
+fn checksum(short_name: &[u8; 11]) -> u8 {
+    short_name
+        .iter()
+        .fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte))
+}
+
+/// Returns the short name that [name] is, if it is an 8.3 name whose base and extension are
+/// each in a single case, along with the DIR_NTRes flags that restore its case.
+fn lowercase_short_name(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, extension) = name.split_once('.').unwrap_or((name, ""));
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 || extension.contains('.') {
+        return None;
+    }
+
+    let mut short_name = [b' '; 11];
+    let mut case = 0;
+
+    for (part, offset, flag) in [
+        (base, 0, LOWERCASE_BASE),
+        (extension, 8, LOWERCASE_EXTENSION),
+    ] {
+        let lower = part.bytes().any(|it| it.is_ascii_lowercase());
+        let upper = part.bytes().any(|it| it.is_ascii_uppercase());
+        if lower && upper {
+            return None;
+        }
+
+        if lower {
+            case |= flag;
+        }
+
+        for (index, byte) in part.bytes().enumerate() {
+            if short_character(byte) != Some(byte.to_ascii_uppercase()) {
+                return None;
+            }
+
+            short_name[offset + index] = byte.to_ascii_uppercase();
+        }
+    }
+
+    Some((short_name, case))
+}
+
+/// Returns [byte] as it may appear in a short name, or [None] if it has to be dropped.
+/// Characters that aren't allowed are replaced with `_`.
+fn short_character(byte: u8) -> Option<u8> {
+    match byte {
+        b' ' | b'.' => None,
+        byte if !(0x20..0x80).contains(&byte) || INVALID_SHORT_CHARACTERS.contains(&byte) => {
+            Some(b'_')
+        }
+        byte => Some(byte.to_ascii_uppercase()),
+    }
+}
+
+/// Whether [short_name] is what [name] would be as a short name.
+fn short_name_matches(short_name: &[u8; 11], name: &str) -> bool {
+    lowercase_short_name(name).is_some_and(|(it, _)| it == *short_name)
+}
+
+/// Writes [value] in decimal to the start of [buffer], returning the amount of digits.
+fn write_decimal(buffer: &mut [u8], value: u32) -> usize {
+    let mut digits = [0u8; 10];
+    let mut length = 0;
+    let mut value = value;
+
+    loop {
+        digits[length] = b'0' + (value % 10) as u8;
+        length += 1;
+        value /= 10;
+
+        if value == 0 {
+            break;
+        }
+    }
+
+    for (index, digit) in digits[..length].iter().rev().enumerate() {
+        buffer[index] = *digit;
+    }
+
+    length
+}
+
+impl DirEntry {
+    pub fn is_directory(&self) -> bool {
+        self.attributes.contains(Attributes::Directory)
+    }
+}
+
+impl FileName {
+    const EMPTY: FileName = FileName {
+        units: [0; MAX_NAME_LENGTH],
+        length: 0,
+    };
+
+    /// Creates a [FileName] from [name], which must be a valid long name.
+    pub fn new(name: &str) -> Result<FileName, FatError> {
+        let mut file_name = FileName::EMPTY;
+
+        for unit in name.encode_utf16() {
+            if file_name.length == MAX_NAME_LENGTH
+                || unit < 0x20
+                || INVALID_LONG_CHARACTERS.contains(&unit)
+            {
+                return Err(FatError::InvalidName);
+            }
+
+            file_name.units[file_name.length] = unit;
+            file_name.length += 1;
+        }
+
+        // Trailing spaces and dots are ignored by other implementations, so names that end with
+        // them couldn't be opened there.
+        if matches!(name, "" | "." | "..") || name.ends_with([' ', '.']) {
+            return Err(FatError::InvalidName);
+        }
+
+        Ok(file_name)
+    }
+
+    /// Creates a [FileName] for an entry that only has a short name.
+    pub(super) fn from_short_name(short_name: &[u8; 11], case: u8) -> FileName {
+        let mut file_name = FileName::EMPTY;
+
+        let base = &short_name[..8];
+        let extension = &short_name[8..];
+        let parts = [
+            (base, case & LOWERCASE_BASE),
+            (extension, case & LOWERCASE_EXTENSION),
+        ];
+
+        for (index, (part, lowercase)) in parts.into_iter().enumerate() {
+            let length = part
+                .iter()
+                .rposition(|it| *it != b' ')
+                .map_or(0, |it| it + 1);
+            if length == 0 {
+                continue;
+            }
+
+            if index == 1 {
+                file_name.units[file_name.length] = b'.' as u16;
+                file_name.length += 1;
+            }
+
+            for byte in &part[..length] {
+                let byte = if lowercase != 0 {
+                    byte.to_ascii_lowercase()
+                } else {
+                    *byte
+                };
+
+                file_name.units[file_name.length] = byte as u16;
+                file_name.length += 1;
+            }
+        }
+
+        file_name
+    }
+
+    /// Returns the characters of the name, with any invalid UTF-16 replaced.
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        char::decode_utf16(self.units[..self.length].iter().copied())
+            .map(|it| it.unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    /// Compares the name against [name], without regard to ASCII case.
+    pub fn eq_ignore_case(&self, name: &str) -> bool {
+        let mut units = self.units[..self.length].iter();
+
+        for unit in name.encode_utf16() {
+            let Some(own) = units.next() else {
+                return false;
+            };
+
+            if ascii_lowercase(*own) != ascii_lowercase(unit) {
+                return false;
+            }
+        }
+
+        units.next().is_none()
+    }
+}
+
+fn ascii_lowercase(unit: u16) -> u16 {
+    match unit {
+        0x41..=0x5A => unit + 0x20,
+        unit => unit,
+    }
+}
+
+impl Display for FileName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for character in self.chars() {
+            write!(f, "{}", character)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for FileName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self)
+    }
+}
+
+/// Timestamps are shown as `YYYY-MM-DD HH:MM:SS`, times are stored in 2 second steps.
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            1980 + (self.date >> 9),
+            (self.date >> 5) & 0xF,
+            self.date & 0x1F,
+            self.time >> 11,
+            (self.time >> 5) & 0x3F,
+            (self.time & 0x1F) * 2
+        )
+    }
+}
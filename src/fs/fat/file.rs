@@ -0,0 +1,206 @@
+use super::{Attributes, DirEntry, FatError, FatFilesystem};
+use crate::io::block::BlockDevice;
+
+/// An open file, which remembers its position and the cluster that was reached last.
+#[derive(Debug, Clone, Copy)]
+pub struct File {
+    entry: DirEntry,
+    position: u64,
+
+    /// The index of a cluster within the file's chain, and the cluster itself.
+    cursor: Option<(u32, u32)>,
+}
+
+/// Where [File::seek] should move to.
+#[derive(Debug, Clone, Copy)]
+pub enum SeekFrom {
+    Start(u64),
+    Current(i64),
+    End(i64),
+}
+
+/// Where the data written by [FatFilesystem::write_range] comes from.
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Data(&'a [u8]),
+    Zeroes,
+}
+
+impl<D: BlockDevice> FatFilesystem<D> {
+    /// Opens the file at [path].
+    pub fn open(&self, path: &str) -> Result<File, FatError> {
+        self.open_entry(self.find(path)?)
+    }
+
+    /// Opens the file that [entry] refers to.
+    pub(super) fn open_entry(&self, entry: DirEntry) -> Result<File, FatError> {
+        if entry.is_directory() {
+            return Err(FatError::IsADirectory);
+        }
+
+        Ok(File {
+            entry,
+            position: 0,
+            cursor: None,
+        })
+    }
+
+    /// Reads from [file] into [buffer], returning the amount of bytes that were read, which is 0
+    /// at the end of the file.
+    pub fn read(&self, file: &mut File, buffer: &mut [u8]) -> Result<usize, FatError> {
+        let size = file.entry.size as u64;
+        if file.position >= size {
+            return Ok(0);
+        }
+
+        let cluster_size = self.boot_sector.cluster_size();
+        let length = (size - file.position).min(buffer.len() as u64) as usize;
+
+        let mut done = 0;
+        while done < length {
+            let index = (file.position / cluster_size) as u32;
+            let offset = file.position % cluster_size;
+
+            // The size says that there is more data, so the chain can't end here.
+            let cluster = match self.walk(file, index)? {
+                Some((reached, cluster)) if reached == index => cluster,
+                _ => return Err(FatError::InvalidChain(file.entry.first_cluster)),
+            };
+
+            let chunk = ((cluster_size - offset) as usize).min(length - done);
+            let address = self.boot_sector.cluster_address(cluster) + offset;
+            self.read_at(address, &mut buffer[done..done + chunk])?;
+
+            done += chunk;
+            file.position += chunk as u64;
+        }
+
+        Ok(done)
+    }
+
+    /// Writes [data] to [file] at its position, extending it if needed, and returns the amount
+    /// of bytes that were written.
+    ///
+    /// If the position is past the end of the file, the gap is filled with zeroes. The clusters
+    /// are allocated and written before the entry is updated, so a crash in between leaves the
+    /// file as it was, and at worst leaks the new clusters.
+    pub fn write(&mut self, file: &mut File, data: &[u8]) -> Result<usize, FatError> {
+        let end = file.position + data.len() as u64;
+        if end > u32::MAX as u64 {
+            return Err(FatError::FileTooLarge);
+        }
+
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        self.mark_dirty()?;
+
+        let size = file.entry.size as u64;
+        if file.position > size {
+            self.write_range(file, size, file.position - size, Source::Zeroes)?;
+        }
+
+        self.write_range(file, file.position, data.len() as u64, Source::Data(data))?;
+
+        file.position = end;
+        file.entry.size = file.entry.size.max(end as u32);
+        file.entry.attributes |= Attributes::Archive;
+        self.update_entry(&file.entry)?;
+
+        Ok(data.len())
+    }
+
+    /// Writes [length] bytes from [source] to [file] at [start], allocating any clusters that
+    /// it doesn't have yet.
+    fn write_range(
+        &mut self,
+        file: &mut File,
+        start: u64,
+        length: u64,
+        source: Source,
+    ) -> Result<(), FatError> {
+        let cluster_size = self.boot_sector.cluster_size();
+
+        let mut done = 0;
+        while done < length {
+            let position = start + done;
+            let index = (position / cluster_size) as u32;
+            let offset = position % cluster_size;
+
+            let (mut reached, mut cluster) = match self.walk(file, index)? {
+                Some(cursor) => cursor,
+                None => {
+                    let cluster = self.allocate_cluster(None)?;
+                    file.entry.first_cluster = cluster;
+                    (0, cluster)
+                }
+            };
+
+            while reached < index {
+                cluster = self.allocate_cluster(Some(cluster))?;
+                reached += 1;
+            }
+
+            file.cursor = Some((index, cluster));
+
+            let chunk = (cluster_size - offset).min(length - done);
+            let address = self.boot_sector.cluster_address(cluster) + offset;
+            match source {
+                Source::Data(data) => {
+                    let range = done as usize..(done + chunk) as usize;
+                    self.write_at(address, &data[range])?;
+                }
+                Source::Zeroes => self.zero_at(address, chunk)?,
+            }
+
+            done += chunk;
+        }
+
+        Ok(())
+    }
+
+    /// Follows [file]'s chain towards the cluster at [index], stopping early if the chain ends.
+    /// Returns the index and cluster that were reached, or [None] if the file has no clusters.
+    fn walk(&self, file: &mut File, index: u32) -> Result<Option<(u32, u32)>, FatError> {
+        let (mut reached, mut cluster) = match file.cursor {
+            Some((reached, cluster)) if reached <= index => (reached, cluster),
+            _ if file.entry.first_cluster == 0 => return Ok(None),
+            _ => (0, file.entry.first_cluster),
+        };
+
+        if !self.boot_sector.is_valid_cluster(cluster) {
+            return Err(FatError::InvalidChain(cluster));
+        }
+
+        while reached < index {
+            match self.next_cluster(cluster)? {
+                Some(next) => cluster = next,
+                None => break,
+            }
+
+            reached += 1;
+        }
+
+        file.cursor = Some((reached, cluster));
+        Ok(Some((reached, cluster)))
+    }
+}
+
+impl File {
+    pub fn size(&self) -> u64 {
+        self.entry.size as u64
+    }
+
+    /// Moves the position of the file, which may be past its end, and returns the new position.
+    pub fn seek(&mut self, from: SeekFrom) -> Result<u64, FatError> {
+        let position = match from {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+            SeekFrom::End(offset) => self.size().checked_add_signed(offset),
+        };
+
+        self.position = position.ok_or(FatError::InvalidSeek)?;
+        Ok(self.position)
+    }
+}
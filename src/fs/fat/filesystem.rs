@@ -0,0 +1,314 @@
+use super::{read_u32, BootSector, FatError, FatKind};
+use crate::{io::block::BlockDevice, sync::Mutex};
+
+/// The largest sector that can be held in the [SectorCache].
+const MAX_SECTOR_SIZE: usize = BootSector::MAX_SECTOR_SIZE as usize;
+
+/// The sector that was read last, which is usually a FAT or directory sector that is about to
+/// be read again.
+///
+/// Writes go straight through to the device, so the cache never holds anything that the device
+/// doesn't. Its lock is held while the sector is transferred, so it sleeps.
+struct SectorCache {
+    sector: Option<u64>,
+    data: [u8; MAX_SECTOR_SIZE],
+}
+
+/// A FAT12, FAT16 or FAT32 volume on a [BlockDevice].
+///
+/// The volume is marked as dirty in FAT[1] before it is first changed, and as clean again by
+/// [FatFilesystem::flush], so a crash in between can be detected the next time it is mounted.
+pub struct FatFilesystem<D: BlockDevice> {
+    pub(super) device: D,
+    pub(super) boot_sector: BootSector,
+    cache: Mutex<SectorCache>,
+
+    /// The amount of free clusters, or [None] if it has to be counted.
+    pub(super) free_clusters: Option<u32>,
+
+    /// Where to start looking for a free cluster.
+    pub(super) next_free: u32,
+
+    /// Whether the volume has been marked as dirty since it was mounted or flushed.
+    dirty: bool,
+
+    /// Whether the volume wasn't flushed before it was last unmounted.
+    was_unclean: bool,
+}
+
+impl<D: BlockDevice> FatFilesystem<D> {
+    /// FSInfo: The signatures at the start, in the middle and at the end of the sector, and the
+    /// offsets of the free cluster count and the next free cluster hint.
+    /// 5. FAT32 FSInfo Sector Structure: https://academy.cba.mit.edu/classes/networking_communications/SD/FAT.pdf
+    const FS_INFO_LEAD_SIGNATURE: u32 = 0x4161_5252;
+    const FS_INFO_STRUCTURE_SIGNATURE: u32 = 0x6141_7272;
+    const FS_INFO_TRAIL_SIGNATURE: u32 = 0xAA55_0000;
+    const FS_INFO_FREE_COUNT: usize = 488;
+    const FS_INFO_NEXT_FREE: usize = 492;
+
+    /// FSInfo: The value of a field that isn't known.
+    const FS_INFO_UNKNOWN: u32 = 0xFFFF_FFFF;
+
+    /// Mounts the volume on [device], whose blocks must be the volume's sectors.
+    ///
+    /// If the volume wasn't flushed before it was last unmounted, the free cluster count in
+    /// FSInfo isn't trusted, and the volume stays marked as dirty until it is checked elsewhere.
+    pub fn mount(device: D) -> Result<FatFilesystem<D>, FatError> {
+        let block_size = device.block_size();
+        if !(BootSector::MIN_SECTOR_SIZE as usize..=MAX_SECTOR_SIZE).contains(&block_size) {
+            return Err(FatError::UnsupportedSectorSize(block_size));
+        }
+
+        let mut sector = [0u8; MAX_SECTOR_SIZE];
+        device
+            .read_blocks(0, &mut sector[..block_size])
+            .map_err(FatError::Block)?;
+
+        let boot_sector = BootSector::parse(&sector)?;
+        if boot_sector.bytes_per_sector as usize != block_size {
+            return Err(FatError::UnsupportedSectorSize(block_size));
+        }
+
+        let sectors = boot_sector.first_data_sector as u64
+            + boot_sector.cluster_count as u64 * boot_sector.sectors_per_cluster as u64;
+        if sectors > device.block_count() {
+            return Err(FatError::InvalidBootSector);
+        }
+
+        let mut filesystem = FatFilesystem {
+            device,
+            boot_sector,
+            cache: Mutex::new(SectorCache {
+                sector: None,
+                data: [0; MAX_SECTOR_SIZE],
+            }),
+            free_clusters: None,
+            next_free: BootSector::FIRST_CLUSTER,
+            dirty: false,
+            was_unclean: false,
+        };
+
+        filesystem.was_unclean = !filesystem.is_clean()?;
+        if !filesystem.was_unclean {
+            filesystem.read_fs_info()?;
+        }
+
+        Ok(filesystem)
+    }
+
+    /// The device that holds the volume.
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    pub fn boot_sector(&self) -> &BootSector {
+        &self.boot_sector
+    }
+
+    /// Whether the volume wasn't flushed before it was last unmounted, which means that it may
+    /// hold lost clusters or half-written files.
+    pub fn was_unclean(&self) -> bool {
+        self.was_unclean
+    }
+
+    /// Writes the free cluster count back to FSInfo, and marks the volume as clean.
+    /// This should be called once a batch of changes is complete, and before rebooting.
+    ///
+    /// A volume that was already unclean when it was mounted stays marked as dirty, as only a
+    /// full check can tell whether it is consistent.
+    pub fn flush(&mut self) -> Result<(), FatError> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        self.write_fs_info()?;
+        if !self.was_unclean {
+            self.set_clean(true)?;
+        }
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Marks the volume as dirty, this must be called before anything on it is changed.
+    pub(super) fn mark_dirty(&mut self) -> Result<(), FatError> {
+        if self.dirty {
+            return Ok(());
+        }
+
+        self.set_clean(false)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Reads [buffer] from the volume, starting at the byte [address].
+    pub(super) fn read_at(&self, address: u64, buffer: &mut [u8]) -> Result<(), FatError> {
+        let sector_size = self.boot_sector.bytes_per_sector as usize;
+
+        let mut address = address;
+        let mut done = 0;
+        while done < buffer.len() {
+            let remaining = &mut buffer[done..];
+            let sector = address / sector_size as u64;
+            let offset = (address % sector_size as u64) as usize;
+
+            let length = if offset == 0 && remaining.len() >= sector_size {
+                // Whole sectors are read straight into the buffer.
+                let length = remaining.len() / sector_size * sector_size;
+                self.device
+                    .read_blocks(sector, &mut remaining[..length])
+                    .map_err(FatError::Block)?;
+
+                length
+            } else {
+                let length = (sector_size - offset).min(remaining.len());
+                let mut cache = self.cache.lock();
+                self.load(&mut cache, sector)?;
+                remaining[..length].copy_from_slice(&cache.data[offset..offset + length]);
+
+                length
+            };
+
+            done += length;
+            address += length as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Writes [data] to the volume, starting at the byte [address].
+    pub(super) fn write_at(&self, address: u64, data: &[u8]) -> Result<(), FatError> {
+        let sector_size = self.boot_sector.bytes_per_sector as usize;
+
+        let mut address = address;
+        let mut done = 0;
+        while done < data.len() {
+            let remaining = &data[done..];
+            let sector = address / sector_size as u64;
+            let offset = (address % sector_size as u64) as usize;
+            let mut cache = self.cache.lock();
+
+            let length = if offset == 0 && remaining.len() >= sector_size {
+                let length = remaining.len() / sector_size * sector_size;
+                let count = (length / sector_size) as u64;
+                if cache
+                    .sector
+                    .is_some_and(|it| (sector..sector + count).contains(&it))
+                {
+                    cache.sector = None;
+                }
+
+                self.device
+                    .write_blocks(sector, &remaining[..length])
+                    .map_err(FatError::Block)?;
+
+                length
+            } else {
+                let length = (sector_size - offset).min(remaining.len());
+                self.load(&mut cache, sector)?;
+                cache.data[offset..offset + length].copy_from_slice(&remaining[..length]);
+
+                if let Err(error) = self.device.write_blocks(sector, &cache.data[..sector_size]) {
+                    cache.sector = None;
+                    return Err(FatError::Block(error));
+                }
+
+                length
+            };
+
+            done += length;
+            address += length as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Writes [length] zeroes to the volume, starting at the byte [address].
+    pub(super) fn zero_at(&self, address: u64, length: u64) -> Result<(), FatError> {
+        const ZEROES: [u8; MAX_SECTOR_SIZE] = [0; MAX_SECTOR_SIZE];
+
+        let mut done = 0;
+        while done < length {
+            let chunk = (length - done).min(MAX_SECTOR_SIZE as u64);
+            self.write_at(address + done, &ZEROES[..chunk as usize])?;
+            done += chunk;
+        }
+
+        Ok(())
+    }
+
+    /// Makes sure that [cache] holds [sector].
+    fn load(&self, cache: &mut SectorCache, sector: u64) -> Result<(), FatError> {
+        if cache.sector == Some(sector) {
+            return Ok(());
+        }
+
+        let sector_size = self.boot_sector.bytes_per_sector as usize;
+        cache.sector = None;
+        self.device
+            .read_blocks(sector, &mut cache.data[..sector_size])
+            .map_err(FatError::Block)?;
+
+        cache.sector = Some(sector);
+        Ok(())
+    }
+
+    /// Reads the free cluster count and hint from FSInfo, if the volume has a valid one.
+    fn read_fs_info(&mut self) -> Result<(), FatError> {
+        let Some(address) = self.fs_info_address() else {
+            return Ok(());
+        };
+
+        let mut fs_info = [0u8; 512];
+        self.read_at(address, &mut fs_info)?;
+        if !Self::is_valid_fs_info(&fs_info) {
+            return Ok(());
+        }
+
+        let free = read_u32(&fs_info, Self::FS_INFO_FREE_COUNT);
+        if free != Self::FS_INFO_UNKNOWN && free <= self.boot_sector.cluster_count {
+            self.free_clusters = Some(free);
+        }
+
+        let next = read_u32(&fs_info, Self::FS_INFO_NEXT_FREE);
+        if self.boot_sector.is_valid_cluster(next) {
+            self.next_free = next;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the free cluster count and hint to FSInfo, if the volume has a valid one.
+    fn write_fs_info(&mut self) -> Result<(), FatError> {
+        let Some(address) = self.fs_info_address() else {
+            return Ok(());
+        };
+
+        let mut fs_info = [0u8; 512];
+        self.read_at(address, &mut fs_info)?;
+        if !Self::is_valid_fs_info(&fs_info) {
+            return Ok(());
+        }
+
+        let free = self.free_clusters.unwrap_or(Self::FS_INFO_UNKNOWN);
+        fs_info[Self::FS_INFO_FREE_COUNT..][..4].copy_from_slice(&free.to_le_bytes());
+        fs_info[Self::FS_INFO_NEXT_FREE..][..4].copy_from_slice(&self.next_free.to_le_bytes());
+
+        self.write_at(address, &fs_info)
+    }
+
+    fn fs_info_address(&self) -> Option<u64> {
+        let sector = self.boot_sector.fs_info_sector;
+        if self.boot_sector.kind != FatKind::Fat32 || sector == 0 || sector == 0xFFFF {
+            return None;
+        }
+
+        Some(sector as u64 * self.boot_sector.bytes_per_sector as u64)
+    }
+
+    fn is_valid_fs_info(fs_info: &[u8]) -> bool {
+        read_u32(fs_info, 0) == Self::FS_INFO_LEAD_SIGNATURE
+            && read_u32(fs_info, 484) == Self::FS_INFO_STRUCTURE_SIGNATURE
+            && read_u32(fs_info, 508) == Self::FS_INFO_TRAIL_SIGNATURE
+    }
+}
@@ -0,0 +1,102 @@
+pub mod boot_sector;
+pub mod directory;
+pub mod file;
+pub mod filesystem;
+pub mod table;
+pub mod volume;
+
+pub use boot_sector::{BootSector, FatKind};
+pub use directory::{Attributes, DirEntry};
+pub use file::SeekFrom;
+pub use filesystem::FatFilesystem;
+pub use volume::FatVolume;
+
+use crate::{
+    io::block::{BlockDevice, BlockError},
+    println,
+};
+
+/// Represents an error that can occur while using a FAT volume.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum FatError {
+    /// Occurs when the device can't be read from or written to.
+    Block(BlockError),
+
+    /// Occurs when the first sector doesn't hold a valid FAT boot sector.
+    InvalidBootSector,
+
+    /// Occurs when the device's blocks aren't the volume's sectors.
+    UnsupportedSectorSize(usize),
+
+    /// Occurs when a cluster chain links to a free, bad or out of range cluster, or ends before
+    /// the data that it should hold.
+    InvalidChain(u32),
+
+    /// Occurs when a path doesn't lead to an entry.
+    NotFound,
+
+    /// Occurs when a directory was expected, but a file was found.
+    NotADirectory,
+
+    /// Occurs when a file was expected, but a directory was found.
+    IsADirectory,
+
+    /// Occurs when an entry with the same name already exists.
+    AlreadyExists,
+
+    /// Occurs when a directory that still has entries is removed.
+    DirectoryNotEmpty,
+
+    /// Occurs when a name is empty, too long, or holds characters that FAT doesn't allow.
+    InvalidName,
+
+    /// Occurs when there are no free clusters left.
+    NoSpace,
+
+    /// Occurs when a directory has no room for another entry, and can't be grown.
+    DirectoryFull,
+
+    /// Occurs when a file would grow past 4 GiB, the most that FAT can hold.
+    FileTooLarge,
+
+    /// Occurs when a seek would move before the start of a file.
+    InvalidSeek,
+}
+
+/// Prints the layout of [filesystem] and its free space.
+pub fn report<D: BlockDevice>(filesystem: &mut FatFilesystem<D>) {
+    let boot_sector = *filesystem.boot_sector();
+    println!(
+        "[angeldust::fs] {:?} volume '{}', {} clusters of {} KiB{}",
+        boot_sector.kind,
+        boot_sector.label(),
+        boot_sector.cluster_count,
+        boot_sector.cluster_size() / 1024,
+        if filesystem.was_unclean() {
+            ", not cleanly unmounted"
+        } else {
+            ""
+        }
+    );
+
+    match filesystem.free_clusters() {
+        Ok(free) => println!(
+            "[angeldust::fs] {} MiB free",
+            free as u64 * boot_sector.cluster_size() / (1024 * 1024)
+        ),
+        Err(error) => println!("[angeldust::fs] failed to count free clusters: {:?}", error),
+    }
+}
+
+/// Reads a little-endian u16 from [bytes] at [offset].
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Reads a little-endian u32 from [bytes] at [offset].
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
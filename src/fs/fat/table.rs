@@ -0,0 +1,221 @@
+use super::{boot_sector::FatKind, BootSector, FatError, FatFilesystem};
+use crate::io::block::BlockDevice;
+
+/// The meaning of a single entry in the file allocation table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatEntry {
+    Free,
+
+    /// The cluster is followed by this cluster.
+    Next(u32),
+
+    Bad,
+
+    /// The cluster is the last one of its chain.
+    EndOfChain,
+}
+
+impl<D: BlockDevice> FatFilesystem<D> {
+    /// FAT[1]: Cleared while the volume is mounted with changes that haven't been flushed.
+    const FAT16_CLEAN: u32 = 1 << 15;
+    const FAT32_CLEAN: u32 = 1 << 27;
+
+    /// FAT32 entries are 28 bits, the upper 4 bits are reserved and must be preserved.
+    const FAT32_MASK: u32 = 0x0FFF_FFFF;
+
+    /// Returns the entry for [cluster].
+    pub(super) fn fat_entry(&self, cluster: u32) -> Result<FatEntry, FatError> {
+        let value = self.raw_fat_entry(cluster)?;
+
+        let (bad, end_of_chain) = match self.boot_sector.kind {
+            FatKind::Fat12 => (0xFF7, 0xFF8),
+            FatKind::Fat16 => (0xFFF7, 0xFFF8),
+            FatKind::Fat32 => (0x0FFF_FFF7, 0x0FFF_FFF8),
+        };
+
+        Ok(match value {
+            0 => FatEntry::Free,
+            value if value == bad => FatEntry::Bad,
+            value if value >= end_of_chain => FatEntry::EndOfChain,
+            value => FatEntry::Next(value),
+        })
+    }
+
+    /// Replaces the entry for [cluster] in every FAT.
+    pub(super) fn set_fat_entry(&mut self, cluster: u32, entry: FatEntry) -> Result<(), FatError> {
+        let value = match entry {
+            FatEntry::Free => 0,
+            FatEntry::Next(next) => next,
+            FatEntry::Bad => 0x0FFF_FFF7,
+            FatEntry::EndOfChain => 0x0FFF_FFFF,
+        };
+
+        self.set_raw_fat_entry(cluster, value)
+    }
+
+    /// Returns the cluster that follows [cluster], or [None] if it is the last of its chain.
+    pub(super) fn next_cluster(&self, cluster: u32) -> Result<Option<u32>, FatError> {
+        match self.fat_entry(cluster)? {
+            FatEntry::Next(next) if self.boot_sector.is_valid_cluster(next) => Ok(Some(next)),
+            FatEntry::EndOfChain => Ok(None),
+            _ => Err(FatError::InvalidChain(cluster)),
+        }
+    }
+
+    /// Allocates a free cluster, marks it as the end of its chain, and links it after
+    /// [previous] if there is one.
+    ///
+    /// The new cluster is marked before it is linked, so a crash in between leaks a cluster
+    /// rather than leaving a chain that ends in a free cluster.
+    pub(super) fn allocate_cluster(&mut self, previous: Option<u32>) -> Result<u32, FatError> {
+        self.mark_dirty()?;
+
+        let first = self.boot_sector.is_valid_cluster(self.next_free);
+        let start = if first {
+            self.next_free
+        } else {
+            BootSector::FIRST_CLUSTER
+        };
+
+        // Search from the hint to the end, then wrap around to the start.
+        let last = self.boot_sector.last_cluster();
+        let cluster = (start..=last)
+            .chain(BootSector::FIRST_CLUSTER..start)
+            .find_map(|cluster| match self.fat_entry(cluster) {
+                Ok(FatEntry::Free) => Some(Ok(cluster)),
+                Ok(_) => None,
+                Err(error) => Some(Err(error)),
+            })
+            .ok_or(FatError::NoSpace)??;
+
+        self.set_fat_entry(cluster, FatEntry::EndOfChain)?;
+        if let Some(previous) = previous {
+            self.set_fat_entry(previous, FatEntry::Next(cluster))?;
+        }
+
+        self.next_free = cluster + 1;
+        if let Some(free) = self.free_clusters.as_mut() {
+            *free = free.saturating_sub(1);
+        }
+
+        Ok(cluster)
+    }
+
+    /// Frees every cluster in the chain that starts at [first].
+    pub(super) fn free_chain(&mut self, first: u32) -> Result<(), FatError> {
+        self.mark_dirty()?;
+
+        let mut cluster = Some(first);
+        while let Some(current) = cluster {
+            cluster = self.next_cluster(current)?;
+            self.set_fat_entry(current, FatEntry::Free)?;
+
+            if let Some(free) = self.free_clusters.as_mut() {
+                *free += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the amount of free clusters, counting them if FSInfo couldn't be trusted.
+    pub fn free_clusters(&mut self) -> Result<u32, FatError> {
+        if let Some(free) = self.free_clusters {
+            return Ok(free);
+        }
+
+        let mut free = 0;
+        for cluster in BootSector::FIRST_CLUSTER..=self.boot_sector.last_cluster() {
+            if self.fat_entry(cluster)? == FatEntry::Free {
+                free += 1;
+            }
+        }
+
+        self.free_clusters = Some(free);
+        Ok(free)
+    }
+
+    /// Whether the volume was flushed after it was last changed.
+    /// FAT12 volumes don't have a flag, so they are always considered to be clean.
+    pub(super) fn is_clean(&self) -> Result<bool, FatError> {
+        let flag = match self.boot_sector.kind {
+            FatKind::Fat12 => return Ok(true),
+            FatKind::Fat16 => Self::FAT16_CLEAN,
+            FatKind::Fat32 => Self::FAT32_CLEAN,
+        };
+
+        Ok(self.raw_fat_entry(1)? & flag != 0)
+    }
+
+    /// Sets or clears the clean flag in FAT[1].
+    pub(super) fn set_clean(&mut self, clean: bool) -> Result<(), FatError> {
+        let flag = match self.boot_sector.kind {
+            FatKind::Fat12 => return Ok(()),
+            FatKind::Fat16 => Self::FAT16_CLEAN,
+            FatKind::Fat32 => Self::FAT32_CLEAN,
+        };
+
+        let value = self.raw_fat_entry(1)?;
+        let value = if clean { value | flag } else { value & !flag };
+        self.set_raw_fat_entry(1, value)
+    }
+
+    /// Returns the offset of [cluster]'s entry within a FAT, and its size in bytes.
+    /// FAT12 entries are 12 bits, so they are read as 16 bits that share a byte with a neighbour.
+    fn fat_offset(&self, cluster: u32) -> (u64, usize) {
+        let cluster = cluster as u64;
+
+        match self.boot_sector.kind {
+            FatKind::Fat12 => (cluster + cluster / 2, 2),
+            FatKind::Fat16 => (cluster * 2, 2),
+            FatKind::Fat32 => (cluster * 4, 4),
+        }
+    }
+
+    fn raw_fat_entry(&self, cluster: u32) -> Result<u32, FatError> {
+        let (offset, size) = self.fat_offset(cluster);
+        let fat = self.boot_sector.active_fat.unwrap_or(0);
+
+        let mut bytes = [0u8; 4];
+        self.read_at(
+            self.boot_sector.fat_address(fat) + offset,
+            &mut bytes[..size],
+        )?;
+        let value = u32::from_le_bytes(bytes);
+
+        Ok(match self.boot_sector.kind {
+            FatKind::Fat12 if cluster % 2 == 1 => value >> 4,
+            FatKind::Fat12 => value & 0xFFF,
+            FatKind::Fat16 => value,
+            FatKind::Fat32 => value & Self::FAT32_MASK,
+        })
+    }
+
+    fn set_raw_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), FatError> {
+        let (offset, size) = self.fat_offset(cluster);
+
+        let fats = match self.boot_sector.active_fat {
+            Some(fat) => fat..fat + 1,
+            None => 0..self.boot_sector.fat_count,
+        };
+
+        for fat in fats {
+            let address = self.boot_sector.fat_address(fat) + offset;
+
+            let mut bytes = [0u8; 4];
+            self.read_at(address, &mut bytes[..size])?;
+            let current = u32::from_le_bytes(bytes);
+
+            let value = match self.boot_sector.kind {
+                FatKind::Fat12 if cluster % 2 == 1 => (current & 0x000F) | ((value & 0xFFF) << 4),
+                FatKind::Fat12 => (current & 0xF000) | (value & 0xFFF),
+                FatKind::Fat16 => value & 0xFFFF,
+                FatKind::Fat32 => (current & !Self::FAT32_MASK) | (value & Self::FAT32_MASK),
+            };
+
+            self.write_at(address, &value.to_le_bytes()[..size])?;
+        }
+
+        Ok(())
+    }
+}
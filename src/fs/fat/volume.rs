@@ -0,0 +1,225 @@
+use super::{
+    directory::{Directory, FileName},
+    Attributes, DirEntry, FatError, FatFilesystem, SeekFrom,
+};
+use crate::{
+    fs::vfs::{DirectoryEntry, FileType, Filesystem, InodeId, Metadata, Name, VfsError},
+    io::block::BlockDevice,
+    sync::Mutex,
+};
+
+/// A [FatFilesystem] that can be mounted in the VFS, once one has been attached to it.
+///
+/// Inodes are identified by the directory that holds their entry and the slot of the entry
+/// within it, which stay the same for as long as the entry exists.
+///
+/// The filesystem is behind a sleeping lock, as it is held across every transfer to the device.
+pub struct FatVolume<D: BlockDevice> {
+    filesystem: Mutex<Option<FatFilesystem<D>>>,
+}
+
+impl<D: BlockDevice> FatVolume<D> {
+    /// The root directory doesn't have an entry, so it gets an id that no slot can have.
+    const ROOT: InodeId = InodeId(u64::MAX);
+
+    pub const fn new() -> FatVolume<D> {
+        FatVolume {
+            filesystem: Mutex::new(None),
+        }
+    }
+
+    /// Makes [filesystem] available through the VFS.
+    pub fn attach(&self, filesystem: FatFilesystem<D>) {
+        let mut current = self.filesystem.lock();
+        *current = Some(filesystem);
+    }
+
+    /// Like [Filesystem::sync], but returns [None] straight away if the filesystem is in use,
+    /// rather than waiting for it.
+    pub fn try_sync(&self) -> Option<Result<(), VfsError>> {
+        let mut filesystem = self.filesystem.try_lock()?;
+        Some(match filesystem.as_mut() {
+            Some(filesystem) => filesystem.flush().map_err(VfsError::from),
+            None => Ok(()),
+        })
+    }
+
+    /// Runs [f] with the attached filesystem.
+    pub fn with<T>(
+        &self,
+        f: impl FnOnce(&mut FatFilesystem<D>) -> Result<T, FatError>,
+    ) -> Result<T, VfsError> {
+        let mut filesystem = self.filesystem.lock();
+        let filesystem = filesystem.as_mut().ok_or(VfsError::Io)?;
+        f(filesystem).map_err(VfsError::from)
+    }
+
+    fn id(entry: &DirEntry) -> InodeId {
+        let Some(location) = entry.location else {
+            return Self::ROOT;
+        };
+
+        let directory = match location.directory {
+            Directory::FixedRoot => 0,
+            Directory::Cluster(cluster) => cluster,
+        };
+
+        InodeId(((directory as u64) << 32) | location.slot as u64)
+    }
+
+    fn entry(filesystem: &FatFilesystem<D>, inode: InodeId) -> Result<DirEntry, FatError> {
+        if inode == Self::ROOT {
+            return Ok(filesystem.root_entry());
+        }
+
+        let directory = match (inode.0 >> 32) as u32 {
+            0 => Directory::FixedRoot,
+            cluster => Directory::Cluster(cluster),
+        };
+
+        filesystem.entry_at(directory, inode.0 as u32)
+    }
+
+    fn directory(filesystem: &FatFilesystem<D>, inode: InodeId) -> Result<Directory, FatError> {
+        filesystem.directory(&Self::entry(filesystem, inode)?)
+    }
+}
+
+impl<D: BlockDevice + Send> Filesystem for FatVolume<D> {
+    fn name(&self) -> &'static str {
+        "fat"
+    }
+
+    fn root(&self) -> InodeId {
+        Self::ROOT
+    }
+
+    fn lookup(&self, directory: InodeId, name: &str) -> Result<InodeId, VfsError> {
+        self.with(|filesystem| {
+            let directory = Self::directory(filesystem, directory)?;
+            let entry = filesystem
+                .find_in(directory, name)?
+                .ok_or(FatError::NotFound)?;
+
+            Ok(Self::id(&entry))
+        })
+    }
+
+    fn metadata(&self, inode: InodeId) -> Result<Metadata, VfsError> {
+        self.with(|filesystem| {
+            let entry = Self::entry(filesystem, inode)?;
+
+            Ok(Metadata {
+                kind: if entry.is_directory() {
+                    FileType::Directory
+                } else {
+                    FileType::File
+                },
+                size: entry.size as u64,
+                read_only: entry.attributes.contains(Attributes::ReadOnly),
+            })
+        })
+    }
+
+    /// The cookie is the slot after the entry that was read last.
+    fn read_dir(
+        &self,
+        directory: InodeId,
+        cookie: u64,
+    ) -> Result<Option<(DirectoryEntry, u64)>, VfsError> {
+        self.with(|filesystem| {
+            let directory = Self::directory(filesystem, directory)?;
+            let Some(entry) = filesystem.entries_from(directory, cookie as u32).next() else {
+                return Ok(None);
+            };
+
+            let entry = entry?;
+            let location = entry.location.ok_or(FatError::NotFound)?;
+
+            // Long names that don't fit in the VFS are shown by their short name, which can
+            // also be used to open them.
+            let name = Name::from_chars(entry.name.chars())
+                .or_else(|| {
+                    Name::from_chars(FileName::from_short_name(&entry.short_name, 0).chars())
+                })
+                .ok_or(FatError::InvalidName)?;
+
+            let directory_entry = DirectoryEntry {
+                name,
+                inode: Self::id(&entry),
+                kind: if entry.is_directory() {
+                    FileType::Directory
+                } else {
+                    FileType::File
+                },
+            };
+
+            Ok(Some((directory_entry, location.slot as u64 + 1)))
+        })
+    }
+
+    fn read(&self, inode: InodeId, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        self.with(|filesystem| {
+            let mut file = filesystem.open_entry(Self::entry(filesystem, inode)?)?;
+            file.seek(SeekFrom::Start(offset))?;
+            filesystem.read(&mut file, buffer)
+        })
+    }
+
+    fn write(&self, inode: InodeId, offset: u64, data: &[u8]) -> Result<usize, VfsError> {
+        self.with(|filesystem| {
+            let mut file = filesystem.open_entry(Self::entry(filesystem, inode)?)?;
+            file.seek(SeekFrom::Start(offset))?;
+            filesystem.write(&mut file, data)
+        })
+    }
+
+    fn create(&self, directory: InodeId, name: &str, kind: FileType) -> Result<InodeId, VfsError> {
+        self.with(|filesystem| {
+            let directory = Self::directory(filesystem, directory)?;
+            let entry = match kind {
+                FileType::File => {
+                    filesystem.create_entry(directory, name, Attributes::Archive, 0)?
+                }
+                FileType::Directory => filesystem.create_directory_in(directory, name)?,
+            };
+
+            Ok(Self::id(&entry))
+        })
+    }
+
+    fn remove(&self, directory: InodeId, name: &str) -> Result<(), VfsError> {
+        self.with(|filesystem| {
+            let directory = Self::directory(filesystem, directory)?;
+            let entry = filesystem
+                .find_in(directory, name)?
+                .ok_or(FatError::NotFound)?;
+
+            filesystem.remove_entry(&entry)
+        })
+    }
+
+    fn sync(&self) -> Result<(), VfsError> {
+        self.with(|filesystem| filesystem.flush())
+    }
+}
+
+impl From<FatError> for VfsError {
+    fn from(error: FatError) -> VfsError {
+        match error {
+            FatError::Block(_) => VfsError::Io,
+            FatError::InvalidBootSector
+            | FatError::UnsupportedSectorSize(_)
+            | FatError::InvalidChain(_) => VfsError::Corrupted,
+            FatError::NotFound => VfsError::NotFound,
+            FatError::NotADirectory => VfsError::NotADirectory,
+            FatError::IsADirectory => VfsError::IsADirectory,
+            FatError::AlreadyExists => VfsError::AlreadyExists,
+            FatError::DirectoryNotEmpty => VfsError::DirectoryNotEmpty,
+            FatError::InvalidName => VfsError::InvalidPath,
+            FatError::NoSpace | FatError::DirectoryFull => VfsError::NoSpace,
+            FatError::FileTooLarge => VfsError::FileTooLarge,
+            FatError::InvalidSeek => VfsError::InvalidSeek,
+        }
+    }
+}
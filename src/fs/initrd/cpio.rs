@@ -0,0 +1,67 @@
+use super::{parse_name, Entry, EntryKind, InitrdError};
+
+/// The magic of the "new" portable format, and of the same format with checksums.
+pub const MAGIC: &[u8] = b"07070";
+
+/// The size of a header, which is followed by the name.
+const HEADER_SIZE: usize = 110;
+
+/// The name of the entry that marks the end of the archive.
+const TRAILER: &str = "TRAILER!!!";
+
+const MODE_TYPE_MASK: u32 = 0o170000;
+const MODE_DIRECTORY: u32 = 0o040000;
+const MODE_FILE: u32 = 0o100000;
+
+/// Parses the `newc` entry at [offset], or returns [None] at the trailer.
+/// https://www.kernel.org/doc/html/latest/driver-api/early-userspace/buffer-format.html
+pub fn parse(data: &'static [u8], offset: usize) -> Result<Option<Entry>, InitrdError> {
+    let header = data
+        .get(offset..offset + HEADER_SIZE)
+        .ok_or(InitrdError::Truncated(offset))?;
+
+    if !header.starts_with(MAGIC) || !matches!(header[5], b'1' | b'2') {
+        return Err(InitrdError::InvalidHeader(offset));
+    }
+
+    let mode = read_hex(header, 14).ok_or(InitrdError::InvalidHeader(offset))?;
+    let file_size = read_hex(header, 54).ok_or(InitrdError::InvalidHeader(offset))? as usize;
+    let name_size = read_hex(header, 94).ok_or(InitrdError::InvalidHeader(offset))? as usize;
+
+    // The name includes its null terminator.
+    let name_start = offset + HEADER_SIZE;
+    let name_bytes = data
+        .get(name_start..name_start + name_size)
+        .ok_or(InitrdError::Truncated(offset))?;
+
+    let name = parse_name(name_bytes).ok_or(InitrdError::InvalidName(offset))?;
+    if name == TRAILER {
+        return Ok(None);
+    }
+
+    let data_start = (name_start + name_size).next_multiple_of(4);
+    let contents = data
+        .get(data_start..data_start + file_size)
+        .ok_or(InitrdError::Truncated(offset))?;
+
+    let kind = match mode & MODE_TYPE_MASK {
+        MODE_DIRECTORY => EntryKind::Directory,
+        MODE_FILE => EntryKind::File,
+        _ => EntryKind::Other,
+    };
+
+    Ok(Some(Entry {
+        offset,
+        prefix: "",
+        name,
+        kind,
+        data: contents,
+        next: (data_start + file_size).next_multiple_of(4),
+    }))
+}
+
+/// Reads the field at [offset] in [header], which is 8 hexadecimal digits.
+fn read_hex(header: &[u8], offset: usize) -> Option<u32> {
+    let digits = core::str::from_utf8(header.get(offset..offset + 8)?).ok()?;
+    u32::from_str_radix(digits, 16).ok()
+}
@@ -0,0 +1,404 @@
+pub mod cpio;
+pub mod tar;
+
+use super::vfs::{DirectoryEntry, FileType, Filesystem, InodeId, Metadata, Name, VfsError};
+use crate::{fdt, memory, mutex::Mutex};
+
+/// Represents an error that can occur while finding or parsing the initrd.
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub enum InitrdError {
+    /// Occurs when the device tree doesn't describe an initrd, e.g. because `initramfs` is
+    /// missing from `config.txt`, or QEMU wasn't given `-initrd`.
+    NotPresent,
+
+    /// Occurs when the initrd ends before it starts.
+    InvalidRange { start: u64, end: u64 },
+
+    /// Occurs when the initrd's frames weren't reserved by [memory::initialize], so they may
+    /// have been allocated.
+    NotReserved,
+
+    /// Occurs when the archive is compressed, which isn't supported.
+    Compressed,
+
+    /// Occurs when the archive is neither a `newc` cpio archive nor a `ustar` archive.
+    UnknownFormat,
+
+    /// Occurs when the entry at the offset goes past the end of the archive.
+    Truncated(usize),
+
+    /// Occurs when the header of the entry at the offset can't be parsed.
+    InvalidHeader(usize),
+
+    /// Occurs when the header of the `ustar` entry at the offset has the wrong checksum.
+    InvalidChecksum(usize),
+
+    /// Occurs when the name of the entry at the offset isn't valid UTF-8.
+    InvalidName(usize),
+}
+
+/// The formats of archive that can be used as an initrd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Cpio,
+    Tar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Directory,
+
+    /// Anything else, e.g. symbolic links or devices, which are ignored.
+    Other,
+}
+
+/// A single entry of an [Archive].
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    /// Where the entry's header is in the archive, which identifies it.
+    pub offset: usize,
+
+    /// The `ustar` prefix, which comes before [Entry::name].
+    pub prefix: &'static str,
+    pub name: &'static str,
+    pub kind: EntryKind,
+    pub data: &'static [u8],
+
+    /// Where the next entry's header is in the archive.
+    pub next: usize,
+}
+
+/// A validated cpio or tar archive in memory.
+#[derive(Debug, Clone, Copy)]
+pub struct Archive {
+    data: &'static [u8],
+    format: Format,
+}
+
+/// Where an entry is in the directory tree of an [Archive].
+///
+/// Archives don't have to contain every directory, e.g. `bin/init` may be the only entry, so a
+/// directory may only exist as a prefix of the path of another entry. A location is therefore the
+/// first [Location::depth] components of the path of the entry at [Location::offset], and the
+/// root directory is any location with a depth of 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Location {
+    offset: usize,
+    depth: usize,
+}
+
+/// An [Archive] that is mounted read-only through the VFS.
+pub struct Ramdisk {
+    archive: Mutex<Option<Archive>>,
+}
+
+/// Finds the initrd that the firmware (or QEMU) loaded, from `/chosen/linux,initrd-start` and
+/// `/chosen/linux,initrd-end` in the device tree, whose frames [memory::initialize] reserved so
+/// that they are never allocated.
+pub fn find() -> Result<&'static [u8], InitrdError> {
+    let (start, end) = fdt::instance()
+        .and_then(|it| it.initrd())
+        .ok_or(InitrdError::NotPresent)?;
+
+    if end <= start {
+        return Err(InitrdError::InvalidRange { start, end });
+    }
+
+    if !memory::is_reserved(start, end - start) {
+        return Err(InitrdError::NotReserved);
+    }
+
+    // Safety: The memory is mapped in the upper half, and its frames are reserved, so
+    // nothing else will ever write to it.
+    Ok(unsafe {
+        core::slice::from_raw_parts(
+            memory::virtual_address(start) as *const u8,
+            (end - start) as usize,
+        )
+    })
+}
+
+impl Archive {
+    /// Detects the format of [data], and makes sure that every entry can be parsed, so the
+    /// archive can be walked without any errors later.
+    pub fn parse(data: &'static [u8]) -> Result<Archive, InitrdError> {
+        let format = if data.starts_with(cpio::MAGIC) {
+            Format::Cpio
+        } else if data
+            .get(tar::MAGIC_OFFSET..)
+            .is_some_and(|it| it.starts_with(tar::MAGIC))
+        {
+            Format::Tar
+        } else if data.starts_with(&[0x1f, 0x8b]) {
+            return Err(InitrdError::Compressed);
+        } else {
+            return Err(InitrdError::UnknownFormat);
+        };
+
+        let archive = Archive { data, format };
+        let mut offset = 0;
+        while let Some(entry) = archive.entry_at(offset)? {
+            offset = entry.next;
+        }
+
+        Ok(archive)
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Iterates over the files and directories in the archive, starting with the one at [offset].
+    pub fn entries_from(&self, offset: usize) -> impl Iterator<Item = Entry> {
+        let archive = *self;
+        let mut next = Some(offset);
+
+        core::iter::from_fn(move || {
+            let entry = archive.entry_at(next?).ok().flatten();
+            next = entry.map(|it| it.next);
+            entry
+        })
+        .filter(|it| it.kind != EntryKind::Other && it.components().next().is_some())
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = Entry> {
+        self.entries_from(0)
+    }
+
+    fn entry_at(&self, offset: usize) -> Result<Option<Entry>, InitrdError> {
+        match self.format {
+            Format::Cpio => cpio::parse(self.data, offset),
+            Format::Tar => tar::parse(self.data, offset),
+        }
+    }
+}
+
+impl Entry {
+    /// The components of the entry's path, which is relative to the root of the archive.
+    pub fn components(&self) -> impl Iterator<Item = &'static str> {
+        self.prefix
+            .split('/')
+            .chain(self.name.split('/'))
+            .filter(|it| !it.is_empty() && *it != ".")
+    }
+}
+
+/// Reads a name from [bytes], which ends at the first null byte (if there is one).
+fn parse_name(bytes: &'static [u8]) -> Option<&'static str> {
+    let length = bytes.iter().position(|it| *it == 0).unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..length]).ok()
+}
+
+impl Location {
+    const ROOT: Location = Location {
+        offset: 0,
+        depth: 0,
+    };
+
+    fn from_inode(inode: InodeId) -> Location {
+        Location {
+            offset: (inode.0 >> 8) as usize,
+            depth: (inode.0 & 0xFF) as usize,
+        }
+    }
+
+    fn inode(&self) -> InodeId {
+        match self.depth {
+            0 => InodeId(0),
+            depth => InodeId(((self.offset as u64) << 8) | depth as u64),
+        }
+    }
+}
+
+impl Ramdisk {
+    pub const fn new() -> Ramdisk {
+        Ramdisk {
+            archive: Mutex::new(None),
+        }
+    }
+
+    /// Makes [archive] available through the VFS.
+    pub fn attach(&self, archive: Archive) {
+        *self.archive.lock() = Some(archive);
+    }
+
+    fn archive(&self) -> Result<Archive, VfsError> {
+        (*self.archive.lock()).ok_or(VfsError::Io)
+    }
+
+    /// Returns the entry at [location], or [None] for the root.
+    fn entry(&self, location: Location) -> Result<Option<Entry>, VfsError> {
+        if location.depth == 0 {
+            return Ok(None);
+        }
+
+        let entry = self
+            .archive()?
+            .entry_at(location.offset)
+            .ok()
+            .flatten()
+            .ok_or(VfsError::NotFound)?;
+
+        if entry.components().count() < location.depth {
+            return Err(VfsError::NotFound);
+        }
+
+        Ok(Some(entry))
+    }
+
+    /// Returns the name of the child of [directory] that [entry] is in, or [None] if it isn't
+    /// below [directory].
+    fn child_of(directory: Option<&Entry>, depth: usize, entry: &Entry) -> Option<&'static str> {
+        let mut components = entry.components();
+        if let Some(directory) = directory {
+            for component in directory.components().take(depth) {
+                if components.next() != Some(component) {
+                    return None;
+                }
+            }
+        }
+
+        components.next()
+    }
+
+    /// Finds the child called [name] of [directory], preferring the entry that describes it over
+    /// entries that are only below it.
+    fn find_child(&self, directory: Location, name: &str) -> Result<Location, VfsError> {
+        let parent = self.entry(directory)?;
+        let mut implicit = None;
+
+        for entry in self.archive()?.entries() {
+            if Self::child_of(parent.as_ref(), directory.depth, &entry) != Some(name) {
+                continue;
+            }
+
+            let location = Location {
+                offset: entry.offset,
+                depth: directory.depth + 1,
+            };
+
+            if entry.components().count() == location.depth {
+                return Ok(location);
+            }
+
+            implicit.get_or_insert(location);
+        }
+
+        implicit.ok_or(VfsError::NotFound)
+    }
+}
+
+impl Filesystem for Ramdisk {
+    fn name(&self) -> &'static str {
+        match self.archive().map(|it| it.format()) {
+            Ok(Format::Tar) => "initrd (tar)",
+            _ => "initrd (cpio)",
+        }
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn root(&self) -> InodeId {
+        Location::ROOT.inode()
+    }
+
+    fn lookup(&self, directory: InodeId, name: &str) -> Result<InodeId, VfsError> {
+        if self.metadata(directory)?.kind != FileType::Directory {
+            return Err(VfsError::NotADirectory);
+        }
+
+        Ok(self
+            .find_child(Location::from_inode(directory), name)?
+            .inode())
+    }
+
+    fn metadata(&self, inode: InodeId) -> Result<Metadata, VfsError> {
+        let location = Location::from_inode(inode);
+        let (kind, size) = match self.entry(location)? {
+            Some(entry) if entry.components().count() == location.depth => match entry.kind {
+                EntryKind::File => (FileType::File, entry.data.len() as u64),
+                _ => (FileType::Directory, 0),
+            },
+            _ => (FileType::Directory, 0),
+        };
+
+        Ok(Metadata {
+            kind,
+            size,
+            read_only: true,
+        })
+    }
+
+    fn read_dir(
+        &self,
+        directory: InodeId,
+        cookie: u64,
+    ) -> Result<Option<(DirectoryEntry, u64)>, VfsError> {
+        if self.metadata(directory)?.kind != FileType::Directory {
+            return Err(VfsError::NotADirectory);
+        }
+
+        let location = Location::from_inode(directory);
+        let parent = self.entry(location)?;
+        let archive = self.archive()?;
+
+        // The cookie is the offset of the next entry to look at. A child may be named by many
+        // entries, so it is only returned for the first one.
+        for entry in archive.entries_from(cookie as usize) {
+            let Some(name) = Self::child_of(parent.as_ref(), location.depth, &entry) else {
+                continue;
+            };
+
+            let seen = archive
+                .entries()
+                .take_while(|it| it.offset < entry.offset)
+                .any(|it| Self::child_of(parent.as_ref(), location.depth, &it) == Some(name));
+
+            if seen {
+                continue;
+            }
+
+            // Names that don't fit can't be looked up either, so they are skipped.
+            let Some(name) = Name::from_chars(name.chars()) else {
+                continue;
+            };
+
+            let inode = self.find_child(location, name.as_str())?.inode();
+            let child = DirectoryEntry {
+                name,
+                inode,
+                kind: self.metadata(inode)?.kind,
+            };
+
+            return Ok(Some((child, entry.next as u64)));
+        }
+
+        Ok(None)
+    }
+
+    fn read(&self, inode: InodeId, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+        let location = Location::from_inode(inode);
+        let entry = match self.entry(location)? {
+            Some(entry)
+                if entry.kind == EntryKind::File
+                    && entry.components().count() == location.depth =>
+            {
+                entry
+            }
+            _ => return Err(VfsError::IsADirectory),
+        };
+
+        let start = entry.data.len().min(offset as usize);
+        let length = buffer.len().min(entry.data.len() - start);
+        buffer[..length].copy_from_slice(&entry.data[start..start + length]);
+        Ok(length)
+    }
+}
@@ -0,0 +1,88 @@
+use super::{parse_name, Entry, EntryKind, InitrdError};
+
+/// The magic of a POSIX archive, at [MAGIC_OFFSET] in every header.
+pub const MAGIC: &[u8] = b"ustar";
+pub const MAGIC_OFFSET: usize = 257;
+
+/// The size of a header, and the alignment of the data that follows it.
+const BLOCK_SIZE: usize = 512;
+
+/// Parses the `ustar` entry at [offset], or returns [None] at the zeroed block that ends the
+/// archive.
+/// https://pubs.opengroup.org/onlinepubs/9699919799/utilities/pax.html#tag_20_92_13_06
+pub fn parse(data: &'static [u8], offset: usize) -> Result<Option<Entry>, InitrdError> {
+    // Some tools leave out the zeroed blocks at the end.
+    if offset == data.len() {
+        return Ok(None);
+    }
+
+    let header = data
+        .get(offset..offset + BLOCK_SIZE)
+        .ok_or(InitrdError::Truncated(offset))?;
+
+    if header.iter().all(|it| *it == 0) {
+        return Ok(None);
+    }
+
+    if !header[MAGIC_OFFSET..].starts_with(MAGIC) {
+        return Err(InitrdError::InvalidHeader(offset));
+    }
+
+    // The checksum is calculated as if its own field were spaces.
+    let checksum = read_octal(&header[148..156]).ok_or(InitrdError::InvalidHeader(offset))?;
+    let sum = header
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| match index {
+            148..156 => b' ' as u64,
+            _ => *byte as u64,
+        })
+        .sum::<u64>();
+
+    if sum != checksum {
+        return Err(InitrdError::InvalidChecksum(offset));
+    }
+
+    let size = read_octal(&header[124..136]).ok_or(InitrdError::InvalidHeader(offset))? as usize;
+    let name = parse_name(&header[0..100]).ok_or(InitrdError::InvalidName(offset))?;
+    let prefix = parse_name(&header[345..500]).ok_or(InitrdError::InvalidName(offset))?;
+
+    let kind = match header[156] {
+        b'0' | b'\0' => EntryKind::File,
+        b'5' => EntryKind::Directory,
+        _ => EntryKind::Other,
+    };
+
+    // Links, devices, directories and FIFOs have no data, whatever their size says. Anything
+    // else (e.g. pax extended headers) is skipped over along with its data.
+    let data_size = if matches!(header[156], b'1'..=b'6') {
+        0
+    } else {
+        size
+    };
+    let data_start = offset + BLOCK_SIZE;
+    let contents = data
+        .get(data_start..data_start + data_size)
+        .ok_or(InitrdError::Truncated(offset))?;
+
+    Ok(Some(Entry {
+        offset,
+        prefix,
+        name,
+        kind,
+        data: contents,
+        next: data_start + data_size.next_multiple_of(BLOCK_SIZE),
+    }))
+}
+
+/// Reads a numeric field, which is octal digits padded by spaces or null bytes.
+fn read_octal(field: &[u8]) -> Option<u64> {
+    let digits = core::str::from_utf8(field)
+        .ok()?
+        .trim_matches(|it: char| it == ' ' || it == '\0');
+
+    match digits {
+        "" => Some(0),
+        digits => u64::from_str_radix(digits, 8).ok(),
+    }
+}
@@ -0,0 +1,453 @@
+pub mod fat;
+pub mod initrd;
+pub mod vfs;
+
+use crate::{
+    console,
+    io::{
+        emmc::Emmc,
+        partition::{self, Partition},
+    },
+    params::{self, Parameter},
+    println, scheduler,
+};
+use core::time::Duration;
+use fat::{FatError, FatFilesystem, FatVolume};
+use initrd::{Archive, Ramdisk};
+use vfs::{
+    file::{self, Fd, OpenFlags, SeekFrom},
+    FileType, VfsError,
+};
+
+/// The boot partition of the SD card, which holds the firmware and the kernel.
+static BOOT: FatVolume<Partition<Emmc>> = FatVolume::new();
+
+/// Where the boot partition is mounted.
+const BOOT_MOUNT_POINT: &str = "/boot";
+
+/// The initrd, which is mounted read-only as the root filesystem.
+static ROOT: Ramdisk = Ramdisk::new();
+
+/// The parameters accepted by the filesystems.
+pub const PARAMETERS: &[Parameter] = &[Parameter {
+    key: "fs.boot",
+    default: "auto",
+    description:
+        "the sd card partition to mount at /boot (auto for the first fat partition, or its number)",
+    apply: |params, value| {
+        params.fs.boot_partition = match value {
+            "auto" => None,
+            number => Some(params::parse_u32(number, 1, 128)?),
+        };
+
+        Ok(())
+    },
+}];
+
+/// The filesystems' [crate::params::BootParams].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsParams {
+    /// The partition to mount at [BOOT_MOUNT_POINT], or [None] for the first FAT partition.
+    pub boot_partition: Option<u32>,
+}
+
+/// Where each boot is counted on the boot partition, relative to its root.
+const BOOT_LOG_DIRECTORY: &str = "angeldust";
+const BOOT_LOG_PATH: &str = "angeldust/boots.log";
+
+/// Every line of [BOOT_LOG_PATH] has the same length, so that the last one can be read from the
+/// end of the file.
+const BOOT_LOG_LINE: [u8; 14] = *b"boot 00000000\n";
+
+/// The log is started over once it holds this many boots.
+const BOOT_LOG_MAX_LINES: u64 = 1024;
+
+/// How many lines [print_tail] prints.
+const TAIL_LINES: usize = 10;
+
+/// How often [sync_periodically] writes changes back, which bounds how long a FAT volume stays
+/// marked as dirty after it was last changed.
+const SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Mounts the initrd that was loaded alongside the kernel at `/`.
+/// The kernel can boot without it, so any problems are only reported.
+/// [crate::memory::initialize] must be called before this, as it reserves the initrd's frames.
+pub fn mount_initrd() {
+    let data = match initrd::find() {
+        Ok(data) => data,
+        Err(error) => {
+            println!("[angeldust::fs] no initrd to mount: {:?}", error);
+            return;
+        }
+    };
+
+    let archive = match Archive::parse(data) {
+        Ok(archive) => archive,
+        Err(error) => {
+            println!(
+                "[angeldust::fs] failed to parse the initrd at {:p}: {:?}",
+                data.as_ptr(),
+                error
+            );
+            return;
+        }
+    };
+
+    println!(
+        "[angeldust::fs] initrd at {:p} ({} KiB, {:?}, {} entries)",
+        data.as_ptr(),
+        archive.size() / 1024,
+        archive.format(),
+        archive.entries().count()
+    );
+
+    ROOT.attach(archive);
+    if let Err(error) = vfs::mount("/", &ROOT, true) {
+        println!(
+            "[angeldust::fs] failed to mount the initrd at /: {:?}",
+            error
+        );
+        return;
+    }
+
+    list("/");
+}
+
+/// Mounts the partition on [sd_card] that [FsParams::boot_partition] selects at
+/// [BOOT_MOUNT_POINT]. The kernel can boot without it, so any problems are only reported.
+pub fn initialize(sd_card: Emmc) {
+    let table = match partition::read_table(&sd_card) {
+        Ok(table) => table,
+        Err(error) => {
+            println!("[angeldust::fs] no partition table to mount: {:?}", error);
+            return;
+        }
+    };
+
+    let info = match params::instance().fs.boot_partition {
+        Some(number) => table.find(number),
+        None => table.partitions().find(|it| it.kind.is_fat()).copied(),
+    };
+
+    let Some(info) = info else {
+        println!(
+            "[angeldust::fs] no partition to mount at {}",
+            BOOT_MOUNT_POINT
+        );
+        return;
+    };
+
+    let mut filesystem = match FatFilesystem::mount(Partition::new(sd_card, info)) {
+        Ok(filesystem) => filesystem,
+        Err(error) => {
+            println!(
+                "[angeldust::fs] failed to mount partition {}: {:?}",
+                info.number, error
+            );
+            return;
+        }
+    };
+
+    fat::report(&mut filesystem);
+
+    // Writes would only fail later on, so don't let anything try.
+    let read_only = sd_card.card().write_protected;
+    if !read_only {
+        record_boot(&mut filesystem);
+    }
+
+    BOOT.attach(filesystem);
+    if let Err(error) = vfs::mount(BOOT_MOUNT_POINT, &BOOT, read_only) {
+        println!(
+            "[angeldust::fs] failed to mount partition {} at {}: {:?}",
+            info.number, BOOT_MOUNT_POINT, error
+        );
+        return;
+    }
+
+    list(BOOT_MOUNT_POINT);
+}
+
+/// Counts this boot in [BOOT_LOG_PATH] on [filesystem], so that unexpected restarts (e.g. by
+/// the watchdog) can be noticed afterwards. Any problems are only reported.
+fn record_boot(filesystem: &mut FatFilesystem<Partition<Emmc>>) {
+    match append_boot(filesystem) {
+        Ok(count) => println!(
+            "[angeldust::fs] this is boot {}, recorded in {}/{}",
+            count, BOOT_MOUNT_POINT, BOOT_LOG_PATH
+        ),
+        Err(error) => println!("[angeldust::fs] failed to record this boot: {:?}", error),
+    }
+}
+
+/// Appends the next line to [BOOT_LOG_PATH], creating it if needed, and returns its count.
+fn append_boot(filesystem: &mut FatFilesystem<Partition<Emmc>>) -> Result<u32, FatError> {
+    if let Err(FatError::NotFound) = filesystem.find(BOOT_LOG_DIRECTORY) {
+        filesystem.create_directory(BOOT_LOG_DIRECTORY)?;
+    }
+
+    let mut file = match filesystem.open(BOOT_LOG_PATH) {
+        Err(FatError::NotFound) => {
+            filesystem.create_file(BOOT_LOG_PATH)?;
+            filesystem.open(BOOT_LOG_PATH)?
+        }
+        result => result?,
+    };
+
+    // The previous boot is the last line, if it can't be read the count starts over.
+    let length = BOOT_LOG_LINE.len() as u64;
+    let end = file.seek(fat::SeekFrom::End(0))?;
+    let mut previous = 0;
+    if end >= length {
+        let mut line = BOOT_LOG_LINE;
+        file.seek(fat::SeekFrom::Current(-(length as i64)))?;
+        filesystem.read(&mut file, &mut line)?;
+
+        previous = core::str::from_utf8(&line)
+            .ok()
+            .and_then(|it| it.strip_prefix("boot "))
+            .and_then(|it| it.trim_end().parse::<u32>().ok())
+            .unwrap_or(0);
+    }
+
+    // A line that was only partly written (or a log that has grown too long) starts a new log.
+    if !end.is_multiple_of(length) || end >= BOOT_LOG_MAX_LINES * length {
+        filesystem.remove(BOOT_LOG_PATH)?;
+        filesystem.create_file(BOOT_LOG_PATH)?;
+        file = filesystem.open(BOOT_LOG_PATH)?;
+    }
+
+    let count = (previous + 1) % 100_000_000;
+    let mut line = BOOT_LOG_LINE;
+    let mut value = count;
+    for digit in line[5..13].iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+
+    filesystem.write(&mut file, &line)?;
+    Ok(count)
+}
+
+/// Prints which partition is mounted at [BOOT_MOUNT_POINT], and how much space is free on it.
+pub fn report() {
+    let result = BOOT.with(|filesystem| {
+        let info = filesystem.device().info();
+        let cluster_size = filesystem.boot_sector().cluster_size();
+        Ok((info, filesystem.free_clusters()? as u64 * cluster_size))
+    });
+
+    match result {
+        Ok((info, free)) => println!(
+            "[angeldust::fs] partition {} ({} '{}') at {}, {} MiB free",
+            info.number,
+            info.kind,
+            info.label,
+            BOOT_MOUNT_POINT,
+            free / (1024 * 1024)
+        ),
+        Err(error) => println!(
+            "[angeldust::fs] nothing usable is mounted at {}: {:?}",
+            BOOT_MOUNT_POINT, error
+        ),
+    }
+}
+
+/// Writes anything that is pending on the mounted filesystems to their devices, which marks the
+/// FAT volumes as clean again. Any problems are only reported.
+///
+/// This takes the filesystems' sleeping locks, so it may only be called from a thread that can
+/// block, see [scheduler::can_block].
+pub fn sync() {
+    if let Err(error) = vfs::mount::sync_all() {
+        println!(
+            "[angeldust::fs] failed to sync the filesystems: {:?}",
+            error
+        );
+    }
+}
+
+/// Unmounts the SD card's partition before the board goes down, which writes back anything that
+/// is pending and marks it as clean. If any of its files are still open, it is only synced.
+///
+/// Like [sync], this may only be called from a thread that can block.
+pub fn unmount() {
+    match vfs::mount::unmount(BOOT_MOUNT_POINT) {
+        Ok(()) => println!("[angeldust::fs] unmounted {}", BOOT_MOUNT_POINT),
+        Err(VfsError::NotFound) => {}
+        Err(VfsError::MountPointBusy) => {
+            println!(
+                "[angeldust::fs] {} is still in use, only syncing it",
+                BOOT_MOUNT_POINT
+            );
+            sync();
+        }
+        Err(error) => println!(
+            "[angeldust::fs] failed to unmount {}: {:?}",
+            BOOT_MOUNT_POINT, error
+        ),
+    }
+}
+
+/// Writes the SD card's partition back every [SYNC_INTERVAL], so that changes reach it even if
+/// the board is never rebooted cleanly. This is meant to be run in its own thread.
+///
+/// A round is skipped while the partition is in use, rather than writing it back in the middle
+/// of whatever is changing it.
+pub fn sync_periodically() {
+    loop {
+        scheduler::sleep(SYNC_INTERVAL);
+
+        if let Some(Err(error)) = BOOT.try_sync() {
+            println!(
+                "[angeldust::fs] failed to sync {}: {:?}",
+                BOOT_MOUNT_POINT, error
+            );
+        }
+    }
+}
+
+/// Prints the entries of the directory at [path].
+pub fn list(path: &str) {
+    let fd = match file::open(path, OpenFlags::Read) {
+        Ok(fd) => fd,
+        Err(error) => {
+            println!("[angeldust::fs] failed to open {}: {:?}", path, error);
+            return;
+        }
+    };
+
+    loop {
+        match file::read_dir(fd) {
+            Ok(Some(entry)) => println!(
+                "[angeldust::fs] {}/{}{}",
+                path,
+                entry.name,
+                if entry.kind == FileType::Directory {
+                    "/"
+                } else {
+                    ""
+                }
+            ),
+            Ok(None) => break,
+            Err(error) => {
+                println!("[angeldust::fs] failed to read {}: {:?}", path, error);
+                break;
+            }
+        }
+    }
+
+    let _ = file::close(fd);
+}
+
+/// Prints the file at [path] to the console.
+pub fn print(path: &str) {
+    if let Err(error) = with_file(path, OpenFlags::Read, copy_to_console) {
+        println!("[angeldust::fs] failed to read {}: {:?}", path, error);
+    }
+}
+
+/// Prints the last [TAIL_LINES] lines of the file at [path], e.g. of [BOOT_LOG_PATH].
+pub fn print_tail(path: &str) {
+    let result = with_file(path, OpenFlags::Read, |fd| {
+        let start = find_tail(fd)?;
+        file::seek(fd, SeekFrom::Start(start))?;
+        copy_to_console(fd)
+    });
+
+    if let Err(error) = result {
+        println!("[angeldust::fs] failed to read {}: {:?}", path, error);
+    }
+}
+
+/// Prints what kind of entry is at [path], and how large it is.
+pub fn print_metadata(path: &str) {
+    match vfs::stat(path) {
+        Ok(metadata) => println!(
+            "[angeldust::fs] {}: {:?}, {} bytes{}",
+            path,
+            metadata.kind,
+            metadata.size,
+            if metadata.read_only {
+                ", read-only"
+            } else {
+                ""
+            }
+        ),
+        Err(error) => println!("[angeldust::fs] failed to stat {}: {:?}", path, error),
+    }
+}
+
+/// Appends [text] to the file at [path] as a line of its own, creating the file if needed.
+pub fn append(path: &str, text: &str) {
+    let flags = OpenFlags::Append | OpenFlags::Create;
+    let result = with_file(path, flags, |fd| {
+        file::write(fd, text.as_bytes())?;
+        file::write(fd, b"\n")
+    });
+
+    if let Err(error) = result {
+        println!("[angeldust::fs] failed to write {}: {:?}", path, error);
+    }
+}
+
+/// Removes the file or empty directory at [path].
+pub fn remove(path: &str) {
+    if let Err(error) = vfs::remove(path) {
+        println!("[angeldust::fs] failed to remove {}: {:?}", path, error);
+    }
+}
+
+/// Opens the file at [path] with [flags] for as long as [f] runs.
+fn with_file<T>(
+    path: &str,
+    flags: OpenFlags,
+    f: impl FnOnce(Fd) -> Result<T, VfsError>,
+) -> Result<T, VfsError> {
+    let fd = file::open(path, flags)?;
+    let result = f(fd);
+    let _ = file::close(fd);
+    result
+}
+
+/// Prints the rest of [fd] from its position.
+fn copy_to_console(fd: Fd) -> Result<(), VfsError> {
+    let mut buffer = [0u8; 256];
+    loop {
+        match file::read(fd, &mut buffer)? {
+            0 => return Ok(()),
+            length => console::write_bytes(&buffer[..length]),
+        }
+    }
+}
+
+/// Returns where the last [TAIL_LINES] lines of [fd] start, by reading it backwards from the end
+/// a chunk at a time.
+fn find_tail(fd: Fd) -> Result<u64, VfsError> {
+    // A line break at the very end doesn't start another line, so it is skipped.
+    let mut position = match file::seek(fd, SeekFrom::End(-1)) {
+        Ok(position) => position,
+        Err(VfsError::InvalidSeek) => return Ok(0),
+        Err(error) => return Err(error),
+    };
+
+    let mut chunk = [0u8; 256];
+    let mut lines = 0;
+    while position > 0 {
+        let length = position.min(chunk.len() as u64) as i64;
+        file::seek(fd, SeekFrom::Current(-length))?;
+        let read = file::read(fd, &mut chunk[..length as usize])?;
+        position = file::seek(fd, SeekFrom::Current(-length))?;
+
+        for (index, byte) in chunk[..read].iter().enumerate().rev() {
+            if *byte == b'\n' {
+                lines += 1;
+                if lines == TAIL_LINES {
+                    return Ok(position + index as u64 + 1);
+                }
+            }
+        }
+    }
+
+    Ok(0)
+}
@@ -0,0 +1,106 @@
+use super::{Inode, InodeId};
+use crate::mutex::Mutex;
+
+/// The amount of lookups that are remembered.
+const CACHE_SIZE: usize = 64;
+
+/// The longest name that is remembered, longer names are always looked up by the driver.
+const MAX_CACHED_NAME_LENGTH: usize = 32;
+
+/// A lookup of a name in a directory that was already done, so it doesn't have to go through the
+/// filesystem driver again.
+#[derive(Clone, Copy)]
+struct Dentry {
+    mount: usize,
+    parent: InodeId,
+    name: [u8; MAX_CACHED_NAME_LENGTH],
+    name_length: usize,
+    inode: InodeId,
+
+    /// When the entry was last used, the least recently used entry is replaced first.
+    last_used: u64,
+}
+
+struct DentryCache {
+    entries: [Option<Dentry>; CACHE_SIZE],
+    clock: u64,
+}
+
+static DENTRIES: Mutex<DentryCache> = Mutex::new(DentryCache {
+    entries: [None; CACHE_SIZE],
+    clock: 0,
+});
+
+/// Returns the inode that [name] in [parent] was last found to be.
+pub(super) fn lookup(parent: Inode, name: &str) -> Option<InodeId> {
+    let mut cache = DENTRIES.lock();
+    cache.clock += 1;
+    let clock = cache.clock;
+
+    let dentry = cache.entries.iter_mut().flatten().find(|it| {
+        it.mount == parent.mount
+            && it.parent == parent.id
+            && &it.name[..it.name_length] == name.as_bytes()
+    })?;
+
+    dentry.last_used = clock;
+    Some(dentry.inode)
+}
+
+/// Remembers that [name] in [parent] is [inode].
+pub(super) fn insert(parent: Inode, name: &str, inode: InodeId) {
+    if name.len() > MAX_CACHED_NAME_LENGTH {
+        return;
+    }
+
+    let mut cache = DENTRIES.lock();
+    cache.clock += 1;
+
+    let mut dentry = Dentry {
+        mount: parent.mount,
+        parent: parent.id,
+        name: [0; MAX_CACHED_NAME_LENGTH],
+        name_length: name.len(),
+        inode,
+        last_used: cache.clock,
+    };
+
+    dentry.name[..name.len()].copy_from_slice(name.as_bytes());
+
+    let slot = match cache.entries.iter().position(|it| it.is_none()) {
+        Some(index) => index,
+        None => cache
+            .entries
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, it)| it.map_or(0, |it| it.last_used))
+            .map_or(0, |(index, _)| index),
+    };
+
+    cache.entries[slot] = Some(dentry);
+}
+
+/// Forgets every lookup that led to, or went through [inode], as it was removed.
+/// Names may be matched without regard to case, so they can't be forgotten by name alone.
+pub(super) fn forget_inode(inode: Inode) {
+    let mut cache = DENTRIES.lock();
+
+    for slot in cache.entries.iter_mut() {
+        if slot.is_some_and(|it| {
+            it.mount == inode.mount && (it.inode == inode.id || it.parent == inode.id)
+        }) {
+            *slot = None;
+        }
+    }
+}
+
+/// Forgets every lookup on the filesystem at [mount], as it was unmounted.
+pub(super) fn forget_mount(mount: usize) {
+    let mut cache = DENTRIES.lock();
+
+    for slot in cache.entries.iter_mut() {
+        if slot.is_some_and(|it| it.mount == mount) {
+            *slot = None;
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use super::{
+    create, dentry, mount_of, page_cache, resolve, DirectoryEntry, FileType, Inode, Metadata, Path,
+    VfsError,
+};
+use crate::mutex::Mutex;
+use bitflags::bitflags;
+
+/// The most files that can be open at once.
+pub const MAX_OPEN_FILES: usize = 32;
+
+bitflags! {
+    /// How a file is opened.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct OpenFlags: u32 {
+        const Read = 1 << 0;
+        const Write = 1 << 1;
+
+        /// Creates the file if it doesn't exist yet.
+        const Create = 1 << 2;
+
+        /// Moves to the end of the file before every write.
+        const Append = 1 << 3;
+    }
+}
+
+/// A handle to an open file or directory, returned by [open].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fd(pub usize);
+
+/// Where [seek] should move to.
+#[derive(Debug, Clone, Copy)]
+pub enum SeekFrom {
+    Start(u64),
+    Current(i64),
+    End(i64),
+}
+
+#[derive(Clone, Copy)]
+struct OpenFile {
+    inode: Inode,
+    kind: FileType,
+    flags: OpenFlags,
+
+    /// The position within a file, or the cookie of the next entry of a directory.
+    position: u64,
+}
+
+static FILES: Mutex<[Option<OpenFile>; MAX_OPEN_FILES]> = Mutex::new([None; MAX_OPEN_FILES]);
+
+/// Opens the file or directory at [path].
+/// Directories can only be opened for reading, and read with [read_dir].
+pub fn open(path: &str, flags: OpenFlags) -> Result<Fd, VfsError> {
+    let parsed = Path::parse(path)?;
+    let inode = match resolve(&parsed) {
+        Ok(inode) => inode,
+        Err(VfsError::NotFound) if flags.contains(OpenFlags::Create) => {
+            create(path, FileType::File)?
+        }
+        Err(error) => return Err(error),
+    };
+
+    let metadata = super::metadata(inode)?;
+    if flags.intersects(OpenFlags::Write | OpenFlags::Append) {
+        if metadata.kind == FileType::Directory {
+            return Err(VfsError::IsADirectory);
+        }
+
+        if metadata.read_only {
+            return Err(VfsError::ReadOnly);
+        }
+    }
+
+    let mut files = FILES.lock();
+    let index = files
+        .iter()
+        .position(|it| it.is_none())
+        .ok_or(VfsError::TooManyOpenFiles)?;
+
+    files[index] = Some(OpenFile {
+        inode,
+        kind: metadata.kind,
+        flags,
+        position: 0,
+    });
+
+    Ok(Fd(index))
+}
+
+/// Opens the file of [fd] again, with the same flags and position, e.g. for a forked process.
+/// The new [Fd] has its own position from then on.
+pub fn duplicate(fd: Fd) -> Result<Fd, VfsError> {
+    let file = get(fd)?;
+    let mut files = FILES.lock();
+    let index = files
+        .iter()
+        .position(|it| it.is_none())
+        .ok_or(VfsError::TooManyOpenFiles)?;
+
+    files[index] = Some(file);
+    Ok(Fd(index))
+}
+
+/// Closes [fd], which may then be returned by another [open].
+pub fn close(fd: Fd) -> Result<(), VfsError> {
+    let mut files = FILES.lock();
+    let file = files.get_mut(fd.0).ok_or(VfsError::BadFileDescriptor)?;
+    file.take().ok_or(VfsError::BadFileDescriptor)?;
+    Ok(())
+}
+
+/// Reads from [fd] at its position into [buffer], returning the amount of bytes that were read,
+/// which is 0 at the end of the file.
+pub fn read(fd: Fd, buffer: &mut [u8]) -> Result<usize, VfsError> {
+    let position = get(fd)?.position;
+    let length = read_at(fd, position, buffer)?;
+
+    set_position(fd, position + length as u64)?;
+    Ok(length)
+}
+
+/// Reads from [fd] at [offset] into [buffer], like [read] but without using or moving its
+/// position, e.g. for pages of a program that are loaded when they are first used.
+pub fn read_at(fd: Fd, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError> {
+    let file = get(fd)?;
+    if file.kind == FileType::Directory {
+        return Err(VfsError::IsADirectory);
+    }
+
+    if !file.flags.contains(OpenFlags::Read) {
+        return Err(VfsError::PermissionDenied);
+    }
+
+    let mount = mount_of(file.inode)?;
+    page_cache::read(mount.filesystem, file.inode, offset, buffer)
+}
+
+/// Writes [data] to [fd] at its position, extending the file if needed, and returns the amount
+/// of bytes that were written.
+pub fn write(fd: Fd, data: &[u8]) -> Result<usize, VfsError> {
+    let file = get(fd)?;
+    if !file.flags.intersects(OpenFlags::Write | OpenFlags::Append) {
+        return Err(VfsError::PermissionDenied);
+    }
+
+    let mount = mount_of(file.inode)?;
+    let size = super::metadata(file.inode)?.size;
+    let position = if file.flags.contains(OpenFlags::Append) {
+        size
+    } else {
+        file.position
+    };
+
+    // A write past the end fills the gap with zeroes, so the pages from the old end onwards
+    // change too. They are forgotten even if the write fails, as part of it may have made it.
+    let result = mount.filesystem.write(file.inode.id, position, data);
+    let start = position.min(size);
+    page_cache::invalidate(file.inode, start, position + data.len() as u64 - start);
+    let length = result?;
+
+    set_position(fd, position + length as u64)?;
+    Ok(length)
+}
+
+/// Moves the position of [fd], which may be past the end of the file, and returns the new
+/// position. A write past the end fills the gap with zeroes.
+pub fn seek(fd: Fd, from: SeekFrom) -> Result<u64, VfsError> {
+    let file = get(fd)?;
+    if file.kind == FileType::Directory {
+        return Err(VfsError::IsADirectory);
+    }
+
+    let position = match from {
+        SeekFrom::Start(offset) => Some(offset),
+        SeekFrom::Current(offset) => file.position.checked_add_signed(offset),
+        SeekFrom::End(offset) => super::metadata(file.inode)?.size.checked_add_signed(offset),
+    };
+
+    let position = position.ok_or(VfsError::InvalidSeek)?;
+    set_position(fd, position)?;
+    Ok(position)
+}
+
+pub fn stat(fd: Fd) -> Result<Metadata, VfsError> {
+    super::metadata(get(fd)?.inode)
+}
+
+/// Reads the next entry of the directory [fd], or returns [None] once every entry was read.
+pub fn read_dir(fd: Fd) -> Result<Option<DirectoryEntry>, VfsError> {
+    let file = get(fd)?;
+    if file.kind != FileType::Directory {
+        return Err(VfsError::NotADirectory);
+    }
+
+    let mount = mount_of(file.inode)?;
+    let Some((entry, cookie)) = mount.filesystem.read_dir(file.inode.id, file.position)? else {
+        return Ok(None);
+    };
+
+    // Whatever is listed is likely to be opened next, so remember where it leads.
+    if !matches!(entry.name.as_str(), "." | "..") {
+        dentry::insert(file.inode, entry.name.as_str(), entry.inode);
+    }
+
+    set_position(fd, cookie)?;
+    Ok(Some(entry))
+}
+
+/// Whether any file on the filesystem at [mount] is open.
+pub(super) fn is_mount_in_use(mount: usize) -> bool {
+    let files = FILES.lock();
+    files.iter().flatten().any(|it| it.inode.mount == mount)
+}
+
+/// Whether [inode] is open.
+pub(super) fn is_open(inode: Inode) -> bool {
+    let files = FILES.lock();
+    files.iter().flatten().any(|it| it.inode == inode)
+}
+
+fn get(fd: Fd) -> Result<OpenFile, VfsError> {
+    let files = FILES.lock();
+    files
+        .get(fd.0)
+        .copied()
+        .flatten()
+        .ok_or(VfsError::BadFileDescriptor)
+}
+
+fn set_position(fd: Fd, position: u64) -> Result<(), VfsError> {
+    let mut files = FILES.lock();
+    let file = files
+        .get_mut(fd.0)
+        .and_then(|it| it.as_mut())
+        .ok_or(VfsError::BadFileDescriptor)?;
+
+    file.position = position;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use super::{FileType, InodeId, Metadata, Name, VfsError, PAGE_SIZE};
+
+/// A single entry of a directory, as returned by [Filesystem::read_dir].
+#[derive(Debug, Clone, Copy)]
+pub struct DirectoryEntry {
+    pub name: Name,
+    pub inode: InodeId,
+    pub kind: FileType,
+}
+
+/// A filesystem driver, which is plugged into the VFS with [super::mount].
+///
+/// Inodes are only identified by an [InodeId] that the driver chooses, which has to stay the
+/// same for as long as the file exists. The VFS keeps no other state about them, so drivers
+/// are free to look them up again on every call.
+pub trait Filesystem: Sync {
+    /// The name of the driver, e.g. `fat`.
+    fn name(&self) -> &'static str;
+
+    /// Whether the filesystem can't be changed, regardless of how it was mounted.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
+    /// Returns the inode of the root directory.
+    fn root(&self) -> InodeId;
+
+    /// Returns the inode of the entry called [name] in [directory].
+    fn lookup(&self, directory: InodeId, name: &str) -> Result<InodeId, VfsError>;
+
+    fn metadata(&self, inode: InodeId) -> Result<Metadata, VfsError>;
+
+    /// Reads the entry of [directory] at [cookie], which is 0 for the first entry.
+    /// Returns the entry and the cookie of the entry after it, or [None] at the end.
+    fn read_dir(
+        &self,
+        directory: InodeId,
+        cookie: u64,
+    ) -> Result<Option<(DirectoryEntry, u64)>, VfsError>;
+
+    /// Reads from [inode] at [offset] into [buffer], returning the amount of bytes that were
+    /// read, which is less than requested only at the end of the file.
+    fn read(&self, inode: InodeId, offset: u64, buffer: &mut [u8]) -> Result<usize, VfsError>;
+
+    /// Reads page number [index] of [inode], returning the amount of bytes that were read.
+    ///
+    /// This is the path that the page cache uses, drivers that can read a whole page at once
+    /// (e.g. because it is a whole number of clusters) should override it.
+    fn read_page(
+        &self,
+        inode: InodeId,
+        index: u64,
+        page: &mut [u8; PAGE_SIZE],
+    ) -> Result<usize, VfsError> {
+        self.read(inode, index * PAGE_SIZE as u64, page)
+    }
+
+    /// Writes [data] to [inode] at [offset], extending it if needed.
+    fn write(&self, _inode: InodeId, _offset: u64, _data: &[u8]) -> Result<usize, VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+
+    /// Creates an empty file or directory called [name] in [directory].
+    fn create(
+        &self,
+        _directory: InodeId,
+        _name: &str,
+        _kind: FileType,
+    ) -> Result<InodeId, VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+
+    /// Removes the file or empty directory called [name] from [directory].
+    fn remove(&self, _directory: InodeId, _name: &str) -> Result<(), VfsError> {
+        Err(VfsError::ReadOnly)
+    }
+
+    /// Writes anything that is still pending to the underlying device.
+    fn sync(&self) -> Result<(), VfsError> {
+        Ok(())
+    }
+}
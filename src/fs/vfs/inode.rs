@@ -0,0 +1,75 @@
+use core::fmt::{self, Display, Formatter};
+
+/// The longest name of a single directory entry, in bytes of UTF-8.
+pub const MAX_NAME_LENGTH: usize = 255;
+
+/// Identifies an inode within its filesystem, the meaning is up to the driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InodeId(pub u64);
+
+/// Identifies an inode within the VFS, which is an [InodeId] on a mounted filesystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Inode {
+    /// The index of the filesystem in the mount table.
+    pub mount: usize,
+    pub id: InodeId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Directory,
+}
+
+/// What [super::stat] returns about an inode.
+#[derive(Debug, Clone, Copy)]
+pub struct Metadata {
+    pub kind: FileType,
+    pub size: u64,
+    pub read_only: bool,
+}
+
+/// The name of a single directory entry.
+#[derive(Clone, Copy)]
+pub struct Name {
+    bytes: [u8; MAX_NAME_LENGTH],
+    length: usize,
+}
+
+impl Name {
+    /// Creates a [Name] from the characters of [chars], or returns [None] if they don't fit.
+    pub fn from_chars(chars: impl Iterator<Item = char>) -> Option<Name> {
+        let mut name = Name {
+            bytes: [0; MAX_NAME_LENGTH],
+            length: 0,
+        };
+
+        for character in chars {
+            let remaining = name.bytes.get_mut(name.length..)?;
+            if remaining.len() < character.len_utf8() {
+                return None;
+            }
+
+            name.length += character.encode_utf8(remaining).len();
+        }
+
+        (name.length > 0).then_some(name)
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Names are only ever created from valid UTF-8.
+        core::str::from_utf8(&self.bytes[..self.length]).unwrap_or("?")
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", self.as_str())
+    }
+}
@@ -0,0 +1,169 @@
+pub mod dentry;
+pub mod file;
+pub mod filesystem;
+pub mod inode;
+pub mod mount;
+pub mod page_cache;
+pub mod path;
+
+pub use filesystem::{DirectoryEntry, Filesystem};
+pub use inode::{FileType, Inode, InodeId, Metadata, Name, MAX_NAME_LENGTH};
+pub use mount::mount;
+pub use path::Path;
+
+use mount::Mount;
+
+/// The size of the pages that the page cache holds, and that [Filesystem::read_page] reads.
+pub const PAGE_SIZE: usize = 4096;
+
+/// Represents an error that can occur while using the VFS, or one of its filesystems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VfsError {
+    /// Occurs when a path doesn't lead to a file or directory.
+    NotFound,
+
+    /// Occurs when a directory was expected, but a file was found.
+    NotADirectory,
+
+    /// Occurs when a file was expected, but a directory was found.
+    IsADirectory,
+
+    /// Occurs when a file or directory with the same name already exists.
+    AlreadyExists,
+
+    /// Occurs when a directory that still has entries is removed.
+    DirectoryNotEmpty,
+
+    /// Occurs when a path isn't absolute, is too deep, or a name isn't allowed by the filesystem.
+    InvalidPath,
+
+    /// Occurs when a single component of a path is longer than [MAX_NAME_LENGTH].
+    NameTooLong,
+
+    /// Occurs when a filesystem that is mounted read-only would be changed.
+    ReadOnly,
+
+    /// Occurs when a file is read or written without being opened to do so.
+    PermissionDenied,
+
+    /// Occurs when the filesystem is full.
+    NoSpace,
+
+    /// Occurs when a file would grow past the most that its filesystem can hold.
+    FileTooLarge,
+
+    /// Occurs when a seek would move before the start of a file.
+    InvalidSeek,
+
+    /// Occurs when [file::MAX_OPEN_FILES] files are already open.
+    TooManyOpenFiles,
+
+    /// Occurs when a file descriptor isn't open.
+    BadFileDescriptor,
+
+    /// Occurs when [mount::MAX_MOUNTS] filesystems are already mounted.
+    TooManyMounts,
+
+    /// Occurs when a filesystem is already mounted at the path, or is unmounted while in use.
+    MountPointBusy,
+
+    /// Occurs when a file that is open is removed.
+    Busy,
+
+    /// Occurs when the device that holds the filesystem can't be read from or written to.
+    Io,
+
+    /// Occurs when the filesystem's structures are damaged.
+    Corrupted,
+}
+
+/// Returns the metadata of the file or directory at [path].
+pub fn stat(path: &str) -> Result<Metadata, VfsError> {
+    metadata(resolve(&Path::parse(path)?)?)
+}
+
+/// Creates an empty file or directory at [path], whose parent directory must already exist.
+pub fn create(path: &str, kind: FileType) -> Result<Inode, VfsError> {
+    let parsed = Path::parse(path)?;
+    let (parent, name) = parsed.split_last().ok_or(VfsError::AlreadyExists)?;
+
+    let parent = resolve(&parent)?;
+    let mount = mount_of(parent)?;
+    if mount.read_only {
+        return Err(VfsError::ReadOnly);
+    }
+
+    let id = mount.filesystem.create(parent.id, name, kind)?;
+    dentry::insert(parent, name, id);
+
+    Ok(Inode {
+        mount: parent.mount,
+        id,
+    })
+}
+
+/// Removes the file or empty directory at [path], which must not be open or a mount point.
+pub fn remove(path: &str) -> Result<(), VfsError> {
+    let parsed = Path::parse(path)?;
+    let (parent_path, name) = parsed.split_last().ok_or(VfsError::MountPointBusy)?;
+
+    let (_, _, depth) = mount::find(&parsed).ok_or(VfsError::NotFound)?;
+    if depth == parsed.components().len() {
+        return Err(VfsError::MountPointBusy);
+    }
+
+    let inode = resolve(&parsed)?;
+    if file::is_open(inode) {
+        return Err(VfsError::Busy);
+    }
+
+    let parent = resolve(&parent_path)?;
+    let mount = mount_of(parent)?;
+    if mount.read_only {
+        return Err(VfsError::ReadOnly);
+    }
+
+    mount.filesystem.remove(parent.id, name)?;
+    dentry::forget_inode(inode);
+    page_cache::forget_inode(inode);
+    Ok(())
+}
+
+/// Walks [path] from the root of the filesystem that it is mounted on, looking up each of its
+/// remaining components in the dentry cache before asking the filesystem.
+fn resolve(path: &Path) -> Result<Inode, VfsError> {
+    let (index, mount, depth) = mount::find(path).ok_or(VfsError::NotFound)?;
+
+    let mut inode = Inode {
+        mount: index,
+        id: mount.filesystem.root(),
+    };
+
+    for name in &path.components()[depth..] {
+        let id = match dentry::lookup(inode, name) {
+            Some(id) => id,
+            None => {
+                let id = mount.filesystem.lookup(inode.id, name)?;
+                dentry::insert(inode, name, id);
+                id
+            }
+        };
+
+        inode = Inode { mount: index, id };
+    }
+
+    Ok(inode)
+}
+
+/// Returns the metadata of [inode], which is also read-only if its mount is.
+fn metadata(inode: Inode) -> Result<Metadata, VfsError> {
+    let mount = mount_of(inode)?;
+
+    let mut metadata = mount.filesystem.metadata(inode.id)?;
+    metadata.read_only |= mount.read_only;
+    Ok(metadata)
+}
+
+fn mount_of(inode: Inode) -> Result<Mount, VfsError> {
+    mount::get(inode.mount).ok_or(VfsError::NotFound)
+}
@@ -0,0 +1,178 @@
+use super::{
+    dentry, file, page_cache, resolve, FileType, Filesystem, Path, VfsError, MAX_NAME_LENGTH,
+};
+use crate::{println, sync::RwLock};
+
+/// The most filesystems that can be mounted at once.
+pub const MAX_MOUNTS: usize = 8;
+
+/// The longest path that a filesystem can be mounted at.
+const MAX_MOUNT_PATH_LENGTH: usize = MAX_NAME_LENGTH + 1;
+
+/// A filesystem that is mounted at a path.
+#[derive(Clone, Copy)]
+pub struct Mount {
+    path: [u8; MAX_MOUNT_PATH_LENGTH],
+    path_length: usize,
+    pub filesystem: &'static dyn Filesystem,
+
+    /// Whether the filesystem can't be changed through this mount.
+    pub read_only: bool,
+}
+
+struct MountTable {
+    mounts: [Option<Mount>; MAX_MOUNTS],
+}
+
+/// Read whenever a path is resolved, and only written by [mount] and [unmount].
+static MOUNTS: RwLock<MountTable> = RwLock::new(MountTable {
+    mounts: [None; MAX_MOUNTS],
+});
+
+/// Mounts [filesystem] at [path].
+///
+/// If [path] exists on another mounted filesystem, it has to be a directory there. Otherwise the
+/// mount point doesn't have to exist, so e.g. `/boot` can be mounted before there is a root, or
+/// on a read-only root that has no `/boot` directory.
+pub fn mount(
+    path: &str,
+    filesystem: &'static dyn Filesystem,
+    read_only: bool,
+) -> Result<(), VfsError> {
+    let parsed = Path::parse(path)?;
+
+    let mut mount = Mount {
+        path: [0; MAX_MOUNT_PATH_LENGTH],
+        path_length: 0,
+        filesystem,
+        read_only: read_only || filesystem.is_read_only(),
+    };
+
+    // The path is stored normalized, so it can be compared component by component.
+    for component in parsed.components() {
+        let end = mount.path_length + 1 + component.len();
+        let slot = mount
+            .path
+            .get_mut(mount.path_length..end)
+            .ok_or(VfsError::NameTooLong)?;
+
+        slot[0] = b'/';
+        slot[1..].copy_from_slice(component.as_bytes());
+        mount.path_length = end;
+    }
+
+    if find(&parsed).is_some() {
+        match resolve(&parsed) {
+            Ok(inode) if super::metadata(inode)?.kind != FileType::Directory => {
+                return Err(VfsError::NotADirectory)
+            }
+            Ok(_) | Err(VfsError::NotFound) => {}
+            Err(error) => return Err(error),
+        }
+    }
+
+    let mut table = MOUNTS.write();
+    if table
+        .mounts
+        .iter()
+        .flatten()
+        .any(|it| it.path() == mount.path())
+    {
+        return Err(VfsError::MountPointBusy);
+    }
+
+    let slot = table
+        .mounts
+        .iter_mut()
+        .find(|it| it.is_none())
+        .ok_or(VfsError::TooManyMounts)?;
+
+    *slot = Some(mount);
+    println!(
+        "[angeldust::vfs] mounted {} at {}{}",
+        filesystem.name(),
+        mount.path(),
+        if mount.read_only { " (read-only)" } else { "" }
+    );
+
+    Ok(())
+}
+
+/// Unmounts the filesystem at [path], after syncing it.
+/// Fails with [VfsError::MountPointBusy] if any of its files are still open.
+pub fn unmount(path: &str) -> Result<(), VfsError> {
+    let parsed = Path::parse(path)?;
+    let (index, mount, _) = find(&parsed).ok_or(VfsError::NotFound)?;
+    if mount.path_components().count() != parsed.components().len() {
+        return Err(VfsError::NotFound);
+    }
+
+    if file::is_mount_in_use(index) {
+        return Err(VfsError::MountPointBusy);
+    }
+
+    mount.filesystem.sync()?;
+    dentry::forget_mount(index);
+    page_cache::forget_mount(index);
+
+    let mut table = MOUNTS.write();
+    table.mounts[index] = None;
+    Ok(())
+}
+
+/// Writes anything that is pending on every mounted filesystem to its device.
+pub fn sync_all() -> Result<(), VfsError> {
+    for index in 0..MAX_MOUNTS {
+        if let Some(mount) = get(index) {
+            mount.filesystem.sync()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the mount at [index] in the mount table.
+pub fn get(index: usize) -> Option<Mount> {
+    let table = MOUNTS.read();
+    table.mounts.get(index).copied().flatten()
+}
+
+/// Finds the mount that [path] is on, which is the one with the longest matching path.
+/// Returns its index, the mount, and the amount of components of [path] that it covers.
+pub(super) fn find(path: &Path) -> Option<(usize, Mount, usize)> {
+    let table = MOUNTS.read();
+
+    table
+        .mounts
+        .iter()
+        .enumerate()
+        .filter_map(|(index, mount)| Some((index, (*mount)?)))
+        .filter_map(|(index, mount)| {
+            let mut depth = 0;
+            for component in mount.path_components() {
+                if path.components().get(depth) != Some(&component) {
+                    return None;
+                }
+
+                depth += 1;
+            }
+
+            Some((index, mount, depth))
+        })
+        .max_by_key(|(_, _, depth)| *depth)
+}
+
+impl Mount {
+    /// The normalized path that the filesystem is mounted at.
+    pub fn path(&self) -> &str {
+        match core::str::from_utf8(&self.path[..self.path_length]) {
+            Ok("") => "/",
+            Ok(path) => path,
+            Err(_) => "?",
+        }
+    }
+
+    fn path_components(&self) -> impl Iterator<Item = &str> {
+        self.path().split('/').filter(|it| !it.is_empty())
+    }
+}
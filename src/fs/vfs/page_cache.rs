@@ -0,0 +1,134 @@
+use super::{Filesystem, Inode, VfsError, PAGE_SIZE};
+use crate::sync::Mutex;
+
+/// The amount of pages that are cached.
+const CACHE_PAGES: usize = 16;
+
+/// A page of a file, which is identified by its inode and its index within the file.
+struct Page {
+    key: Option<(Inode, u64)>,
+
+    /// The amount of bytes that were read, which is less than [PAGE_SIZE] at the end of a file.
+    length: usize,
+
+    /// When the page was last used, the least recently used page is replaced first.
+    last_used: u64,
+
+    data: [u8; PAGE_SIZE],
+}
+
+struct PageCache {
+    pages: [Page; CACHE_PAGES],
+    clock: u64,
+}
+
+/// A sleeping lock, as pages are read from their filesystem while it is held.
+static PAGES: Mutex<PageCache> = Mutex::new(PageCache {
+    pages: [const {
+        Page {
+            key: None,
+            length: 0,
+            last_used: 0,
+            data: [0; PAGE_SIZE],
+        }
+    }; CACHE_PAGES],
+    clock: 0,
+});
+
+/// Reads from [inode] at [offset] into [buffer] through the cache, loading any pages that
+/// aren't cached with [Filesystem::read_page].
+/// Returns the amount of bytes that were read, which is less than requested at the end of the
+/// file.
+pub(super) fn read(
+    filesystem: &dyn Filesystem,
+    inode: Inode,
+    offset: u64,
+    buffer: &mut [u8],
+) -> Result<usize, VfsError> {
+    let mut cache = PAGES.lock();
+
+    let mut done = 0;
+    while done < buffer.len() {
+        let position = offset + done as u64;
+        let index = position / PAGE_SIZE as u64;
+        let page_offset = (position % PAGE_SIZE as u64) as usize;
+
+        let page = cache.get(filesystem, inode, index)?;
+        if page_offset >= page.length {
+            break;
+        }
+
+        let length = (page.length - page_offset).min(buffer.len() - done);
+        buffer[done..done + length].copy_from_slice(&page.data[page_offset..][..length]);
+        done += length;
+
+        if page.length < PAGE_SIZE {
+            break;
+        }
+    }
+
+    Ok(done)
+}
+
+/// Forgets the pages of [inode] that overlap the [length] bytes at [offset], as they were
+/// written to.
+pub(super) fn invalidate(inode: Inode, offset: u64, length: u64) {
+    let first = offset / PAGE_SIZE as u64;
+    let last = (offset + length).div_ceil(PAGE_SIZE as u64);
+    forget(|key| key.0 == inode && (first..last).contains(&key.1));
+}
+
+/// Forgets every page of [inode], as it was removed.
+pub(super) fn forget_inode(inode: Inode) {
+    forget(|key| key.0 == inode);
+}
+
+/// Forgets every page on the filesystem at [mount], as it was unmounted.
+pub(super) fn forget_mount(mount: usize) {
+    forget(|key| key.0.mount == mount);
+}
+
+fn forget(matches: impl Fn(&(Inode, u64)) -> bool) {
+    let mut cache = PAGES.lock();
+
+    for page in cache.pages.iter_mut() {
+        if page.key.as_ref().is_some_and(&matches) {
+            page.key = None;
+        }
+    }
+}
+
+impl PageCache {
+    /// Returns page [index] of [inode], loading it if it isn't cached.
+    fn get(
+        &mut self,
+        filesystem: &dyn Filesystem,
+        inode: Inode,
+        index: u64,
+    ) -> Result<&Page, VfsError> {
+        self.clock += 1;
+        let key = Some((inode, index));
+
+        let slot = match self.pages.iter().position(|it| it.key == key) {
+            Some(slot) => slot,
+            None => {
+                let slot = self
+                    .pages
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, it)| if it.key.is_none() { 0 } else { it.last_used })
+                    .map_or(0, |(slot, _)| slot);
+
+                let page = &mut self.pages[slot];
+                page.key = None;
+                page.length = filesystem.read_page(inode.id, index, &mut page.data)?;
+                page.key = key;
+                slot
+            }
+        };
+
+        let page = &mut self.pages[slot];
+        page.last_used = self.clock;
+        Ok(page)
+    }
+}
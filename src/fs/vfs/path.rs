@@ -0,0 +1,62 @@
+use super::{VfsError, MAX_NAME_LENGTH};
+
+/// The most components that a path may have once it is normalized.
+pub const MAX_DEPTH: usize = 32;
+
+/// An absolute path, split into its components.
+///
+/// `.` and `..` are resolved while parsing, which is safe as there are no symbolic links.
+/// `..` at the root stays at the root.
+#[derive(Debug, Clone, Copy)]
+pub struct Path<'a> {
+    components: [&'a str; MAX_DEPTH],
+    length: usize,
+}
+
+impl<'a> Path<'a> {
+    /// Parses [path], which must start with `/`.
+    pub fn parse(path: &'a str) -> Result<Path<'a>, VfsError> {
+        let Some(relative) = path.strip_prefix('/') else {
+            return Err(VfsError::InvalidPath);
+        };
+
+        let mut parsed = Path {
+            components: [""; MAX_DEPTH],
+            length: 0,
+        };
+
+        for component in relative.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => parsed.length = parsed.length.saturating_sub(1),
+                component if component.len() > MAX_NAME_LENGTH => {
+                    return Err(VfsError::NameTooLong)
+                }
+                component => {
+                    let slot = parsed
+                        .components
+                        .get_mut(parsed.length)
+                        .ok_or(VfsError::InvalidPath)?;
+
+                    *slot = component;
+                    parsed.length += 1;
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn components(&self) -> &[&'a str] {
+        &self.components[..self.length]
+    }
+
+    /// Splits the path into its parent and its last component, or returns [None] for the root.
+    pub fn split_last(&self) -> Option<(Path<'a>, &'a str)> {
+        let last = *self.components().last()?;
+
+        let mut parent = *self;
+        parent.length -= 1;
+        Some((parent, last))
+    }
+}
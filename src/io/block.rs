@@ -0,0 +1,80 @@
+use crate::io::emmc::EmmcError;
+
+/// Represents an error that can occur while reading from or writing to a [BlockDevice].
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum BlockError {
+    /// Occurs when a transfer would go past the last block of the device.
+    OutOfRange {
+        start: u64,
+        count: u64,
+        block_count: u64,
+    },
+
+    /// Occurs when the buffer's length isn't a multiple of the device's block size.
+    InvalidLength { length: usize, block_size: usize },
+
+    /// Occurs when writing to a device that can only be read.
+    ReadOnly,
+
+    /// Occurs when the SD card reports an error.
+    Emmc(EmmcError),
+}
+
+/// A device that is read and written in fixed-size blocks, like an SD card.
+///
+/// Transfers always cover whole blocks, the length of the buffer decides how many are
+/// transferred.
+pub trait BlockDevice {
+    /// The size of a block in bytes.
+    fn block_size(&self) -> usize;
+
+    /// The amount of blocks on the device.
+    fn block_count(&self) -> u64;
+
+    /// Reads the blocks starting at [start] into [buffer].
+    fn read_blocks(&self, start: u64, buffer: &mut [u8]) -> Result<(), BlockError>;
+
+    /// Writes [buffer] to the blocks starting at [start].
+    fn write_blocks(&self, start: u64, buffer: &[u8]) -> Result<(), BlockError>;
+
+    /// Makes sure that a transfer of [length] bytes from [start] covers whole blocks, and doesn't
+    /// go past the end of the device, returning the amount of blocks that it covers.
+    fn check_transfer(&self, start: u64, length: usize) -> Result<u64, BlockError> {
+        let block_size = self.block_size();
+        if !length.is_multiple_of(block_size) {
+            return Err(BlockError::InvalidLength { length, block_size });
+        }
+
+        let count = (length / block_size) as u64;
+        let block_count = self.block_count();
+        if start.checked_add(count).is_none_or(|end| end > block_count) {
+            return Err(BlockError::OutOfRange {
+                start,
+                count,
+                block_count,
+            });
+        }
+
+        Ok(count)
+    }
+}
+
+/// Allows a [BlockDevice] to be shared, for example by every partition on it.
+impl<T: BlockDevice + ?Sized> BlockDevice for &T {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn block_count(&self) -> u64 {
+        (**self).block_count()
+    }
+
+    fn read_blocks(&self, start: u64, buffer: &mut [u8]) -> Result<(), BlockError> {
+        (**self).read_blocks(start, buffer)
+    }
+
+    fn write_blocks(&self, start: u64, buffer: &[u8]) -> Result<(), BlockError> {
+        (**self).write_blocks(start, buffer)
+    }
+}
@@ -0,0 +1,177 @@
+use super::message::{
+    ClockId, ClockRateMessage, ClockState, ClockStateMessage, SetClockRateMessage, TurboMessage,
+};
+use crate::io::mailbox::{types::MessageTag, Channel, Mailbox, MailboxError};
+use core::fmt::Debug;
+
+/// Represents an error that can occur while managing the [Clocks].
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum ClockError {
+    /// Occurs when the firmware reports that the requested clock does not exist on this board.
+    DoesNotExist(ClockId),
+
+    /// Occurs when the firmware responds about a different clock than the one we asked about.
+    /// This usually means that the firmware does not understand the tag that was sent.
+    UnexpectedClock { expected: ClockId, actual: u32 },
+
+    /// Occurs when the mailbox returns an error that we can not recover from.
+    Mailbox(MailboxError),
+}
+
+/// A snapshot of the rates of a single clock, in Hz.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockRates {
+    pub state: ClockState,
+    pub rate: u32,
+    pub measured_rate: u32,
+    pub min_rate: u32,
+    pub max_rate: u32,
+}
+
+/// Manages the clocks of the Raspberry Pi through the [Mailbox].
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#clocks
+#[derive(Clone, Copy)]
+pub struct Clocks {
+    mailbox: Mailbox,
+}
+
+impl Clocks {
+    /// Creates a new instance of [Clocks], which will send its messages through [mailbox].
+    pub const fn new(mailbox: Mailbox) -> Clocks {
+        Clocks { mailbox }
+    }
+
+    /// Returns the [ClockState] of [clock].
+    ///
+    /// ## Errors
+    /// - [ClockError::DoesNotExist] if the clock is not present on this board.
+    pub fn state(&self, clock: ClockId) -> Result<ClockState, ClockError> {
+        let response = self.send(clock, ClockStateMessage::new_get(clock), |it| it.clock_id)?;
+        let state = ClockState::from_bits_retain(response.state);
+
+        if state.contains(ClockState::DoesNotExist) {
+            return Err(ClockError::DoesNotExist(clock));
+        }
+
+        Ok(state)
+    }
+
+    /// Turns [clock] on or off, returning the new [ClockState].
+    pub fn set_state(&self, clock: ClockId, on: bool) -> Result<ClockState, ClockError> {
+        let response = self.send(clock, ClockStateMessage::new_set(clock, on), |it| {
+            it.clock_id
+        })?;
+        let state = ClockState::from_bits_retain(response.state);
+
+        if state.contains(ClockState::DoesNotExist) {
+            return Err(ClockError::DoesNotExist(clock));
+        }
+
+        Ok(state)
+    }
+
+    /// Returns the rate that was last requested for [clock] in Hz.
+    pub fn rate(&self, clock: ClockId) -> Result<u32, ClockError> {
+        self.send(clock, ClockRateMessage::new_get(clock), |it| it.clock_id)
+            .map(|it| it.rate)
+    }
+
+    /// Returns the rate that [clock] is actually running at in Hz.
+    pub fn measured_rate(&self, clock: ClockId) -> Result<u32, ClockError> {
+        self.send(clock, ClockRateMessage::new_get_measured(clock), |it| {
+            it.clock_id
+        })
+        .map(|it| it.rate)
+    }
+
+    /// Returns the maximum supported rate of [clock] in Hz.
+    pub fn max_rate(&self, clock: ClockId) -> Result<u32, ClockError> {
+        self.send(clock, ClockRateMessage::new_get_max(clock), |it| {
+            it.clock_id
+        })
+        .map(|it| it.rate)
+    }
+
+    /// Returns the minimum supported rate of [clock] in Hz.
+    pub fn min_rate(&self, clock: ClockId) -> Result<u32, ClockError> {
+        self.send(clock, ClockRateMessage::new_get_min(clock), |it| {
+            it.clock_id
+        })
+        .map(|it| it.rate)
+    }
+
+    /// Requests that [clock] runs at [rate] Hz, returning the rate that the firmware settled on.
+    ///
+    /// Unless [skip_setting_turbo] is set, the firmware will also raise the turbo state (and the
+    /// voltage) when the rate requires it.
+    pub fn set_rate(
+        &self,
+        clock: ClockId,
+        rate: u32,
+        skip_setting_turbo: bool,
+    ) -> Result<u32, ClockError> {
+        self.send(
+            clock,
+            SetClockRateMessage::new(clock, rate, skip_setting_turbo),
+            |it| it.clock_id,
+        )
+        .map(|it| it.rate)
+    }
+
+    /// Raises [clock] to its maximum supported rate, returning the rate that the firmware settled on.
+    pub fn set_max_rate(&self, clock: ClockId) -> Result<u32, ClockError> {
+        let max_rate = self.max_rate(clock)?;
+        self.set_rate(clock, max_rate, false)
+    }
+
+    /// Whether or not turbo is currently enabled.
+    pub fn turbo(&self) -> Result<bool, ClockError> {
+        self.mailbox
+            .send_single::<_, TurboMessage>(Channel::PropertyTags, TurboMessage::new_get())
+            .map(|it| it.level != 0)
+            .map_err(ClockError::Mailbox)
+    }
+
+    /// Enables or disables turbo, returning the new turbo state.
+    pub fn set_turbo(&self, enabled: bool) -> Result<bool, ClockError> {
+        self.mailbox
+            .send_single::<_, TurboMessage>(Channel::PropertyTags, TurboMessage::new_set(enabled))
+            .map(|it| it.level != 0)
+            .map_err(ClockError::Mailbox)
+    }
+
+    /// Collects all of the rates of [clock] at once.
+    pub fn rates(&self, clock: ClockId) -> Result<ClockRates, ClockError> {
+        Ok(ClockRates {
+            state: self.state(clock)?,
+            rate: self.rate(clock)?,
+            measured_rate: self.measured_rate(clock)?,
+            min_rate: self.min_rate(clock)?,
+            max_rate: self.max_rate(clock)?,
+        })
+    }
+
+    /// Sends a single tag about [clock], making sure that the response is about the same clock.
+    fn send<T: Debug>(
+        &self,
+        clock: ClockId,
+        request: MessageTag<T>,
+        clock_id: fn(&T) -> u32,
+    ) -> Result<T, ClockError> {
+        let response: T = self
+            .mailbox
+            .send_single(Channel::PropertyTags, request)
+            .map_err(ClockError::Mailbox)?;
+
+        let actual = clock_id(&response);
+        if actual != clock as u32 {
+            return Err(ClockError::UnexpectedClock {
+                expected: clock,
+                actual,
+            });
+        }
+
+        Ok(response)
+    }
+}
@@ -0,0 +1,206 @@
+// Not all messages will be used.
+#![allow(dead_code)]
+
+use crate::io::mailbox::{types::MessageTag, TagIdentifier};
+use bitflags::bitflags;
+
+/// Represents the clocks that can be controlled through the mailbox.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#clocks
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ClockId {
+    Emmc = 0x1,
+    Uart = 0x2,
+    Arm = 0x3,
+    Core = 0x4,
+    V3d = 0x5,
+    H264 = 0x6,
+    Isp = 0x7,
+    Sdram = 0x8,
+    Pixel = 0x9,
+    Pwm = 0xA,
+    Hevc = 0xB,
+    Emmc2 = 0xC,
+    M2mc = 0xD,
+    PixelBvb = 0xE,
+}
+
+impl ClockId {
+    /// Every clock known to the firmware, in identifier order.
+    pub const ALL: [ClockId; 14] = [
+        ClockId::Emmc,
+        ClockId::Uart,
+        ClockId::Arm,
+        ClockId::Core,
+        ClockId::V3d,
+        ClockId::H264,
+        ClockId::Isp,
+        ClockId::Sdram,
+        ClockId::Pixel,
+        ClockId::Pwm,
+        ClockId::Hevc,
+        ClockId::Emmc2,
+        ClockId::M2mc,
+        ClockId::PixelBvb,
+    ];
+}
+
+bitflags! {
+    /// The state of a clock, as returned by [TagIdentifier::GetClockState].
+    /// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-clock-state
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ClockState: u32 {
+        const On = 1 << 0;
+        const DoesNotExist = 1 << 1;
+    }
+}
+
+/// Gets or sets the state of a clock.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-clock-state
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#set-clock-state
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ClockStateMessage {
+    pub clock_id: u32,
+    pub state: u32,
+}
+
+impl ClockStateMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new_get(clock: ClockId) -> MessageTag<ClockStateMessage> {
+        MessageTag::new(
+            TagIdentifier::GetClockState,
+            ClockStateMessage {
+                clock_id: clock as u32,
+                state: 0,
+            },
+        )
+    }
+
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new_set(clock: ClockId, on: bool) -> MessageTag<ClockStateMessage> {
+        let state = if on {
+            ClockState::On
+        } else {
+            ClockState::empty()
+        };
+        MessageTag::new(
+            TagIdentifier::SetClockState,
+            ClockStateMessage {
+                clock_id: clock as u32,
+                state: state.bits(),
+            },
+        )
+    }
+}
+
+/// Asks for one of the rates of a clock, in Hz.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-clock-rate
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ClockRateMessage {
+    pub clock_id: u32,
+    pub rate: u32,
+}
+
+impl ClockRateMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    ///
+    /// The rate returned is the one that was last requested, which may not be what the clock is
+    /// actually running at.
+    pub fn new_get(clock: ClockId) -> MessageTag<ClockRateMessage> {
+        Self::new(TagIdentifier::GetClockRate, clock)
+    }
+
+    /// A helper function for creating a [MessageTag] for this request.
+    ///
+    /// The rate returned is the one that the clock is actually running at.
+    /// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-onboard-clock-rate-measured
+    pub fn new_get_measured(clock: ClockId) -> MessageTag<ClockRateMessage> {
+        Self::new(TagIdentifier::GetClockRateMeasured, clock)
+    }
+
+    /// A helper function for creating a [MessageTag] for this request.
+    /// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-max-clock-rate
+    pub fn new_get_max(clock: ClockId) -> MessageTag<ClockRateMessage> {
+        Self::new(TagIdentifier::GetMaxClockRate, clock)
+    }
+
+    /// A helper function for creating a [MessageTag] for this request.
+    /// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-min-clock-rate
+    pub fn new_get_min(clock: ClockId) -> MessageTag<ClockRateMessage> {
+        Self::new(TagIdentifier::GetMinClockRate, clock)
+    }
+
+    fn new(identifier: TagIdentifier, clock: ClockId) -> MessageTag<ClockRateMessage> {
+        MessageTag::new(
+            identifier,
+            ClockRateMessage {
+                clock_id: clock as u32,
+                rate: 0,
+            },
+        )
+    }
+}
+
+/// Sets the rate of a clock, in Hz.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#set-clock-rate
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SetClockRateMessage {
+    pub clock_id: u32,
+    pub rate: u32,
+
+    /// When this is 0, the firmware will also change the turbo state (and voltage) when needed.
+    /// The response does not use this field.
+    pub skip_setting_turbo: u32,
+}
+
+impl SetClockRateMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new(
+        clock: ClockId,
+        rate: u32,
+        skip_setting_turbo: bool,
+    ) -> MessageTag<SetClockRateMessage> {
+        MessageTag::new(
+            TagIdentifier::SetClockRate,
+            SetClockRateMessage {
+                clock_id: clock as u32,
+                rate,
+                skip_setting_turbo: skip_setting_turbo as u32,
+            },
+        )
+    }
+}
+
+/// Gets or sets the turbo state, which applies to every clock at once.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-turbo
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#set-turbo
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TurboMessage {
+    /// This should always be 0.
+    pub id: u32,
+
+    /// 0 for non-turbo, 1 for turbo.
+    pub level: u32,
+}
+
+impl TurboMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new_get() -> MessageTag<TurboMessage> {
+        MessageTag::new(TagIdentifier::GetTurbo, TurboMessage { id: 0, level: 0 })
+    }
+
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new_set(enabled: bool) -> MessageTag<TurboMessage> {
+        MessageTag::new(
+            TagIdentifier::SetTurbo,
+            TurboMessage {
+                id: 0,
+                level: enabled as u32,
+            },
+        )
+    }
+}
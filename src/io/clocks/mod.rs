@@ -0,0 +1,60 @@
+pub mod implementation;
+pub mod message;
+
+pub use implementation::*;
+pub use message::{ClockId, ClockState};
+
+use crate::{mailbox, println};
+
+/// Raises the ARM clock to its maximum rate and reports the rates of every clock on this board.
+///
+/// The firmware boots the ARM cores at a low clock rate, so this should be done as early as the
+/// mailbox allows.
+pub fn initialize() {
+    let clocks = instance();
+
+    match clocks.set_max_rate(ClockId::Arm) {
+        Ok(rate) => println!(
+            "[angeldust::clocks] raised the arm clock to {} MHz",
+            rate / 1_000_000
+        ),
+        Err(error) => println!(
+            "[angeldust::clocks] failed to raise the arm clock: {:?}",
+            error
+        ),
+    }
+
+    report(&clocks);
+}
+
+/// Returns an instance of [Clocks] which uses the global mailbox.
+/// You must call [mailbox::initialize] before running this.
+pub fn instance() -> Clocks {
+    Clocks::new(mailbox::instance())
+}
+
+/// Prints the state and rates of every clock.
+pub fn report(clocks: &Clocks) {
+    for clock in ClockId::ALL {
+        match clocks.rates(clock) {
+            Ok(rates) => println!(
+                "[angeldust::clocks] {:?}: {} MHz (measured {} MHz, min {} MHz, max {} MHz, on: {})",
+                clock,
+                rates.rate / 1_000_000,
+                rates.measured_rate / 1_000_000,
+                rates.min_rate / 1_000_000,
+                rates.max_rate / 1_000_000,
+                rates.state.contains(ClockState::On)
+            ),
+            Err(ClockError::DoesNotExist(_)) => {
+                println!("[angeldust::clocks] {:?}: not present", clock)
+            }
+            Err(error) => println!("[angeldust::clocks] {:?}: {:?}", clock, error),
+        }
+    }
+
+    match clocks.turbo() {
+        Ok(enabled) => println!("[angeldust::clocks] turbo: {}", enabled),
+        Err(error) => println!("[angeldust::clocks] turbo: {:?}", error),
+    }
+}
@@ -0,0 +1,90 @@
+use super::registers::CommandFlags;
+
+/// The response that the card sends back for a [Command].
+/// 4.9. Responses: https://www.sdcard.org/downloads/pls/ (Physical Layer Simplified Specification)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Response {
+    None,
+
+    /// The card status.
+    R1,
+
+    /// The card status, after which the card holds DAT0 low while it is busy.
+    R1b,
+
+    /// The CID or CSD register.
+    R2,
+
+    /// The OCR register, which is sent without a valid CRC.
+    R3,
+
+    /// The card's relative address.
+    R6,
+
+    /// The card interface condition.
+    R7,
+}
+
+/// A command that can be sent to an SD card.
+/// 4.7.4. Detailed Command Description: https://www.sdcard.org/downloads/pls/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub index: u8,
+    pub response: Response,
+
+    /// Whether this is an application specific command (ACMD), which must follow [APP_CMD].
+    pub application: bool,
+}
+
+pub const GO_IDLE_STATE: Command = Command::new(0, Response::None);
+pub const ALL_SEND_CID: Command = Command::new(2, Response::R2);
+pub const SEND_RELATIVE_ADDR: Command = Command::new(3, Response::R6);
+pub const SWITCH_FUNC: Command = Command::new(6, Response::R1);
+pub const SELECT_CARD: Command = Command::new(7, Response::R1b);
+pub const SEND_IF_COND: Command = Command::new(8, Response::R7);
+pub const SEND_CSD: Command = Command::new(9, Response::R2);
+pub const SET_BLOCKLEN: Command = Command::new(16, Response::R1);
+pub const READ_SINGLE_BLOCK: Command = Command::new(17, Response::R1);
+pub const READ_MULTIPLE_BLOCK: Command = Command::new(18, Response::R1);
+pub const WRITE_BLOCK: Command = Command::new(24, Response::R1);
+pub const WRITE_MULTIPLE_BLOCK: Command = Command::new(25, Response::R1);
+pub const APP_CMD: Command = Command::new(55, Response::R1);
+
+pub const SET_BUS_WIDTH: Command = Command::new_application(6, Response::R1);
+pub const SD_SEND_OP_COND: Command = Command::new_application(41, Response::R3);
+pub const SEND_SCR: Command = Command::new_application(51, Response::R1);
+
+impl Command {
+    const fn new(index: u8, response: Response) -> Command {
+        Command {
+            index,
+            response,
+            application: false,
+        }
+    }
+
+    const fn new_application(index: u8, response: Response) -> Command {
+        Command {
+            index,
+            response,
+            application: true,
+        }
+    }
+
+    /// Returns the value of the CMDTM register for this command, without any data flags.
+    pub fn flags(&self) -> CommandFlags {
+        let response = match self.response {
+            Response::None => CommandFlags::empty(),
+            Response::R1 | Response::R6 | Response::R7 => {
+                CommandFlags::Response48 | CommandFlags::CrcCheck | CommandFlags::IndexCheck
+            }
+            Response::R1b => {
+                CommandFlags::Response48Busy | CommandFlags::CrcCheck | CommandFlags::IndexCheck
+            }
+            Response::R2 => CommandFlags::Response136 | CommandFlags::CrcCheck,
+            Response::R3 => CommandFlags::Response48,
+        };
+
+        response | CommandFlags::from_bits_retain((self.index as u32) << 24)
+    }
+}
@@ -0,0 +1,934 @@
+use super::{
+    command::{self, Command, Response},
+    registers::{Capabilities, CommandFlags, Control0, Control1, Interrupt, Registers, Status},
+};
+use crate::{
+    arch::aarch64::cache,
+    cpu::RaspberryPi,
+    io::{
+        block::{BlockDevice, BlockError},
+        clocks::ClockError,
+        gpio::{Function, Gpio},
+        power::{DeviceId, PowerError},
+    },
+    memory,
+    sync::Mutex,
+    timer,
+};
+use core::{
+    ptr::{read_volatile, write_volatile},
+    time::Duration,
+};
+
+/// How long to wait for the host to finish a reset, or for its clock to become stable.
+const RESET_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long to wait for the card to respond to a command.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long to wait for each block of a transfer, or for a busy card to finish programming.
+const DATA_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the card may take to power up after the first ACMD41.
+const POWER_UP_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Represents an error that can occur while using the SD card.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum EmmcError {
+    /// Occurs when the SD card's power domain could not be enabled.
+    Power(PowerError),
+
+    /// Occurs when the firmware can not tell us the rate of the host's base clock.
+    Clock(ClockError),
+
+    /// Occurs when neither the firmware nor the host know the rate of the base clock.
+    UnknownBaseClock,
+
+    /// Occurs when the host doesn't finish resetting.
+    ResetTimedOut,
+
+    /// Occurs when the host's clock doesn't become stable after changing its rate.
+    ClockNotStable,
+
+    /// Occurs when the previous command or transfer never releases the command or data lines.
+    Busy,
+
+    /// Occurs when no card responds to the identification commands.
+    NoCard,
+
+    /// Occurs when the card reports a capacity that doesn't even cover its first block.
+    InvalidCapacity { block_count: u64 },
+
+    /// Occurs when the card can't run at the 2.7-3.6V that the host supplies.
+    UnsupportedVoltage,
+
+    /// Occurs when the card doesn't finish powering up within [POWER_UP_TIMEOUT].
+    NotReady,
+
+    /// Occurs when the card doesn't respond to a command.
+    CommandTimedOut { command: u8 },
+
+    /// Occurs when the host reports an error while sending a command, or receiving its response.
+    CommandFailed { command: u8, interrupt: Interrupt },
+
+    /// Occurs when the card accepted a command, but reports an error in its card status.
+    CardStatus { command: u8, status: u32 },
+
+    /// Occurs when the card doesn't send or accept a block of data in time.
+    DataTimedOut { command: u8 },
+
+    /// Occurs when the host reports an error while transferring data.
+    DataFailed { command: u8, interrupt: Interrupt },
+}
+
+/// The SD host controller that the SD card slot is connected to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controller {
+    /// The Arasan SDHCI found in the BCM2835, BCM2836 and BCM2837 (and QEMU's raspi3b).
+    Arasan,
+
+    /// The EMMC2 controller found in the BCM2711.
+    Emmc2,
+}
+
+/// The card that was found by [Emmc::initialize].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Card {
+    /// The relative card address (RCA) that the card published, which selects it.
+    pub relative_address: u32,
+
+    /// Whether the card is addressed in blocks (SDHC and SDXC), rather than in bytes (SDSC).
+    pub high_capacity: bool,
+
+    /// The capacity of the card in blocks of [Emmc::BLOCK_SIZE] bytes.
+    pub block_count: u64,
+
+    pub manufacturer_id: u8,
+    pub product_name: [u8; 5],
+
+    /// The amount of data lines that are used, either 1 or 4.
+    pub bus_width: u8,
+
+    /// Whether the card was switched to high speed mode.
+    pub high_speed: bool,
+
+    /// Whether the card's CSD says that it is permanently or temporarily write protected.
+    pub write_protected: bool,
+
+    /// The rate of the SD clock in Hz.
+    pub clock_rate: u32,
+}
+
+/// An SD card behind an SD host controller, whose blocks are transferred by programmed I/O, or
+/// by the host's ADMA2 engine when [Emmc::uses_dma].
+///
+/// https://www.sdcard.org/downloads/pls/ (Physical Layer and Host Controller Simplified
+/// Specifications)
+#[derive(Clone, Copy, Debug)]
+pub struct Emmc {
+    registers: Registers,
+    controller: Controller,
+    base_clock: u32,
+    dma: bool,
+    card: Card,
+}
+
+/// The data that is transferred by [Emmc::transfer].
+enum Buffer<'a> {
+    Read(&'a mut [u8]),
+    Write(&'a [u8]),
+}
+
+/// A 32-bit ADMA2 descriptor, which describes a single contiguous part of a transfer.
+/// 1.13.4. ADMA2: https://www.sdcard.org/downloads/pls/ (Host Controller Simplified Specification)
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct AdmaDescriptor {
+    attributes: u16,
+    length: u16,
+    address: u32,
+}
+
+#[repr(C, align(8))]
+struct DescriptorTable([AdmaDescriptor; DESCRIPTOR_COUNT]);
+
+/// The amount of [AdmaDescriptor]s that a single transfer can use.
+const DESCRIPTOR_COUNT: usize = 16;
+
+/// The amount of bytes that each [AdmaDescriptor] covers, which must fit in its 16-bit length.
+const DESCRIPTOR_LENGTH: usize = 32 * 1024;
+
+/// Held for the whole of a transfer, which sleeps rather than spins while another thread uses
+/// the card.
+static DESCRIPTORS: Mutex<DescriptorTable> =
+    Mutex::new(DescriptorTable([AdmaDescriptor::EMPTY; DESCRIPTOR_COUNT]));
+
+impl Emmc {
+    /// The power domains that must be enabled before the [Emmc] is initialized.
+    pub const POWER_DOMAINS: &'static [DeviceId] = &[DeviceId::SdCard];
+
+    /// The size of a block on the card, which is fixed at 512 bytes for SDHC and SDXC cards.
+    pub const BLOCK_SIZE: usize = 512;
+
+    /// The largest amount of blocks that are transferred by a single command.
+    const MAX_TRANSFER_BLOCKS: usize = DESCRIPTOR_COUNT * DESCRIPTOR_LENGTH / Self::BLOCK_SIZE;
+
+    /// The SD clock's rate while the card is identified, and in default and high speed mode.
+    const IDENTIFICATION_CLOCK: u32 = 400_000;
+    const DEFAULT_SPEED_CLOCK: u32 = 25_000_000;
+    const HIGH_SPEED_CLOCK: u32 = 50_000_000;
+
+    /// CMD8: Asks whether the card can run at 2.7-3.6V, with a check pattern of 0xAA.
+    const INTERFACE_CONDITION: u32 = 0x1AA;
+
+    /// ACMD41: The 2.7-3.6V voltage window, and whether the host supports high capacity cards.
+    const OCR_VOLTAGE_WINDOW: u32 = 0x00FF_8000;
+    const OCR_HIGH_CAPACITY: u32 = 1 << 30;
+    const OCR_POWERED_UP: u32 = 1 << 31;
+
+    /// R1: The card status bits that report an error in the command that was just sent.
+    /// ILLEGAL_COMMAND and COM_CRC_ERROR are left out, as they report on the previous command.
+    const CARD_STATUS_ERRORS: u32 = 0xFD38_0000;
+
+    /// ACMD6: Switches the card to a 4-bit bus.
+    const BUS_WIDTH_4: u32 = 0b10;
+
+    /// CMD6: Checks for, or switches to, high speed in function group 1.
+    const CHECK_HIGH_SPEED: u32 = 0x00FF_FFF1;
+    const SWITCH_HIGH_SPEED: u32 = 0x80FF_FFF1;
+
+    /// SLOTISR_VER: The host specification version from which the clock divisor is 10 bits.
+    const HOST_VERSION_3: u32 = 2;
+
+    /// CONTROL1: The largest data timeout (TMCLK * 2^27), as the transfers are timed in software.
+    const DATA_TIMEOUT_EXPONENT: u32 = 0xE << 16;
+
+    /// The host controllers see the first GiB of memory from 0xC000_0000 (which skips the L2
+    /// cache on the BCM2837, and is the EMMC2 bus' `dma-ranges` on the BCM2711).
+    const DMA_BUS_OFFSET: usize = 0xC000_0000;
+    const DMA_LIMIT: usize = 0x4000_0000;
+
+    /// Creates a new instance of [Emmc].
+    ///
+    /// # Safety
+    /// - This function assumes that the [RaspberryPi::peripheral_base_address] is valid.
+    pub fn new(controller: Controller) -> Emmc {
+        let offset = match controller {
+            Controller::Arasan => 0x300000,
+            Controller::Emmc2 => 0x340000,
+        };
+
+        let base_address = unsafe {
+            RaspberryPi::instance()
+                .peripheral_base_address()
+                .byte_offset(offset) as *mut u32
+        };
+
+        Emmc {
+            registers: unsafe { Registers::new(base_address) },
+            controller,
+            base_clock: 0,
+            dma: false,
+            card: Card::default(),
+        }
+    }
+
+    /// Resets the host, then identifies the card and switches it to the fastest bus width and
+    /// speed that both of them support.
+    ///
+    /// [base_clock] is the rate of the host's base clock in Hz, or 0 if it should be read from
+    /// the host's capabilities. If [dma] is set, and the host has an ADMA2 engine, blocks are
+    /// transferred with it.
+    pub fn initialize(&mut self, base_clock: u32, dma: bool) -> Result<(), EmmcError> {
+        if self.controller == Controller::Arasan {
+            // The firmware routes the SD card to its own SDHOST controller, and the Arasan
+            // controller to the WiFi chip, so the card has to be moved over.
+            let gpio = Gpio::new();
+            for pin in 48..=53 {
+                gpio.set_function(pin, Function::Alt3);
+            }
+        }
+
+        self.reset()?;
+
+        let capabilities = self.capabilities();
+        self.base_clock = match base_clock {
+            0 => ((capabilities & Capabilities::BaseClock).bits() >> 8) * 1_000_000,
+            rate => rate,
+        };
+
+        if self.base_clock == 0 {
+            return Err(EmmcError::UnknownBaseClock);
+        }
+
+        self.dma = dma && capabilities.contains(Capabilities::Adma2);
+
+        unsafe {
+            write_volatile(
+                self.registers.control0,
+                (Control0::BusPower | Control0::BusVoltage3V3).bits(),
+            );
+
+            // Every interrupt is reported in INTERRUPT, but none of them are signalled, as the
+            // driver polls for them.
+            write_volatile(self.registers.interrupt_enable, 0);
+            write_volatile(self.registers.interrupt_mask, 0xFFFF_FFFF);
+            write_volatile(self.registers.interrupt, 0xFFFF_FFFF);
+        }
+
+        self.card.clock_rate = self.set_clock(Self::IDENTIFICATION_CLOCK)?;
+        self.identify_card()
+    }
+
+    /// Returns the [Card] that was found by [Emmc::initialize].
+    pub fn card(&self) -> Card {
+        self.card
+    }
+
+    /// Whether blocks are transferred with the host's ADMA2 engine.
+    pub fn uses_dma(&self) -> bool {
+        self.dma
+    }
+
+    /// Runs the card identification sequence (CMD0, CMD8, ACMD41, CMD2, CMD3), then selects the
+    /// card and switches it to a 4-bit bus and high speed mode if it supports them.
+    /// 4.2. Card Identification Mode: https://www.sdcard.org/downloads/pls/
+    fn identify_card(&mut self) -> Result<(), EmmcError> {
+        self.card = Card {
+            clock_rate: self.card.clock_rate,
+            ..Card::default()
+        };
+
+        self.send(command::GO_IDLE_STATE, 0)?;
+
+        // Version 2.00 cards echo the check pattern, older cards don't respond at all.
+        let version2 = match self.send(command::SEND_IF_COND, Self::INTERFACE_CONDITION) {
+            Ok(response) if response[0] & 0xFFF == Self::INTERFACE_CONDITION => true,
+            Ok(_) => return Err(EmmcError::UnsupportedVoltage),
+            Err(EmmcError::CommandTimedOut { .. }) => false,
+            Err(error) => return Err(error),
+        };
+
+        let mut argument = Self::OCR_VOLTAGE_WINDOW;
+        if version2 {
+            argument |= Self::OCR_HIGH_CAPACITY;
+        }
+
+        let deadline = timer::uptime() + POWER_UP_TIMEOUT;
+        let ocr = loop {
+            let ocr = match self.send(command::SD_SEND_OP_COND, argument) {
+                Ok(response) => response[0],
+                Err(EmmcError::CommandTimedOut { .. }) => return Err(EmmcError::NoCard),
+                Err(error) => return Err(error),
+            };
+
+            if ocr & Self::OCR_POWERED_UP != 0 {
+                break ocr;
+            }
+
+            if timer::uptime() > deadline {
+                return Err(EmmcError::NotReady);
+            }
+
+            timer::delay(Duration::from_millis(10));
+        };
+
+        self.card.high_capacity = ocr & Self::OCR_HIGH_CAPACITY != 0;
+
+        let cid = Self::long_response(self.send(command::ALL_SEND_CID, 0)?);
+        self.card.manufacturer_id = bits(cid, 127, 120) as u8;
+        self.card
+            .product_name
+            .copy_from_slice(&bits(cid, 103, 64).to_be_bytes()[3..]);
+
+        self.card.relative_address = self.send(command::SEND_RELATIVE_ADDR, 0)?[0] >> 16;
+
+        let csd = Self::long_response(self.send(command::SEND_CSD, self.card_argument())?);
+        self.card.block_count = Self::block_count(csd);
+        self.card.write_protected = bits(csd, 13, 12) != 0;
+
+        self.card.clock_rate = self.set_clock(Self::DEFAULT_SPEED_CLOCK)?;
+        self.send(command::SELECT_CARD, self.card_argument())?;
+
+        // SDHC and SDXC cards always use 512-byte blocks.
+        if !self.card.high_capacity {
+            self.send(command::SET_BLOCKLEN, Self::BLOCK_SIZE as u32)?;
+        }
+
+        // SCR: The first byte holds SD_SPEC, and the second holds SD_BUS_WIDTHS.
+        // 5.6. SCR register: https://www.sdcard.org/downloads/pls/
+        let mut scr = [0u8; 8];
+        self.transfer(command::SEND_SCR, 0, scr.len(), Buffer::Read(&mut scr))?;
+
+        self.card.bus_width = 1;
+        if scr[1] & 0b0100 != 0 {
+            self.send(command::SET_BUS_WIDTH, Self::BUS_WIDTH_4)?;
+            self.modify_control0(|it| it | Control0::FourBitBus);
+            self.card.bus_width = 4;
+        }
+
+        // CMD6 is only supported from version 1.10 of the specification.
+        let supports_switch = scr[0] & 0xF >= 1;
+        if supports_switch
+            && self.capabilities().contains(Capabilities::HighSpeed)
+            && self.switch_to_high_speed()?
+        {
+            self.modify_control0(|it| it | Control0::HighSpeed);
+            self.card.clock_rate = self.set_clock(Self::HIGH_SPEED_CLOCK)?;
+            self.card.high_speed = true;
+        }
+
+        Ok(())
+    }
+
+    /// Asks the card to switch to high speed mode, returning whether it did.
+    /// 4.3.10. Switch Function Command: https://www.sdcard.org/downloads/pls/
+    fn switch_to_high_speed(&self) -> Result<bool, EmmcError> {
+        // The 512-bit switch status is sent most significant byte first. Bit 401 is set if
+        // function group 1 supports high speed, and bits 379:376 hold the function that the
+        // group switched to.
+        let mut status = [0u8; 64];
+        self.transfer(
+            command::SWITCH_FUNC,
+            Self::CHECK_HIGH_SPEED,
+            status.len(),
+            Buffer::Read(&mut status),
+        )?;
+
+        if status[13] & 0b10 == 0 {
+            return Ok(false);
+        }
+
+        self.transfer(
+            command::SWITCH_FUNC,
+            Self::SWITCH_HIGH_SPEED,
+            status.len(),
+            Buffer::Read(&mut status),
+        )?;
+
+        Ok(status[16] & 0xF == 1)
+    }
+
+    /// Sends a [command] that doesn't transfer any data, returning its response.
+    fn send(&self, command: Command, argument: u32) -> Result<[u32; 4], EmmcError> {
+        self.issue(command, argument, CommandFlags::empty())
+    }
+
+    /// Sends a [command] that transfers [buffer] in blocks of [block_size] bytes.
+    /// Transfers of more than one block are stopped by the host with an automatic CMD12.
+    fn transfer(
+        &self,
+        command: Command,
+        argument: u32,
+        block_size: usize,
+        buffer: Buffer,
+    ) -> Result<(), EmmcError> {
+        let (address, length) = match &buffer {
+            Buffer::Read(buffer) => (buffer.as_ptr() as usize, buffer.len()),
+            Buffer::Write(buffer) => (buffer.as_ptr() as usize, buffer.len()),
+        };
+
+        let block_count = length / block_size;
+
+        let mut flags = CommandFlags::Data | CommandFlags::BlockCountEnable;
+        if let Buffer::Read(_) = buffer {
+            flags |= CommandFlags::DataRead;
+        }
+
+        if block_count > 1 {
+            flags |= CommandFlags::MultiBlock | CommandFlags::AutoCommand12;
+        }
+
+        self.wait_until_idle(true)?;
+
+        // The descriptors must stay untouched until the transfer has finished.
+        let mut descriptors = DESCRIPTORS.lock();
+        let dma = self.prepare_dma(&mut descriptors, address, length);
+        if dma {
+            flags |= CommandFlags::Dma;
+        }
+
+        unsafe {
+            write_volatile(
+                self.registers.block_size_count,
+                ((block_count as u32) << 16) | block_size as u32,
+            );
+        }
+
+        self.issue(command, argument, flags)?;
+
+        if !dma {
+            match buffer {
+                Buffer::Read(buffer) => {
+                    for block in buffer.chunks_exact_mut(block_size) {
+                        self.wait_for_data(command, Interrupt::ReadReady)?;
+                        for word in block.chunks_exact_mut(4) {
+                            let value = unsafe { read_volatile(self.registers.data) };
+                            word.copy_from_slice(&value.to_le_bytes());
+                        }
+                    }
+                }
+
+                Buffer::Write(buffer) => {
+                    for block in buffer.chunks_exact(block_size) {
+                        self.wait_for_data(command, Interrupt::WriteReady)?;
+                        for word in block.chunks_exact(4) {
+                            let value = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+                            unsafe { write_volatile(self.registers.data, value) }
+                        }
+                    }
+                }
+            }
+        }
+
+        self.wait_for_data(command, Interrupt::DataDone)?;
+
+        if dma {
+            // Drop anything that was speculatively loaded into the cache while the host wrote.
+            cache::clean_and_invalidate(address, length);
+        }
+
+        Ok(())
+    }
+
+    /// Fills [descriptors] for a transfer of [length] bytes at [address], returning whether the
+    /// transfer can use DMA. Buffers that the host can't reach, or that don't start and end on a
+    /// cache line, are transferred by programmed I/O instead.
+    fn prepare_dma(
+        &self,
+        descriptors: &mut DescriptorTable,
+        address: usize,
+        length: usize,
+    ) -> bool {
+        // Maintaining a line that is shared with something else would write back (or drop)
+        // whatever the CPU stores next to the buffer while the host is transferring.
+        let line_size = cache::data_line_size();
+        if !self.dma || !address.is_multiple_of(line_size) || !length.is_multiple_of(line_size) {
+            return false;
+        }
+
+        let table_address = descriptors.0.as_ptr() as usize;
+        let table_length = size_of::<DescriptorTable>();
+
+        let (Some(bus_address), Some(table_bus_address)) = (
+            Self::bus_address(address, length),
+            Self::bus_address(table_address, table_length),
+        ) else {
+            return false;
+        };
+
+        let count = length.div_ceil(DESCRIPTOR_LENGTH);
+        for (index, descriptor) in descriptors.0[..count].iter_mut().enumerate() {
+            let offset = index * DESCRIPTOR_LENGTH;
+
+            let mut attributes = AdmaDescriptor::VALID | AdmaDescriptor::TRANSFER;
+            if index == count - 1 {
+                attributes |= AdmaDescriptor::END;
+            }
+
+            *descriptor = AdmaDescriptor {
+                attributes,
+                length: (length - offset).min(DESCRIPTOR_LENGTH) as u16,
+                address: bus_address + offset as u32,
+            };
+        }
+
+        // Make sure that the host sees the descriptors, and anything that is about to be written.
+        cache::clean_and_invalidate(table_address, table_length);
+        cache::clean_and_invalidate(address, length);
+
+        unsafe { write_volatile(self.registers.adma_address, table_bus_address) }
+        self.modify_control0(|it| (it - Control0::DmaSelect) | Control0::Adma2);
+
+        true
+    }
+
+    /// Sends [command] with [flags], returning its response.
+    /// Application specific commands are preceded by [command::APP_CMD].
+    fn issue(
+        &self,
+        command: Command,
+        argument: u32,
+        flags: CommandFlags,
+    ) -> Result<[u32; 4], EmmcError> {
+        if command.application {
+            self.issue(
+                command::APP_CMD,
+                self.card_argument(),
+                CommandFlags::empty(),
+            )?;
+        }
+
+        let busy = command.response == Response::R1b;
+        self.wait_until_idle(busy || flags.contains(CommandFlags::Data))?;
+
+        unsafe {
+            write_volatile(self.registers.interrupt, 0xFFFF_FFFF);
+            write_volatile(self.registers.argument, argument);
+            write_volatile(self.registers.command, (command.flags() | flags).bits());
+        }
+
+        if let Err(interrupt) = self.wait_for(Interrupt::CommandDone, COMMAND_TIMEOUT) {
+            self.reset_lines();
+
+            if interrupt.is_empty() || interrupt.contains(Interrupt::CommandTimeout) {
+                return Err(EmmcError::CommandTimedOut {
+                    command: command.index,
+                });
+            }
+
+            return Err(EmmcError::CommandFailed {
+                command: command.index,
+                interrupt,
+            });
+        }
+
+        let response = self
+            .registers
+            .response
+            .map(|it| unsafe { read_volatile(it) });
+
+        if matches!(command.response, Response::R1 | Response::R1b)
+            && response[0] & Self::CARD_STATUS_ERRORS != 0
+        {
+            return Err(EmmcError::CardStatus {
+                command: command.index,
+                status: response[0],
+            });
+        }
+
+        // The card holds DAT0 low while it is busy, which the host reports like the end of a
+        // transfer.
+        if busy && !flags.contains(CommandFlags::Data) {
+            self.wait_for_data(command, Interrupt::DataDone)?;
+        }
+
+        Ok(response)
+    }
+
+    /// Waits for any of [interrupts] while [command] transfers data, resetting the command and
+    /// data lines if it fails.
+    fn wait_for_data(&self, command: Command, interrupts: Interrupt) -> Result<(), EmmcError> {
+        self.wait_for(interrupts, DATA_TIMEOUT)
+            .map_err(|interrupt| {
+                self.reset_lines();
+
+                if interrupt.is_empty() || interrupt.contains(Interrupt::DataTimeout) {
+                    EmmcError::DataTimedOut {
+                        command: command.index,
+                    }
+                } else {
+                    EmmcError::DataFailed {
+                        command: command.index,
+                        interrupt,
+                    }
+                }
+            })
+    }
+
+    /// Waits until all of [interrupts] are raised, then clears them.
+    /// If an error is raised instead, it is cleared and returned, and an empty [Interrupt] is
+    /// returned if nothing was raised within [timeout].
+    fn wait_for(&self, interrupts: Interrupt, timeout: Duration) -> Result<(), Interrupt> {
+        let deadline = timer::uptime() + timeout;
+
+        loop {
+            let interrupt =
+                Interrupt::from_bits_retain(unsafe { read_volatile(self.registers.interrupt) });
+
+            // The interrupt bits are cleared by writing 1 to them.
+            if interrupt.intersects(Interrupt::Errors) {
+                let errors = interrupt & Interrupt::Errors;
+                unsafe { write_volatile(self.registers.interrupt, errors.bits()) }
+                return Err(errors);
+            }
+
+            if interrupt.contains(interrupts) {
+                unsafe { write_volatile(self.registers.interrupt, interrupts.bits()) }
+                return Ok(());
+            }
+
+            if timer::uptime() > deadline {
+                return Err(Interrupt::empty());
+            }
+
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Waits until the host can send a command, and if [data] is set, use the data lines.
+    fn wait_until_idle(&self, data: bool) -> Result<(), EmmcError> {
+        let mut inhibit = Status::CommandInhibit;
+        if data {
+            inhibit |= Status::DataInhibit;
+        }
+
+        let deadline = timer::uptime() + DATA_TIMEOUT;
+        while Status::from_bits_retain(unsafe { read_volatile(self.registers.status) })
+            .intersects(inhibit)
+        {
+            if timer::uptime() > deadline {
+                return Err(EmmcError::Busy);
+            }
+
+            core::hint::spin_loop();
+        }
+
+        Ok(())
+    }
+
+    /// Resets the whole host, which also stops the SD clock.
+    fn reset(&self) -> Result<(), EmmcError> {
+        unsafe { write_volatile(self.registers.control1, Control1::ResetHost.bits()) }
+
+        if !self.wait_for_control1(|it| !it.contains(Control1::ResetHost)) {
+            return Err(EmmcError::ResetTimedOut);
+        }
+
+        Ok(())
+    }
+
+    /// Resets the command and data lines after an error, so that the next command can be sent.
+    /// This is best effort, as the next command will report the error if it doesn't work.
+    fn reset_lines(&self) {
+        let lines = Control1::ResetCommand | Control1::ResetData;
+        let control1 = unsafe { read_volatile(self.registers.control1) };
+        unsafe { write_volatile(self.registers.control1, control1 | lines.bits()) }
+
+        self.wait_for_control1(|it| !it.intersects(lines));
+    }
+
+    /// Changes the SD clock to the fastest rate that doesn't exceed [frequency], returning it.
+    fn set_clock(&self, frequency: u32) -> Result<u32, EmmcError> {
+        self.wait_until_idle(true)?;
+
+        // The clock must be stopped while its divisor changes.
+        let control1 =
+            Control1::from_bits_retain(unsafe { read_volatile(self.registers.control1) })
+                - (Control1::ClockEnable | Control1::ClockDivisor | Control1::DataTimeout);
+        unsafe { write_volatile(self.registers.control1, control1.bits()) }
+
+        // The base clock is divided by twice the divisor, or not at all if the divisor is 0.
+        // Hosts before version 3.00 only support powers of two, in 8 bits.
+        let version = (unsafe { read_volatile(self.registers.slot_version) } >> 16) & 0xFF;
+        let divisor = match self.base_clock.div_ceil(2 * frequency) {
+            _ if self.base_clock <= frequency => 0,
+            divisor if version >= Self::HOST_VERSION_3 => divisor.min(0x3FF),
+            divisor => divisor.next_power_of_two().min(0x80),
+        };
+
+        let rate = match divisor {
+            0 => self.base_clock,
+            divisor => self.base_clock / (2 * divisor),
+        };
+
+        // CONTROL1 holds the lower 8 bits of the divisor in bits 15:8, and the upper 2 in 7:6.
+        let divisor = ((divisor & 0xFF) << 8) | ((divisor >> 8) << 6);
+
+        unsafe {
+            write_volatile(
+                self.registers.control1,
+                control1.bits()
+                    | Control1::InternalClockEnable.bits()
+                    | Self::DATA_TIMEOUT_EXPONENT
+                    | divisor,
+            );
+        }
+
+        if !self.wait_for_control1(|it| it.contains(Control1::InternalClockStable)) {
+            return Err(EmmcError::ClockNotStable);
+        }
+
+        let control1 = unsafe { read_volatile(self.registers.control1) };
+        unsafe {
+            write_volatile(
+                self.registers.control1,
+                control1 | Control1::ClockEnable.bits(),
+            );
+        }
+
+        Ok(rate)
+    }
+
+    /// Waits up to [RESET_TIMEOUT] for [condition] to hold for CONTROL1, returning whether it
+    /// did.
+    fn wait_for_control1(&self, condition: impl Fn(Control1) -> bool) -> bool {
+        let deadline = timer::uptime() + RESET_TIMEOUT;
+
+        loop {
+            let control1 =
+                Control1::from_bits_retain(unsafe { read_volatile(self.registers.control1) });
+            if condition(control1) {
+                return true;
+            }
+
+            if timer::uptime() > deadline {
+                return false;
+            }
+
+            core::hint::spin_loop();
+        }
+    }
+
+    fn modify_control0(&self, modify: impl FnOnce(Control0) -> Control0) {
+        unsafe {
+            let control0 = Control0::from_bits_retain(read_volatile(self.registers.control0));
+            write_volatile(self.registers.control0, modify(control0).bits());
+        }
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::from_bits_retain(unsafe { read_volatile(self.registers.capabilities) })
+    }
+
+    /// The argument that addresses the selected card, for commands like CMD7 and CMD55.
+    fn card_argument(&self) -> u32 {
+        self.card.relative_address << 16
+    }
+
+    /// Returns the argument that addresses [block] for a read or write command.
+    fn block_argument(&self, block: u64) -> u32 {
+        match self.card.high_capacity {
+            true => block as u32,
+            false => (block * Self::BLOCK_SIZE as u64) as u32,
+        }
+    }
+
+    /// Returns the 136-bit response to CMD2 or CMD9 as the register that was sent.
+    /// The host strips the CRC, and stores bits 127:8 of the register in bits 119:0.
+    fn long_response(response: [u32; 4]) -> u128 {
+        let value = response
+            .iter()
+            .rev()
+            .fold(0u128, |value, it| (value << 32) | *it as u128);
+
+        value << 8
+    }
+
+    /// Returns the capacity of the card in blocks of [Emmc::BLOCK_SIZE] bytes from its CSD.
+    /// 5.3. CSD Register: https://www.sdcard.org/downloads/pls/
+    fn block_count(csd: u128) -> u64 {
+        match bits(csd, 127, 126) {
+            // Version 1.0: (C_SIZE + 1) * 2^(C_SIZE_MULT + 2) * 2^READ_BL_LEN bytes.
+            0 => {
+                let size = bits(csd, 73, 62) + 1;
+                let multiplier = bits(csd, 49, 47) + 2;
+                let block_length = bits(csd, 83, 80);
+
+                (size << (multiplier + block_length)) / Self::BLOCK_SIZE as u64
+            }
+
+            // Version 2.0 and 3.0: (C_SIZE + 1) * 512 KiB.
+            _ => (bits(csd, 69, 48) + 1) * 1024,
+        }
+    }
+
+    /// Returns the bus address at which the host sees [length] bytes from the kernel's
+    /// [address], if it can reach all of them.
+    fn bus_address(address: usize, length: usize) -> Option<u32> {
+        let physical = memory::physical_address(address as u64)? as usize;
+        if physical + length > Self::DMA_LIMIT {
+            return None;
+        }
+
+        Some((physical + Self::DMA_BUS_OFFSET) as u32)
+    }
+}
+
+impl BlockDevice for Emmc {
+    fn block_size(&self) -> usize {
+        Self::BLOCK_SIZE
+    }
+
+    fn block_count(&self) -> u64 {
+        self.card.block_count
+    }
+
+    fn read_blocks(&self, start: u64, buffer: &mut [u8]) -> Result<(), BlockError> {
+        self.check_transfer(start, buffer.len())?;
+
+        let chunk_length = Self::MAX_TRANSFER_BLOCKS * Self::BLOCK_SIZE;
+        for (index, chunk) in buffer.chunks_mut(chunk_length).enumerate() {
+            let block = start + (index * Self::MAX_TRANSFER_BLOCKS) as u64;
+            let command = match chunk.len() {
+                Self::BLOCK_SIZE => command::READ_SINGLE_BLOCK,
+                _ => command::READ_MULTIPLE_BLOCK,
+            };
+
+            self.transfer(
+                command,
+                self.block_argument(block),
+                Self::BLOCK_SIZE,
+                Buffer::Read(chunk),
+            )
+            .map_err(BlockError::Emmc)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_blocks(&self, start: u64, buffer: &[u8]) -> Result<(), BlockError> {
+        self.check_transfer(start, buffer.len())?;
+        if self.card.write_protected {
+            return Err(BlockError::ReadOnly);
+        }
+
+        let chunk_length = Self::MAX_TRANSFER_BLOCKS * Self::BLOCK_SIZE;
+        for (index, chunk) in buffer.chunks(chunk_length).enumerate() {
+            let block = start + (index * Self::MAX_TRANSFER_BLOCKS) as u64;
+            let command = match chunk.len() {
+                Self::BLOCK_SIZE => command::WRITE_BLOCK,
+                _ => command::WRITE_MULTIPLE_BLOCK,
+            };
+
+            self.transfer(
+                command,
+                self.block_argument(block),
+                Self::BLOCK_SIZE,
+                Buffer::Write(chunk),
+            )
+            .map_err(BlockError::Emmc)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Card {
+    /// The capacity of the card in MiB.
+    pub fn capacity_mib(&self) -> u64 {
+        self.block_count * Emmc::BLOCK_SIZE as u64 / (1024 * 1024)
+    }
+
+    /// The product name from the card's CID, which is usually ASCII.
+    pub fn product_name(&self) -> &str {
+        core::str::from_utf8(&self.product_name).unwrap_or("?")
+    }
+}
+
+impl AdmaDescriptor {
+    const EMPTY: AdmaDescriptor = AdmaDescriptor {
+        attributes: 0,
+        length: 0,
+        address: 0,
+    };
+
+    /// Valid, End, and the "tran" action, which transfers the data that the descriptor covers.
+    const VALID: u16 = 1 << 0;
+    const END: u16 = 1 << 1;
+    const TRANSFER: u16 = 0b10 << 4;
+}
+
+/// Returns bits [high]:[low] of [value].
+fn bits(value: u128, high: u32, low: u32) -> u64 {
+    ((value >> low) & ((1 << (high - low + 1)) - 1)) as u64
+}
+
+/// # Safety
+/// - We always use [Emmc] within a [crate::Mutex].
+unsafe impl Send for Emmc {}
+
+/// # Safety
+/// - We always use [Emmc] within a [crate::Mutex].
+unsafe impl Sync for Emmc {}
@@ -0,0 +1,112 @@
+pub mod command;
+pub mod implementation;
+pub mod registers;
+
+pub use implementation::*;
+
+use crate::{
+    cpu::{BoardType, RaspberryPi},
+    io::{
+        block::{BlockDevice, BlockError},
+        clocks::{self, ClockId},
+        power,
+    },
+    params::{self, Parameter, ParameterError},
+    println,
+    sync::Mutex,
+};
+
+static EMMC: Mutex<Option<Emmc>> = Mutex::new(None);
+
+/// The parameters accepted by the SD card driver.
+pub const PARAMETERS: &[Parameter] = &[Parameter {
+    key: "emmc.dma",
+    default: "off",
+    description: "transfer blocks with the sd host's dma engine if it has one (on or off)",
+    apply: |params, value| {
+        params.emmc.dma = match value {
+            "on" => true,
+            "off" => false,
+            _ => {
+                return Err(ParameterError::UnknownValue {
+                    expected: "on or off",
+                })
+            }
+        };
+
+        Ok(())
+    },
+}];
+
+/// The SD card driver's [crate::params::BootParams].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EmmcParams {
+    pub dma: bool,
+}
+
+/// Finds the SD card behind this board's SD host controller, and prepares it for transfers.
+///
+/// The Raspberry Pi 3 (and QEMU's raspi3b with `-sd`) uses the Arasan controller, while the
+/// Raspberry Pi 4 uses EMMC2. [crate::mailbox::initialize] must be called before this.
+pub fn initialize() -> Result<(), EmmcError> {
+    let (controller, clock) = match RaspberryPi::instance().board_type() {
+        BoardType::Pi4 => (Controller::Emmc2, ClockId::Emmc2),
+        _ => (Controller::Arasan, ClockId::Emmc),
+    };
+
+    power::enable_domains("emmc", Emmc::POWER_DOMAINS).map_err(EmmcError::Power)?;
+
+    // Nothing else in the kernel turns the controller's clock on, so don't rely on the firmware.
+    let clocks = clocks::instance();
+    clocks.set_state(clock, true).map_err(EmmcError::Clock)?;
+    let base_clock = clocks.rate(clock).map_err(EmmcError::Clock)?;
+
+    let params = params::instance().emmc;
+    let mut emmc = Emmc::new(controller);
+    emmc.initialize(base_clock, params.dma)?;
+
+    if params.dma && !emmc.uses_dma() {
+        println!(
+            "[angeldust::emmc] warning: the {:?} controller can't do dma, using programmed i/o",
+            controller
+        );
+    }
+
+    // Make sure that blocks can actually be transferred before anything relies on the card.
+    let mut block = [0u8; Emmc::BLOCK_SIZE];
+    emmc.read_blocks(0, &mut block)
+        .map_err(|error| match error {
+            BlockError::Emmc(error) => error,
+            _ => EmmcError::InvalidCapacity {
+                block_count: emmc.card().block_count,
+            },
+        })?;
+
+    let card = emmc.card();
+    println!(
+        "[angeldust::emmc] found a {} MiB card '{}' (manufacturer {:#04x}) with a {}-bit bus at {} kHz{}{}",
+        card.capacity_mib(),
+        card.product_name(),
+        card.manufacturer_id,
+        card.bus_width,
+        card.clock_rate / 1000,
+        if card.high_speed { " (high speed)" } else { "" },
+        if card.write_protected {
+            ", write protected"
+        } else {
+            ""
+        }
+    );
+
+    *EMMC.lock() = Some(emmc);
+    Ok(())
+}
+
+/// Retrieves the global [Emmc].
+/// You must call [initialize] before running this.
+pub fn instance() -> Emmc {
+    match *EMMC.lock() {
+        Some(instance) => instance,
+        _ => panic!("emmc::initialize() should be called before emmc::instance()"),
+    }
+}
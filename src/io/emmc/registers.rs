@@ -0,0 +1,137 @@
+use bitflags::bitflags;
+
+/// The registers of an SD host controller, which follow the SD Host Controller Simplified
+/// Specification. The BCM2835 datasheet names are used where they exist.
+///
+/// 5.4. Registers: https://datasheets.raspberrypi.com/bcm2835/bcm2835-peripherals.pdf
+/// https://www.sdcard.org/downloads/pls/ (SD Host Controller Simplified Specification)
+#[derive(Clone, Copy, Debug)]
+pub struct Registers {
+    pub block_size_count: *mut u32,
+    pub argument: *mut u32,
+    pub command: *mut u32,
+    pub response: [*mut u32; 4],
+    pub data: *mut u32,
+    pub status: *mut u32,
+    pub control0: *mut u32,
+    pub control1: *mut u32,
+    pub interrupt: *mut u32,
+    pub interrupt_mask: *mut u32,
+    pub interrupt_enable: *mut u32,
+    pub capabilities: *mut u32,
+    pub adma_address: *mut u32,
+    pub slot_version: *mut u32,
+}
+
+bitflags! {
+    /// CMDTM: The flags that are sent along with a command's index.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct CommandFlags: u32 {
+        /// Transfers the data with the host's DMA engine, rather than through the DATA register.
+        const Dma = 1 << 0;
+        const BlockCountEnable = 1 << 1;
+        const AutoCommand12 = 0b01 << 2;
+        const DataRead = 1 << 4;
+        const MultiBlock = 1 << 5;
+        const Response136 = 0b01 << 16;
+        const Response48 = 0b10 << 16;
+        const Response48Busy = 0b11 << 16;
+        const CrcCheck = 1 << 19;
+        const IndexCheck = 1 << 20;
+        const Data = 1 << 21;
+    }
+
+    /// STATUS
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Status: u32 {
+        const CommandInhibit = 1 << 0;
+        const DataInhibit = 1 << 1;
+        const DataActive = 1 << 2;
+        const CardInserted = 1 << 16;
+    }
+
+    /// CONTROL0, which also holds the standard host's power control register.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Control0: u32 {
+        const FourBitBus = 1 << 1;
+        const HighSpeed = 1 << 2;
+        const DmaSelect = 0b11 << 3;
+        const Adma2 = 0b10 << 3;
+        const BusPower = 1 << 8;
+        const BusVoltage3V3 = 0b111 << 9;
+    }
+
+    /// CONTROL1
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Control1: u32 {
+        const InternalClockEnable = 1 << 0;
+        const InternalClockStable = 1 << 1;
+        const ClockEnable = 1 << 2;
+        const ClockDivisor = 0xFFC0;
+        const DataTimeout = 0xF << 16;
+        const ResetHost = 1 << 24;
+        const ResetCommand = 1 << 25;
+        const ResetData = 1 << 26;
+    }
+
+    /// INTERRUPT, IRPT_MASK and IRPT_EN
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Interrupt: u32 {
+        const CommandDone = 1 << 0;
+        const DataDone = 1 << 1;
+        const WriteReady = 1 << 4;
+        const ReadReady = 1 << 5;
+        const Error = 1 << 15;
+        const CommandTimeout = 1 << 16;
+        const CommandCrc = 1 << 17;
+        const CommandEndBit = 1 << 18;
+        const CommandIndex = 1 << 19;
+        const DataTimeout = 1 << 20;
+        const DataCrc = 1 << 21;
+        const DataEndBit = 1 << 22;
+        const AutoCommand = 1 << 24;
+        const Adma = 1 << 25;
+
+        /// Every error, including the ones that aren't named.
+        const Errors = 0xFFFF_8000;
+    }
+
+    /// The standard host's capabilities register, which the BCM2835 datasheet doesn't document.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Capabilities: u32 {
+        /// The base clock in MHz, or 0 if it has to be found another way.
+        const BaseClock = 0xFF << 8;
+        const Adma2 = 1 << 19;
+        const HighSpeed = 1 << 21;
+    }
+}
+
+impl Registers {
+    /// Creates a new instance of [Registers].
+    ///
+    /// # Safety
+    /// - This assumes that the provided [base_address] is valid.
+    pub const unsafe fn new(base_address: *mut u32) -> Registers {
+        Registers {
+            block_size_count: base_address.byte_offset(0x04),
+            argument: base_address.byte_offset(0x08),
+            command: base_address.byte_offset(0x0C),
+            response: [
+                base_address.byte_offset(0x10),
+                base_address.byte_offset(0x14),
+                base_address.byte_offset(0x18),
+                base_address.byte_offset(0x1C),
+            ],
+            data: base_address.byte_offset(0x20),
+            status: base_address.byte_offset(0x24),
+            control0: base_address.byte_offset(0x28),
+            control1: base_address.byte_offset(0x2C),
+            interrupt: base_address.byte_offset(0x30),
+            interrupt_mask: base_address.byte_offset(0x34),
+            interrupt_enable: base_address.byte_offset(0x38),
+            capabilities: base_address.byte_offset(0x40),
+            adma_address: base_address.byte_offset(0x58),
+            slot_version: base_address.byte_offset(0xFC),
+        }
+    }
+}
@@ -1,17 +1,19 @@
 use super::message::{
     AllocateBufferRequest, FramebufferInitializeRequest, FramebufferInitializeResponse, PixelOrder,
 };
+use super::FramebufferParams;
 use crate::io::framebuffer::message::SetVirtualOffsetMessage;
 use crate::mailbox::{types::Message, Channel, Mailbox, MailboxError};
 use crate::{
     io::framebuffer::message::{
         GetPitchMessage, SetDepthMessage, SetDisplaySizeMessage, SetPixelOrderMessage,
     },
-    print, println,
+    memory, println,
 };
 
 /// Represents an error that can occur during the [Framebuffer]'s operations.
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum FramebufferError {
     /// Occurs when the framebuffer has already been initialized.
     AlreadyInitialized,
@@ -27,7 +29,7 @@ pub enum FramebufferError {
     },
 
     /// Occurs when the Pi does not set the pixel depth to the value that we requested.
-    UnsupportedDepth(u32),
+    UnsupportedDepth { expected: u32, actual: u32 },
 
     /// Occurs when the Pi does not set its pixel order to the one we requested.
     UnsupportedPixelOrder(PixelOrder),
@@ -55,6 +57,9 @@ struct FramebufferInfo {
 
     // The bytes-per-line of the framebuffer.
     pitch: u32,
+
+    /// The number of bits per pixel, either 16 or 32.
+    depth: u32,
 }
 
 /// Represents the Raspberry Pi's framebuffer.
@@ -66,8 +71,12 @@ pub struct Framebuffer {
 }
 
 impl Framebuffer {
-    /// Attempts to initialize the [Framebuffer].
-    pub fn initialize(&mut self, mailbox: &Mailbox) -> Result<(), FramebufferError> {
+    /// Attempts to initialize the [Framebuffer] with the size and depth from [params].
+    pub fn initialize(
+        &mut self,
+        mailbox: &Mailbox,
+        params: &FramebufferParams,
+    ) -> Result<(), FramebufferError> {
         // If the info is already set, we don't want to start allocating a new framebuffer,
         // as that will de-allocate the current one.
         if self.info.is_some() {
@@ -79,10 +88,16 @@ impl Framebuffer {
         // Furthermore, if the allocate tag is omitted, no change occurs unless it can be accomodated
         // without changing the buffer size (which is not possible most of the time).
         let request = FramebufferInitializeRequest {
-            set_physical_size_request: SetDisplaySizeMessage::new_physical(1280, 720),
-            set_virtual_size_request: SetDisplaySizeMessage::new_virtual(1280, 720),
+            set_physical_size_request: SetDisplaySizeMessage::new_physical(
+                params.width,
+                params.height,
+            ),
+            set_virtual_size_request: SetDisplaySizeMessage::new_virtual(
+                params.width,
+                params.height,
+            ),
             set_virtual_offset_request: SetVirtualOffsetMessage::new(0, 0),
-            set_depth_request: SetDepthMessage::new(32),
+            set_depth_request: SetDepthMessage::new(params.depth),
             set_pixel_order_request: SetPixelOrderMessage::new(PixelOrder::BGR),
             allocate_buffer_request: AllocateBufferRequest::new(4096),
             get_pitch_request: GetPitchMessage::new(),
@@ -90,21 +105,27 @@ impl Framebuffer {
 
         let response: FramebufferInitializeResponse = mailbox
             .send(Channel::PropertyTags, Message::new(request))
-            .map_err(|it| FramebufferError::Mailbox(it))?;
+            .map_err(FramebufferError::Mailbox)?;
 
         // Checks to make sure that in the responses, we receive options that we can work with.
         // For example, if the Pi doesn't support RGB, we will throw an error.
-        self.validate_response(&response)?;
+        self.validate_response(&response, params.depth)?;
 
         // If everything is valid, we can continue to set the info.
         self.info = Some(FramebufferInfo {
-            address: (response.allocate_buffer_response().base_address & 0x3FFFFFFF) as *mut u32,
+            address: memory::virtual_address(
+                (response.allocate_buffer_response().base_address & 0x3FFFFFFF) as u64,
+            ) as *mut u32,
             // size: response.allocate_buffer_response().size,
             pitch: response.get_pitch_response().bytes_per_line,
+            depth: params.depth,
         });
 
         println!(
-            "[angeldust::framebuffer] initialized framebuffer at {:#0x}",
+            "[angeldust::framebuffer] initialized {}x{}x{} framebuffer at {:#0x}",
+            params.width,
+            params.height,
+            params.depth,
             response.allocate_buffer_response().base_address
         );
 
@@ -119,6 +140,7 @@ impl Framebuffer {
     ///
     /// ## Safety
     /// - This function assumes that [FramebufferInfo::address] is valid.
+    #[allow(dead_code)]
     pub fn draw_pixel(&self, x: u32, y: u32, color: u32) -> Result<(), FramebufferError> {
         let info = match self.info {
             Some(value) => value,
@@ -126,9 +148,9 @@ impl Framebuffer {
         };
 
         // FIXME: Make sure X and Y don't go out of bounds? Or is that too high-level for here?
-        let offset = (x * 4) + (y * info.pitch);
+        let offset = (x * info.bytes_per_pixel()) + (y * info.pitch);
         let ptr = unsafe { info.address.byte_offset(offset.try_into().unwrap()) };
-        unsafe { *ptr = color };
+        unsafe { info.write_pixel(ptr, color) };
 
         Ok(())
     }
@@ -154,8 +176,11 @@ impl Framebuffer {
         };
 
         let mut line = unsafe {
-            info.address
-                .byte_add(((y * info.pitch) + (x * 4)).try_into().unwrap())
+            info.address.byte_add(
+                ((y * info.pitch) + (x * info.bytes_per_pixel()))
+                    .try_into()
+                    .unwrap(),
+            )
         };
 
         let mut rect_y: u32 = 0;
@@ -164,10 +189,10 @@ impl Framebuffer {
             let mut pixel = line;
 
             while rect_x < width - x {
-                unsafe { (pixel as *mut u32).write(color) };
+                unsafe { info.write_pixel(pixel, color) };
 
                 rect_x += 1;
-                pixel = unsafe { pixel.byte_add(4) };
+                pixel = unsafe { pixel.byte_add(info.bytes_per_pixel() as usize) };
             }
 
             rect_y += 1;
@@ -182,6 +207,7 @@ impl Framebuffer {
     fn validate_response(
         &self,
         response: &FramebufferInitializeResponse,
+        expected_depth: u32,
     ) -> Result<(), FramebufferError> {
         // Ensure that the display size was set to something larger than 0.
         let physical_size = response.set_physical_size_response();
@@ -203,10 +229,13 @@ impl Framebuffer {
             return Err(FramebufferError::UnsupportedPixelOrder(pixel_order));
         }
 
-        // Ensure that the depth is 32 (R, G, B, A).
+        // Ensure that the depth is the one that we asked for.
         let depth = response.set_depth_response().bits_per_pixel;
-        if depth != 32 {
-            return Err(FramebufferError::UnsupportedDepth(depth));
+        if depth != expected_depth {
+            return Err(FramebufferError::UnsupportedDepth {
+                expected: expected_depth,
+                actual: depth,
+            });
         }
 
         // Ensure that the buffer has been allocated somewhat-correctly.
@@ -225,10 +254,34 @@ impl Framebuffer {
     }
 }
 
+impl FramebufferInfo {
+    const fn bytes_per_pixel(&self) -> u32 {
+        self.depth / 8
+    }
+
+    /// Writes [color] (in ABGR format) to [pixel], converting it to RGB565 at a depth of 16.
+    ///
+    /// ## Safety
+    /// - [pixel] must be within the framebuffer.
+    unsafe fn write_pixel(&self, pixel: *mut u32, color: u32) {
+        match self.depth {
+            16 => {
+                let (red, green, blue) = (color & 0xFF, (color >> 8) & 0xFF, (color >> 16) & 0xFF);
+                let color = ((red >> 3) << 11) | ((green >> 2) << 5) | (blue >> 3);
+                (pixel as *mut u16).write(color as u16)
+            }
+
+            _ => pixel.write(color),
+        }
+    }
+}
+
 /// # Safety
-/// - We always use [Framebuffer] within a [crate::Mutex].
+/// - The [Framebuffer] is only changed before it is shared, and drawing only writes pixels,
+///   which is harmless if two threads draw over each other.
 unsafe impl Send for Framebuffer {}
 
 /// # Safety
-/// - We always use [Framebuffer] within a [crate::Mutex].
+/// - The [Framebuffer] is only changed before it is shared, and drawing only writes pixels,
+///   which is harmless if two threads draw over each other.
 unsafe impl Sync for Framebuffer {}
@@ -52,6 +52,7 @@ impl FramebufferInitializeResponse {
         self.get_pitch_response.data
     }
 
+    #[allow(dead_code)]
     pub fn set_virtual_offset_response(&self) -> SetVirtualOffsetMessage {
         self.set_virtual_offset_response.data
     }
@@ -145,6 +146,7 @@ impl SetDepthMessage {
 /// Represents the different pixel orders supported by the Raspberry Pi's framebuffer.
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[allow(dead_code, clippy::upper_case_acronyms)]
 pub enum PixelOrder {
     // Blue, Green, Red.
     BGR = 0x0,
@@ -4,22 +4,69 @@ pub use implementation::*;
 mod message;
 
 use crate::mailbox;
-use crate::mutex::Mutex;
+use crate::params::{self, Parameter};
+use crate::sync::Once;
 
-pub static FRAMEBUFFER: Mutex<Option<Framebuffer>> = Mutex::new(None);
+static FRAMEBUFFER: Once<Framebuffer> = Once::new();
+
+/// The parameters accepted by the framebuffer.
+pub const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        key: "framebuffer.width",
+        default: "1280",
+        description: "the width of the display in pixels",
+        apply: |params, value| {
+            params.framebuffer.width = params::parse_u32(value, 128, 4096)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        key: "framebuffer.height",
+        default: "720",
+        description: "the height of the display in pixels",
+        apply: |params, value| {
+            params.framebuffer.height = params::parse_u32(value, 128, 4096)?;
+            Ok(())
+        },
+    },
+    Parameter {
+        key: "framebuffer.depth",
+        default: "32",
+        description: "the number of bits per pixel (16 or 32)",
+        apply: |params, value| {
+            params.framebuffer.depth = match params::parse_u32(value, 16, 32)? {
+                depth @ (16 | 32) => depth,
+                _ => {
+                    return Err(params::ParameterError::UnknownValue {
+                        expected: "16 or 32",
+                    })
+                }
+            };
+
+            Ok(())
+        },
+    },
+];
+
+/// The framebuffer's [crate::params::BootParams].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FramebufferParams {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+}
 
 pub fn initialize() {
     let mut framebuffer = Framebuffer::default();
     framebuffer
-        .initialize(&mailbox::instance())
+        .initialize(&mailbox::instance(), &params::instance().framebuffer)
         .expect("framebuffer::initialize() failed");
 
-    *FRAMEBUFFER.lock() = Some(framebuffer);
+    FRAMEBUFFER.set(framebuffer);
 }
 
 pub fn instance() -> Framebuffer {
-    match *FRAMEBUFFER.lock() {
-        Some(instance) => instance,
-        _ => panic!("framebuffer::initialize() should be called before framebuffer::instance()"),
-    }
+    *FRAMEBUFFER
+        .get()
+        .expect("framebuffer::initialize() should be called before framebuffer::instance()")
 }
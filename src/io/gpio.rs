@@ -0,0 +1,64 @@
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::cpu::RaspberryPi;
+
+/// The general purpose I/O pins, which are shared between the peripherals through their
+/// alternate functions.
+/// 5. General Purpose I/O (GPIO): https://datasheets.raspberrypi.com/bcm2711/bcm2711-peripherals.pdf
+#[derive(Clone, Copy, Debug)]
+pub struct Gpio {
+    base_address: *mut u8,
+}
+
+/// 5.2. Alternative Function Assignments
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum Function {
+    Input = 0b000,
+    Output = 0b001,
+    Alt0 = 0b100,
+    Alt1 = 0b101,
+    Alt2 = 0b110,
+    Alt3 = 0b111,
+    Alt4 = 0b011,
+    Alt5 = 0b010,
+}
+
+impl Gpio {
+    /// The amount of GPIO pins on the BCM2835 and BCM2711.
+    pub const PIN_COUNT: u32 = 58;
+
+    /// GPFSEL0: The first function select register, each one holds the functions of 10 pins.
+    const FUNCTION_SELECT: usize = 0x00;
+
+    /// Creates a new instance of [Gpio].
+    ///
+    /// # Safety
+    /// - This function assumes that the [RaspberryPi::peripheral_base_address] is valid.
+    pub fn new() -> Gpio {
+        let base_address = unsafe {
+            RaspberryPi::instance()
+                .peripheral_base_address()
+                .byte_offset(0x200000)
+        };
+
+        Gpio { base_address }
+    }
+
+    /// Routes [pin] to [function].
+    pub fn set_function(&self, pin: u32, function: Function) {
+        assert!(pin < Self::PIN_COUNT, "gpio pin {} does not exist", pin);
+
+        let register = unsafe {
+            self.base_address
+                .byte_add(Self::FUNCTION_SELECT + (pin / 10) as usize * 4) as *mut u32
+        };
+        let shift = (pin % 10) * 3;
+
+        unsafe {
+            let value = read_volatile(register) & !(0b111 << shift);
+            write_volatile(register, value | ((function as u32) << shift));
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use super::Interrupt;
+use core::ptr::{read_volatile, write_volatile};
+
+/// The per-core interrupt routing found in the ARM local peripherals of the BCM2836 and BCM2837.
+///
+/// https://datasheets.raspberrypi.com/bcm2836/bcm2836-peripherals.pdf
+#[derive(Clone, Copy)]
+pub struct LocalInterruptController {
+    base_address: *mut u8,
+}
+
+impl LocalInterruptController {
+    /// The offset of the first core's timer interrupt control register.
+    const TIMER_CONTROL: usize = 0x40;
+
+    /// The offset of the first core's mailbox interrupt control register.
+    const MAILBOX_CONTROL: usize = 0x50;
+
+    /// The offset of the first core's IRQ source register.
+    const IRQ_SOURCE: usize = 0x60;
+
+    /// The offsets of the first core's mailbox 0 write-set and write-clear registers. Each core
+    /// has four mailboxes, so these are 16 bytes apart for each core.
+    const MAILBOX_SET: usize = 0x80;
+    const MAILBOX_CLEAR: usize = 0xC0;
+
+    /// The bit used for the virtual timer (`nCNTVIRQ`) in the control and source registers.
+    const VIRTUAL_TIMER: u32 = 1 << 3;
+
+    /// The bit used for mailbox 0 in the mailbox control register.
+    const MAILBOX_0_CONTROL: u32 = 1 << 0;
+
+    /// The bit used for mailbox 0 in the source register.
+    const MAILBOX_0: u32 = 1 << 4;
+
+    /// Creates a new instance of [LocalInterruptController].
+    ///
+    /// # Safety
+    /// - This assumes that the provided [base_address] points to the ARM local peripherals.
+    pub const unsafe fn new(base_address: *mut u8) -> LocalInterruptController {
+        LocalInterruptController { base_address }
+    }
+
+    /// Routes [interrupt] to the IRQ line of [core].
+    pub fn enable(&self, interrupt: Interrupt, core: u32) {
+        let (register, bit) = self.control_for(interrupt, core);
+        unsafe { write_volatile(register, read_volatile(register) | bit) }
+    }
+
+    /// Stops [interrupt] from being routed to [core].
+    pub fn disable(&self, interrupt: Interrupt, core: u32) {
+        let (register, bit) = self.control_for(interrupt, core);
+        unsafe { write_volatile(register, read_volatile(register) & !bit) }
+    }
+
+    /// Raises an [Interrupt::Ipi] on [core], by setting a bit in its mailbox 0.
+    pub fn send_ipi(&self, core: u32) {
+        let register = self.mailbox_register(Self::MAILBOX_SET, core);
+        unsafe { write_volatile(register, 1) }
+    }
+
+    /// Clears the source of a single bit from [LocalInterruptController::pending], so that it
+    /// isn't raised again once it has been handled. The timer is cleared by re-arming it instead.
+    pub fn acknowledge(&self, source_bit: u32, core: u32) {
+        if source_bit == Self::MAILBOX_0 {
+            let register = self.mailbox_register(Self::MAILBOX_CLEAR, core);
+            unsafe { write_volatile(register, u32::MAX) }
+        }
+    }
+
+    /// Returns the raw source bits of the interrupts pending for [core].
+    pub fn pending(&self, core: u32) -> u32 {
+        unsafe { read_volatile(self.core_register(Self::IRQ_SOURCE, core)) }
+    }
+
+    /// Maps a single bit from [LocalInterruptController::pending] to an [Interrupt].
+    pub const fn interrupt_for(source_bit: u32) -> Option<Interrupt> {
+        match source_bit {
+            Self::VIRTUAL_TIMER => Some(Interrupt::VirtualTimer),
+            Self::MAILBOX_0 => Some(Interrupt::Ipi),
+            _ => None,
+        }
+    }
+
+    /// The control register of [core] that routes [interrupt], and the bit for it.
+    fn control_for(&self, interrupt: Interrupt, core: u32) -> (*mut u32, u32) {
+        match interrupt {
+            Interrupt::VirtualTimer => (
+                self.core_register(Self::TIMER_CONTROL, core),
+                Self::VIRTUAL_TIMER,
+            ),
+            Interrupt::Ipi => (
+                self.core_register(Self::MAILBOX_CONTROL, core),
+                Self::MAILBOX_0_CONTROL,
+            ),
+        }
+    }
+
+    /// Each core has its own mailbox 0, 16 bytes apart.
+    fn mailbox_register(&self, offset: usize, core: u32) -> *mut u32 {
+        unsafe {
+            self.base_address
+                .byte_add(offset + (core as usize * 16))
+                .cast()
+        }
+    }
+
+    /// Each core has its own copy of a register, 4 bytes apart.
+    fn core_register(&self, offset: usize, core: u32) -> *mut u32 {
+        unsafe {
+            self.base_address
+                .byte_add(offset + (core as usize * 4))
+                .cast()
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use super::Interrupt;
+use core::ptr::{read_volatile, write_volatile};
+
+/// The ARM GIC-400 interrupt controller used by the BCM2711.
+///
+/// The firmware's stub has already configured the secure side of the GIC (and placed every
+/// interrupt into the non-secure group), so we only need to deal with the non-secure registers.
+///
+/// https://developer.arm.com/documentation/ddi0471/b/programmers-model/gic-400-register-map
+#[derive(Clone, Copy)]
+pub struct Gic400 {
+    distributor: *mut u8,
+    cpu_interface: *mut u8,
+}
+
+impl Gic400 {
+    const DISTRIBUTOR_CONTROL: usize = 0x000;
+    const SET_ENABLE: usize = 0x100;
+    const CLEAR_ENABLE: usize = 0x180;
+    const PRIORITY: usize = 0x400;
+    const SOFTWARE_GENERATED_INTERRUPT: usize = 0xF00;
+
+    const CPU_CONTROL: usize = 0x000;
+    const CPU_PRIORITY_MASK: usize = 0x004;
+    const CPU_ACKNOWLEDGE: usize = 0x00C;
+    const CPU_END_OF_INTERRUPT: usize = 0x010;
+
+    /// The interrupt ID returned when there is nothing left to acknowledge.
+    const SPURIOUS: u32 = 1023;
+
+    /// The private peripheral interrupt raised by the virtual timer.
+    const VIRTUAL_TIMER: u32 = 27;
+
+    /// The software generated interrupt that cores send each other.
+    const IPI: u32 = 0;
+
+    /// The shift of the target list in `GICD_SGIR`, which has a bit for each core.
+    const SGI_TARGET_SHIFT: u32 = 16;
+
+    /// Creates a new instance of [Gic400].
+    ///
+    /// # Safety
+    /// - This assumes that the provided [local_base_address] points to the ARM local peripherals
+    ///   of a BCM2711, where the GIC-400 lives.
+    pub const unsafe fn new(local_base_address: *mut u8) -> Gic400 {
+        Gic400 {
+            distributor: local_base_address.byte_add(0x4_1000),
+            cpu_interface: local_base_address.byte_add(0x4_2000),
+        }
+    }
+
+    /// Enables the distributor and the current core's CPU interface.
+    pub fn initialize(&self) {
+        unsafe { write_volatile(self.distributor(Self::DISTRIBUTOR_CONTROL), 1) };
+        self.initialize_cpu_interface();
+    }
+
+    /// Enables the current core's CPU interface, which every core has its own copy of.
+    pub fn initialize_cpu_interface(&self) {
+        unsafe {
+            // Allow interrupts of every priority through to this core.
+            write_volatile(self.cpu_interface(Self::CPU_PRIORITY_MASK), 0xFF);
+            write_volatile(self.cpu_interface(Self::CPU_CONTROL), 1);
+        }
+    }
+
+    /// Enables [interrupt].
+    /// Private peripheral and software generated interrupts are banked, so this only affects the current core.
+    pub fn enable(&self, interrupt: Interrupt) {
+        let id = Self::id_for(interrupt);
+
+        unsafe {
+            let priority = self.distributor(Self::PRIORITY + id as usize).cast::<u8>();
+            write_volatile(priority, 0xA0);
+
+            let register = self.distributor(Self::SET_ENABLE + (id as usize / 32) * 4);
+            write_volatile(register, 1 << (id % 32));
+        }
+    }
+
+    /// Disables [interrupt].
+    pub fn disable(&self, interrupt: Interrupt) {
+        let id = Self::id_for(interrupt);
+
+        unsafe {
+            let register = self.distributor(Self::CLEAR_ENABLE + (id as usize / 32) * 4);
+            write_volatile(register, 1 << (id % 32));
+        }
+    }
+
+    /// Raises an [Interrupt::Ipi] on [core].
+    pub fn send_ipi(&self, core: u32) {
+        let value = (1 << (Self::SGI_TARGET_SHIFT + core)) | Self::IPI;
+        unsafe { write_volatile(self.distributor(Self::SOFTWARE_GENERATED_INTERRUPT), value) }
+    }
+
+    /// Acknowledges the highest priority pending interrupt, returning its raw acknowledge value.
+    /// This must be passed to [Gic400::end_of_interrupt] once the interrupt has been handled.
+    pub fn acknowledge(&self) -> Option<u32> {
+        let value = unsafe { read_volatile(self.cpu_interface(Self::CPU_ACKNOWLEDGE)) };
+        if value & 0x3FF == Self::SPURIOUS {
+            return None;
+        }
+
+        Some(value)
+    }
+
+    /// Signals that the interrupt returned by [Gic400::acknowledge] has been handled.
+    pub fn end_of_interrupt(&self, value: u32) {
+        unsafe { write_volatile(self.cpu_interface(Self::CPU_END_OF_INTERRUPT), value) }
+    }
+
+    /// Maps the acknowledge value from [Gic400::acknowledge] to an [Interrupt].
+    pub const fn interrupt_for(value: u32) -> Option<Interrupt> {
+        match value & 0x3FF {
+            Self::VIRTUAL_TIMER => Some(Interrupt::VirtualTimer),
+            Self::IPI => Some(Interrupt::Ipi),
+            _ => None,
+        }
+    }
+
+    const fn id_for(interrupt: Interrupt) -> u32 {
+        match interrupt {
+            Interrupt::VirtualTimer => Self::VIRTUAL_TIMER,
+            Interrupt::Ipi => Self::IPI,
+        }
+    }
+
+    fn distributor(&self, offset: usize) -> *mut u32 {
+        unsafe { self.distributor.byte_add(offset).cast() }
+    }
+
+    fn cpu_interface(&self, offset: usize) -> *mut u32 {
+        unsafe { self.cpu_interface.byte_add(offset).cast() }
+    }
+}
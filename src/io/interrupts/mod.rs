@@ -0,0 +1,177 @@
+pub mod bcm2836;
+pub mod gic400;
+
+use crate::{
+    arch::aarch64::{daif, mpidr_el1::MultiprocessorAffinityRegister},
+    cpu::{BoardType, RaspberryPi},
+    mutex::Mutex,
+    println,
+};
+use bcm2836::LocalInterruptController;
+use gic400::Gic400;
+
+/// Represents the interrupts that the kernel knows how to handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(usize)]
+pub enum Interrupt {
+    /// The current core's generic (virtual) timer.
+    VirtualTimer,
+
+    /// Another core wants the current core's attention, see [send_ipi].
+    Ipi,
+}
+
+impl Interrupt {
+    /// The amount of variants in [Interrupt].
+    const COUNT: usize = 2;
+
+    /// Every variant of [Interrupt], in the order of their values.
+    const ALL: [Interrupt; Interrupt::COUNT] = [Interrupt::VirtualTimer, Interrupt::Ipi];
+}
+
+/// The interrupt controller used by this Raspberry Pi.
+#[derive(Clone, Copy)]
+pub enum InterruptController {
+    /// The Raspberry Pi 3 routes interrupts through the BCM2836 local peripherals.
+    Bcm2836(LocalInterruptController),
+
+    /// The Raspberry Pi 4 routes interrupts through a GIC-400.
+    Gic400(Gic400),
+}
+
+/// A function that is called whenever an [Interrupt] is raised.
+pub type InterruptHandler = fn();
+
+static CONTROLLER: Mutex<Option<InterruptController>> = Mutex::new(None);
+static HANDLERS: Mutex<[Option<InterruptHandler>; Interrupt::COUNT]> =
+    Mutex::new([None; Interrupt::COUNT]);
+
+pub fn initialize() {
+    let local_base_address = RaspberryPi::instance().local_peripheral_base_address();
+
+    let controller = match RaspberryPi::instance().board_type() {
+        BoardType::Pi4 => InterruptController::Gic400(unsafe { Gic400::new(local_base_address) }),
+        _ => InterruptController::Bcm2836(unsafe {
+            LocalInterruptController::new(local_base_address)
+        }),
+    };
+
+    if let InterruptController::Gic400(gic) = controller {
+        gic.initialize();
+    }
+
+    *CONTROLLER.lock() = Some(controller);
+}
+
+/// Sets up the current core, which isn't the one that called [initialize], to receive the
+/// interrupts that have been registered. Every interrupt is per core for now, e.g. the timer.
+pub fn initialize_core() {
+    daif::without_irqs(|| {
+        let controller = controller();
+        if let InterruptController::Gic400(gic) = controller {
+            gic.initialize_cpu_interface();
+        }
+
+        let handlers = *HANDLERS.lock();
+        for interrupt in Interrupt::ALL {
+            if handlers[interrupt as usize].is_some() {
+                match controller {
+                    InterruptController::Bcm2836(local) => local.enable(interrupt, current_core()),
+                    InterruptController::Gic400(gic) => gic.enable(interrupt),
+                }
+            }
+        }
+    });
+}
+
+/// Registers [handler] to be called whenever [interrupt] is raised, and enables the interrupt
+/// for the current core.
+///
+/// The handler runs with IRQs masked, so it should be kept short.
+pub fn register(interrupt: Interrupt, handler: InterruptHandler) {
+    daif::without_irqs(|| {
+        HANDLERS.lock()[interrupt as usize] = Some(handler);
+
+        match controller() {
+            InterruptController::Bcm2836(local) => local.enable(interrupt, current_core()),
+            InterruptController::Gic400(gic) => gic.enable(interrupt),
+        }
+    });
+}
+
+/// Stops [interrupt] from being raised on the current core, and removes its handler.
+#[allow(dead_code)]
+pub fn unregister(interrupt: Interrupt) {
+    daif::without_irqs(|| {
+        match controller() {
+            InterruptController::Bcm2836(local) => local.disable(interrupt, current_core()),
+            InterruptController::Gic400(gic) => gic.disable(interrupt),
+        }
+
+        HANDLERS.lock()[interrupt as usize] = None;
+    });
+}
+
+/// Raises an [Interrupt::Ipi] on [core], whose handler must have been registered.
+pub fn send_ipi(core: usize) {
+    match controller() {
+        InterruptController::Bcm2836(local) => local.send_ipi(core as u32),
+        InterruptController::Gic400(gic) => gic.send_ipi(core as u32),
+    }
+}
+
+/// Called by the exception handler whenever an IRQ is taken.
+/// Dispatches every pending interrupt to its registered handler.
+pub fn handle_irq() {
+    match controller() {
+        InterruptController::Bcm2836(local) => {
+            let pending = local.pending(current_core());
+
+            for bit in 0..32 {
+                let source = 1 << bit;
+                if pending & source != 0 {
+                    local.acknowledge(source, current_core());
+                    dispatch(LocalInterruptController::interrupt_for(source), source);
+                }
+            }
+        }
+
+        InterruptController::Gic400(gic) => {
+            while let Some(value) = gic.acknowledge() {
+                dispatch(Gic400::interrupt_for(value), value);
+                gic.end_of_interrupt(value);
+            }
+        }
+    }
+}
+
+fn dispatch(interrupt: Option<Interrupt>, raw: u32) {
+    let handler = interrupt.and_then(|it| HANDLERS.lock()[it as usize]);
+
+    match handler {
+        Some(handler) => handler(),
+        None => println!(
+            "[angeldust::interrupts] received an unhandled interrupt: {:?} ({:#0x})",
+            interrupt, raw
+        ),
+    }
+}
+
+fn controller() -> InterruptController {
+    match *CONTROLLER.lock() {
+        Some(value) => value,
+        _ => panic!("interrupts::initialize() should be called before using interrupts"),
+    }
+}
+
+fn current_core() -> u32 {
+    MultiprocessorAffinityRegister::read().core_id
+}
+
+/// # Safety
+/// - We always use [InterruptController] within a [crate::Mutex].
+unsafe impl Send for InterruptController {}
+
+/// # Safety
+/// - We always use [InterruptController] within a [crate::Mutex].
+unsafe impl Sync for InterruptController {}
@@ -1,11 +1,22 @@
-use super::types::{Message, MessageStatus, MessageTag};
-use crate::{cpu::RaspberryPi, print, println};
+use super::{
+    trace::{self, Direction},
+    types::{Message, MessageStatus, MessageTag},
+};
+use crate::{
+    arch::aarch64::{cache, daif},
+    cpu::RaspberryPi,
+    memory,
+    mutex::Mutex,
+};
 use bitflags::bitflags;
 use core::{
     fmt::Debug,
     ptr::{read_volatile, write_volatile},
 };
 
+/// Held while a message is in flight, as there is only one mailbox for every core.
+static IN_FLIGHT: Mutex<()> = Mutex::new(());
+
 #[derive(Debug)]
 pub enum MailboxError {
     /// Occurs when the mailbox receives [MessageStatus::Error] as a response.
@@ -73,9 +84,28 @@ impl Mailbox {
         channel: Channel,
         request: Message<Request>,
     ) -> Result<Response, MailboxError> {
-        // FIXME: Deal with memory caching when the MMU is enabled.
-        let ptr = (&request as *const Message<Request>) as u32;
-        self.write(ptr, channel);
+        let ptr = (&request as *const Message<Request>) as u64;
+        let length = size_of::<Message<Request>>().max(size_of::<Message<Response>>());
+
+        // The VideoCore only knows where the message is in physical memory.
+        let physical =
+            memory::physical_address(ptr).expect("the message is in the kernel's memory");
+
+        unsafe { trace::trace(Direction::Request, ptr as *const u32) };
+
+        // The VideoCore reads the message from memory, and writes the response there, so nothing
+        // of it can be left in the cache.
+        cache::clean_and_invalidate(ptr as usize, length);
+
+        // An interrupt handler or another core may also use the mailbox. If it did so while we
+        // were waiting for a response, it would consume our response while looking for its own.
+        daif::without_irqs(|| {
+            let _in_flight = IN_FLIGHT.lock();
+            self.write(physical as u32, channel)
+        });
+
+        cache::clean_and_invalidate(ptr as usize, length);
+        unsafe { trace::trace(Direction::Response, ptr as *const u32) };
 
         let response = unsafe { read_volatile(ptr as *const Message<Response>) };
         match response.status {
@@ -103,10 +133,6 @@ impl Mailbox {
         // The upper 28 bits are the pointer to the data, while the lower 28 bits are
         // the channel's identifier.
         let request = (value & !0xF) | (channel as u32 & 0xF);
-        println!(
-            "[angeldust::mailbox] sending a request with data at: {:#0x}",
-            request
-        );
         unsafe { write_volatile(self.registers.write, request) }
 
         loop {
@@ -120,11 +146,6 @@ impl Mailbox {
             // if it's not, this message isn't for us.
             let response = unsafe { read_volatile(self.registers.read) };
             if request == response {
-                println!(
-                    "[angeldust::mailbox] mailbox replied with data at: {:#0x}",
-                    response
-                );
-
                 break;
             }
         }
@@ -163,9 +184,9 @@ impl Registers {
 }
 
 /// # Safety
-/// - We always use [Mailbox] within a [crate::Mutex].
+/// - Every message is sent with [IN_FLIGHT] locked.
 unsafe impl Send for Mailbox {}
 
 /// # Safety
-/// - We always use [Mailbox] within a [crate::Mutex].
+/// - Every message is sent with [IN_FLIGHT] locked.
 unsafe impl Sync for Mailbox {}
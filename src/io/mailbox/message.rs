@@ -7,13 +7,36 @@ use crate::io::mac::MacAddress;
 
 use super::types::MessageTag;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum TagIdentifier {
     GetFirmwareVersion = 0x0_0001,
+    GetFirmwareHash = 0x0_0003,
+    GetBoardModel = 0x1_0001,
     GetBoardRevision = 0x1_0002,
     GetBoardMacAddress = 0x1_0003,
+    GetBoardSerial = 0x1_0004,
     GetArmMemory = 0x1_0005,
+    GetVcMemory = 0x1_0006,
+
+    GetPowerState = 0x2_0001,
+    GetTiming = 0x2_0002,
+    SetPowerState = 0x2_8001,
+
+    GetClockState = 0x3_0001,
+    SetClockState = 0x3_8001,
+    GetClockRate = 0x3_0002,
+    GetClockRateMeasured = 0x3_0047,
+    SetClockRate = 0x3_8002,
+    GetMaxClockRate = 0x3_0004,
+    GetMinClockRate = 0x3_0007,
+    GetTurbo = 0x3_0009,
+    SetTurbo = 0x3_8009,
+
+    GetVoltage = 0x3_0003,
+    GetTemperature = 0x3_0006,
+    GetMaxTemperature = 0x3_000A,
+    GetThrottled = 0x3_0046,
 
     AllocateBuffer = 0x4_0001,
     SetPhysicalDisplaySize = 0x4_8003,
@@ -22,6 +45,57 @@ pub enum TagIdentifier {
     SetPixelOrder = 0x4_8006,
     GetPitch = 0x4_0008,
     SetVirtualOffset = 0x4_8009,
+
+    GetCommandLine = 0x5_0001,
+}
+
+impl TagIdentifier {
+    /// Attempts to match a raw tag identifier (as found in a message buffer) to a [TagIdentifier].
+    pub const fn from_raw(value: u32) -> Option<TagIdentifier> {
+        let identifier = match value {
+            0x0_0001 => TagIdentifier::GetFirmwareVersion,
+            0x0_0003 => TagIdentifier::GetFirmwareHash,
+            0x1_0001 => TagIdentifier::GetBoardModel,
+            0x1_0002 => TagIdentifier::GetBoardRevision,
+            0x1_0003 => TagIdentifier::GetBoardMacAddress,
+            0x1_0004 => TagIdentifier::GetBoardSerial,
+            0x1_0005 => TagIdentifier::GetArmMemory,
+            0x1_0006 => TagIdentifier::GetVcMemory,
+
+            0x2_0001 => TagIdentifier::GetPowerState,
+            0x2_0002 => TagIdentifier::GetTiming,
+            0x2_8001 => TagIdentifier::SetPowerState,
+
+            0x3_0001 => TagIdentifier::GetClockState,
+            0x3_8001 => TagIdentifier::SetClockState,
+            0x3_0002 => TagIdentifier::GetClockRate,
+            0x3_0047 => TagIdentifier::GetClockRateMeasured,
+            0x3_8002 => TagIdentifier::SetClockRate,
+            0x3_0004 => TagIdentifier::GetMaxClockRate,
+            0x3_0007 => TagIdentifier::GetMinClockRate,
+            0x3_0009 => TagIdentifier::GetTurbo,
+            0x3_8009 => TagIdentifier::SetTurbo,
+
+            0x3_0003 => TagIdentifier::GetVoltage,
+            0x3_0006 => TagIdentifier::GetTemperature,
+            0x3_000A => TagIdentifier::GetMaxTemperature,
+            0x3_0046 => TagIdentifier::GetThrottled,
+
+            0x4_0001 => TagIdentifier::AllocateBuffer,
+            0x4_8003 => TagIdentifier::SetPhysicalDisplaySize,
+            0x4_8004 => TagIdentifier::SetVirtualDisplaySize,
+            0x4_8005 => TagIdentifier::SetDepth,
+            0x4_8006 => TagIdentifier::SetPixelOrder,
+            0x4_0008 => TagIdentifier::GetPitch,
+            0x4_8009 => TagIdentifier::SetVirtualOffset,
+
+            0x5_0001 => TagIdentifier::GetCommandLine,
+
+            _ => return None,
+        };
+
+        Some(identifier)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -30,6 +104,19 @@ pub struct GetFirmwareVersionMessage {
     pub firmware_version: u32,
 }
 
+/// The SHA-1 hash of the firmware's source revision.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct GetFirmwareHash {
+    pub hash: [u8; 20],
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct GetBoardModel {
+    pub board_model: u32,
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 #[repr(C)]
 pub struct GetBoardMacAddress {
@@ -43,12 +130,42 @@ pub struct GetArmMemory {
     pub size: u32,
 }
 
+/// The VideoCore's share of the memory, which is not available to the ARM cores.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct GetVcMemory {
+    pub base_address: u32,
+    pub size: u32,
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 #[repr(C)]
 pub struct GetBoardRevision {
     pub board_revision: u32,
 }
 
+/// The board's serial number.
+///
+/// This is split into two words, as a `u64` would be aligned to 8 bytes and add padding after
+/// the tag's header.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct GetBoardSerial {
+    pub serial_low: u32,
+    pub serial_high: u32,
+}
+
+/// The size of the buffer used to receive the kernel command line.
+pub const COMMAND_LINE_SIZE: usize = 1024;
+
+/// The command line passed to the kernel, as configured in `cmdline.txt`.
+/// The string is not null-terminated, its length is held in the response code of the tag.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct GetCommandLine {
+    pub command_line: [u8; COMMAND_LINE_SIZE],
+}
+
 impl GetFirmwareVersionMessage {
     pub fn new() -> MessageTag<GetFirmwareVersionMessage> {
         MessageTag::new(
@@ -58,6 +175,18 @@ impl GetFirmwareVersionMessage {
     }
 }
 
+impl GetFirmwareHash {
+    pub fn new() -> MessageTag<GetFirmwareHash> {
+        MessageTag::new(TagIdentifier::GetFirmwareHash, GetFirmwareHash::default())
+    }
+}
+
+impl GetBoardModel {
+    pub fn new() -> MessageTag<GetBoardModel> {
+        MessageTag::new(TagIdentifier::GetBoardModel, GetBoardModel::default())
+    }
+}
+
 impl GetBoardMacAddress {
     pub fn new() -> MessageTag<GetBoardMacAddress> {
         MessageTag::new(
@@ -78,3 +207,26 @@ impl GetBoardRevision {
         MessageTag::new(TagIdentifier::GetBoardRevision, GetBoardRevision::default())
     }
 }
+
+impl GetVcMemory {
+    pub fn new() -> MessageTag<GetVcMemory> {
+        MessageTag::new(TagIdentifier::GetVcMemory, GetVcMemory::default())
+    }
+}
+
+impl GetBoardSerial {
+    pub fn new() -> MessageTag<GetBoardSerial> {
+        MessageTag::new(TagIdentifier::GetBoardSerial, GetBoardSerial::default())
+    }
+}
+
+impl GetCommandLine {
+    pub fn new() -> MessageTag<GetCommandLine> {
+        MessageTag::new(
+            TagIdentifier::GetCommandLine,
+            GetCommandLine {
+                command_line: [0; COMMAND_LINE_SIZE],
+            },
+        )
+    }
+}
@@ -1,22 +1,57 @@
 pub mod implementation;
 pub mod message;
+pub mod trace;
 pub mod types;
 
 pub use implementation::*;
 pub use message::*;
+pub use trace::{is_enabled as is_tracing, set_enabled as set_tracing, TraceMode};
 
-use crate::mutex::Mutex;
+use crate::{
+    params::{Parameter, ParameterError},
+    sync::Once,
+};
 
-pub static MAILBOX: Mutex<Option<Mailbox>> = Mutex::new(None);
+static MAILBOX: Once<Mailbox> = Once::new();
+
+/// The parameters accepted by the mailbox.
+pub const PARAMETERS: &[Parameter] = &[Parameter {
+    key: "mailbox.trace",
+    default: if cfg!(debug_assertions) {
+        "boot"
+    } else {
+        "off"
+    },
+    description:
+        "decode every mailbox transaction on the console (on, off, or boot to stop once booted)",
+    apply: |params, value| {
+        params.mailbox.trace = match value {
+            "on" => TraceMode::On,
+            "off" => TraceMode::Off,
+            "boot" => TraceMode::Boot,
+            _ => {
+                return Err(ParameterError::UnknownValue {
+                    expected: "on, off or boot",
+                })
+            }
+        };
+
+        Ok(())
+    },
+}];
+
+/// The mailbox's [crate::params::BootParams].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MailboxParams {
+    pub trace: TraceMode,
+}
 
 pub fn initialize() {
-    let mailbox = Mailbox::new();
-    *MAILBOX.lock() = Some(mailbox);
+    MAILBOX.call_once(Mailbox::new);
 }
 
 pub fn instance() -> Mailbox {
-    match *MAILBOX.lock() {
-        Some(value) => value,
-        _ => panic!("mailbox::initialize() should be called before mailbox::instance()"),
-    }
+    *MAILBOX
+        .get()
+        .expect("mailbox::initialize() should be called before mailbox::instance()")
 }
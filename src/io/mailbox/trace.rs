@@ -0,0 +1,143 @@
+use super::{types::MessageStatus, TagIdentifier};
+use crate::{print, println};
+use core::{
+    ptr::read_volatile,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether or not mailbox transactions should be decoded and printed out.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The bit set in a tag's codes word when the VideoCore has written a response into it.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#tags-format
+const RESPONSE_BIT: u32 = 1 << 31;
+
+/// Enables or disables tracing of mailbox transactions.
+/// This can be changed at any point, and takes effect on the next transaction.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether or not mailbox transactions are currently being traced.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// When mailbox transactions are traced, as set by the `mailbox.trace` [crate::params::Parameter].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceMode {
+    /// Nothing is traced, unless tracing is turned on later (e.g. from the shell).
+    #[default]
+    Off,
+
+    /// Every transaction is traced until the kernel has booted, after which periodic users of
+    /// the mailbox (like the thermal monitor) would flood the console.
+    Boot,
+
+    /// Every transaction is traced.
+    On,
+}
+
+/// The direction of a traced buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The buffer is about to be handed to the VideoCore.
+    Request,
+
+    /// The buffer has been handed back by the VideoCore.
+    Response,
+}
+
+/// Decodes and prints the message buffer at [buffer] if tracing is enabled.
+///
+/// # Safety
+/// - This function assumes that [buffer] points to a valid property message, where the first
+///   word holds the size of the entire buffer in bytes.
+pub unsafe fn trace(direction: Direction, buffer: *const u32) {
+    if !is_enabled() {
+        return;
+    }
+
+    let size = read_volatile(buffer);
+    let word_count = (size / 4) as usize;
+    let status = read_volatile(buffer.add(1));
+
+    let arrow = match direction {
+        Direction::Request => "->",
+        Direction::Response => "<-",
+    };
+
+    match MessageStatus::from_raw(status) {
+        Some(status) => println!(
+            "[angeldust::mailbox] {} {:?} at {:#0x} ({} bytes, status: {:?})",
+            arrow, direction, buffer as usize, size, status
+        ),
+        None => println!(
+            "[angeldust::mailbox] {} {:?} at {:#0x} ({} bytes, status: unknown {:#0x})",
+            arrow, direction, buffer as usize, size, status
+        ),
+    };
+
+    // The tags start after the size and status words, and continue until the end tag (0x0).
+    let mut index = 2;
+    let mut found_unknown_tag = false;
+
+    while index + 3 <= word_count {
+        let identifier = read_volatile(buffer.add(index));
+        if identifier == 0 {
+            break;
+        }
+
+        let value_size = read_volatile(buffer.add(index + 1));
+        let codes = read_volatile(buffer.add(index + 2));
+        let value_words = value_size.div_ceil(4) as usize;
+
+        match TagIdentifier::from_raw(identifier) {
+            Some(tag) => print!("[angeldust::mailbox]    {:?} ({:#0x})", tag, identifier),
+            None => {
+                found_unknown_tag = true;
+                print!("[angeldust::mailbox]    unknown tag ({:#0x})", identifier)
+            }
+        }
+
+        if codes & RESPONSE_BIT != 0 {
+            print!(" response, {} bytes:", codes & !RESPONSE_BIT);
+        } else if direction == Direction::Response {
+            // The VideoCore did not touch this tag, which usually means it doesn't understand it.
+            print!(" not handled, codes {:#0x}:", codes);
+        } else {
+            print!(" request, {} bytes:", value_size);
+        }
+
+        let values_start = index + 3;
+        let values_end = (values_start + value_words).min(word_count);
+        for value_index in values_start..values_end {
+            print!(" {:#0x}", read_volatile(buffer.add(value_index)));
+        }
+
+        println!();
+        index = values_start + value_words;
+    }
+
+    if found_unknown_tag {
+        dump(buffer, word_count);
+    }
+}
+
+/// Prints the raw contents of a message buffer, four words per line.
+///
+/// # Safety
+/// - This function assumes that [buffer] is valid for [word_count] words.
+unsafe fn dump(buffer: *const u32, word_count: usize) {
+    println!("[angeldust::mailbox]    raw buffer:");
+
+    for line_start in (0..word_count).step_by(4) {
+        print!("[angeldust::mailbox]      {:04x}:", line_start * 4);
+
+        for index in line_start..(line_start + 4).min(word_count) {
+            print!(" {:08x}", read_volatile(buffer.add(index)));
+        }
+
+        println!();
+    }
+}
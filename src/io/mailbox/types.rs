@@ -50,6 +50,18 @@ pub enum MessageStatus {
     Error = 0x8000_0001,
 }
 
+impl MessageStatus {
+    /// Attempts to match a raw status word (as found in a message buffer) to a [MessageStatus].
+    pub const fn from_raw(value: u32) -> Option<MessageStatus> {
+        match value {
+            0x0000_0000 => Some(MessageStatus::Request),
+            0x8000_0000 => Some(MessageStatus::Success),
+            0x8000_0001 => Some(MessageStatus::Error),
+            _ => None,
+        }
+    }
+}
+
 impl<T: Debug> Message<T> {
     pub fn new(tag: T) -> Message<T> {
         Message {
@@ -0,0 +1,142 @@
+use bitflags::bitflags;
+use core::ptr::{read_volatile, write_volatile};
+
+use crate::{cpu::RaspberryPi, io::power::DeviceId};
+
+/// The auxiliary mini UART (UART1).
+///
+/// Unlike the PL011 [crate::io::uart::Uart], its baud rate is derived from the VideoCore's core
+/// clock, so it must be reprogrammed if the core clock changes.
+/// 2.2. Mini UART: https://datasheets.raspberrypi.com/bcm2711/bcm2711-peripherals.pdf
+#[derive(Clone, Copy, Debug)]
+pub struct MiniUart {
+    registers: Registers,
+}
+
+// 2.1.1. Auxiliary peripherals register map
+#[derive(Clone, Copy, Debug)]
+struct Registers {
+    enables: *mut u32,
+    io: *mut u32,
+    interrupt_enable: *mut u32,
+    line_control: *mut u32,
+    line_status: *mut u32,
+    extra_control: *mut u32,
+    baud_rate: *mut u32,
+}
+
+bitflags! {
+    /// 2.2.2. AUX_ENABLES Register
+    struct Enables: u32 {
+        const MiniUart = 1 << 0;
+    }
+
+    /// 2.2.2. AUX_MU_LSR_REG Register
+    struct LineStatus: u32 {
+        const DataReady = 1 << 0;
+        const TransmitterEmpty = 1 << 5;
+    }
+
+    /// 2.2.2. AUX_MU_CNTL_REG Register
+    struct ExtraControl: u32 {
+        const ReceiverEnable = 1 << 0;
+        const TransmitterEnable = 1 << 1;
+    }
+}
+
+impl MiniUart {
+    /// The power domains that must be enabled before the [MiniUart] is initialized.
+    pub const POWER_DOMAINS: &'static [DeviceId] = &[DeviceId::Uart1];
+
+    /// The baud rate that [MiniUart::initialize] configures, which matches the PL011.
+    pub const BAUD_RATE: u32 = 115_200;
+
+    /// Creates a new instance of [MiniUart].
+    ///
+    /// # Safety
+    /// - This function assumes that the [RaspberryPi::peripheral_base_address] is valid.
+    pub fn new() -> MiniUart {
+        let base_address = unsafe {
+            RaspberryPi::instance()
+                .peripheral_base_address()
+                .byte_offset(0x215000) as *mut u32
+        };
+
+        MiniUart {
+            registers: unsafe { Registers::new(base_address) },
+        }
+    }
+
+    /// Enables the mini UART with 8 data bits, and a baud rate of [MiniUart::BAUD_RATE] derived
+    /// from [core_clock_rate] (in Hz).
+    ///
+    /// The GPIO pins are expected to have been set up by the firmware (`enable_uart=1`).
+    pub fn initialize(&mut self, core_clock_rate: u32) {
+        let enables = unsafe { read_volatile(self.registers.enables) };
+        unsafe {
+            write_volatile(self.registers.enables, enables | Enables::MiniUart.bits());
+
+            // The transmitter and receiver must be disabled while the UART is reconfigured.
+            write_volatile(self.registers.extra_control, 0);
+            write_volatile(self.registers.interrupt_enable, 0);
+
+            // 0b11 = 8-bit mode, despite the datasheet documenting only bit 0.
+            write_volatile(self.registers.line_control, 0b11);
+
+            // baudrate = core_clock / (8 * (baudrate_reg + 1))
+            let divisor = (core_clock_rate / (8 * Self::BAUD_RATE)).saturating_sub(1);
+            write_volatile(self.registers.baud_rate, divisor);
+
+            write_volatile(
+                self.registers.extra_control,
+                (ExtraControl::ReceiverEnable | ExtraControl::TransmitterEnable).bits(),
+            );
+        }
+    }
+
+    pub fn write(&self, byte: u8) {
+        // Loop until the transmit FIFO can accept another byte.
+        while !LineStatus::from_bits_retain(unsafe { read_volatile(self.registers.line_status) })
+            .contains(LineStatus::TransmitterEmpty)
+        {}
+
+        unsafe { write_volatile(self.registers.io, byte.into()) }
+    }
+
+    /// Reads a byte from the receive FIFO, or returns [None] if nothing has been received.
+    pub fn read(&self) -> Option<u8> {
+        if !LineStatus::from_bits_retain(unsafe { read_volatile(self.registers.line_status) })
+            .contains(LineStatus::DataReady)
+        {
+            return None;
+        }
+
+        Some(unsafe { read_volatile(self.registers.io) } as u8)
+    }
+}
+
+impl Registers {
+    /// Creates a new instance of [Registers].
+    ///
+    /// # Safety
+    /// - This assumes that the provided [aux_base] is valid.
+    const unsafe fn new(aux_base: *mut u32) -> Registers {
+        Registers {
+            enables: aux_base.byte_offset(0x04),
+            io: aux_base.byte_offset(0x40),
+            interrupt_enable: aux_base.byte_offset(0x44),
+            line_control: aux_base.byte_offset(0x4C),
+            line_status: aux_base.byte_offset(0x54),
+            extra_control: aux_base.byte_offset(0x60),
+            baud_rate: aux_base.byte_offset(0x68),
+        }
+    }
+}
+
+/// # Safety
+/// - We always use [MiniUart] within a [crate::Mutex].
+unsafe impl Send for MiniUart {}
+
+/// # Safety
+/// - We always use [MiniUart] within a [crate::Mutex].
+unsafe impl Sync for MiniUart {}
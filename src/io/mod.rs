@@ -1,4 +1,14 @@
+pub mod block;
+pub mod clocks;
+pub mod emmc;
 pub mod framebuffer;
+pub mod gpio;
+pub mod interrupts;
 pub mod mac;
 pub mod mailbox;
+pub mod mini_uart;
+pub mod partition;
+pub mod power;
+pub mod rng;
+pub mod thermal;
 pub mod uart;
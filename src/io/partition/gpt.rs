@@ -0,0 +1,380 @@
+use super::{
+    read_u32, read_u64, Label, PartitionError, PartitionInfo, PartitionKind, PartitionTable, Scheme,
+};
+use crate::io::block::{BlockDevice, BlockError};
+use core::fmt::{self, Display, Formatter};
+
+/// The primary header is always in the block after the protective MBR.
+const PRIMARY_HEADER: u64 = 1;
+
+const SIGNATURE: &[u8; 8] = b"EFI PART";
+
+/// The size of the header that this parser understands, larger headers are padded with zeroes.
+const MIN_HEADER_SIZE: u32 = 92;
+
+/// The size of the entries that this parser understands, larger entries are padded.
+const MIN_ENTRY_SIZE: u32 = 128;
+
+/// The legacy BIOS bootable attribute of an entry.
+const ATTRIBUTE_BOOTABLE: u64 = 1 << 2;
+
+/// Represents an error that can occur while reading one of the GPT headers and its entries.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum GptError {
+    /// Occurs when the header or its entries can't be read.
+    Block(BlockError),
+
+    /// Occurs when the device has no blocks, so there is nowhere to look for the backup header.
+    EmptyDevice,
+
+    /// Occurs when the block doesn't start with [SIGNATURE].
+    InvalidSignature,
+
+    /// Occurs when the header's size is smaller than [MIN_HEADER_SIZE], or larger than a block.
+    InvalidHeaderSize(u32),
+
+    /// Occurs when the header's CRC32 doesn't match its contents.
+    HeaderCrc { expected: u32, actual: u32 },
+
+    /// Occurs when the header doesn't think that it is stored where it was read from.
+    WrongLocation { expected: u64, actual: u64 },
+
+    /// Occurs when the entries aren't a power of two of at least [MIN_ENTRY_SIZE] bytes, which
+    /// fits in a block.
+    UnsupportedEntrySize(u32),
+
+    /// Occurs when the entries go past the end of the device.
+    EntriesOutOfRange,
+
+    /// Occurs when the CRC32 of the entries doesn't match the one in the header.
+    EntriesCrc { expected: u32, actual: u32 },
+
+    /// Occurs when an entry isn't within the header's usable blocks.
+    InvalidEntry { number: u32 },
+
+    /// Occurs when the table holds more than [super::MAX_PARTITIONS] partitions.
+    TooManyPartitions,
+}
+
+/// A globally unique identifier, stored with its first three fields in little-endian.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Guid(pub [u8; 16]);
+
+/// The fields of a GPT header that are used.
+/// 5.3.2. GPT Header: https://uefi.org/specs/UEFI/2.10/05_GUID_Partition_Table_Format.html
+struct Header {
+    alternate: u64,
+    first_usable: u64,
+    last_usable: u64,
+    disk_guid: Guid,
+    entries_start: u64,
+    entry_count: u32,
+    entry_size: u32,
+    entries_crc: u32,
+}
+
+/// Reads the GPT of [device], falling back to the backup at the end of the device if the primary
+/// header or its entries are damaged. [block] is used to read each block.
+pub fn read(device: &impl BlockDevice, block: &mut [u8]) -> Result<PartitionTable, PartitionError> {
+    let primary = read_header(device, block, PRIMARY_HEADER);
+
+    // The primary header knows where the backup is, but it may be the part that is damaged.
+    let backup_location = match &primary {
+        Ok(header) => Ok(header.alternate),
+        Err(_) => device
+            .block_count()
+            .checked_sub(1)
+            .ok_or(GptError::EmptyDevice),
+    };
+
+    let primary = primary.and_then(|header| read_entries(device, block, &header, false));
+    let primary = match primary {
+        Ok(table) => return Ok(table),
+        Err(error) => error,
+    };
+
+    let backup = backup_location
+        .and_then(|location| read_header(device, block, location))
+        .and_then(|header| read_entries(device, block, &header, true));
+
+    match backup {
+        Ok(table) => Ok(table),
+        Err(backup) => Err(PartitionError::Gpt { primary, backup }),
+    }
+}
+
+/// Reads and verifies the header at [location].
+fn read_header(
+    device: &impl BlockDevice,
+    block: &mut [u8],
+    location: u64,
+) -> Result<Header, GptError> {
+    device
+        .read_blocks(location, block)
+        .map_err(GptError::Block)?;
+
+    if &block[..8] != SIGNATURE {
+        return Err(GptError::InvalidSignature);
+    }
+
+    let header_size = read_u32(block, 12);
+    if header_size < MIN_HEADER_SIZE || header_size as usize > block.len() {
+        return Err(GptError::InvalidHeaderSize(header_size));
+    }
+
+    // The CRC32 is calculated with its own field set to zero.
+    let expected = read_u32(block, 16);
+    let mut crc = Crc32::new();
+    crc.update(&block[..16]);
+    crc.update(&[0; 4]);
+    crc.update(&block[20..header_size as usize]);
+
+    let actual = crc.finish();
+    if actual != expected {
+        return Err(GptError::HeaderCrc { expected, actual });
+    }
+
+    let current = read_u64(block, 24);
+    if current != location {
+        return Err(GptError::WrongLocation {
+            expected: location,
+            actual: current,
+        });
+    }
+
+    let mut disk_guid = Guid([0; 16]);
+    disk_guid.0.copy_from_slice(&block[56..72]);
+
+    Ok(Header {
+        alternate: read_u64(block, 32),
+        first_usable: read_u64(block, 40),
+        last_usable: read_u64(block, 48),
+        disk_guid,
+        entries_start: read_u64(block, 72),
+        entry_count: read_u32(block, 80),
+        entry_size: read_u32(block, 84),
+        entries_crc: read_u32(block, 88),
+    })
+}
+
+/// Reads the entries that [header] points to, verifying them against its CRC32.
+/// The entries are read a block at a time, as there is nowhere to keep all of them.
+fn read_entries(
+    device: &impl BlockDevice,
+    block: &mut [u8],
+    header: &Header,
+    from_backup: bool,
+) -> Result<PartitionTable, GptError> {
+    let block_size = block.len();
+    let entry_size = header.entry_size as usize;
+    if header.entry_size < MIN_ENTRY_SIZE
+        || !entry_size.is_power_of_two()
+        || entry_size > block_size
+    {
+        return Err(GptError::UnsupportedEntrySize(header.entry_size));
+    }
+
+    let length = header.entry_count as u64 * entry_size as u64;
+    let block_count = length.div_ceil(block_size as u64);
+    let end = header.entries_start.checked_add(block_count);
+    if end.is_none_or(|end| end > device.block_count()) {
+        return Err(GptError::EntriesOutOfRange);
+    }
+
+    let mut table = PartitionTable::new(Scheme::Gpt {
+        disk_guid: header.disk_guid,
+        from_backup,
+    });
+
+    let entries_per_block = block_size / entry_size;
+    let mut crc = Crc32::new();
+
+    for index in 0..block_count {
+        device
+            .read_blocks(header.entries_start + index, block)
+            .map_err(GptError::Block)?;
+
+        let used = (length - index * block_size as u64).min(block_size as u64) as usize;
+        crc.update(&block[..used]);
+
+        for (offset, entry) in block[..used].chunks_exact(entry_size).enumerate() {
+            let number = (index as usize * entries_per_block + offset + 1) as u32;
+            if let Some(partition) = parse_entry(entry, number, header)? {
+                if !table.push(partition) {
+                    return Err(GptError::TooManyPartitions);
+                }
+            }
+        }
+    }
+
+    let actual = crc.finish();
+    if actual != header.entries_crc {
+        return Err(GptError::EntriesCrc {
+            expected: header.entries_crc,
+            actual,
+        });
+    }
+
+    Ok(table)
+}
+
+/// Parses a single partition entry, returning [None] if it is unused.
+/// 5.3.3. GPT Partition Entry Array: https://uefi.org/specs/UEFI/2.10/05_GUID_Partition_Table_Format.html
+fn parse_entry(
+    entry: &[u8],
+    number: u32,
+    header: &Header,
+) -> Result<Option<PartitionInfo>, GptError> {
+    let mut kind = Guid([0; 16]);
+    kind.0.copy_from_slice(&entry[..16]);
+    if kind == Guid::UNUSED {
+        return Ok(None);
+    }
+
+    // The last block is inclusive.
+    let first = read_u64(entry, 32);
+    let last = read_u64(entry, 40);
+    if first > last || first < header.first_usable || last > header.last_usable {
+        return Err(GptError::InvalidEntry { number });
+    }
+
+    let mut label = Label::EMPTY;
+    for (unit, bytes) in label.0.iter_mut().zip(entry[56..128].chunks_exact(2)) {
+        *unit = u16::from_le_bytes([bytes[0], bytes[1]]);
+    }
+
+    Ok(Some(PartitionInfo {
+        number,
+        start: first,
+        block_count: last - first + 1,
+        kind: PartitionKind::Gpt(kind),
+        label,
+        bootable: read_u64(entry, 48) & ATTRIBUTE_BOOTABLE != 0,
+    }))
+}
+
+impl Guid {
+    pub const UNUSED: Guid = Guid([0; 16]);
+    pub const EFI_SYSTEM: Guid = Guid::new(
+        0xC12A7328,
+        0xF81F,
+        0x11D2,
+        [0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E, 0xC9, 0x3B],
+    );
+    pub const BASIC_DATA: Guid = Guid::new(
+        0xEBD0A0A2,
+        0xB9E5,
+        0x4433,
+        [0x87, 0xC0, 0x68, 0xB6, 0xB7, 0x26, 0x99, 0xC7],
+    );
+    pub const LINUX_FILESYSTEM: Guid = Guid::new(
+        0x0FC63DAF,
+        0x8483,
+        0x4772,
+        [0x8E, 0x79, 0x3D, 0x69, 0xD8, 0x47, 0x7D, 0xE4],
+    );
+    pub const LINUX_SWAP: Guid = Guid::new(
+        0x0657FD6D,
+        0xA4AB,
+        0x43C4,
+        [0x84, 0xE5, 0x09, 0x33, 0xC8, 0x4B, 0x4F, 0x4F],
+    );
+
+    /// Creates a [Guid] from the fields that it is usually written with, e.g.
+    /// `C12A7328-F81F-11D2-BA4B-00A0C93EC93B`.
+    pub const fn new(first: u32, second: u16, third: u16, rest: [u8; 8]) -> Guid {
+        let first = first.to_le_bytes();
+        let second = second.to_le_bytes();
+        let third = third.to_le_bytes();
+
+        Guid([
+            first[0], first[1], first[2], first[3], second[0], second[1], third[0], third[1],
+            rest[0], rest[1], rest[2], rest[3], rest[4], rest[5], rest[6], rest[7],
+        ])
+    }
+
+    /// Returns a human readable name for the well known partition type GUIDs.
+    pub fn description(&self) -> Option<&'static str> {
+        match *self {
+            Guid::EFI_SYSTEM => Some("EFI system"),
+            Guid::BASIC_DATA => Some("basic data"),
+            Guid::LINUX_FILESYSTEM => Some("Linux filesystem"),
+            Guid::LINUX_SWAP => Some("Linux swap"),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Guid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let bytes = &self.0;
+
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-",
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            u16::from_le_bytes([bytes[4], bytes[5]]),
+            u16::from_le_bytes([bytes[6], bytes[7]])
+        )?;
+
+        for (index, byte) in bytes[8..].iter().enumerate() {
+            if index == 2 {
+                write!(f, "-")?;
+            }
+
+            write!(f, "{:02X}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The CRC32 used by the GPT (and zlib and Ethernet), with the reflected 0xEDB88320 polynomial.
+struct Crc32 {
+    value: u32,
+}
+
+impl Crc32 {
+    const TABLE: [u32; 256] = Self::table();
+
+    const fn new() -> Crc32 {
+        Crc32 { value: 0xFFFF_FFFF }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            let index = (self.value ^ *byte as u32) & 0xFF;
+            self.value = (self.value >> 8) ^ Self::TABLE[index as usize];
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        !self.value
+    }
+
+    const fn table() -> [u32; 256] {
+        let mut table = [0u32; 256];
+
+        let mut index = 0;
+        while index < 256 {
+            let mut value = index as u32;
+
+            let mut bit = 0;
+            while bit < 8 {
+                value = if value & 1 != 0 {
+                    (value >> 1) ^ 0xEDB8_8320
+                } else {
+                    value >> 1
+                };
+
+                bit += 1;
+            }
+
+            table[index] = value;
+            index += 1;
+        }
+
+        table
+    }
+}
@@ -0,0 +1,173 @@
+use super::{
+    read_u32, Label, PartitionError, PartitionInfo, PartitionKind, PartitionTable, Scheme,
+    MAX_PARTITIONS,
+};
+use crate::io::block::BlockDevice;
+
+/// The partition type of a protective MBR, which means that the device uses a GPT.
+pub const PROTECTIVE_KIND: u8 = 0xEE;
+
+/// The partition types that hold a chain of EBRs, each describing a logical partition.
+const EXTENDED_KINDS: [u8; 3] = [0x05, 0x0F, 0x85];
+
+/// The partition types that hold a FAT file system.
+pub const FAT_KINDS: [u8; 6] = [0x01, 0x04, 0x06, 0x0B, 0x0C, 0x0E];
+
+/// Every MBR and EBR ends with this signature.
+const SIGNATURE: [u8; 2] = [0x55, 0xAA];
+const SIGNATURE_OFFSET: usize = 510;
+
+/// The four partition entries start at this offset, each entry is 16 bytes long.
+const ENTRIES_OFFSET: usize = 446;
+const ENTRY_SIZE: usize = 16;
+
+/// The status of a bootable ("active") partition.
+const STATUS_BOOTABLE: u8 = 0x80;
+
+/// The number of the first logical partition.
+const FIRST_LOGICAL_NUMBER: u32 = 5;
+
+/// A partition entry in an MBR or EBR.
+/// https://en.wikipedia.org/wiki/Master_boot_record#Partition_table_entries
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    pub status: u8,
+    pub kind: u8,
+    pub start: u32,
+    pub block_count: u32,
+}
+
+/// Returns the four entries of the MBR or EBR in [block], or [None] if it isn't one.
+///
+/// A FAT boot sector without a partition table also ends with the signature, but the first
+/// byte of its entries is very unlikely to be a valid status.
+pub fn entries(block: &[u8]) -> Option<[Entry; 4]> {
+    if block[SIGNATURE_OFFSET..SIGNATURE_OFFSET + 2] != SIGNATURE {
+        return None;
+    }
+
+    let entries = core::array::from_fn(|index| {
+        let entry = &block[ENTRIES_OFFSET + index * ENTRY_SIZE..][..ENTRY_SIZE];
+
+        Entry {
+            status: entry[0],
+            kind: entry[4],
+            start: read_u32(entry, 8),
+            block_count: read_u32(entry, 12),
+        }
+    });
+
+    let valid = entries
+        .iter()
+        .all(|it: &Entry| it.status == 0 || it.status == STATUS_BOOTABLE);
+
+    valid.then_some(entries)
+}
+
+/// Reads the primary partitions in [entries], and the logical partitions in the extended
+/// partition if there is one. [block] is used to read the EBRs.
+pub fn read(
+    device: &impl BlockDevice,
+    block: &mut [u8],
+    entries: [Entry; 4],
+) -> Result<PartitionTable, PartitionError> {
+    let mut table = PartitionTable::new(Scheme::Mbr);
+
+    for (index, entry) in entries.iter().enumerate() {
+        if entry.is_empty() {
+            continue;
+        }
+
+        if EXTENDED_KINDS.contains(&entry.kind) {
+            read_extended(device, block, entry.start.into(), &mut table)?;
+            continue;
+        }
+
+        push(&mut table, index as u32 + 1, 0, entry)?;
+    }
+
+    Ok(table)
+}
+
+/// Follows the chain of EBRs in the extended partition at [extended_start].
+///
+/// Each EBR describes one logical partition relative to itself, and the next EBR relative to the
+/// start of the extended partition.
+fn read_extended(
+    device: &impl BlockDevice,
+    block: &mut [u8],
+    extended_start: u64,
+    table: &mut PartitionTable,
+) -> Result<(), PartitionError> {
+    let mut ebr = extended_start;
+    let mut number = FIRST_LOGICAL_NUMBER;
+
+    // A chain that is longer than the table can hold must loop somewhere.
+    for _ in 0..MAX_PARTITIONS {
+        device
+            .read_blocks(ebr, block)
+            .map_err(PartitionError::Block)?;
+
+        let [logical, next, ..] = entries(block).ok_or(PartitionError::InvalidExtendedPartition)?;
+        if !logical.is_empty() {
+            push(table, number, ebr, &logical)?;
+            number += 1;
+        }
+
+        if next.is_empty() {
+            return Ok(());
+        }
+
+        ebr = extended_start + next.start as u64;
+    }
+
+    Err(PartitionError::InvalidExtendedPartition)
+}
+
+fn push(
+    table: &mut PartitionTable,
+    number: u32,
+    base: u64,
+    entry: &Entry,
+) -> Result<(), PartitionError> {
+    let pushed = table.push(PartitionInfo {
+        number,
+        start: base + entry.start as u64,
+        block_count: entry.block_count.into(),
+        kind: PartitionKind::Mbr(entry.kind),
+        label: Label::EMPTY,
+        bootable: entry.status == STATUS_BOOTABLE,
+    });
+
+    if !pushed {
+        return Err(PartitionError::TooManyPartitions);
+    }
+
+    Ok(())
+}
+
+/// Returns a human readable name for the well known MBR partition types.
+pub fn description(kind: u8) -> Option<&'static str> {
+    let description = match kind {
+        0x01 => "FAT12",
+        0x04 | 0x06 => "FAT16",
+        0x05 | 0x0F | 0x85 => "extended",
+        0x07 => "NTFS/exFAT",
+        0x0B => "FAT32",
+        0x0C => "FAT32 LBA",
+        0x0E => "FAT16 LBA",
+        0x82 => "Linux swap",
+        0x83 => "Linux",
+        0xEE => "GPT protective",
+        0xEF => "EFI system",
+        _ => return None,
+    };
+
+    Some(description)
+}
+
+impl Entry {
+    fn is_empty(&self) -> bool {
+        self.kind == 0 || self.block_count == 0
+    }
+}
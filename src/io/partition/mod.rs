@@ -0,0 +1,305 @@
+pub mod gpt;
+pub mod mbr;
+
+use crate::{
+    io::block::{BlockDevice, BlockError},
+    println,
+};
+use core::fmt::{self, Display, Formatter};
+use gpt::{GptError, Guid};
+
+/// The most partitions that a [PartitionTable] can hold.
+pub const MAX_PARTITIONS: usize = 32;
+
+/// The smallest and largest block sizes that partition tables can be read from.
+const MIN_BLOCK_SIZE: usize = 512;
+const MAX_BLOCK_SIZE: usize = 4096;
+
+/// Represents an error that can occur while reading a partition table.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum PartitionError {
+    /// Occurs when the device can't be read.
+    Block(BlockError),
+
+    /// Occurs when the device's blocks are smaller than 512 bytes, or larger than 4 KiB.
+    UnsupportedBlockSize(usize),
+
+    /// Occurs when the first block doesn't hold an MBR, or a protective MBR.
+    NoPartitionTable,
+
+    /// Occurs when the table holds more than [MAX_PARTITIONS] partitions.
+    TooManyPartitions,
+
+    /// Occurs when a partition goes past the end of the device.
+    OutOfRange { number: u32 },
+
+    /// Occurs when the chain of EBRs in an extended partition is broken, or loops.
+    InvalidExtendedPartition,
+
+    /// Occurs when neither the primary nor the backup GPT can be used.
+    Gpt { primary: GptError, backup: GptError },
+}
+
+/// The kind of partition table that was found on a device.
+#[derive(Debug, Clone, Copy)]
+pub enum Scheme {
+    Mbr,
+
+    /// A GUID partition table, which was read from the backup header at the end of the device
+    /// if [Scheme::Gpt::from_backup] is set.
+    Gpt {
+        disk_guid: Guid,
+        from_backup: bool,
+    },
+}
+
+/// The type of a partition, as it is stored in the partition table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionKind {
+    Mbr(u8),
+    Gpt(Guid),
+}
+
+/// The label of a partition, which only GPT partitions have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label(pub [u16; 36]);
+
+/// A single partition in a [PartitionTable].
+#[derive(Debug, Clone, Copy)]
+pub struct PartitionInfo {
+    /// The partition's number, counted from 1. Logical MBR partitions start at 5.
+    pub number: u32,
+
+    /// The first block of the partition.
+    pub start: u64,
+
+    pub block_count: u64,
+    pub kind: PartitionKind,
+    pub label: Label,
+    pub bootable: bool,
+}
+
+/// The partitions on a device, read by [read_table].
+#[derive(Debug, Clone, Copy)]
+pub struct PartitionTable {
+    scheme: Scheme,
+    partitions: [Option<PartitionInfo>; MAX_PARTITIONS],
+}
+
+/// A partition of a [BlockDevice], which is a block device of its own.
+#[derive(Debug, Clone, Copy)]
+pub struct Partition<D: BlockDevice> {
+    device: D,
+    info: PartitionInfo,
+}
+
+/// Reads the GPT or MBR partition table of [device].
+///
+/// A GPT is used if the MBR holds a protective partition, otherwise the MBR's primary partitions
+/// and the logical partitions in its extended partition are used.
+pub fn read_table(device: &impl BlockDevice) -> Result<PartitionTable, PartitionError> {
+    let block_size = device.block_size();
+    if !(MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) {
+        return Err(PartitionError::UnsupportedBlockSize(block_size));
+    }
+
+    let mut buffer = [0u8; MAX_BLOCK_SIZE];
+    let block = &mut buffer[..block_size];
+
+    device
+        .read_blocks(0, block)
+        .map_err(PartitionError::Block)?;
+    let entries = mbr::entries(block).ok_or(PartitionError::NoPartitionTable)?;
+
+    let table = if entries.iter().any(|it| it.kind == mbr::PROTECTIVE_KIND) {
+        gpt::read(device, block)?
+    } else {
+        mbr::read(device, block, entries)?
+    };
+
+    for partition in table.partitions() {
+        let end = partition.start.checked_add(partition.block_count);
+        if end.is_none_or(|end| end > device.block_count()) {
+            return Err(PartitionError::OutOfRange {
+                number: partition.number,
+            });
+        }
+    }
+
+    Ok(table)
+}
+
+/// Prints the partition table of [device].
+pub fn report(device: &impl BlockDevice) {
+    let table = match read_table(device) {
+        Ok(table) => table,
+        Err(error) => {
+            println!(
+                "[angeldust::partition] no usable partition table: {:?}",
+                error
+            );
+            return;
+        }
+    };
+
+    match table.scheme() {
+        Scheme::Mbr => println!("[angeldust::partition] mbr partition table"),
+        Scheme::Gpt {
+            disk_guid,
+            from_backup,
+        } => println!(
+            "[angeldust::partition] gpt partition table for disk {}{}",
+            disk_guid,
+            if from_backup {
+                ", read from the backup as the primary is damaged"
+            } else {
+                ""
+            }
+        ),
+    }
+
+    for partition in table.partitions() {
+        println!(
+            "[angeldust::partition] {}: {} MiB from block {}, {} '{}'{}",
+            partition.number,
+            partition.block_count * device.block_size() as u64 / (1024 * 1024),
+            partition.start,
+            partition.kind,
+            partition.label,
+            if partition.bootable {
+                " (bootable)"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+impl PartitionTable {
+    const fn new(scheme: Scheme) -> PartitionTable {
+        PartitionTable {
+            scheme,
+            partitions: [None; MAX_PARTITIONS],
+        }
+    }
+
+    pub fn scheme(&self) -> Scheme {
+        self.scheme
+    }
+
+    /// Returns every partition, in the order that they appear in the table.
+    pub fn partitions(&self) -> impl Iterator<Item = &PartitionInfo> {
+        self.partitions.iter().flatten()
+    }
+
+    /// Returns the partition numbered [number], if there is one.
+    pub fn find(&self, number: u32) -> Option<PartitionInfo> {
+        self.partitions().find(|it| it.number == number).copied()
+    }
+
+    /// Adds [partition] to the table, returning false if it is already full.
+    fn push(&mut self, partition: PartitionInfo) -> bool {
+        match self.partitions.iter_mut().find(|it| it.is_none()) {
+            Some(slot) => {
+                *slot = Some(partition);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<D: BlockDevice> Partition<D> {
+    /// Creates a new instance of [Partition], which covers [info] on [device].
+    /// [info] should come from the [PartitionTable] of [device], which checks that it fits.
+    pub fn new(device: D, info: PartitionInfo) -> Partition<D> {
+        Partition { device, info }
+    }
+
+    pub fn info(&self) -> PartitionInfo {
+        self.info
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for Partition<D> {
+    fn block_size(&self) -> usize {
+        self.device.block_size()
+    }
+
+    fn block_count(&self) -> u64 {
+        self.info.block_count
+    }
+
+    fn read_blocks(&self, start: u64, buffer: &mut [u8]) -> Result<(), BlockError> {
+        self.check_transfer(start, buffer.len())?;
+        self.device.read_blocks(self.info.start + start, buffer)
+    }
+
+    fn write_blocks(&self, start: u64, buffer: &[u8]) -> Result<(), BlockError> {
+        self.check_transfer(start, buffer.len())?;
+        self.device.write_blocks(self.info.start + start, buffer)
+    }
+}
+
+impl PartitionKind {
+    /// Whether the partition is expected to hold a FAT file system.
+    pub fn is_fat(&self) -> bool {
+        match self {
+            PartitionKind::Mbr(kind) => mbr::FAT_KINDS.contains(kind),
+            PartitionKind::Gpt(guid) => *guid == Guid::EFI_SYSTEM || *guid == Guid::BASIC_DATA,
+        }
+    }
+
+    /// Returns a human readable name for the well known partition types.
+    pub fn description(&self) -> Option<&'static str> {
+        match self {
+            PartitionKind::Mbr(kind) => mbr::description(*kind),
+            PartitionKind::Gpt(guid) => guid.description(),
+        }
+    }
+}
+
+impl Display for PartitionKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionKind::Mbr(kind) => write!(f, "{:#04x}", kind)?,
+            PartitionKind::Gpt(guid) => write!(f, "{}", guid)?,
+        }
+
+        match self.description() {
+            Some(description) => write!(f, " ({})", description),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Label {
+    pub const EMPTY: Label = Label([0; 36]);
+}
+
+/// Labels are UTF-16, and end at the first null character.
+impl Display for Label {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let units = self.0.iter().copied().take_while(|it| *it != 0);
+        for character in char::decode_utf16(units) {
+            write!(f, "{}", character.unwrap_or(char::REPLACEMENT_CHARACTER))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a little-endian u32 from [bytes] at [offset].
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+/// Reads a little-endian u64 from [bytes] at [offset].
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(value)
+}
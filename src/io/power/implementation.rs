@@ -0,0 +1,133 @@
+use super::message::{DeviceId, PowerState, PowerStateMessage, TimingMessage};
+use crate::{
+    io::mailbox::{types::MessageTag, Channel, Mailbox, MailboxError},
+    timer,
+};
+use core::{fmt::Debug, time::Duration};
+
+/// Represents an error that can occur while managing [Power] domains.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum PowerError {
+    /// Occurs when the firmware reports that the requested device does not exist on this board.
+    DeviceDoesNotExist(DeviceId),
+
+    /// Occurs when the device did not report itself as powered on in time.
+    TimedOut(DeviceId),
+
+    /// Occurs when the firmware responds about a different device than the one we asked about.
+    /// This usually means that the firmware does not understand the tag that was sent.
+    UnexpectedDevice { expected: DeviceId, actual: u32 },
+
+    /// Occurs when the mailbox returns an error that we can not recover from.
+    Mailbox(MailboxError),
+}
+
+/// Manages the power domains of the Raspberry Pi's peripherals through the [Mailbox].
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#power
+#[derive(Clone, Copy)]
+pub struct Power {
+    mailbox: Mailbox,
+}
+
+impl Power {
+    /// The shortest time that [Power::power_on] will wait for a device, as some devices report an
+    /// enable wait time of 0.
+    const MIN_POWER_ON_TIMEOUT: Duration = Duration::from_millis(1);
+
+    /// Creates a new instance of [Power], which will send its messages through [mailbox].
+    pub const fn new(mailbox: Mailbox) -> Power {
+        Power { mailbox }
+    }
+
+    /// Whether or not [device] is currently powered on.
+    ///
+    /// ## Errors
+    /// - [PowerError::DeviceDoesNotExist] if the device is not present on this board.
+    pub fn is_on(&self, device: DeviceId) -> Result<bool, PowerError> {
+        let response = self.send(device, PowerStateMessage::new_get(device), |it| {
+            it.device_id
+        })?;
+
+        Self::state_from(device, response.state).map(|it| it.contains(PowerState::On))
+    }
+
+    /// Returns the time that [device] takes to become stable after being powered on.
+    pub fn enable_wait_time(&self, device: DeviceId) -> Result<Duration, PowerError> {
+        let response = self.send(device, TimingMessage::new(device), |it| it.device_id)?;
+        Ok(Duration::from_micros(response.wait_time.into()))
+    }
+
+    /// Powers [device] on, and waits until it reports itself as on.
+    ///
+    /// ## Errors
+    /// - [PowerError::DeviceDoesNotExist] if the device is not present on this board.
+    /// - [PowerError::TimedOut] if the device did not come up within twice its enable wait time.
+    pub fn power_on(&self, device: DeviceId) -> Result<(), PowerError> {
+        let state = self.set_state(device, PowerState::On | PowerState::WaitOrDoesNotExist)?;
+        if state.contains(PowerState::On) {
+            return Ok(());
+        }
+
+        // The firmware should have waited for us, but some devices take longer than it expects.
+        let timeout = (self.enable_wait_time(device)? * 2).max(Self::MIN_POWER_ON_TIMEOUT);
+        let deadline = timer::uptime() + timeout;
+
+        while timer::uptime() < deadline {
+            if self.is_on(device)? {
+                return Ok(());
+            }
+
+            timer::delay(Duration::from_micros(100));
+        }
+
+        Err(PowerError::TimedOut(device))
+    }
+
+    /// Powers [device] off.
+    pub fn power_off(&self, device: DeviceId) -> Result<(), PowerError> {
+        self.set_state(device, PowerState::WaitOrDoesNotExist)
+            .map(|_| ())
+    }
+
+    fn set_state(&self, device: DeviceId, state: PowerState) -> Result<PowerState, PowerError> {
+        let response = self.send(device, PowerStateMessage::new_set(device, state), |it| {
+            it.device_id
+        })?;
+
+        Self::state_from(device, response.state)
+    }
+
+    /// Parses the state from a response, checking whether the device exists.
+    fn state_from(device: DeviceId, state: u32) -> Result<PowerState, PowerError> {
+        let state = PowerState::from_bits_retain(state);
+        if state.contains(PowerState::WaitOrDoesNotExist) {
+            return Err(PowerError::DeviceDoesNotExist(device));
+        }
+
+        Ok(state)
+    }
+
+    /// Sends a single tag about [device], making sure that the response is about the same device.
+    fn send<T: Debug>(
+        &self,
+        device: DeviceId,
+        request: MessageTag<T>,
+        device_id: fn(&T) -> u32,
+    ) -> Result<T, PowerError> {
+        let response: T = self
+            .mailbox
+            .send_single(Channel::PropertyTags, request)
+            .map_err(PowerError::Mailbox)?;
+
+        let actual = device_id(&response);
+        if actual != device as u32 {
+            return Err(PowerError::UnexpectedDevice {
+                expected: device,
+                actual,
+            });
+        }
+
+        Ok(response)
+    }
+}
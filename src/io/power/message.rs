@@ -0,0 +1,95 @@
+// Not all messages will be used.
+#![allow(dead_code)]
+
+use crate::io::mailbox::{types::MessageTag, TagIdentifier};
+use bitflags::bitflags;
+
+/// Represents the devices whose power domains can be controlled through the mailbox.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#power
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeviceId {
+    SdCard = 0x0,
+    Uart0 = 0x1,
+    Uart1 = 0x2,
+    UsbHcd = 0x3,
+    I2c0 = 0x4,
+    I2c1 = 0x5,
+    I2c2 = 0x6,
+    Spi = 0x7,
+    Ccp2Tx = 0x8,
+}
+
+bitflags! {
+    /// The power state of a device.
+    /// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-power-state
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PowerState: u32 {
+        /// In a request, this is whether the device should be powered on.
+        /// In a response, this is whether the device is powered on.
+        const On = 1 << 0;
+
+        /// In a [TagIdentifier::SetPowerState] request, this asks the firmware to wait for the
+        /// device to become stable before responding.
+        /// In a response, this is set when the device does not exist.
+        const WaitOrDoesNotExist = 1 << 1;
+    }
+}
+
+/// Gets or sets the power state of a device.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-power-state
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#set-power-state
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PowerStateMessage {
+    pub device_id: u32,
+    pub state: u32,
+}
+
+impl PowerStateMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new_get(device: DeviceId) -> MessageTag<PowerStateMessage> {
+        MessageTag::new(
+            TagIdentifier::GetPowerState,
+            PowerStateMessage {
+                device_id: device as u32,
+                state: 0,
+            },
+        )
+    }
+
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new_set(device: DeviceId, state: PowerState) -> MessageTag<PowerStateMessage> {
+        MessageTag::new(
+            TagIdentifier::SetPowerState,
+            PowerStateMessage {
+                device_id: device as u32,
+                state: state.bits(),
+            },
+        )
+    }
+}
+
+/// Asks for the time that a device takes to become stable after being powered on.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-timing
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TimingMessage {
+    pub device_id: u32,
+
+    /// The enable wait time in microseconds.
+    pub wait_time: u32,
+}
+
+impl TimingMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new(device: DeviceId) -> MessageTag<TimingMessage> {
+        MessageTag::new(
+            TagIdentifier::GetTiming,
+            TimingMessage {
+                device_id: device as u32,
+                wait_time: 0,
+            },
+        )
+    }
+}
@@ -0,0 +1,43 @@
+pub mod implementation;
+pub mod message;
+pub mod pm;
+pub mod watchdog;
+
+pub use implementation::*;
+pub use message::DeviceId;
+
+use crate::{mailbox, println};
+
+/// Returns an instance of [Power] which uses the global mailbox.
+/// You must call [mailbox::initialize] before running this.
+pub fn instance() -> Power {
+    Power::new(mailbox::instance())
+}
+
+/// Powers on every domain that [driver] depends on, waiting for each of them to come up.
+///
+/// Drivers declare their domains as a `POWER_DOMAINS` constant, and this should be called with it
+/// before the driver probes its hardware.
+pub fn enable_domains(driver: &str, domains: &[DeviceId]) -> Result<(), PowerError> {
+    let power = instance();
+
+    for domain in domains {
+        power.power_on(*domain)?;
+        println!("[angeldust::power] powered on {:?} for {}", domain, driver);
+    }
+
+    Ok(())
+}
+
+/// Powers off every domain that [driver] depends on, once it no longer uses its hardware.
+/// Domains that are shared with another driver that is still in use must not be passed here.
+pub fn disable_domains(driver: &str, domains: &[DeviceId]) -> Result<(), PowerError> {
+    let power = instance();
+
+    for domain in domains {
+        power.power_off(*domain)?;
+        println!("[angeldust::power] powered off {:?} for {}", domain, driver);
+    }
+
+    Ok(())
+}
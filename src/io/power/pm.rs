@@ -0,0 +1,211 @@
+use crate::{
+    cpu::{self, RaspberryPi},
+    fs, println, scheduler, timer,
+};
+use core::{
+    convert::Infallible,
+    ptr::{read_volatile, write_volatile},
+    time::Duration,
+};
+
+/// The power management block, which controls the reset and watchdog logic.
+///
+/// This is undocumented in the BCM2835/BCM2711 peripheral datasheets, the register layout comes
+/// from Linux's `bcm2835_wdt` driver.
+#[derive(Clone, Copy, Debug)]
+pub struct PowerManagement {
+    registers: Registers,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Registers {
+    reset_control: *mut u32,
+    reset_status: *mut u32,
+    watchdog: *mut u32,
+}
+
+impl PowerManagement {
+    /// Every write to the power management block must include this password, or it is ignored.
+    const PASSWORD: u32 = 0x5A00_0000;
+
+    /// PM_RSTC: The bits that configure what happens when the watchdog expires.
+    const RESET_CONTROL_CONFIGURATION_CLEAR: u32 = 0xFFFF_FFCF;
+    const RESET_CONTROL_FULL_RESET: u32 = 0x0000_0020;
+    const RESET_CONTROL_RESET: u32 = 0x0000_0102;
+
+    /// PM_RSTS: Clears the bits that hold the boot partition, which are spread out over every
+    /// other bit of the low 12 bits.
+    const RESET_STATUS_PARTITION_CLEAR: u32 = 0xFFFF_FAAA;
+
+    /// PM_WDOG: The watchdog counts down in units of 1/65536 seconds, and is 20 bits wide.
+    const WATCHDOG_TICKS_PER_SECOND: u32 = 1 << 16;
+    const WATCHDOG_TIME_MASK: u32 = 0x000F_FFFF;
+
+    /// The longest timeout that the watchdog supports, just under 16 seconds.
+    pub const MAX_WATCHDOG_TIMEOUT: Duration = Duration::from_secs(15);
+
+    /// The partition that the firmware treats as a request to power off, rather than reboot.
+    pub const HALT_PARTITION: u8 = 63;
+
+    /// The highest partition that fits in the 6 bits that PM_RSTS has for it.
+    pub const MAX_PARTITION: u8 = 63;
+
+    /// Creates a new instance of [PowerManagement].
+    ///
+    /// # Safety
+    /// - This function assumes that the [RaspberryPi::peripheral_base_address] is valid.
+    pub fn new() -> PowerManagement {
+        let base_address = unsafe {
+            RaspberryPi::instance()
+                .peripheral_base_address()
+                .byte_offset(0x100000) as *mut u32
+        };
+
+        PowerManagement {
+            registers: unsafe { Registers::new(base_address) },
+        }
+    }
+
+    /// Starts (or refreshes) the watchdog, which will reset the board once [timeout] has passed.
+    /// [timeout] is clamped to [PowerManagement::MAX_WATCHDOG_TIMEOUT].
+    pub fn start_watchdog(&self, timeout: Duration) {
+        let timeout = timeout.min(Self::MAX_WATCHDOG_TIMEOUT);
+        let ticks = (timeout.as_millis() as u32 * Self::WATCHDOG_TICKS_PER_SECOND) / 1000;
+
+        unsafe {
+            write_volatile(
+                self.registers.watchdog,
+                Self::PASSWORD | (ticks & Self::WATCHDOG_TIME_MASK),
+            );
+
+            let control = read_volatile(self.registers.reset_control);
+            write_volatile(
+                self.registers.reset_control,
+                Self::PASSWORD
+                    | (control & Self::RESET_CONTROL_CONFIGURATION_CLEAR)
+                    | Self::RESET_CONTROL_FULL_RESET,
+            );
+        }
+    }
+
+    /// Stops the watchdog, so that it will never reset the board.
+    pub fn stop_watchdog(&self) {
+        unsafe {
+            write_volatile(
+                self.registers.reset_control,
+                Self::PASSWORD | Self::RESET_CONTROL_RESET,
+            )
+        }
+    }
+
+    /// Resets the board, asking the firmware to boot from [partition] next.
+    /// Partition 0 is the default, and [PowerManagement::HALT_PARTITION] powers the board off.
+    ///
+    /// [partition] must not be larger than [PowerManagement::MAX_PARTITION], only its low 6 bits
+    /// are used.
+    pub fn reset(&self, partition: u8) -> ! {
+        // The partition's bits are spread out over every other bit of PM_RSTS.
+        let partition = partition as u32;
+        let partition_bits = (0..6).fold(0, |bits, bit| bits | ((partition & (1 << bit)) << bit));
+
+        unsafe {
+            let status = read_volatile(self.registers.reset_status);
+            write_volatile(
+                self.registers.reset_status,
+                Self::PASSWORD | (status & Self::RESET_STATUS_PARTITION_CLEAR) | partition_bits,
+            );
+        }
+
+        // Let the watchdog expire as soon as possible (10 ticks is ~150us).
+        unsafe {
+            write_volatile(self.registers.watchdog, Self::PASSWORD | 10);
+
+            let control = read_volatile(self.registers.reset_control);
+            write_volatile(
+                self.registers.reset_control,
+                Self::PASSWORD
+                    | (control & Self::RESET_CONTROL_CONFIGURATION_CLEAR)
+                    | Self::RESET_CONTROL_FULL_RESET,
+            );
+        }
+
+        // The reset should happen long before this, but it isn't instant. This may be called while
+        // panicking, so we can't panic if it doesn't happen.
+        timer::delay(Duration::from_millis(100));
+        println!("[angeldust::pm] the board did not reset, halting");
+        cpu::halt()
+    }
+}
+
+impl Registers {
+    /// Creates a new instance of [Registers].
+    ///
+    /// # Safety
+    /// - This assumes that the provided [pm_base] is valid.
+    const unsafe fn new(pm_base: *mut u32) -> Registers {
+        Registers {
+            reset_control: pm_base.byte_offset(0x1C),
+            reset_status: pm_base.byte_offset(0x20),
+            watchdog: pm_base.byte_offset(0x24),
+        }
+    }
+}
+
+/// Writes back anything that is pending on the mounted filesystems before the board goes down,
+/// and unmounts the SD card so that it is marked as clean, see [fs::unmount].
+///
+/// This can only be done from a thread, as it blocks. Everywhere else (e.g. an interrupt handler),
+/// it is skipped, and only what the periodic [fs::sync_periodically] already
+/// wrote is kept.
+fn sync_filesystems() {
+    if scheduler::can_block() {
+        fs::unmount();
+    } else {
+        println!("[angeldust::pm] can't sync the filesystems from here, skipping it");
+    }
+}
+
+/// Reboots the board into the default partition, after syncing the filesystems.
+pub fn reboot() -> ! {
+    let Err(error) = reboot_to_partition(0);
+    unreachable!("partition 0 can always be booted from: {:?}", error)
+}
+
+/// Reboots the board, asking the firmware to boot from [partition] (as used by NOOBS and the
+/// `tryboot` mechanism), after syncing the filesystems. This only returns if [partition] can't
+/// be booted from.
+pub fn reboot_to_partition(partition: u8) -> Result<Infallible, PmError> {
+    if partition > PowerManagement::MAX_PARTITION {
+        return Err(PmError::InvalidPartition(partition));
+    }
+
+    sync_filesystems();
+    println!("[angeldust::pm] rebooting into partition {}", partition);
+    PowerManagement::new().reset(partition)
+}
+
+/// Powers the board off, after syncing the filesystems.
+///
+/// The firmware treats a reset into [PowerManagement::HALT_PARTITION] as a halt, and waits for
+/// the power to be cycled (or GPIO3 to be pulled low) before booting again.
+pub fn halt() -> ! {
+    sync_filesystems();
+    println!("[angeldust::pm] powering off");
+    PowerManagement::new().reset(PowerManagement::HALT_PARTITION)
+}
+
+/// Represents an error that can occur while rebooting the board.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum PmError {
+    /// Occurs when the partition is larger than [PowerManagement::MAX_PARTITION].
+    InvalidPartition(u8),
+}
+
+/// # Safety
+/// - [PowerManagement] only ever accesses its registers with volatile reads and writes.
+unsafe impl Send for PowerManagement {}
+
+/// # Safety
+/// - [PowerManagement] only ever accesses its registers with volatile reads and writes.
+unsafe impl Sync for PowerManagement {}
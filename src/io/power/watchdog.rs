@@ -0,0 +1,176 @@
+use super::pm::PowerManagement;
+use crate::{
+    arch::aarch64::daif,
+    mutex::Mutex,
+    params::{self, Parameter},
+    println,
+    timer::{self, TimerError},
+};
+use core::time::Duration;
+
+/// The maximum amount of clients that can be registered with [register].
+const MAX_CLIENTS: usize = 8;
+
+/// How often the clients are checked, and the hardware watchdog is refreshed.
+const CHECK_PERIOD: Duration = Duration::from_secs(1);
+
+/// The parameters accepted by the watchdog.
+pub const PARAMETERS: &[Parameter] = &[Parameter {
+    key: "watchdog",
+    default: "0",
+    description: "reset the board if a kernel thread stops for this many seconds (0 disables it)",
+    apply: |params, value| {
+        params.watchdog.timeout = params::parse_u32(
+            value,
+            0,
+            PowerManagement::MAX_WATCHDOG_TIMEOUT.as_secs() as u32,
+        )?;
+
+        Ok(())
+    },
+}];
+
+/// The watchdog's [crate::params::BootParams].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WatchdogParams {
+    /// The timeout in seconds, or 0 if the watchdog is disabled.
+    pub timeout: u32,
+}
+
+/// Represents an error that can occur while using the watchdog.
+#[derive(Debug)]
+pub enum WatchdogError {
+    /// Occurs when [MAX_CLIENTS] clients have already been registered.
+    TooManyClients,
+
+    /// Occurs when the periodic check could not be registered with the timer.
+    Timer(TimerError),
+}
+
+/// A kernel thread that must [WatchdogClient::pet] the watchdog at least once per timeout.
+#[derive(Debug, Clone, Copy)]
+pub struct WatchdogClient {
+    index: usize,
+}
+
+#[derive(Clone, Copy)]
+struct ClientState {
+    name: &'static str,
+
+    /// The tick at which the client last called [WatchdogClient::pet].
+    last_pet: u64,
+}
+
+struct WatchdogState {
+    timeout_ticks: u64,
+    expired: bool,
+    clients: [Option<ClientState>; MAX_CLIENTS],
+}
+
+static WATCHDOG: Mutex<Option<WatchdogState>> = Mutex::new(None);
+
+/// Starts the hardware watchdog with the timeout from the [crate::params::BootParams], unless it
+/// is disabled.
+///
+/// The hardware watchdog is refreshed from the timer interrupt for as long as every registered
+/// [WatchdogClient] keeps petting it, so [timer::initialize] must be called before this.
+pub fn initialize() -> Result<(), WatchdogError> {
+    let timeout = params::instance().watchdog.timeout;
+    if timeout == 0 {
+        // A previous kernel may have left the watchdog running before rebooting into us.
+        PowerManagement::new().stop_watchdog();
+        println!("[angeldust::watchdog] disabled");
+        return Ok(());
+    }
+
+    daif::without_irqs(|| {
+        *WATCHDOG.lock() = Some(WatchdogState {
+            timeout_ticks: timeout as u64 * timer::TICK_FREQUENCY,
+            expired: false,
+            clients: [None; MAX_CLIENTS],
+        })
+    });
+
+    PowerManagement::new().start_watchdog(Duration::from_secs(timeout.into()));
+    timer::every(CHECK_PERIOD, check).map_err(WatchdogError::Timer)?;
+
+    println!("[angeldust::watchdog] started with a {}s timeout", timeout);
+    Ok(())
+}
+
+/// Registers a new [WatchdogClient] called [name], which must start petting the watchdog
+/// straight away.
+///
+/// If the watchdog is disabled, the client is accepted but never checked.
+pub fn register(name: &'static str) -> Result<WatchdogClient, WatchdogError> {
+    daif::without_irqs(|| {
+        let mut watchdog = WATCHDOG.lock();
+        let Some(state) = watchdog.as_mut() else {
+            return Ok(WatchdogClient { index: MAX_CLIENTS });
+        };
+
+        let index = state
+            .clients
+            .iter()
+            .position(|it| it.is_none())
+            .ok_or(WatchdogError::TooManyClients)?;
+
+        state.clients[index] = Some(ClientState {
+            name,
+            last_pet: timer::ticks(),
+        });
+
+        Ok(WatchdogClient { index })
+    })
+}
+
+impl WatchdogClient {
+    /// Tells the watchdog that this client is still making progress.
+    pub fn pet(&self) {
+        daif::without_irqs(|| {
+            if let Some(Some(client)) = WATCHDOG
+                .lock()
+                .as_mut()
+                .and_then(|it| it.clients.get_mut(self.index))
+            {
+                client.last_pet = timer::ticks();
+            }
+        })
+    }
+}
+
+/// Called by the timer every [CHECK_PERIOD], this refreshes the hardware watchdog unless a client
+/// has stopped petting it.
+fn check() {
+    let mut watchdog = WATCHDOG.lock();
+    let Some(state) = watchdog.as_mut() else {
+        return;
+    };
+
+    if state.expired {
+        return;
+    }
+
+    let now = timer::ticks();
+    let stalled = state
+        .clients
+        .iter()
+        .flatten()
+        .find(|it| now - it.last_pet > state.timeout_ticks);
+
+    match stalled {
+        Some(client) => {
+            // Stop refreshing the hardware watchdog, it will reset the board shortly.
+            state.expired = true;
+            println!(
+                "[angeldust::watchdog] {} has not made progress for {}s, the board will reset",
+                client.name,
+                (now - client.last_pet) / timer::TICK_FREQUENCY
+            );
+        }
+
+        None => PowerManagement::new().start_watchdog(Duration::from_secs(
+            state.timeout_ticks / timer::TICK_FREQUENCY,
+        )),
+    }
+}
@@ -0,0 +1,67 @@
+use core::ptr::{read_volatile, write_volatile};
+
+/// The hardware random number generator found in the BCM2835, BCM2836 and BCM2837.
+///
+/// This is undocumented in the peripheral datasheets, the register layout comes from Linux's
+/// `bcm2835-rng` driver.
+#[derive(Clone, Copy)]
+pub struct Bcm2835Rng {
+    base_address: *mut u8,
+}
+
+impl Bcm2835Rng {
+    const CONTROL: usize = 0x00;
+    const STATUS: usize = 0x04;
+    const DATA: usize = 0x08;
+    const INTERRUPT_MASK: usize = 0x10;
+
+    /// RNG_CTRL: Enables the random bit generator.
+    const CONTROL_ENABLE: u32 = 1 << 0;
+
+    /// RNG_INT_MASK: Stops the RNG from raising an interrupt when data is available.
+    const INTERRUPT_MASK_DISABLE: u32 = 1 << 0;
+
+    /// RNG_STATUS: The amount of initial numbers to discard, as they are less random.
+    const WARMUP_COUNT: u32 = 0x40000;
+
+    /// The `compatible` string of the generator's node in the device tree.
+    pub const COMPATIBLE: &'static str = "brcm,bcm2835-rng";
+
+    /// Creates a new instance of [Bcm2835Rng].
+    ///
+    /// # Safety
+    /// - This assumes that the provided [base_address] points to the RNG's registers.
+    pub const unsafe fn new(base_address: *mut u8) -> Bcm2835Rng {
+        Bcm2835Rng { base_address }
+    }
+
+    /// Starts the generator, discarding the first [Bcm2835Rng::WARMUP_COUNT] numbers.
+    pub fn initialize(&self) {
+        unsafe {
+            write_volatile(self.register(Self::STATUS), Self::WARMUP_COUNT);
+
+            let mask = read_volatile(self.register(Self::INTERRUPT_MASK));
+            write_volatile(
+                self.register(Self::INTERRUPT_MASK),
+                mask | Self::INTERRUPT_MASK_DISABLE,
+            );
+
+            write_volatile(self.register(Self::CONTROL), Self::CONTROL_ENABLE);
+        }
+    }
+
+    /// Returns a random word if one is available, the generator is fairly slow.
+    pub fn try_read(&self) -> Option<u32> {
+        // The top byte of the status register holds the amount of words that are available.
+        let available = unsafe { read_volatile(self.register(Self::STATUS)) } >> 24;
+        if available == 0 {
+            return None;
+        }
+
+        Some(unsafe { read_volatile(self.register(Self::DATA)) })
+    }
+
+    const fn register(&self, offset: usize) -> *mut u32 {
+        unsafe { self.base_address.byte_add(offset) as *mut u32 }
+    }
+}
@@ -0,0 +1,110 @@
+/// A cryptographically secure pseudo-random number generator based on the ChaCha20 block function.
+///
+/// After every request the key is replaced with fresh output ("fast key erasure"), so capturing
+/// the state does not reveal anything that was generated before it.
+///
+/// https://www.rfc-editor.org/rfc/rfc8439#section-2.3
+pub struct ChaCha20Rng {
+    key: [u32; 8],
+    counter: u64,
+}
+
+impl ChaCha20Rng {
+    /// "expand 32-byte k"
+    const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646E, 0x7962_2D32, 0x6B20_6574];
+
+    /// The size of a single block of output in bytes.
+    const BLOCK_SIZE: usize = 64;
+
+    /// Creates a new instance of [ChaCha20Rng] from a 256-bit [seed].
+    pub fn new(seed: [u32; 8]) -> ChaCha20Rng {
+        let mut rng = ChaCha20Rng {
+            key: seed,
+            counter: 0,
+        };
+
+        rng.rekey();
+        rng
+    }
+
+    /// Mixes [entropy] into the key.
+    /// The new key is a one-way function of the old key and [entropy], so a poor source of entropy
+    /// can never make the output less random.
+    pub fn reseed(&mut self, entropy: [u32; 8]) {
+        for (key, entropy) in self.key.iter_mut().zip(entropy) {
+            *key ^= entropy;
+        }
+
+        self.rekey();
+    }
+
+    /// Fills [destination] with random bytes.
+    pub fn fill_bytes(&mut self, destination: &mut [u8]) {
+        for chunk in destination.chunks_mut(Self::BLOCK_SIZE) {
+            let block = self.next_block();
+            let random = block.iter().flat_map(|it| it.to_le_bytes());
+            for (byte, random) in chunk.iter_mut().zip(random) {
+                *byte = random;
+            }
+        }
+
+        self.rekey();
+    }
+
+    /// Replaces the key with the first half of the next block.
+    fn rekey(&mut self) {
+        let block = self.next_block();
+        self.key.copy_from_slice(&block[..8]);
+    }
+
+    fn next_block(&mut self) -> [u32; 16] {
+        let block = Self::block(&self.key, self.counter);
+        self.counter = self.counter.wrapping_add(1);
+
+        block
+    }
+
+    /// The ChaCha20 block function, with a 64-bit counter and a zero nonce.
+    fn block(key: &[u32; 8], counter: u64) -> [u32; 16] {
+        let mut initial = [0u32; 16];
+        initial[..4].copy_from_slice(&Self::CONSTANTS);
+        initial[4..12].copy_from_slice(key);
+        initial[12] = counter as u32;
+        initial[13] = (counter >> 32) as u32;
+
+        let mut state = initial;
+        for _ in 0..10 {
+            // Column rounds.
+            Self::quarter_round(&mut state, 0, 4, 8, 12);
+            Self::quarter_round(&mut state, 1, 5, 9, 13);
+            Self::quarter_round(&mut state, 2, 6, 10, 14);
+            Self::quarter_round(&mut state, 3, 7, 11, 15);
+
+            // Diagonal rounds.
+            Self::quarter_round(&mut state, 0, 5, 10, 15);
+            Self::quarter_round(&mut state, 1, 6, 11, 12);
+            Self::quarter_round(&mut state, 2, 7, 8, 13);
+            Self::quarter_round(&mut state, 3, 4, 9, 14);
+        }
+
+        for (word, initial) in state.iter_mut().zip(initial) {
+            *word = word.wrapping_add(initial);
+        }
+
+        state
+    }
+
+    fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(16);
+
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(12);
+
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(8);
+
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(7);
+    }
+}
@@ -0,0 +1,239 @@
+pub mod bcm2835;
+pub mod chacha;
+pub mod rng200;
+
+use crate::{
+    arch::aarch64::{daif, generic_timer::GenericTimer},
+    cpu::{BoardType, RaspberryPi},
+    fdt, memory,
+    mutex::Mutex,
+    println, scheduler, timer,
+};
+use bcm2835::Bcm2835Rng;
+use chacha::ChaCha20Rng;
+use core::time::Duration;
+use rng200::Rng200;
+
+/// How long to wait for the hardware generator to produce a word before giving up.
+/// This is fairly long, as the first read has to wait for the generator to warm up.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often the [ChaCha20Rng] is reseeded from the hardware generator.
+const RESEED_PERIOD: Duration = Duration::from_secs(60);
+
+/// Represents an error that can occur while reading from the hardware generator.
+#[derive(Debug)]
+pub enum RngError {
+    /// Occurs when the generator did not produce a word within [READ_TIMEOUT].
+    TimedOut,
+}
+
+/// The hardware random number generator used by this Raspberry Pi.
+#[derive(Clone, Copy)]
+pub enum HardwareRng {
+    /// The Raspberry Pi 3 (and QEMU's raspi3b) use the BCM2835's generator.
+    Bcm2835(Bcm2835Rng),
+
+    /// The Raspberry Pi 4 uses an iProc RNG200.
+    Rng200(Rng200),
+}
+
+static HARDWARE: Mutex<Option<HardwareRng>> = Mutex::new(None);
+static POOL: Mutex<Option<ChaCha20Rng>> = Mutex::new(None);
+
+/// Starts the hardware generator, and seeds the kernel's [ChaCha20Rng] from it and from timer
+/// jitter.
+///
+/// If the hardware generator doesn't work, the pool is seeded from timer jitter alone, which is
+/// much weaker, so a warning is printed.
+/// [timer::initialize] must be called before this, as reading the hardware generator times out.
+pub fn initialize() {
+    let hardware = find_hardware();
+    hardware.initialize();
+    daif::without_irqs(|| *HARDWARE.lock() = Some(hardware));
+
+    let mut seed = jitter_entropy();
+    match hardware.read_seed() {
+        Ok(hardware_seed) => {
+            for (seed, hardware_seed) in seed.iter_mut().zip(hardware_seed) {
+                *seed ^= hardware_seed;
+            }
+        }
+
+        Err(error) => println!(
+            "[angeldust::rng] warning: the hardware generator failed ({:?}), only using timer jitter",
+            error
+        ),
+    }
+
+    daif::without_irqs(|| *POOL.lock() = Some(ChaCha20Rng::new(seed)));
+
+    println!("[angeldust::rng] seeded the entropy pool");
+}
+
+/// Fills [destination] with cryptographically secure random bytes.
+/// You must call [initialize] before running this.
+pub fn fill_bytes(destination: &mut [u8]) {
+    daif::without_irqs(|| match POOL.lock().as_mut() {
+        Some(pool) => pool.fill_bytes(destination),
+        _ => panic!("rng::initialize() should be called before rng::fill_bytes()"),
+    })
+}
+
+/// Returns a cryptographically secure random u64.
+/// You must call [initialize] before running this.
+pub fn next_u64() -> u64 {
+    let mut bytes = [0u8; 8];
+    fill_bytes(&mut bytes);
+
+    u64::from_le_bytes(bytes)
+}
+
+/// Mixes fresh hardware entropy into the pool every [RESEED_PERIOD].
+/// This is meant to run as its own kernel thread, as reading the seed can take a while.
+pub fn reseed_periodically() {
+    loop {
+        scheduler::sleep(RESEED_PERIOD);
+        reseed();
+    }
+}
+
+/// Mixes fresh hardware entropy into the pool.
+fn reseed() {
+    let Some(hardware) = daif::without_irqs(|| *HARDWARE.lock()) else {
+        return;
+    };
+
+    // The seed is read without holding any lock, as the generator may take up to [READ_TIMEOUT]
+    // per word, and the pool is only locked to mix it in.
+    match hardware.read_seed() {
+        Ok(seed) => daif::without_irqs(|| {
+            if let Some(pool) = POOL.lock().as_mut() {
+                pool.reseed(seed);
+            }
+        }),
+        Err(error) => println!(
+            "[angeldust::rng] warning: failed to reseed the entropy pool: {:?}",
+            error
+        ),
+    }
+}
+
+/// Finds the hardware generator by its compatible string in the device tree, or from the board
+/// type if there is no device tree (or it doesn't describe a generator).
+fn find_hardware() -> HardwareRng {
+    let from_device_tree = fdt::instance().and_then(|device_tree| {
+        let soc = device_tree.find_node("/soc")?;
+
+        [Rng200::COMPATIBLE, Bcm2835Rng::COMPATIBLE]
+            .into_iter()
+            .find_map(|compatible| {
+                let node = device_tree.find_compatible(compatible)?;
+                let address = device_tree.translate_soc_address(node.reg(&soc).next()?.address)?;
+
+                println!(
+                    "[angeldust::rng] found {} ({}) at {:#x} in the device tree",
+                    node.name(),
+                    compatible,
+                    address
+                );
+
+                Some((compatible, memory::virtual_address(address) as *mut u8))
+            })
+    });
+
+    // Safety: The address is either the generator's node in the device tree, or where the
+    // generator is on this board type.
+    match from_device_tree {
+        Some((Rng200::COMPATIBLE, address)) => HardwareRng::Rng200(unsafe { Rng200::new(address) }),
+        Some((_, address)) => HardwareRng::Bcm2835(unsafe { Bcm2835Rng::new(address) }),
+
+        None => {
+            let address = unsafe {
+                RaspberryPi::instance()
+                    .peripheral_base_address()
+                    .byte_offset(0x104000)
+            };
+
+            match RaspberryPi::instance().board_type() {
+                BoardType::Pi4 => HardwareRng::Rng200(unsafe { Rng200::new(address) }),
+                _ => HardwareRng::Bcm2835(unsafe { Bcm2835Rng::new(address) }),
+            }
+        }
+    }
+}
+
+/// Collects 256 bits from the jitter between reads of the system counter around a small amount of
+/// work, which varies with caches, pipelines and the memory bus.
+///
+/// This is weak on its own (and close to useless under emulation), but is mixed in so that the
+/// pool never depends on the hardware generator alone.
+fn jitter_entropy() -> [u32; 8] {
+    let mut entropy = [0u32; 8];
+    let mut previous = GenericTimer::counter();
+
+    for round in 0..1024 {
+        // Some work whose duration isn't constant.
+        let mut work = previous;
+        for _ in 0..(previous & 0xF) {
+            work = work.rotate_left(7) ^ round;
+        }
+
+        let now = GenericTimer::counter();
+        let delta = now.wrapping_sub(previous) ^ work;
+        previous = now;
+
+        let word = &mut entropy[round as usize % entropy.len()];
+        *word = word.rotate_left(5) ^ (delta as u32) ^ ((delta >> 32) as u32);
+    }
+
+    entropy
+}
+
+impl HardwareRng {
+    fn initialize(&self) {
+        match self {
+            HardwareRng::Bcm2835(rng) => rng.initialize(),
+            HardwareRng::Rng200(rng) => rng.initialize(),
+        }
+    }
+
+    /// Reads a word from the generator, waiting up to [READ_TIMEOUT] for one to be available.
+    /// The RNG200 must also have warmed up within that time.
+    fn read(&self) -> Result<u32, RngError> {
+        let deadline = timer::uptime() + READ_TIMEOUT;
+
+        while timer::uptime() < deadline {
+            let word = match self {
+                HardwareRng::Bcm2835(rng) => rng.try_read(),
+                HardwareRng::Rng200(rng) if rng.is_warmed_up() => rng.try_read(),
+                HardwareRng::Rng200(_) => None,
+            };
+
+            if let Some(word) = word {
+                return Ok(word);
+            }
+
+            core::hint::spin_loop();
+        }
+
+        Err(RngError::TimedOut)
+    }
+
+    fn read_seed(&self) -> Result<[u32; 8], RngError> {
+        let mut seed = [0u32; 8];
+        for word in seed.iter_mut() {
+            *word = self.read()?;
+        }
+
+        Ok(seed)
+    }
+}
+
+/// # Safety
+/// - We always use [HardwareRng] within a [crate::Mutex].
+unsafe impl Send for HardwareRng {}
+
+/// # Safety
+/// - We always use [HardwareRng] within a [crate::Mutex].
+unsafe impl Sync for HardwareRng {}
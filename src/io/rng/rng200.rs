@@ -0,0 +1,119 @@
+use core::ptr::{read_volatile, write_volatile};
+
+/// The iProc RNG200 hardware random number generator found in the BCM2711.
+///
+/// This is undocumented in the peripheral datasheet, the register layout comes from Linux's
+/// `iproc-rng200` driver.
+#[derive(Clone, Copy)]
+pub struct Rng200 {
+    base_address: *mut u8,
+}
+
+impl Rng200 {
+    const CONTROL: usize = 0x00;
+    const RNG_SOFT_RESET: usize = 0x04;
+    const RBG_SOFT_RESET: usize = 0x08;
+    const TOTAL_BIT_COUNT: usize = 0x0C;
+    const TOTAL_BIT_COUNT_THRESHOLD: usize = 0x10;
+    const INTERRUPT_STATUS: usize = 0x18;
+    const FIFO_DATA: usize = 0x20;
+    const FIFO_COUNT: usize = 0x24;
+
+    /// RNG_CTRL: The bits that control the random bit generator, and the value that enables it.
+    const CONTROL_RBGEN_MASK: u32 = 0x1FFF;
+    const CONTROL_RBGEN_ENABLE: u32 = 0x1;
+
+    /// RNG_INT_STATUS: Set when the generator has locked up, or failed its self-tests.
+    const INTERRUPT_STATUS_FAILURE: u32 = (1 << 31) | (1 << 5);
+
+    /// RNG_FIFO_COUNT: The amount of words in the FIFO, and where the FIFO threshold starts.
+    const FIFO_COUNT_MASK: u32 = 0xFF;
+    const FIFO_THRESHOLD_SHIFT: u32 = 8;
+
+    /// The amount of bits to generate before the generator is considered warmed up.
+    const WARMUP_COUNT: u32 = 0x40000;
+
+    /// The `compatible` string of the generator's node in the device tree.
+    pub const COMPATIBLE: &'static str = "brcm,bcm2711-rng200";
+
+    /// Creates a new instance of [Rng200].
+    ///
+    /// # Safety
+    /// - This assumes that the provided [base_address] points to the RNG's registers.
+    pub const unsafe fn new(base_address: *mut u8) -> Rng200 {
+        Rng200 { base_address }
+    }
+
+    /// Starts the generator. The first [Rng200::WARMUP_COUNT] bits are discarded by the hardware,
+    /// [Rng200::is_warmed_up] can be used to wait for them.
+    pub fn initialize(&self) {
+        unsafe {
+            write_volatile(
+                self.register(Self::TOTAL_BIT_COUNT_THRESHOLD),
+                Self::WARMUP_COUNT,
+            );
+            write_volatile(
+                self.register(Self::FIFO_COUNT),
+                2 << Self::FIFO_THRESHOLD_SHIFT,
+            );
+        }
+
+        self.set_enabled(true);
+    }
+
+    /// Whether or not the generator has produced enough bits to be used.
+    pub fn is_warmed_up(&self) -> bool {
+        unsafe { read_volatile(self.register(Self::TOTAL_BIT_COUNT)) > 16 }
+    }
+
+    /// Returns a random word if one is available.
+    /// If the generator has failed, it is restarted and [None] is returned.
+    pub fn try_read(&self) -> Option<u32> {
+        let status = unsafe { read_volatile(self.register(Self::INTERRUPT_STATUS)) };
+        if status & Self::INTERRUPT_STATUS_FAILURE != 0 {
+            self.restart();
+            return None;
+        }
+
+        let available = unsafe { read_volatile(self.register(Self::FIFO_COUNT)) };
+        if available & Self::FIFO_COUNT_MASK == 0 {
+            return None;
+        }
+
+        Some(unsafe { read_volatile(self.register(Self::FIFO_DATA)) })
+    }
+
+    /// Resets the generator after a failure.
+    fn restart(&self) {
+        self.set_enabled(false);
+
+        unsafe {
+            // The interrupt status bits are cleared by writing 1 to them.
+            write_volatile(self.register(Self::INTERRUPT_STATUS), 0xFFFF_FFFF);
+
+            write_volatile(self.register(Self::RBG_SOFT_RESET), 1);
+            write_volatile(self.register(Self::RNG_SOFT_RESET), 1);
+            write_volatile(self.register(Self::RNG_SOFT_RESET), 0);
+            write_volatile(self.register(Self::RBG_SOFT_RESET), 0);
+        }
+
+        self.set_enabled(true);
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        unsafe {
+            let control = read_volatile(self.register(Self::CONTROL)) & !Self::CONTROL_RBGEN_MASK;
+            let enable = if enabled {
+                Self::CONTROL_RBGEN_ENABLE
+            } else {
+                0
+            };
+
+            write_volatile(self.register(Self::CONTROL), control | enable);
+        }
+    }
+
+    const fn register(&self, offset: usize) -> *mut u32 {
+        unsafe { self.base_address.byte_add(offset) as *mut u32 }
+    }
+}
@@ -0,0 +1,311 @@
+use super::message::{
+    TemperatureMessage, ThrottledFlags, ThrottledMessage, VoltageId, VoltageMessage,
+};
+use crate::{
+    io::{
+        clocks::{ClockError, ClockId, Clocks},
+        mailbox::{types::MessageTag, Channel, Mailbox, MailboxError},
+        power::pm,
+    },
+    println,
+};
+use core::time::Duration;
+
+/// Represents an error that can occur while reading the [Thermal] sensors.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum ThermalError {
+    /// Occurs when the firmware returns a value of 0, which means that the sensor is not supported
+    /// (QEMU does this, for example).
+    Unsupported,
+
+    /// Occurs when the mailbox returns an error that we can not recover from.
+    Mailbox(MailboxError),
+}
+
+/// Reads the temperature, voltage and throttling sensors through the [Mailbox].
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-temperature
+#[derive(Clone, Copy)]
+pub struct Thermal {
+    mailbox: Mailbox,
+}
+
+impl Thermal {
+    /// Creates a new instance of [Thermal], which will send its messages through [mailbox].
+    pub const fn new(mailbox: Mailbox) -> Thermal {
+        Thermal { mailbox }
+    }
+
+    /// Returns the current temperature of the SoC in thousandths of a degree Celsius.
+    pub fn temperature(&self) -> Result<u32, ThermalError> {
+        self.read_temperature(TemperatureMessage::new_get())
+    }
+
+    /// Returns the temperature at which the firmware starts throttling on its own, in thousandths
+    /// of a degree Celsius.
+    pub fn max_temperature(&self) -> Result<u32, ThermalError> {
+        self.read_temperature(TemperatureMessage::new_get_max())
+    }
+
+    /// Returns [voltage] in microvolts.
+    pub fn voltage(&self, voltage: VoltageId) -> Result<u32, ThermalError> {
+        let response: VoltageMessage = self
+            .mailbox
+            .send_single(Channel::PropertyTags, VoltageMessage::new_get(voltage))
+            .map_err(ThermalError::Mailbox)?;
+
+        match response.value {
+            0 => Err(ThermalError::Unsupported),
+            value => Ok(value),
+        }
+    }
+
+    /// Returns the current [ThrottledFlags], clearing the "occurred" flags.
+    pub fn throttled(&self) -> Result<ThrottledFlags, ThermalError> {
+        self.mailbox
+            .send_single::<_, ThrottledMessage>(Channel::PropertyTags, ThrottledMessage::new_get())
+            .map(|it| ThrottledFlags::from_bits_retain(it.value))
+            .map_err(ThermalError::Mailbox)
+    }
+
+    fn read_temperature(
+        &self,
+        request: MessageTag<TemperatureMessage>,
+    ) -> Result<u32, ThermalError> {
+        let response: TemperatureMessage = self
+            .mailbox
+            .send_single(Channel::PropertyTags, request)
+            .map_err(ThermalError::Mailbox)?;
+
+        match response.value {
+            0 => Err(ThermalError::Unsupported),
+            value => Ok(value),
+        }
+    }
+}
+
+/// Configures the behaviour of the [ThermalMonitor], as the thermal monitor's
+/// [crate::params::BootParams].
+/// All temperatures are in thousandths of a degree Celsius.
+#[derive(Debug, Clone, Copy)]
+pub struct ThermalConfig {
+    /// How often the sensors should be sampled.
+    pub sample_period: Duration,
+
+    /// The temperature at which the ARM clock is lowered to its minimum rate, which is set by the
+    /// `thermal.throttle` [crate::params::Parameter].
+    pub throttle_temperature: u32,
+
+    /// How far below [ThermalConfig::throttle_temperature] the SoC must cool down before the ARM
+    /// clock is raised back to its maximum rate.
+    pub hysteresis: u32,
+
+    /// How far below the firmware's maximum temperature the kernel halts the board.
+    pub shutdown_margin: u32,
+}
+
+impl Default for ThermalConfig {
+    fn default() -> ThermalConfig {
+        ThermalConfig {
+            sample_period: Duration::from_secs(1),
+            throttle_temperature: 75_000,
+            hysteresis: 5_000,
+            shutdown_margin: 2_000,
+        }
+    }
+}
+
+/// Whether or not the [ThermalMonitor] has lowered the ARM clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArmClockState {
+    Normal,
+    Throttled,
+}
+
+/// Periodically samples the [Thermal] sensors, throttling the ARM clock when the SoC gets too hot
+/// and halting the board before it reaches the firmware's maximum temperature.
+pub struct ThermalMonitor {
+    config: ThermalConfig,
+    thermal: Thermal,
+    clocks: Clocks,
+
+    /// The temperature at which the board is halted, if the firmware reported a maximum.
+    shutdown_temperature: Option<u32>,
+
+    arm_clock_state: ArmClockState,
+    last_flags: ThrottledFlags,
+}
+
+impl ThermalMonitor {
+    /// The flags that describe the current state of the board, rather than past events.
+    const CURRENT_FLAGS: ThrottledFlags = ThrottledFlags::UnderVoltage
+        .union(ThrottledFlags::FrequencyCapped)
+        .union(ThrottledFlags::Throttled)
+        .union(ThrottledFlags::SoftTemperatureLimit);
+
+    /// Creates a new instance of [ThermalMonitor].
+    pub fn new(config: ThermalConfig, thermal: Thermal, clocks: Clocks) -> ThermalMonitor {
+        let shutdown_temperature = match thermal.max_temperature() {
+            Ok(max) => Some(max.saturating_sub(config.shutdown_margin)),
+            Err(error) => {
+                println!(
+                    "[angeldust::thermal] failed to read the maximum temperature, the emergency halt is disabled: {:?}",
+                    error
+                );
+                None
+            }
+        };
+
+        ThermalMonitor {
+            config,
+            thermal,
+            clocks,
+            shutdown_temperature,
+            arm_clock_state: ArmClockState::Normal,
+            last_flags: ThrottledFlags::empty(),
+        }
+    }
+
+    /// Reads the sensors once and reacts to their values.
+    pub fn sample(&mut self) {
+        match self.thermal.throttled() {
+            Ok(flags) => self.check_flags(flags),
+            Err(error) => println!(
+                "[angeldust::thermal] failed to read the throttled flags: {:?}",
+                error
+            ),
+        }
+
+        match self.thermal.temperature() {
+            Ok(temperature) => self.check_temperature(temperature),
+
+            // The sensor is not present (e.g. in QEMU), so there is nothing to react to.
+            Err(ThermalError::Unsupported) => {}
+
+            Err(error) => println!(
+                "[angeldust::thermal] failed to read the temperature: {:?}",
+                error
+            ),
+        }
+    }
+
+    /// Logs a warning whenever a throttling flag appears, and a note when it clears.
+    fn check_flags(&mut self, flags: ThrottledFlags) {
+        let current = flags.intersection(Self::CURRENT_FLAGS);
+        let previous = self.last_flags.intersection(Self::CURRENT_FLAGS);
+
+        for (name, flag) in current.difference(previous).iter_names() {
+            if flag == ThrottledFlags::UnderVoltage {
+                println!(
+                    "[angeldust::thermal] warning: under-voltage detected (core: {} mV)",
+                    self.core_millivolts()
+                );
+            } else {
+                println!("[angeldust::thermal] warning: {} is now active", name);
+            }
+        }
+
+        for (name, _) in previous.difference(current).iter_names() {
+            println!("[angeldust::thermal] {} has cleared", name);
+        }
+
+        // The "occurred" flags are cleared on every read, so any that are set without their
+        // current counterpart happened (and stopped) since the last sample.
+        let transient = flags.difference(Self::CURRENT_FLAGS).bits() >> 16;
+        for (name, _) in ThrottledFlags::from_bits_truncate(transient)
+            .difference(current)
+            .iter_names()
+        {
+            println!(
+                "[angeldust::thermal] warning: {} occurred briefly since the last sample",
+                name
+            );
+        }
+
+        self.last_flags = flags;
+    }
+
+    /// Throttles, restores or halts depending on [temperature].
+    fn check_temperature(&mut self, temperature: u32) {
+        if let Some(shutdown_temperature) = self.shutdown_temperature {
+            if temperature >= shutdown_temperature {
+                self.emergency_halt(temperature);
+            }
+        }
+
+        match self.arm_clock_state {
+            ArmClockState::Normal if temperature >= self.config.throttle_temperature => {
+                println!(
+                    "[angeldust::thermal] warning: {} m°C is above the throttling threshold ({} m°C), lowering the arm clock",
+                    temperature, self.config.throttle_temperature
+                );
+
+                match self.lower_arm_clock() {
+                    Ok(rate) => {
+                        self.arm_clock_state = ArmClockState::Throttled;
+                        println!(
+                            "[angeldust::thermal] lowered the arm clock to {} MHz",
+                            rate / 1_000_000
+                        );
+                    }
+                    Err(error) => println!(
+                        "[angeldust::thermal] failed to lower the arm clock: {:?}",
+                        error
+                    ),
+                }
+            }
+
+            ArmClockState::Throttled
+                if temperature
+                    < self
+                        .config
+                        .throttle_temperature
+                        .saturating_sub(self.config.hysteresis) =>
+            {
+                match self.clocks.set_max_rate(ClockId::Arm) {
+                    Ok(rate) => {
+                        self.arm_clock_state = ArmClockState::Normal;
+                        println!(
+                            "[angeldust::thermal] cooled down to {} m°C, raised the arm clock back to {} MHz",
+                            temperature,
+                            rate / 1_000_000
+                        );
+                    }
+                    Err(error) => println!(
+                        "[angeldust::thermal] failed to raise the arm clock: {:?}",
+                        error
+                    ),
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Drops the ARM clock to its minimum rate, without turbo.
+    fn lower_arm_clock(&self) -> Result<u32, ClockError> {
+        self.clocks.set_turbo(false)?;
+
+        let min_rate = self.clocks.min_rate(ClockId::Arm)?;
+        self.clocks.set_rate(ClockId::Arm, min_rate, true)
+    }
+
+    /// Lowers the ARM clock as far as possible and powers the board off, which syncs the
+    /// filesystems first as this runs in a thread.
+    fn emergency_halt(&self, temperature: u32) -> ! {
+        println!(
+            "[angeldust::thermal] emergency: {} m°C is too close to the firmware's maximum, halting",
+            temperature
+        );
+
+        self.lower_arm_clock().ok();
+        pm::halt()
+    }
+
+    fn core_millivolts(&self) -> u32 {
+        self.thermal
+            .voltage(VoltageId::Core)
+            .map(|it| it / 1000)
+            .unwrap_or(0)
+    }
+}
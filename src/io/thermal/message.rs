@@ -0,0 +1,119 @@
+// Not all messages will be used.
+#![allow(dead_code)]
+
+use crate::io::mailbox::{types::MessageTag, TagIdentifier};
+use bitflags::bitflags;
+
+/// Represents the voltages that can be read through the mailbox.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#voltages
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VoltageId {
+    Core = 0x1,
+    SdramController = 0x2,
+    SdramPhy = 0x3,
+    SdramIo = 0x4,
+}
+
+impl VoltageId {
+    /// Every voltage known to the firmware, in identifier order.
+    pub const ALL: [VoltageId; 4] = [
+        VoltageId::Core,
+        VoltageId::SdramController,
+        VoltageId::SdramPhy,
+        VoltageId::SdramIo,
+    ];
+}
+
+bitflags! {
+    /// The throttling state reported by the firmware, as returned by [TagIdentifier::GetThrottled].
+    /// https://www.raspberrypi.com/documentation/computers/os.html#get_throttled
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ThrottledFlags: u32 {
+        const UnderVoltage = 1 << 0;
+        const FrequencyCapped = 1 << 1;
+        const Throttled = 1 << 2;
+        const SoftTemperatureLimit = 1 << 3;
+
+        const UnderVoltageOccurred = 1 << 16;
+        const FrequencyCappedOccurred = 1 << 17;
+        const ThrottledOccurred = 1 << 18;
+        const SoftTemperatureLimitOccurred = 1 << 19;
+    }
+}
+
+/// Asks for the current or maximum temperature of the SoC, in thousandths of a degree Celsius.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-temperature
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-max-temperature
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TemperatureMessage {
+    /// This should always be 0.
+    pub id: u32,
+    pub value: u32,
+}
+
+impl TemperatureMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new_get() -> MessageTag<TemperatureMessage> {
+        MessageTag::new(
+            TagIdentifier::GetTemperature,
+            TemperatureMessage { id: 0, value: 0 },
+        )
+    }
+
+    /// A helper function for creating a [MessageTag] for this request.
+    ///
+    /// The maximum temperature is the one at which the firmware will start throttling the clocks
+    /// on its own.
+    pub fn new_get_max() -> MessageTag<TemperatureMessage> {
+        MessageTag::new(
+            TagIdentifier::GetMaxTemperature,
+            TemperatureMessage { id: 0, value: 0 },
+        )
+    }
+}
+
+/// Asks for one of the voltages of the board, in microvolts.
+/// https://github.com/raspberrypi/firmware/wiki/Mailbox-property-interface#get-voltage
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct VoltageMessage {
+    pub voltage_id: u32,
+    pub value: u32,
+}
+
+impl VoltageMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    pub fn new_get(voltage: VoltageId) -> MessageTag<VoltageMessage> {
+        MessageTag::new(
+            TagIdentifier::GetVoltage,
+            VoltageMessage {
+                voltage_id: voltage as u32,
+                value: 0,
+            },
+        )
+    }
+}
+
+/// Asks for the [ThrottledFlags] of the board.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottledMessage {
+    /// The request holds a mask of the "occurred" bits that should be cleared once they have been
+    /// read, the response holds the [ThrottledFlags].
+    pub value: u32,
+}
+
+impl ThrottledMessage {
+    /// A helper function for creating a [MessageTag] for this request.
+    ///
+    /// The "occurred" flags will be cleared after being read, so each call reports whether they
+    /// happened since the previous call.
+    pub fn new_get() -> MessageTag<ThrottledMessage> {
+        MessageTag::new(
+            TagIdentifier::GetThrottled,
+            ThrottledMessage { value: 0xFFFF },
+        )
+    }
+}
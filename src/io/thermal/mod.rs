@@ -0,0 +1,101 @@
+pub mod implementation;
+pub mod message;
+
+pub use implementation::*;
+pub use message::VoltageId;
+
+use crate::{
+    io::clocks,
+    mailbox,
+    params::{self, Parameter},
+    println,
+    scheduler::{self, Priority},
+    sync::semaphore::Semaphore,
+    timer,
+};
+
+/// The parameters accepted by the thermal monitor.
+pub const PARAMETERS: &[Parameter] = &[Parameter {
+    key: "thermal.throttle",
+    default: "75",
+    description: "the temperature in °C at which the arm clock is lowered to its minimum",
+    apply: |params, value| {
+        params.thermal.throttle_temperature = params::parse_u32(value, 40, 85)? * 1000;
+        Ok(())
+    },
+}];
+
+/// Given by the timer every [ThermalConfig::sample_period], and taken by [monitor].
+static SAMPLE: Semaphore = Semaphore::new(0);
+
+/// Reports the current sensor readings, and starts a thread that samples them every
+/// [ThermalConfig::sample_period], with the [ThermalConfig] from the kernel command line.
+///
+/// [scheduler::initialize] must be called before this.
+pub fn initialize() {
+    report(&instance());
+
+    let sample_period = params::instance().thermal.sample_period;
+    if let Err(error) = timer::every(sample_period, tick) {
+        println!(
+            "[angeldust::thermal] failed to schedule thermal sampling: {:?}",
+            error
+        );
+        return;
+    }
+
+    if let Err(error) = scheduler::spawn("thermal", Priority::High, monitor) {
+        println!(
+            "[angeldust::thermal] failed to start the thermal monitor: {:?}",
+            error
+        );
+    }
+}
+
+/// Returns an instance of [Thermal] which uses the global mailbox.
+/// You must call [mailbox::initialize] before running this.
+pub fn instance() -> Thermal {
+    Thermal::new(mailbox::instance())
+}
+
+/// Prints the current temperature, maximum temperature and voltages.
+pub fn report(thermal: &Thermal) {
+    match (thermal.temperature(), thermal.max_temperature()) {
+        (Ok(temperature), Ok(max_temperature)) => println!(
+            "[angeldust::thermal] temperature: {} m°C (max: {} m°C)",
+            temperature, max_temperature
+        ),
+        (temperature, max_temperature) => println!(
+            "[angeldust::thermal] temperature: {:?} (max: {:?})",
+            temperature, max_temperature
+        ),
+    }
+
+    for voltage in VoltageId::ALL {
+        match thermal.voltage(voltage) {
+            Ok(value) => println!("[angeldust::thermal] {:?}: {} µV", voltage, value),
+            Err(error) => println!("[angeldust::thermal] {:?}: {:?}", voltage, error),
+        }
+    }
+}
+
+/// Called by the timer every [ThermalConfig::sample_period]. Reading the sensors takes several
+/// mailbox round trips, which is too long for an interrupt handler, so this only wakes [monitor].
+fn tick() {
+    // Samples that are missed while the monitor is still busy aren't worth catching up on.
+    if SAMPLE.available() == 0 {
+        SAMPLE.release();
+    }
+}
+
+/// Samples the sensors whenever [tick] says so. This runs in its own thread, so that halting the
+/// board can sync the filesystems first.
+fn monitor() {
+    let config = params::instance().thermal;
+    let mut monitor = ThermalMonitor::new(config, instance(), clocks::instance());
+
+    loop {
+        SAMPLE.acquire();
+        monitor.sample();
+    }
+}
@@ -1,7 +1,7 @@
 use bitflags::{bitflags, Flags};
 use core::ptr::{read_volatile, write_volatile};
 
-use crate::cpu::RaspberryPi;
+use crate::{cpu::RaspberryPi, io::power::DeviceId};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Uart {
@@ -22,6 +22,7 @@ bitflags! {
     /// 11.5. Register View - FR Register
     /// https://datasheets.raspberrypi.com/bcm2711/bcm2711-peripherals.pdf#reg-UART-FR
     struct Flag: u32 {
+        const ReceiveFIFOEmpty = 1 << 4;
         const TransmitFIFOFull = 1 << 5;
         const ReceiveFIFOFUll = 1 << 6;
     }
@@ -44,6 +45,9 @@ bitflags! {
 }
 
 impl Uart {
+    /// The power domains that must be enabled before the [Uart] is initialized.
+    pub const POWER_DOMAINS: &'static [DeviceId] = &[DeviceId::Uart0];
+
     /// Creates a new instance of [Uart].
     ///
     /// # Safety
@@ -96,6 +100,18 @@ impl Uart {
 
         Registers::write_value(self.registers.data, byte.into());
     }
+
+    /// Reads a byte from the receive FIFO, or returns [None] if nothing has been received.
+    pub fn read(&self) -> Option<u8> {
+        if Registers::read_register::<_, Flag>(self.registers.flag).contains(Flag::ReceiveFIFOEmpty)
+        {
+            return None;
+        }
+
+        // The upper bits of the data register are the error flags for this byte.
+        let value = unsafe { read_volatile(self.registers.data) };
+        Some(value as u8)
+    }
 }
 
 impl Registers {
@@ -0,0 +1,504 @@
+//! Checks the order that locks are taken in, when the kernel is built with the `lockdep`
+//! feature, so that a deadlock is reported the first time that it could happen, rather than the
+//! first time that it does.
+//!
+//! Every lock is its own class, identified by its address, which is enough as every lock of the
+//! kernel is a static. Whenever a lock is taken, the classes that the thread already holds are
+//! recorded as coming before it, and it is a violation if that makes a cycle. Taking a lock that
+//! the thread already holds, and waiting for a sleeping lock while holding a spin lock (which
+//! masks IRQs, so the thread can't sleep), are violations too. So is unmasking IRQs while holding
+//! a spin lock that an interrupt handler also takes, e.g. by dropping its guards out of order,
+//! as the handler would spin on it forever.
+//!
+//! Only the first violation is reported, after which nothing is checked anymore. It is printed
+//! once the thread that found it holds no locks, as the console may be one of them.
+
+use crate::{
+    arch::aarch64::daif,
+    cpu::percpu::{self, MAX_CORES},
+    println,
+    scheduler::MAX_THREADS,
+};
+use core::{
+    cell::UnsafeCell,
+    fmt::{self, Display},
+    hint,
+    panic::Location,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// The most locks that are checked, which is enough for every static lock of the kernel.
+const MAX_CLASSES: usize = 128;
+
+/// The most locks that a thread may hold at once.
+const MAX_HELD: usize = 16;
+
+/// Locks are held by threads, or by cores that aren't scheduling threads yet. Interrupt handlers
+/// take locks on behalf of the thread that they interrupted.
+const MAX_CONTEXTS: usize = MAX_THREADS + MAX_CORES;
+
+/// Where a lock was taken.
+type Site = &'static Location<'static>;
+
+/// How a lock was taken, which decides which rules apply to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockKind {
+    /// A [crate::mutex::Mutex], which spins.
+    Spin,
+
+    /// A lock from [crate::sync], which sleeps.
+    Sleeping,
+
+    /// A [crate::sync::RwLock] taken for reading, which a thread may take more than once.
+    Read,
+}
+
+/// Returned by [acquire] and passed to [release], so that a lock is released by the thread that
+/// took it, even if the core is running another thread by then.
+#[derive(Debug, Clone, Copy)]
+pub struct Held {
+    context: usize,
+}
+
+/// A lock that lockdep has seen.
+#[derive(Clone, Copy)]
+struct Class {
+    lock: usize,
+
+    /// Where the lock was first taken, which names it in reports.
+    first_site: Site,
+
+    /// Where the lock was first taken in an interrupt handler.
+    irq_site: Option<Site>,
+
+    /// Where IRQs were first unmasked while the lock was held, outside of an interrupt handler.
+    irqs_unmasked_site: Option<Site>,
+}
+
+#[derive(Clone, Copy)]
+struct HeldLock {
+    class: usize,
+    kind: LockKind,
+    site: Site,
+}
+
+#[derive(Clone, Copy)]
+struct HeldLocks {
+    locks: [Option<HeldLock>; MAX_HELD],
+    length: usize,
+}
+
+/// A violation that hasn't been printed yet.
+#[derive(Clone, Copy)]
+enum Violation {
+    /// [Violation::Cycle::taking] was taken while holding [Violation::Cycle::holding], but
+    /// [Violation::Cycle::previous] was taken after it before, the other way around.
+    Cycle {
+        taking: (usize, Site),
+        holding: (usize, Site),
+        previous: (usize, usize, Site),
+    },
+
+    /// The sleeping lock [Violation::SleepingInSpin::taking] was waited for while holding the
+    /// spin lock [Violation::SleepingInSpin::holding], which never lets the thread sleep.
+    SleepingInSpin {
+        taking: (usize, Site),
+        holding: (usize, Site),
+    },
+
+    /// A spin lock that an interrupt handler takes was held while IRQs were unmasked, which
+    /// deadlocks if the interrupt arrives then.
+    IrqUnsafe {
+        class: usize,
+        irq_site: Site,
+        irqs_unmasked_site: Site,
+    },
+
+    /// Lockdep ran out of room, so the rest of the locks aren't checked.
+    Exhausted(&'static str),
+}
+
+struct State {
+    classes: [Option<Class>; MAX_CLASSES],
+
+    /// A bit for every class that was taken while the class at that index was held.
+    after: [u128; MAX_CLASSES],
+
+    /// Where the class in the second index was first taken while holding the first.
+    edge_sites: [[Option<Site>; MAX_CLASSES]; MAX_CLASSES],
+
+    held: [HeldLocks; MAX_CONTEXTS],
+    violation: Option<Violation>,
+}
+
+/// The state, behind a lock of its own that lockdep doesn't check.
+struct Lockdep {
+    is_locked: AtomicBool,
+    state: UnsafeCell<State>,
+}
+
+static LOCKDEP: Lockdep = Lockdep {
+    is_locked: AtomicBool::new(false),
+    state: UnsafeCell::new(State {
+        classes: [None; MAX_CLASSES],
+        after: [0; MAX_CLASSES],
+        edge_sites: [[None; MAX_CLASSES]; MAX_CLASSES],
+        held: [HeldLocks {
+            locks: [None; MAX_HELD],
+            length: 0,
+        }; MAX_CONTEXTS],
+        violation: None,
+    }),
+};
+
+/// Set once a violation was found, after which nothing is checked.
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Checks that the lock at [lock] can be taken at [site] by the current thread, and records it
+/// as held. This is called before waiting for the lock, so that a deadlock is found before it
+/// happens.
+///
+/// [try_lock] is set for locks that were taken without waiting, which can't deadlock, so they
+/// aren't checked.
+pub fn acquire(lock: usize, kind: LockKind, site: Site, try_lock: bool) -> Held {
+    let context = context();
+    if DISABLED.load(Ordering::Relaxed) {
+        return Held { context };
+    }
+
+    let in_interrupt = percpu::in_interrupt();
+
+    let recursion = LOCKDEP.with(|state| {
+        let Some(class) = state.class_of(lock, site) else {
+            state.violate(Violation::Exhausted("lock classes"));
+            return None;
+        };
+
+        let held = state.held[context];
+        let holding = held.locks[..held.length].iter().flatten();
+
+        if !try_lock {
+            // A reader may take a lock that it already reads from, as long as nothing writes.
+            for it in holding.clone().filter(|it| it.class == class) {
+                if !(kind == LockKind::Read && it.kind == LockKind::Read) {
+                    return Some(it.site);
+                }
+            }
+
+            for it in holding.clone().filter(|it| it.class != class) {
+                state.check_order(it, class, site);
+            }
+
+            if kind != LockKind::Spin {
+                if let Some(spin) = holding.clone().find(|it| it.kind == LockKind::Spin) {
+                    state.violate(Violation::SleepingInSpin {
+                        taking: (class, site),
+                        holding: (spin.class, spin.site),
+                    });
+                }
+            }
+        }
+
+        if kind == LockKind::Spin && in_interrupt {
+            if let Some(entry) = state.classes[class].as_mut() {
+                entry.irq_site.get_or_insert(site);
+            }
+
+            state.check_irq_safety(class);
+        }
+
+        if state.held[context].length == MAX_HELD {
+            state.violate(Violation::Exhausted("held locks"));
+            return None;
+        }
+
+        let held = &mut state.held[context];
+        held.locks[held.length] = Some(HeldLock { class, kind, site });
+        held.length += 1;
+        None
+    });
+
+    // Taking a lock twice never finishes, so there is no point in waiting to report it.
+    if let Some(first_site) = recursion {
+        DISABLED.store(true, Ordering::Relaxed);
+        panic!(
+            "lockdep: the lock at {:#x} is taken again at {}, but is already held since {}",
+            lock, site, first_site
+        );
+    }
+
+    Held { context }
+}
+
+/// Records that the lock at [lock], which [held] came from, is no longer held. Reports the first
+/// violation once the thread holds no more locks.
+pub fn release(lock: usize, held: Held) {
+    if DISABLED.load(Ordering::Relaxed) && !has_violation() {
+        return;
+    }
+
+    let violation = LOCKDEP.with(|state| {
+        let class = state.find_class(lock);
+        let locks = &mut state.held[held.context];
+
+        // Locks aren't always released in the order that they were taken in.
+        let index = (0..locks.length)
+            .rev()
+            .find(|it| locks.locks[*it].is_some_and(|it| Some(it.class) == class));
+
+        if let Some(index) = index {
+            locks.locks.copy_within(index + 1..locks.length, index);
+            locks.length -= 1;
+            locks.locks[locks.length] = None;
+        }
+
+        if locks.length == 0 {
+            state.violation.take().map(|it| (it, state.classes))
+        } else {
+            None
+        }
+    });
+
+    if let Some((violation, classes)) = violation {
+        report(violation, &classes);
+    }
+}
+
+/// Records that IRQs are unmasked at [site], which is a violation if the current thread holds a
+/// spin lock that an interrupt handler takes. This is called before they are unmasked.
+pub fn irqs_unmasked(site: Site) {
+    if DISABLED.load(Ordering::Relaxed) || percpu::in_interrupt() {
+        return;
+    }
+
+    let context = context();
+    LOCKDEP.with(|state| {
+        let held = state.held[context];
+        for it in held.locks[..held.length].iter().flatten() {
+            if it.kind != LockKind::Spin {
+                continue;
+            }
+
+            if let Some(entry) = state.classes[it.class].as_mut() {
+                entry.irqs_unmasked_site.get_or_insert(site);
+            }
+
+            state.check_irq_safety(it.class);
+        }
+    });
+}
+
+/// Whether a violation was found that hasn't been printed yet.
+fn has_violation() -> bool {
+    LOCKDEP.with(|state| state.violation.is_some())
+}
+
+/// The held locks of the thread that is running, or of the core if it isn't scheduling threads
+/// yet.
+fn context() -> usize {
+    let cpu = percpu::this();
+    if cpu.is_online() {
+        cpu.current()
+    } else {
+        MAX_THREADS + cpu.core
+    }
+}
+
+fn report(violation: Violation, classes: &Classes) {
+    println!("[angeldust::lockdep] possible deadlock, turning lockdep off");
+
+    match violation {
+        Violation::Cycle {
+            taking,
+            holding,
+            previous,
+        } => {
+            println!(
+                "[angeldust::lockdep] {} is taken at {}",
+                ClassName(classes[taking.0]),
+                taking.1
+            );
+            println!(
+                "[angeldust::lockdep]   while holding {}, which was taken at {}",
+                ClassName(classes[holding.0]),
+                holding.1
+            );
+            println!(
+                "[angeldust::lockdep]   but {} was taken while holding {} before, at {}",
+                ClassName(classes[previous.1]),
+                ClassName(classes[previous.0]),
+                previous.2
+            );
+        }
+
+        Violation::SleepingInSpin { taking, holding } => {
+            println!(
+                "[angeldust::lockdep] {}, which sleeps, is taken at {}",
+                ClassName(classes[taking.0]),
+                taking.1
+            );
+            println!(
+                "[angeldust::lockdep]   while holding the spin lock {}, which was taken at {}",
+                ClassName(classes[holding.0]),
+                holding.1
+            );
+        }
+
+        Violation::IrqUnsafe {
+            class,
+            irq_site,
+            irqs_unmasked_site,
+        } => {
+            println!(
+                "[angeldust::lockdep] {} is taken in an interrupt handler at {}",
+                ClassName(classes[class]),
+                irq_site
+            );
+            println!(
+                "[angeldust::lockdep]   but IRQs were unmasked while it was held, at {}",
+                irqs_unmasked_site
+            );
+        }
+
+        Violation::Exhausted(what) => {
+            println!("[angeldust::lockdep] ran out of room for {}", what);
+        }
+    }
+}
+
+/// The classes, copied out of the state so that they can be printed without holding it.
+type Classes = [Option<Class>; MAX_CLASSES];
+
+/// Names a class by its address and where it was first taken.
+struct ClassName(Option<Class>);
+
+impl Display for ClassName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(class) => write!(
+                f,
+                "the lock at {:#x} (first taken at {})",
+                class.lock, class.first_site
+            ),
+            None => write!(f, "an unknown lock"),
+        }
+    }
+}
+
+impl State {
+    /// The class of [lock], which is created if this is the first time that it is taken.
+    fn class_of(&mut self, lock: usize, site: Site) -> Option<usize> {
+        if let Some(class) = self.find_class(lock) {
+            return Some(class);
+        }
+
+        let class = self.classes.iter().position(Option::is_none)?;
+        self.classes[class] = Some(Class {
+            lock,
+            first_site: site,
+            irq_site: None,
+            irqs_unmasked_site: None,
+        });
+
+        Some(class)
+    }
+
+    fn find_class(&self, lock: usize) -> Option<usize> {
+        self.classes
+            .iter()
+            .position(|it| it.is_some_and(|it| it.lock == lock))
+    }
+
+    /// Records that [class] is taken at [site] while [holding] is held, which is a violation if
+    /// [holding] was ever taken while [class] was held, directly or through other classes.
+    fn check_order(&mut self, holding: &HeldLock, class: usize, site: Site) {
+        if self.after[holding.class] & (1 << class) != 0 {
+            return;
+        }
+
+        if let Some(previous) = self.path(class, holding.class) {
+            self.violate(Violation::Cycle {
+                taking: (class, site),
+                holding: (holding.class, holding.site),
+                previous,
+            });
+        }
+
+        self.after[holding.class] |= 1 << class;
+        self.edge_sites[holding.class][class] = Some(site);
+    }
+
+    /// Finds out whether [to] was taken after [from], directly or through other classes, and
+    /// returns the last step of the way there: the classes and where the second was taken.
+    fn path(&self, from: usize, to: usize) -> Option<(usize, usize, Site)> {
+        let mut visited: u128 = 1 << from;
+        let mut queue = [0; MAX_CLASSES];
+        let (mut head, mut tail) = (0, 1);
+        queue[0] = from;
+
+        while head < tail {
+            let class = queue[head];
+            head += 1;
+
+            if self.after[class] & (1 << to) != 0 {
+                return Some((class, to, self.edge_sites[class][to]?));
+            }
+
+            for next in 0..MAX_CLASSES {
+                if self.after[class] & (1 << next) != 0 && visited & (1 << next) == 0 {
+                    visited |= 1 << next;
+                    queue[tail] = next;
+                    tail += 1;
+                }
+            }
+        }
+
+        None
+    }
+
+    /// A violation if [class] is taken in an interrupt handler, and was held while IRQs were
+    /// unmasked.
+    fn check_irq_safety(&mut self, class: usize) {
+        let Some(entry) = self.classes[class] else {
+            return;
+        };
+
+        if let (Some(irq_site), Some(irqs_unmasked_site)) =
+            (entry.irq_site, entry.irqs_unmasked_site)
+        {
+            self.violate(Violation::IrqUnsafe {
+                class,
+                irq_site,
+                irqs_unmasked_site,
+            });
+        }
+    }
+
+    /// Keeps the first violation to be reported, and stops checking anything else.
+    fn violate(&mut self, violation: Violation) {
+        if !DISABLED.swap(true, Ordering::Relaxed) {
+            self.violation = Some(violation);
+        }
+    }
+}
+
+impl Lockdep {
+    /// Runs [f] with the state locked and IRQs masked.
+    fn with<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        daif::without_irqs(|| {
+            while self.is_locked.swap(true, Ordering::Acquire) {
+                hint::spin_loop();
+            }
+
+            // Safety: The state is only used while [Lockdep::is_locked] is held.
+            let result = f(unsafe { &mut *self.state.get() });
+
+            self.is_locked.store(false, Ordering::Release);
+            result
+        })
+    }
+}
+
+/// # Safety
+/// - The state is only used while [Lockdep::is_locked] is held.
+unsafe impl Sync for Lockdep {}
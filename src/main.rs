@@ -6,28 +6,56 @@ global_asm!(include_str!("boot/boot.S"));
 mod arch;
 mod console;
 mod cpu;
+mod fdt;
+mod fs;
 mod io;
+#[cfg(feature = "lockdep")]
+mod lockdep;
+mod memory;
 mod mutex;
+mod panic;
+mod params;
+mod process;
+mod scheduler;
+mod shell;
+mod sync;
+mod timer;
 
 use crate::{
-    arch::aarch64::currentel::CurrentELRegister,
-    cpu::{raspberry_pi, RaspberryPi},
-    io::{framebuffer, mailbox},
-};
-use core::{
-    arch::{asm, global_asm},
-    panic::PanicInfo,
+    arch::aarch64::{currentel::CurrentELRegister, daif, exception},
+    cpu::{ipi, percpu, raspberry_pi, smp, system_info, RaspberryPi},
+    fs::vfs::VfsError,
+    io::{
+        clocks, emmc, framebuffer, interrupts,
+        mailbox::{self, TraceMode},
+        partition,
+        power::watchdog,
+        rng, thermal,
+    },
+    process::ElfError,
+    scheduler::Priority,
 };
+use core::{arch::global_asm, time::Duration};
 
 #[no_mangle]
-pub extern "C" fn init() -> ! {
+pub extern "C" fn init(device_tree_address: usize) -> ! {
+    // The exception handler and the scheduler find the current core's data through this.
+    percpu::initialize();
+
+    // The device tree tells us exactly where the peripherals are, so it has to be parsed before
+    // anything touches them. We can't print anything yet, so the result is reported later.
+    let device_tree_result = fdt::initialize(device_tree_address);
+
     // We need to use this to be able to detect the board type.
-    // This allows us to infer the peripheral base address.
+    // This allows us to infer the peripheral base address if there is no device tree.
     raspberry_pi::initialize();
 
     // We must do this as early as possible in order to get information printed out to the Uart.
     console::initialize();
 
+    // Any exception taken from here on will be reported, rather than jumping to a garbage address.
+    exception::initialize();
+
     let board_type = RaspberryPi::instance().board_type();
     if !RaspberryPi::instance().is_supported() {
         panic!("the board type {:?} is not supported", board_type);
@@ -39,6 +67,25 @@ pub extern "C" fn init() -> ! {
         board_type
     );
 
+    match device_tree_result {
+        Ok(()) => fdt::report(&fdt::instance().expect("fdt::initialize() succeeded")),
+        Err(error) => println!(
+            "[angeldust::init] no usable device tree at {:#010x}: {:?}",
+            device_tree_address, error
+        ),
+    }
+
+    println!(
+        "[angeldust::init] peripherals at {:p}, local peripherals at {:p} (from the {})",
+        RaspberryPi::instance().peripheral_base_address(),
+        RaspberryPi::instance().local_peripheral_base_address(),
+        if RaspberryPi::instance().addresses_from_device_tree() {
+            "device tree"
+        } else {
+            "board type"
+        }
+    );
+
     // If we are not on Exception Level 1, we need to bail out, something has gone wrong.
     let el_register = CurrentELRegister::read();
     println!(
@@ -56,21 +103,195 @@ pub extern "C" fn init() -> ! {
     // After we verify that this board is supported, initialize the global mailbox.
     mailbox::initialize();
 
+    // Debug builds decode every mailbox transaction, which makes firmware rejections much easier to
+    // diagnose. The command line isn't known yet, so `mailbox.trace` only takes over once it is
+    // parsed, and the shell's `trace` command toggles it after that.
+    mailbox::set_tracing(cfg!(debug_assertions));
+
+    // Collect everything that the firmware can tell us about the board in one go, and make it
+    // available to every other subsystem.
+    system_info::initialize().expect("system_info::initialize() failed");
+    system_info::instance().print_banner();
+
+    // The board type was only inferred from the CPU, the revision code tells us exactly which
+    // board this is.
+    raspberry_pi::detect_revision();
+
+    // The device tree's bootargs are preferred, as QEMU only passes `-append` through them.
+    let system_info = system_info::instance();
+    let command_line = fdt::instance()
+        .and_then(|it| it.bootargs())
+        .filter(|it| !it.is_empty())
+        .unwrap_or(system_info.command_line());
+
+    params::initialize(command_line);
+    panic::initialize();
+    mailbox::set_tracing(params::instance().mailbox.trace != TraceMode::Off);
+
+    // Now that we know where all of the RAM is, hand it to the frame allocator, keeping whatever
+    // the kernel and the firmware are already using.
+    memory::initialize();
+    memory::report();
+
+    // `boot.S` turned the MMU on with tables that only guess where the RAM ends, and that also
+    // map the lower half. Map exactly the RAM as normal memory in the upper half instead, with
+    // the kernel's code read-only and nothing else executable, and leave the lower half to the
+    // address spaces of user programs.
+    memory::enable_mmu().expect("memory::enable_mmu() failed");
+    println!(
+        "[angeldust::init] kernel mapped at {:#x}",
+        memory::paging::KERNEL_BASE
+    );
+
+    if !RaspberryPi::instance().is_supported() {
+        panic!(
+            "the board {:?} does not match the board type {:?} inferred from the cpu",
+            RaspberryPi::instance().revision(),
+            board_type
+        );
+    }
+
+    // The firmware leaves the ARM cores running at a low clock rate, so we raise it before doing
+    // anything else with the mailbox.
+    clocks::initialize();
+
+    // The console's UART had to be initialized before the mailbox was available, which works as
+    // the firmware leaves it powered on. Now that its power domains can be managed, move it to
+    // the UART that was asked for.
+    console::select(params::instance().console.uart);
+
+    // Start the timer interrupt, which drives any periodic work (like the thermal monitor).
+    interrupts::initialize();
+    timer::initialize();
+    daif::unmask_irqs();
+
+    // Seed the entropy pool now that the timer can time out reads from the hardware generator.
+    rng::initialize();
+    println!("[angeldust::init] boot id: {:016x}", rng::next_u64());
+
+    // From here on, init() is just one of the kernel's threads, which take turns on every tick.
+    scheduler::initialize();
+
+    // The sensors are sampled from a thread, as reading them takes a few mailbox round trips.
+    thermal::initialize();
+
+    // The cores interrupt each other, e.g. to run a thread that was just woken.
+    ipi::initialize();
+
+    // The other cores run threads too, each with its own run queue and idle thread.
+    smp::start_secondary_cores();
+
+    // Anything that waits for another core, like a TLB shootdown, relies on it answering IPIs.
+    ipi::report();
+
+    // The watchdog resets the board if the threads stop being scheduled.
+    if let Err(error) = watchdog::initialize() {
+        println!(
+            "[angeldust::init] failed to start the watchdog: {:?}",
+            error
+        );
+    }
+
+    scheduler::spawn("watchdog", Priority::High, pet_watchdog).expect("scheduler::spawn() failed");
+
+    // The entropy pool is reseeded from a thread, as the hardware generator can be slow.
+    scheduler::spawn("reseed", Priority::Low, rng::reseed_periodically)
+        .expect("scheduler::spawn() failed");
+
+    // The initrd is the root filesystem, anything else is mounted on top of it.
+    fs::mount_initrd();
+
+    // The kernel doesn't need the SD card to boot, so it is fine if there isn't one.
+    match emmc::initialize() {
+        Ok(()) => {
+            partition::report(&emmc::instance());
+            fs::initialize(emmc::instance());
+        }
+        Err(error) => println!("[angeldust::init] no usable sd card: {:?}", error),
+    }
+
+    // Changes to the FAT volumes are written back every few seconds, and before rebooting.
+    scheduler::spawn("sync", Priority::Low, fs::sync_periodically)
+        .expect("scheduler::spawn() failed");
+
     // Once the mailbox is ready, we can initialize the framebuffer.
     framebuffer::initialize();
 
+    let framebuffer_params = params::instance().framebuffer;
     framebuffer::instance()
-        .fill_area(50, 50, 1280 - 50, 720 - 50, 0xFF_FF0000)
+        .fill_area(
+            50,
+            50,
+            framebuffer_params.width - 50,
+            framebuffer_params.height - 50,
+            0xFF_FF0000,
+        )
         .expect("framebuffer::fill_area() failed");
 
-    panic!("reached end of init()");
-}
+    // Periodic mailbox users (like the thermal monitor) would flood the console from here on.
+    if params::instance().mailbox.trace == TraceMode::Boot {
+        mailbox::set_tracing(false);
+    }
+
+    // The first program runs on a thread of its own until it exits, and whatever it forks runs
+    // alongside.
+    let init =
+        scheduler::spawn("init", Priority::Normal, run_init).expect("scheduler::spawn() failed");
+    println!(
+        "[angeldust::init] waiting for thread {} to run {}",
+        init.id().0,
+        process::INIT_PATH
+    );
+    init.join();
 
-#[panic_handler]
-fn panic(info: &PanicInfo) -> ! {
-    println!("\n{}", info);
+    // The shell reads from the console too, so it only starts once /init is done with it.
+    scheduler::spawn("shell", Priority::Normal, shell::run).expect("scheduler::spawn() failed");
 
+    println!("[angeldust::init] reached end of init(), exiting");
+    scheduler::exit();
+}
+
+/// Pets the watchdog for as long as the thread keeps being scheduled.
+fn pet_watchdog() {
+    let client = watchdog::register("scheduler").expect("watchdog::register() failed");
     loop {
-        unsafe { asm!("wfe") }
+        client.pet();
+        scheduler::sleep(Duration::from_millis(500));
+    }
+}
+
+/// Loads and runs the first program, see [process::INIT_PATH].
+fn run_init() {
+    match process::load(process::INIT_PATH, &[process::INIT_PATH], &[]) {
+        Ok(image) => {
+            println!(
+                "[angeldust::init] running {}, entry at {:#x}, stack at {:#x}, asid {}",
+                process::INIT_PATH,
+                image.entry,
+                image.stack_pointer,
+                image.address_space.asid()
+            );
+
+            match process::run(image) {
+                Ok(status) => println!(
+                    "[angeldust::init] {} exited with status {}",
+                    process::INIT_PATH,
+                    status
+                ),
+                Err(errno) => println!(
+                    "[angeldust::init] failed to run {}: {:?}",
+                    process::INIT_PATH,
+                    errno
+                ),
+            }
+        }
+        Err(ElfError::Io(VfsError::NotFound)) => {
+            println!("[angeldust::init] no {} to run", process::INIT_PATH)
+        }
+        Err(error) => println!(
+            "[angeldust::init] failed to load {}: {:?}",
+            process::INIT_PATH,
+            error
+        ),
     }
 }
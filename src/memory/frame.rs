@@ -0,0 +1,280 @@
+use super::paging::virtual_address;
+
+/// The size of a physical frame, which is also the size of the smallest page.
+pub const FRAME_SIZE: u64 = 4096;
+
+/// The most physical memory that is tracked, anything above it is never handed out.
+/// This covers all of the RAM of the 4 GiB boards.
+pub const MAX_MEMORY: u64 = 4 * 1024 * 1024 * 1024;
+
+const MAX_FRAMES: usize = (MAX_MEMORY / FRAME_SIZE) as usize;
+
+/// The most reservations that are remembered for [FrameAllocator::reservations].
+const MAX_RESERVATIONS: usize = 16;
+
+/// Represents an error that can occur while allocating or reserving frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+    /// Occurs when every frame is in use.
+    OutOfMemory,
+
+    /// Occurs when an address isn't aligned to [FRAME_SIZE].
+    Misaligned(u64),
+
+    /// Occurs when a region goes past [MAX_MEMORY].
+    OutOfRange { address: u64, size: u64 },
+
+    /// Occurs when a frame that isn't allocated is freed or shared.
+    NotAllocated(u64),
+
+    /// Occurs when a frame is shared by more owners than can be counted.
+    TooManyShares(u64),
+
+    /// Occurs when a region is reserved after one of its frames was already allocated, so its
+    /// contents may have been overwritten.
+    AlreadyAllocated(u64),
+}
+
+/// A single physical frame of [FRAME_SIZE] bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame(u64);
+
+/// A region that was reserved by [FrameAllocator::reserve], which is never handed out.
+#[derive(Debug, Clone, Copy)]
+pub struct Reservation {
+    pub name: &'static str,
+    pub address: u64,
+    pub size: u64,
+}
+
+/// Hands out physical frames, tracking which ones are in use in a bitmap.
+///
+/// Every frame starts out in use, and only the RAM that is added with [FrameAllocator::add]
+/// becomes free, so holes in the address space are never handed out.
+pub struct FrameAllocator {
+    /// A set bit means that the frame is free. It starts out empty, which also keeps the
+    /// allocator in `.bss` rather than in the kernel's image.
+    available: [u64; MAX_FRAMES / 64],
+
+    /// A set bit means that the frame is RAM.
+    ram: [u64; MAX_FRAMES / 64],
+
+    /// How many owners each frame that is in use has besides the first, e.g. address spaces
+    /// that share it until one of them writes to it.
+    shares: [u8; MAX_FRAMES],
+
+    total: usize,
+    free: usize,
+
+    /// Where to start looking for a free frame.
+    next: usize,
+
+    reservations: [Option<Reservation>; MAX_RESERVATIONS],
+}
+
+impl FrameAllocator {
+    pub const fn new() -> FrameAllocator {
+        FrameAllocator {
+            available: [0; MAX_FRAMES / 64],
+            ram: [0; MAX_FRAMES / 64],
+            shares: [0; MAX_FRAMES],
+            total: 0,
+            free: 0,
+            next: 0,
+            reservations: [None; MAX_RESERVATIONS],
+        }
+    }
+
+    /// Adds the RAM at [address], making the frames that are entirely within it free.
+    /// Anything past [MAX_MEMORY] is ignored.
+    pub fn add(&mut self, address: u64, size: u64) {
+        let first = address.div_ceil(FRAME_SIZE);
+        let last = (address.saturating_add(size).min(MAX_MEMORY)) / FRAME_SIZE;
+
+        for index in first..last {
+            let index = index as usize;
+            if !self.is_set(&self.ram, index) {
+                self.ram[index / 64] |= 1 << (index % 64);
+                self.available[index / 64] |= 1 << (index % 64);
+                self.total += 1;
+                self.free += 1;
+            }
+        }
+    }
+
+    /// Marks every frame that overlaps the region at [address] as in use, so it is never handed
+    /// out. Regions may overlap each other.
+    ///
+    /// Fails with [FrameError::AlreadyAllocated] if any of the frames was already allocated,
+    /// rather than reserved, in which case the rest of them are still reserved.
+    pub fn reserve(
+        &mut self,
+        name: &'static str,
+        address: u64,
+        size: u64,
+    ) -> Result<(), FrameError> {
+        let end = address
+            .checked_add(size)
+            .filter(|it| *it <= MAX_MEMORY)
+            .ok_or(FrameError::OutOfRange { address, size })?;
+
+        let mut result = Ok(());
+        let first = address / FRAME_SIZE;
+        let last = end.div_ceil(FRAME_SIZE);
+        for index in first..last {
+            let index = index as usize;
+            if self.is_set(&self.available, index) {
+                self.available[index / 64] &= !(1 << (index % 64));
+                self.free -= 1;
+            } else if result.is_ok()
+                && self.is_set(&self.ram, index)
+                && !self.is_reserved(index as u64 * FRAME_SIZE, FRAME_SIZE)
+            {
+                result = Err(FrameError::AlreadyAllocated(index as u64 * FRAME_SIZE));
+            }
+        }
+
+        if let Some(slot) = self.reservations.iter_mut().find(|it| it.is_none()) {
+            *slot = Some(Reservation {
+                name,
+                address,
+                size,
+            });
+        }
+
+        result
+    }
+
+    /// Allocates a single frame, whose contents are undefined.
+    pub fn allocate(&mut self) -> Result<Frame, FrameError> {
+        let words = self.available.len();
+
+        for offset in 0..words {
+            let word = (self.next / 64 + offset) % words;
+            let bits = self.available[word];
+            if bits == 0 {
+                continue;
+            }
+
+            let index = word * 64 + bits.trailing_zeros() as usize;
+            self.available[word] &= !(1 << (index % 64));
+            self.free -= 1;
+            self.next = index + 1;
+
+            return Ok(Frame(index as u64 * FRAME_SIZE));
+        }
+
+        Err(FrameError::OutOfMemory)
+    }
+
+    /// Returns [frame] to the allocator, it must have come from [FrameAllocator::allocate]. A
+    /// frame that was shared is only freed once every owner has freed it.
+    pub fn free(&mut self, frame: Frame) -> Result<(), FrameError> {
+        let index = self.allocated_index(frame)?;
+        if self.shares[index] > 0 {
+            self.shares[index] -= 1;
+            return Ok(());
+        }
+
+        self.available[index / 64] |= 1 << (index % 64);
+        self.free += 1;
+        self.next = self.next.min(index);
+        Ok(())
+    }
+
+    /// Adds an owner to [frame], which then has to be freed once more before it is returned to
+    /// the allocator.
+    pub fn share(&mut self, frame: Frame) -> Result<(), FrameError> {
+        let index = self.allocated_index(frame)?;
+        self.shares[index] = self.shares[index]
+            .checked_add(1)
+            .ok_or(FrameError::TooManyShares(frame.0))?;
+
+        Ok(())
+    }
+
+    /// Whether [frame] has more than one owner.
+    pub fn is_shared(&self, frame: Frame) -> bool {
+        self.shares
+            .get((frame.0 / FRAME_SIZE) as usize)
+            .is_some_and(|it| *it > 0)
+    }
+
+    /// Whether every frame that overlaps the [size] bytes at [address] was reserved by
+    /// [FrameAllocator::reserve].
+    pub fn is_reserved(&self, address: u64, size: u64) -> bool {
+        let first = address / FRAME_SIZE;
+        let last = address.saturating_add(size).div_ceil(FRAME_SIZE);
+
+        (first..last).all(|index| {
+            let (start, end) = (index * FRAME_SIZE, (index + 1) * FRAME_SIZE);
+            self.reservations()
+                .any(|it| it.address < end && it.address.saturating_add(it.size) > start)
+        })
+    }
+
+    /// Whether every frame that overlaps the [size] bytes at [address] is RAM.
+    pub fn is_ram(&self, address: u64, size: u64) -> bool {
+        let first = address / FRAME_SIZE;
+        let last = address.saturating_add(size).div_ceil(FRAME_SIZE);
+
+        (first..last).all(|index| self.is_set(&self.ram, index as usize))
+    }
+
+    /// The amount of frames of RAM.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The amount of frames that can still be allocated.
+    pub fn free_frames(&self) -> usize {
+        self.free
+    }
+
+    pub fn reservations(&self) -> impl Iterator<Item = &Reservation> {
+        self.reservations.iter().flatten()
+    }
+
+    /// The index of [frame], which must be allocated.
+    fn allocated_index(&self, frame: Frame) -> Result<usize, FrameError> {
+        let index = (frame.0 / FRAME_SIZE) as usize;
+        if !self.is_set(&self.ram, index) || self.is_set(&self.available, index) {
+            return Err(FrameError::NotAllocated(frame.0));
+        }
+
+        Ok(index)
+    }
+
+    fn is_set(&self, bitmap: &[u64], index: usize) -> bool {
+        bitmap
+            .get(index / 64)
+            .is_some_and(|it| it & (1 << (index % 64)) != 0)
+    }
+}
+
+impl Frame {
+    /// Creates a [Frame] for the frame at [address].
+    pub fn from_address(address: u64) -> Result<Frame, FrameError> {
+        if !address.is_multiple_of(FRAME_SIZE) {
+            return Err(FrameError::Misaligned(address));
+        }
+
+        Ok(Frame(address))
+    }
+
+    /// The physical address of the frame.
+    pub const fn address(&self) -> u64 {
+        self.0
+    }
+
+    /// A pointer to the frame's contents, which are mapped in the upper half.
+    pub const fn as_ptr(&self) -> *mut u8 {
+        virtual_address(self.0) as *mut u8
+    }
+
+    /// Fills the frame with zeroes.
+    pub fn zero(&self) {
+        // Safety: The frame is owned by whoever allocated it, and is mapped in the upper half.
+        unsafe { core::ptr::write_bytes(self.as_ptr(), 0, FRAME_SIZE as usize) }
+    }
+}
@@ -0,0 +1,125 @@
+pub mod frame;
+pub mod paging;
+
+pub use frame::{Frame, FrameAllocator, FrameError, FRAME_SIZE};
+pub use paging::{
+    enable_mmu, physical_address, virtual_address, AddressSpace, PageFlags, PagingError,
+};
+
+use crate::{cpu::system_info, fdt, mutex::Mutex, println};
+
+extern "C" {
+    /// The end of the kernel's image, including its `.bss`, from the linker script. This is where
+    /// it is linked, in the upper half.
+    static _end: u8;
+}
+
+static FRAMES: Mutex<FrameAllocator> = Mutex::new(FrameAllocator::new());
+
+/// Adds the RAM described by the device tree to the frame allocator, or the ARM memory reported
+/// by the firmware if there is no device tree, and reserves everything that the kernel is already
+/// using.
+///
+/// Everything below the end of the kernel is reserved, which covers the spin tables, the boot
+/// stack (which grows down from the kernel's load address) and the kernel itself. So is the
+/// initrd, before anything (like the kernel's page tables) is allocated over it.
+/// [system_info::initialize] must be called before this.
+pub fn initialize() {
+    let mut frames = FRAMES.lock();
+    let device_tree = fdt::instance();
+
+    match device_tree {
+        Some(device_tree) if device_tree.memory().next().is_some() => {
+            for region in device_tree.memory() {
+                frames.add(region.address, region.size);
+            }
+        }
+
+        _ => {
+            let arm_memory = system_info::instance().arm_memory;
+            frames.add(arm_memory.base_address as u64, arm_memory.size as u64);
+        }
+    }
+
+    let kernel_end =
+        physical_address(&raw const _end as u64).expect("the kernel is linked in the upper half");
+    reserve_or_report(&mut frames, "kernel", 0, kernel_end);
+
+    if let Some(device_tree) = device_tree {
+        reserve_or_report(
+            &mut frames,
+            "device tree",
+            device_tree.address() as u64,
+            device_tree.header().total_size as u64,
+        );
+
+        for region in device_tree.reserved_memory() {
+            reserve_or_report(&mut frames, "firmware", region.address, region.size);
+        }
+
+        // The initrd is only checked once it is mounted, see fs::initrd::find.
+        if let Some((start, end)) = device_tree.initrd().filter(|(start, end)| end > start) {
+            reserve_or_report(&mut frames, "initrd", start, end - start);
+        }
+    }
+}
+
+/// Whether every frame that overlaps the [size] bytes at [address] was reserved by [initialize].
+pub fn is_reserved(address: u64, size: u64) -> bool {
+    FRAMES.lock().is_reserved(address, size)
+}
+
+/// Allocates a single frame of physical memory, whose contents are undefined.
+pub fn allocate() -> Result<Frame, FrameError> {
+    FRAMES.lock().allocate()
+}
+
+/// Whether all of the [size] bytes at [address] are RAM.
+pub fn is_ram(address: u64, size: u64) -> bool {
+    FRAMES.lock().is_ram(address, size)
+}
+
+/// Returns [frame], which came from [allocate], to the frame allocator, or drops one of its
+/// owners if it is shared.
+pub fn free(frame: Frame) -> Result<(), FrameError> {
+    FRAMES.lock().free(frame)
+}
+
+/// Adds an owner to [frame], which is then only returned to the frame allocator once [free] was
+/// called for each of them.
+pub fn share(frame: Frame) -> Result<(), FrameError> {
+    FRAMES.lock().share(frame)
+}
+
+/// Whether [frame] has been [share]d and is still owned by more than one address space.
+pub fn is_shared(frame: Frame) -> bool {
+    FRAMES.lock().is_shared(frame)
+}
+
+/// Prints how much memory there is, and what is reserved.
+pub fn report() {
+    let frames = FRAMES.lock();
+    for reservation in frames.reservations() {
+        println!(
+            "[angeldust::memory] reserved: {:#010x} - {:#010x} ({})",
+            reservation.address,
+            reservation.address + reservation.size,
+            reservation.name
+        );
+    }
+
+    println!(
+        "[angeldust::memory] {} MiB of ram, {} MiB free",
+        frames.total() as u64 * FRAME_SIZE / (1024 * 1024),
+        frames.free_frames() as u64 * FRAME_SIZE / (1024 * 1024)
+    );
+}
+
+fn reserve_or_report(frames: &mut FrameAllocator, name: &'static str, address: u64, size: u64) {
+    if let Err(error) = frames.reserve(name, address, size) {
+        println!(
+            "[angeldust::memory] failed to reserve the {}: {:?}",
+            name, error
+        );
+    }
+}
@@ -0,0 +1,730 @@
+use super::{Frame, FrameError, FRAME_SIZE};
+use crate::{
+    arch::aarch64::{daif, mmu},
+    cpu::{
+        ipi,
+        percpu::{self, MAX_CORES},
+    },
+    mutex::Mutex,
+    println,
+    sync::Once,
+};
+use bitflags::bitflags;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// The amount of bits of a virtual address that are translated, which gives each half of the
+/// address space 512 GiB, with three levels of page tables.
+pub const VIRTUAL_ADDRESS_BITS: u32 = 39;
+
+/// Where the upper half of the address space starts, which only the kernel can use. All of the
+/// physical memory below [PHYSICAL_END] is mapped from here, which is also where the kernel is
+/// linked (see `linker.ld`).
+pub const KERNEL_BASE: u64 = !0 << VIRTUAL_ADDRESS_BITS;
+
+/// The physical memory that the kernel maps into the upper half, which covers all of the RAM that
+/// the frame allocator hands out, and the peripherals.
+pub const PHYSICAL_END: u64 = 1 << 32;
+
+/// The first address of user space. Nothing is mapped below it, so that null pointers fault.
+pub const USER_START: u64 = 0x1_0000;
+
+/// The first address past the end of user space, which is all of the lower half.
+pub const USER_END: u64 = 1 << VIRTUAL_ADDRESS_BITS;
+
+/// The amount of ASIDs, which tag the translations of each address space in the TLB so that they
+/// don't have to be forgotten when switching between them. Every core supports 8-bit ASIDs.
+pub const MAX_ASIDS: usize = 256;
+
+/// The ASID of the kernel's empty lower half, which no [AddressSpace] uses.
+const KERNEL_ASID: u16 = 0;
+
+extern "C" {
+    // The page aligned boundaries of the kernel's sections, from `linker.ld`, in the upper half.
+    static _text_start: u8;
+    static _text_end: u8;
+    static _rodata_end: u8;
+    static _end: u8;
+
+    /// The page under the stack that the kernel boots on.
+    static _boot_stack_guard: u8;
+}
+
+/// The size of the blocks that the kernel's map of physical memory is made of, other than where
+/// the kernel's image is, which is mapped in pages.
+const BLOCK_SIZE: u64 = 2 * 1024 * 1024;
+
+/// The amount of first level entries that the kernel's map of physical memory takes up, each of
+/// which covers 1 GiB.
+const KERNEL_ENTRIES: usize = (PHYSICAL_END >> 30) as usize;
+
+/// The amount of descriptors in a page table, which fills a frame.
+const ENTRIES: usize = FRAME_SIZE as usize / 8;
+
+// https://developer.arm.com/documentation/101811/0103/Translation-table-format
+const VALID: u64 = 1 << 0;
+const TABLE: u64 = 1 << 1;
+const BLOCK: u64 = 0 << 1;
+const PAGE: u64 = 1 << 1;
+const ATTRIBUTE_INDEX_SHIFT: u32 = 2;
+const ACCESS_USER: u64 = 1 << 6;
+const READ_ONLY: u64 = 1 << 7;
+const INNER_SHAREABLE: u64 = 0b11 << 8;
+const ACCESSED: u64 = 1 << 10;
+const NOT_GLOBAL: u64 = 1 << 11;
+const PRIVILEGED_EXECUTE_NEVER: u64 = 1 << 53;
+const USER_EXECUTE_NEVER: u64 = 1 << 54;
+
+/// One of the bits that the MMU ignores, which marks a page as [PageFlags::CopyOnWrite].
+const COPY_ON_WRITE: u64 = 1 << 55;
+const ADDRESS_MASK: u64 = 0x0000_FFFF_FFFF_F000;
+
+/// The indexes into `MAIR_EL1` of the memory types that pages can have.
+const NORMAL_MEMORY: u64 = 0;
+const DEVICE_MEMORY: u64 = 1;
+
+bitflags! {
+    /// What a page may be used for. Every mapped page can be read.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct PageFlags: u32 {
+        const Write = 1 << 0;
+        const Execute = 1 << 1;
+
+        /// The page can be used from EL0, rather than only by the kernel.
+        const User = 1 << 2;
+
+        /// The page is mapped as device memory, rather than normal cacheable memory.
+        const Device = 1 << 3;
+
+        /// The page may be written to, but its frame is shared with another address space, so it
+        /// is read-only until the first write copies it. See [AddressSpace::fork].
+        const CopyOnWrite = 1 << 4;
+    }
+}
+
+/// The parts of the kernel's image, which are mapped with different permissions, so that nothing
+/// is both writable and executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelSection {
+    /// `.text`, which is read-only and executable.
+    Text,
+
+    /// `.rodata`, which is read-only.
+    ReadOnlyData,
+
+    /// `.data` and `.bss`, which hold the stacks of the threads, and the memory below the kernel
+    /// where it boots, which holds the boot stack. These are writable, but never executable.
+    Data,
+
+    /// A page that is left unmapped under a stack, so that overflowing it faults rather than
+    /// corrupting whatever is below it. See [add_guard_page].
+    Guard,
+}
+
+/// Represents an error that can occur while changing an [AddressSpace].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PagingError {
+    /// Occurs when a virtual address isn't aligned to [FRAME_SIZE].
+    Misaligned(u64),
+
+    /// Occurs when a virtual address isn't in user space.
+    OutOfRange(u64),
+
+    /// Occurs when a page that is already mapped is mapped again.
+    AlreadyMapped(u64),
+
+    /// Occurs when a page that isn't mapped is used.
+    NotMapped(u64),
+
+    /// Occurs when there are no frames left for a page table.
+    Memory(FrameError),
+
+    /// Occurs when every ASID is used by another address space.
+    OutOfAsids,
+}
+
+/// The page tables that translate the lower half of the virtual address space, which is what a
+/// process sees, between [USER_START] and [USER_END].
+///
+/// The kernel lives in the upper half, which `TTBR1_EL1` translates the same way for every address
+/// space, so nothing of it is mapped here. Each address space has its own ASID, and its frames
+/// and tables are freed when it is dropped.
+pub struct AddressSpace {
+    root: Frame,
+    asid: u16,
+}
+
+/// The tables that the kernel uses on every core, once [enable_mmu] has built them.
+struct KernelTables {
+    /// The first level table of the upper half, which maps all of the physical memory.
+    kernel: Frame,
+
+    /// The first level table of the lower half while no process is running, which is empty.
+    empty: Frame,
+}
+
+static KERNEL_TABLES: Once<KernelTables> = Once::new();
+
+/// A set bit means that the ASID is used by an [AddressSpace], or is [KERNEL_ASID].
+static ASIDS: Mutex<[u64; MAX_ASIDS / 64]> = Mutex::new([1, 0, 0, 0]);
+
+/// A set bit means that the core switched to the ASID since it was claimed, so the TLB of the
+/// core may have its translations cached. Indexed by core.
+static CACHED_ASIDS: [[AtomicU64; MAX_ASIDS / 64]; MAX_CORES] =
+    [const { [const { AtomicU64::new(0) }; MAX_ASIDS / 64] }; MAX_CORES];
+
+/// Maps all of the physical memory below [PHYSICAL_END] into the upper half, switches to it from
+/// the tables that `boot.S` turned the MMU on with, and leaves the lower half empty.
+///
+/// RAM is mapped as normal memory, which is needed for atomics and caching, and everything else
+/// (the peripherals and the VideoCore's memory) is mapped as device memory. Nothing is both
+/// writable and executable: only the kernel's `.text` can be executed, and it and `.rodata` are
+/// read-only (see [KernelSection]). The frame allocator must know about all of the RAM before this
+/// is called.
+pub fn enable_mmu() -> Result<(), PagingError> {
+    let image_end =
+        physical_address(&raw const _end as u64).expect("the kernel is linked in the upper half");
+
+    let root = super::allocate().map_err(PagingError::Memory)?;
+    root.zero();
+
+    for (index, entry) in table_of(root.address())
+        .iter_mut()
+        .take(KERNEL_ENTRIES)
+        .enumerate()
+    {
+        let table = super::allocate().map_err(PagingError::Memory)?;
+        for (block_index, block) in table_of(table.address()).iter_mut().enumerate() {
+            let address = ((index * ENTRIES + block_index) as u64) * BLOCK_SIZE;
+
+            // The kernel's image needs permissions for each of its pages.
+            if address < image_end {
+                *block = kernel_page_table(address)?.address() | TABLE | VALID;
+                continue;
+            }
+
+            let flags = if super::is_ram(address, BLOCK_SIZE) {
+                PageFlags::Write
+            } else {
+                PageFlags::Write | PageFlags::Device
+            };
+
+            *block = (page_descriptor(address, flags) & !PAGE) | BLOCK;
+        }
+
+        *entry = table.address() | TABLE | VALID;
+    }
+
+    let empty = super::allocate().map_err(PagingError::Memory)?;
+    empty.zero();
+
+    KERNEL_TABLES.set(KernelTables {
+        kernel: root,
+        empty,
+    });
+
+    enable_mmu_on_core();
+    Ok(())
+}
+
+/// Switches the current core to the tables that [enable_mmu] built, e.g. when a secondary core
+/// starts on the boot tables.
+pub fn enable_mmu_on_core() {
+    let tables = KERNEL_TABLES
+        .get()
+        .expect("paging::enable_mmu() should be called before paging::enable_mmu_on_core()");
+
+    // Safety: The kernel's tables map the physical memory at the same addresses as the boot
+    // tables, which the kernel is running on.
+    unsafe {
+        mmu::enable(
+            tables.kernel.address(),
+            mmu::ttbr0(tables.empty.address(), KERNEL_ASID),
+            VIRTUAL_ADDRESS_BITS,
+        )
+    };
+}
+
+/// Unmaps the page of the kernel's image at [address], so that touching it faults, e.g. the page
+/// under a stack (see [KernelSection::Guard]). Its contents are never used again.
+pub fn add_guard_page(address: u64) -> Result<(), PagingError> {
+    if !address.is_multiple_of(FRAME_SIZE) {
+        return Err(PagingError::Misaligned(address));
+    }
+
+    let entry = kernel_page_entry(address).ok_or(PagingError::OutOfRange(address))?;
+    *entry = 0;
+    mmu::invalidate_kernel_page(address);
+    Ok(())
+}
+
+/// Which part of the kernel's image [address] is in, or [None] if it isn't in the image.
+pub fn kernel_section(address: u64) -> Option<KernelSection> {
+    let text_start = &raw const _text_start as u64;
+    let text_end = &raw const _text_end as u64;
+    let rodata_end = &raw const _rodata_end as u64;
+    let end = &raw const _end as u64;
+
+    if !(KERNEL_BASE..end).contains(&address) {
+        return None;
+    }
+
+    let entry = kernel_page_entry(address & !(FRAME_SIZE - 1))?;
+    Some(if *entry & VALID == 0 {
+        KernelSection::Guard
+    } else if (text_start..text_end).contains(&address) {
+        KernelSection::Text
+    } else if (text_end..rodata_end).contains(&address) {
+        KernelSection::ReadOnlyData
+    } else {
+        KernelSection::Data
+    })
+}
+
+/// Switches to the kernel's own, empty, lower half, e.g. after a process exits.
+pub fn switch_to_kernel() {
+    if let Some(tables) = KERNEL_TABLES.get() {
+        // Safety: Nothing is mapped in the lower half, and no address space uses the kernel's
+        // ASID.
+        unsafe { mmu::switch(mmu::ttbr0(tables.empty.address(), KERNEL_ASID)) };
+    }
+}
+
+/// Notes that the current core switched to the ASID in `TTBR0_EL1`, e.g. because a thread of a
+/// process was switched to on it, see [CACHED_ASIDS]. IRQs must be masked.
+pub fn note_switch() {
+    let asid = mmu::asid_of(mmu::current_table());
+    CACHED_ASIDS[percpu::this().core][asid as usize / 64]
+        .fetch_or(1 << (asid % 64), Ordering::Relaxed);
+}
+
+/// The address that the kernel can reach the physical memory at [physical] at.
+pub const fn virtual_address(physical: u64) -> u64 {
+    KERNEL_BASE + physical
+}
+
+/// The physical address of the kernel's [address], if it is in the upper half.
+pub fn physical_address(address: u64) -> Option<u64> {
+    address
+        .checked_sub(KERNEL_BASE)
+        .filter(|it| *it < PHYSICAL_END)
+}
+
+impl AddressSpace {
+    /// Creates an address space with nothing mapped in user space.
+    pub fn new() -> Result<AddressSpace, PagingError> {
+        let asid = allocate_asid()?;
+        let root = match super::allocate() {
+            Ok(root) => root,
+            Err(error) => {
+                free_asid(asid);
+                return Err(PagingError::Memory(error));
+            }
+        };
+
+        root.zero();
+        Ok(AddressSpace { root, asid })
+    }
+
+    /// Makes this the address space that EL0 sees.
+    pub fn activate(&self) {
+        daif::without_irqs(|| {
+            // Safety: The lower half only ever maps user space, and the ASID is this address
+            // space's.
+            unsafe { mmu::switch(mmu::ttbr0(self.root.address(), self.asid)) };
+            note_switch();
+        });
+    }
+
+    /// The ASID that the translations of this address space are tagged with.
+    pub fn asid(&self) -> u16 {
+        self.asid
+    }
+
+    /// Maps the page at [address] to [frame].
+    pub fn map(&mut self, address: u64, frame: Frame, flags: PageFlags) -> Result<(), PagingError> {
+        let entry = self.entry(address, true)?;
+        if *entry & VALID != 0 {
+            return Err(PagingError::AlreadyMapped(address));
+        }
+
+        *entry = page_descriptor(frame.address(), flags);
+        mmu::synchronize();
+        Ok(())
+    }
+
+    /// Changes what the page at [address] may be used for.
+    pub fn protect(&mut self, address: u64, flags: PageFlags) -> Result<(), PagingError> {
+        let entry = self.entry(address, false)?;
+        if *entry & VALID == 0 {
+            return Err(PagingError::NotMapped(address));
+        }
+
+        *entry = page_descriptor(*entry & ADDRESS_MASK, flags);
+        self.invalidate_page(address);
+        Ok(())
+    }
+
+    /// Unmaps the page at [address], returning the frame that it was mapped to, which is then
+    /// the caller's to free.
+    ///
+    /// Tables that become empty are only freed with the rest of the address space.
+    pub fn unmap(&mut self, address: u64) -> Result<Frame, PagingError> {
+        let entry = self.entry(address, false)?;
+        if *entry & VALID == 0 {
+            return Err(PagingError::NotMapped(address));
+        }
+
+        let frame = Frame::from_address(*entry & ADDRESS_MASK).map_err(PagingError::Memory)?;
+        *entry = 0;
+        self.invalidate_page(address);
+        Ok(frame)
+    }
+
+    /// Creates a copy of this address space that shares all of its frames. Writable pages become
+    /// [PageFlags::CopyOnWrite] in both, so that whichever writes to a page first gets a copy of
+    /// it. Device memory isn't copied.
+    pub fn fork(&mut self) -> Result<AddressSpace, PagingError> {
+        let mut child = AddressSpace::new()?;
+        let result = self.fork_into(&mut child);
+
+        // Pages that were made copy-on-write may still be writable in the TLB, even if the fork
+        // failed half way.
+        mmu::invalidate_asid(self.asid);
+        result.map(|_| child)
+    }
+
+    fn fork_into(&mut self, child: &mut AddressSpace) -> Result<(), PagingError> {
+        for (first, &entry) in table_of(self.root.address()).iter().enumerate() {
+            if entry & VALID == 0 {
+                continue;
+            }
+
+            for (second, &entry) in table_of(entry & ADDRESS_MASK).iter().enumerate() {
+                if entry & VALID == 0 {
+                    continue;
+                }
+
+                for (third, page) in table_of(entry & ADDRESS_MASK).iter_mut().enumerate() {
+                    if *page & VALID == 0 {
+                        continue;
+                    }
+
+                    let mut flags = page_flags(*page);
+                    if flags.contains(PageFlags::Device) {
+                        continue;
+                    }
+
+                    let physical = *page & ADDRESS_MASK;
+                    if flags.contains(PageFlags::Write) {
+                        flags = (flags - PageFlags::Write) | PageFlags::CopyOnWrite;
+                        *page = page_descriptor(physical, flags);
+                    }
+
+                    let address =
+                        ((first as u64) << 30) | ((second as u64) << 21) | ((third as u64) << 12);
+                    let frame = Frame::from_address(physical).map_err(PagingError::Memory)?;
+                    super::share(frame).map_err(PagingError::Memory)?;
+                    if let Err(error) = child.map(address, frame, flags) {
+                        free_or_report(frame);
+                        return Err(error);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the physical address that [address] is mapped to, and the flags of its page.
+    pub fn translate(&mut self, address: u64) -> Option<(u64, PageFlags)> {
+        let page = address & !(FRAME_SIZE - 1);
+        let entry = *self.entry(page, false).ok()?;
+        if entry & VALID == 0 {
+            return None;
+        }
+
+        Some((
+            (entry & ADDRESS_MASK) | (address & (FRAME_SIZE - 1)),
+            page_flags(entry),
+        ))
+    }
+
+    /// Returns the bytes from [address] up to the end of its page, which must be mapped.
+    pub fn bytes_mut(&mut self, address: u64) -> Result<&mut [u8], PagingError> {
+        let (physical, _) = self
+            .translate(address)
+            .ok_or(PagingError::NotMapped(address))?;
+
+        let length = FRAME_SIZE - (address & (FRAME_SIZE - 1));
+
+        // Safety: The frame belongs to this address space, and is mapped in the upper half.
+        Ok(unsafe {
+            core::slice::from_raw_parts_mut(virtual_address(physical) as *mut u8, length as usize)
+        })
+    }
+
+    /// Copies [data] to [address], which may span several pages that must all be mapped.
+    pub fn write(&mut self, mut address: u64, mut data: &[u8]) -> Result<(), PagingError> {
+        while !data.is_empty() {
+            let bytes = self.bytes_mut(address)?;
+            let length = bytes.len().min(data.len());
+            bytes[..length].copy_from_slice(&data[..length]);
+
+            address += length as u64;
+            data = &data[length..];
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the translation of the page at [address] after its entry was changed. Only the
+    /// current core can have it cached, unless the address space was active on other cores too
+    /// (see [CACHED_ASIDS]), which are asked to forget it with an [ipi::shootdown].
+    fn invalidate_page(&self, address: u64) {
+        daif::without_irqs(|| {
+            let this = percpu::this().core;
+            let shared = (0..MAX_CORES)
+                .filter(|it| *it != this)
+                .any(|it| is_cached(it, self.asid));
+
+            if shared {
+                ipi::shootdown(Some(address));
+            } else {
+                mmu::invalidate_local_asid_page(self.asid, address);
+            }
+        });
+    }
+
+    /// Returns the last level descriptor for [address], creating the tables on the way to it if
+    /// [allocate] is set.
+    fn entry(&mut self, address: u64, allocate: bool) -> Result<&mut u64, PagingError> {
+        if !address.is_multiple_of(FRAME_SIZE) {
+            return Err(PagingError::Misaligned(address));
+        }
+
+        if !(USER_START..USER_END).contains(&address) {
+            return Err(PagingError::OutOfRange(address));
+        }
+
+        let mut table = self.root.address();
+        for level in 1..3 {
+            let entry = &mut table_of(table)[index(address, level)];
+            if *entry & VALID == 0 {
+                if !allocate {
+                    return Err(PagingError::NotMapped(address));
+                }
+
+                let frame = super::allocate().map_err(PagingError::Memory)?;
+                frame.zero();
+                *entry = frame.address() | TABLE | VALID;
+            }
+
+            table = *entry & ADDRESS_MASK;
+        }
+
+        Ok(&mut table_of(table)[index(address, 3)])
+    }
+}
+
+impl Drop for AddressSpace {
+    /// Frees every frame that is mapped in user space, and the tables, and makes the ASID
+    /// available again. The address space mustn't be active on any core.
+    fn drop(&mut self) {
+        mmu::invalidate_asid(self.asid);
+        free_table(self.root, 1);
+        free_asid(self.asid);
+    }
+}
+
+/// Claims an ASID that no other address space uses.
+fn allocate_asid() -> Result<u16, PagingError> {
+    let mut asids = ASIDS.lock();
+    let (word, bits) = asids
+        .iter_mut()
+        .enumerate()
+        .find(|(_, it)| **it != u64::MAX)
+        .ok_or(PagingError::OutOfAsids)?;
+
+    let bit = (!*bits).trailing_zeros();
+    *bits |= 1 << bit;
+    Ok((word * 64) as u16 + bit as u16)
+}
+
+/// Makes [asid] available again. Its translations must have been forgotten first.
+fn free_asid(asid: u16) {
+    // Its translations were forgotten on every core when its address space was dropped.
+    for core in CACHED_ASIDS.iter() {
+        core[asid as usize / 64].fetch_and(!(1 << (asid % 64)), Ordering::Relaxed);
+    }
+
+    ASIDS.lock()[asid as usize / 64] &= !(1 << (asid % 64));
+}
+
+/// Whether [core] may have translations of [asid] cached, see [CACHED_ASIDS].
+fn is_cached(core: usize, asid: u16) -> bool {
+    CACHED_ASIDS[core][asid as usize / 64].load(Ordering::Relaxed) & (1 << (asid % 64)) != 0
+}
+
+/// Frees [table] at [level] (1 to 3), along with every table and frame that it maps. Device
+/// memory doesn't belong to the address space, so it is left alone.
+fn free_table(table: Frame, level: u32) {
+    for &entry in table_of(table.address()).iter() {
+        if entry & VALID == 0 || (level == 3 && page_flags(entry).contains(PageFlags::Device)) {
+            continue;
+        }
+
+        let Ok(frame) = Frame::from_address(entry & ADDRESS_MASK) else {
+            continue;
+        };
+
+        if level < 3 {
+            free_table(frame, level + 1);
+        } else {
+            free_or_report(frame);
+        }
+    }
+
+    free_or_report(table);
+}
+
+fn free_or_report(frame: Frame) {
+    if let Err(error) = super::free(frame) {
+        println!(
+            "[angeldust::memory] failed to free the frame at {:#x}: {:?}",
+            frame.address(),
+            error
+        );
+    }
+}
+
+/// The index into the table at [level] (1 to 3) that translates [address].
+fn index(address: u64, level: u32) -> usize {
+    ((address >> (12 + 9 * (3 - level))) as usize) % ENTRIES
+}
+
+/// Creates the last level table that maps the [BLOCK_SIZE] bytes of physical memory at [block],
+/// which overlaps the kernel's image, with the permissions of each of its sections.
+fn kernel_page_table(block: u64) -> Result<Frame, PagingError> {
+    let text = physical_range(&raw const _text_start, &raw const _text_end);
+    let rodata = physical_range(&raw const _text_end, &raw const _rodata_end);
+    let boot_stack_guard = physical_address(&raw const _boot_stack_guard as u64);
+
+    let table = super::allocate().map_err(PagingError::Memory)?;
+    for (index, entry) in table_of(table.address()).iter_mut().enumerate() {
+        let address = block + index as u64 * FRAME_SIZE;
+        let flags = if Some(address) == boot_stack_guard {
+            None
+        } else if text.contains(&address) {
+            Some(PageFlags::Execute)
+        } else if rodata.contains(&address) {
+            Some(PageFlags::empty())
+        } else if super::is_ram(address, FRAME_SIZE) {
+            Some(PageFlags::Write)
+        } else {
+            Some(PageFlags::Write | PageFlags::Device)
+        };
+
+        *entry = flags.map_or(0, |it| page_descriptor(address, it));
+    }
+
+    Ok(table)
+}
+
+/// The physical addresses between two symbols of the kernel's image.
+fn physical_range(start: *const u8, end: *const u8) -> core::ops::Range<u64> {
+    let physical = |symbol: *const u8| {
+        physical_address(symbol as u64).expect("the kernel is linked in the upper half")
+    };
+
+    physical(start)..physical(end)
+}
+
+/// Returns the last level descriptor of the kernel's page at [address], if it is part of the
+/// kernel's image, which is the only part of the upper half that is mapped in pages.
+fn kernel_page_entry(address: u64) -> Option<&'static mut u64> {
+    let tables = KERNEL_TABLES.get()?;
+    let mut table = tables.kernel.address();
+    for level in 1..3 {
+        let entry = table_of(table)[index(address, level)];
+        if entry & (TABLE | VALID) != TABLE | VALID {
+            return None;
+        }
+
+        table = entry & ADDRESS_MASK;
+    }
+
+    Some(&mut table_of(table)[index(address, 3)])
+}
+
+/// Returns the page table at the physical address [address].
+fn table_of(address: u64) -> &'static mut [u64; ENTRIES] {
+    // Safety: Page tables are whole frames, which are mapped in the upper half.
+    unsafe { &mut *(virtual_address(address) as *mut [u64; ENTRIES]) }
+}
+
+/// Creates the last level descriptor that maps a page to [physical].
+fn page_descriptor(physical: u64, flags: PageFlags) -> u64 {
+    let mut descriptor = physical | PAGE | VALID | ACCESSED;
+
+    descriptor |= if flags.contains(PageFlags::Device) {
+        DEVICE_MEMORY << ATTRIBUTE_INDEX_SHIFT
+    } else {
+        (NORMAL_MEMORY << ATTRIBUTE_INDEX_SHIFT) | INNER_SHAREABLE
+    };
+
+    if !flags.contains(PageFlags::Write) {
+        descriptor |= READ_ONLY;
+    }
+
+    if flags.contains(PageFlags::CopyOnWrite) {
+        descriptor |= READ_ONLY | COPY_ON_WRITE;
+    }
+
+    // The kernel never executes user pages, and user code never executes the kernel's.
+    if flags.contains(PageFlags::User) {
+        descriptor |= ACCESS_USER | NOT_GLOBAL | PRIVILEGED_EXECUTE_NEVER;
+        if !flags.contains(PageFlags::Execute) {
+            descriptor |= USER_EXECUTE_NEVER;
+        }
+    } else {
+        descriptor |= USER_EXECUTE_NEVER;
+        if !flags.contains(PageFlags::Execute) {
+            descriptor |= PRIVILEGED_EXECUTE_NEVER;
+        }
+    }
+
+    descriptor
+}
+
+/// The inverse of [page_descriptor].
+fn page_flags(descriptor: u64) -> PageFlags {
+    let mut flags = PageFlags::empty();
+    if descriptor & READ_ONLY == 0 {
+        flags |= PageFlags::Write;
+    }
+
+    if descriptor & COPY_ON_WRITE != 0 {
+        flags |= PageFlags::CopyOnWrite;
+    }
+
+    if (descriptor >> ATTRIBUTE_INDEX_SHIFT) & 0b111 == DEVICE_MEMORY {
+        flags |= PageFlags::Device;
+    }
+
+    let user = descriptor & ACCESS_USER != 0;
+    if user {
+        flags |= PageFlags::User;
+    }
+
+    let execute_never = if user {
+        USER_EXECUTE_NEVER
+    } else {
+        PRIVILEGED_EXECUTE_NEVER
+    };
+
+    if descriptor & execute_never == 0 {
+        flags |= PageFlags::Execute;
+    }
+
+    flags
+}
@@ -1,15 +1,30 @@
+use crate::arch::aarch64::daif::{self, DaifRegister};
+#[cfg(feature = "lockdep")]
+use crate::lockdep::{self, Held, LockKind};
+#[cfg(feature = "lockdep")]
+use core::panic::Location;
 use core::{
     cell::UnsafeCell,
+    hint,
     ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-/// A very basic [Mutex] implementation.
+/// A very basic [Mutex] implementation, which spins until it can be locked.
 ///
 /// This implementation doesn't have any concept of detecting "poisoning" (when the current thread that
 /// has locked them panics) which prevents other threads that are waiting on the mutex from accessing bad data.
+///
+/// Locking masks IRQs on the current core until the [Guard] is dropped, so an interrupt handler
+/// never spins on a mutex that the code it interrupted holds, and the thread that holds it isn't
+/// preempted, which only happens from an interrupt. This means that nothing may sleep while a
+/// [Mutex] is held, e.g. by locking a [crate::sync::Mutex], which is what data that is held for
+/// a long time should use instead.
+///
+/// Each [Guard] restores the IRQ mask from before it was locked, so guards must be dropped in the
+/// reverse order that they were taken in.
 pub struct Mutex<T> {
-    // FIXME: When the MMU is enabled, make the Mutex actually lock.
-    // is_locked: AtomicBool,
+    is_locked: AtomicBool,
     data: UnsafeCell<T>,
 }
 
@@ -17,7 +32,7 @@ impl<T> Mutex<T> {
     /// Creates a new instance of [Mutex] which holds [data] of [T].
     pub const fn new(data: T) -> Mutex<T> {
         Self {
-            // is_locked: AtomicBool::new(false),
+            is_locked: AtomicBool::new(false),
             data: UnsafeCell::new(data),
         }
     }
@@ -25,13 +40,44 @@ impl<T> Mutex<T> {
     /// Attempts to take ownership of this [Mutex].
     ///
     /// If the mutex is currently owned by another thread, the thread will enter a spin-lock
-    /// until the owner releases their lock (by dropping the [Guard]).
-    pub fn lock(&self) -> Guard<T> {
-        // while self.is_locked.swap(true, Ordering::AcqRel) {
-        //     hint::spin_loop()
-        // }
+    /// until the owner releases their lock (by dropping the [Guard]). IRQs are masked from here
+    /// on, until the [Guard] is dropped.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub fn lock(&self) -> Guard<'_, T> {
+        let daif = DaifRegister::read();
+        daif::mask_irqs();
+
+        #[cfg(feature = "lockdep")]
+        let held = lockdep::acquire(
+            self as *const Self as usize,
+            LockKind::Spin,
+            Location::caller(),
+            false,
+        );
+
+        while self.is_locked.swap(true, Ordering::Acquire) {
+            // Only read the lock while waiting, which doesn't take the cache line away from the
+            // core that holds it.
+            while self.is_locked.load(Ordering::Relaxed) {
+                hint::spin_loop()
+            }
+        }
+
+        Guard {
+            mutex: self,
+            daif,
+            #[cfg(feature = "lockdep")]
+            held,
+        }
+    }
 
-        Guard { mutex: self }
+    /// Unlocks this [Mutex], even though a [Guard] for it may still exist.
+    ///
+    /// # Safety
+    /// - Whoever holds the [Guard] must never use it again, e.g. because the core that holds it
+    ///   has panicked.
+    pub unsafe fn force_unlock(&self) {
+        self.is_locked.store(false, Ordering::Release);
     }
 }
 
@@ -42,6 +88,12 @@ impl<T> Mutex<T> {
 /// out of scope.
 pub struct Guard<'a, T> {
     mutex: &'a Mutex<T>,
+
+    /// The IRQ mask from before the [Mutex] was locked, which is restored once it is unlocked.
+    daif: DaifRegister,
+
+    #[cfg(feature = "lockdep")]
+    held: Held,
 }
 
 /// Allows the owner of the [Guard] to get a non-mutable reference to its value.
@@ -63,7 +115,11 @@ impl<T> DerefMut for Guard<'_, T> {
 /// Unlocks the [Mutex] when the the current [Guard] goes out of scope.
 impl<T> Drop for Guard<'_, T> {
     fn drop(&mut self) {
-        // self.mutex.is_locked.store(false, Ordering::Release);
+        #[cfg(feature = "lockdep")]
+        lockdep::release(self.mutex as *const Mutex<T> as usize, self.held);
+
+        self.mutex.is_locked.store(false, Ordering::Release);
+        self.daif.write();
     }
 }
 
@@ -0,0 +1,117 @@
+use crate::{
+    console,
+    cpu::{self, ipi},
+    io::power::pm,
+    params::{self, Parameter, ParameterError},
+    println, timer,
+};
+use core::{
+    arch::asm,
+    panic::PanicInfo,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// The [PanicBehaviour] from the [params], as encoded by [PanicBehaviour::encode].
+///
+/// The panic handler reads this instead of [params::instance], as whatever panicked may be
+/// holding the lock around the parameters.
+static BEHAVIOUR: AtomicU64 = AtomicU64::new(PanicBehaviour::Halt.encode());
+
+/// The parameters accepted by the panic handler.
+pub const PARAMETERS: &[Parameter] = &[Parameter {
+    key: "panic",
+    default: "halt",
+    description: "what to do after a panic (halt, idle or reboot[:seconds])",
+    apply: |params, value| {
+        params.panic = match value.split_once(':') {
+            None if value == "halt" => PanicBehaviour::Halt,
+            None if value == "idle" => PanicBehaviour::Idle,
+            None if value == "reboot" => PanicBehaviour::Reboot { delay: 0 },
+            Some(("reboot", delay)) => PanicBehaviour::Reboot {
+                delay: params::parse_u32(delay, 0, 3600)?,
+            },
+            _ => {
+                return Err(ParameterError::UnknownValue {
+                    expected: "halt, idle or reboot[:seconds]",
+                })
+            }
+        };
+
+        Ok(())
+    },
+}];
+
+/// What the kernel does once a panic has been reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PanicBehaviour {
+    /// Masks interrupts and stops the core, see [cpu::halt].
+    #[default]
+    Halt,
+
+    /// Waits for interrupts forever, which keeps periodic work like the thermal monitor running.
+    /// Panics in an interrupt handler are treated as [PanicBehaviour::Halt], as interrupts are
+    /// masked while they run.
+    Idle,
+
+    /// Reboots the board after [PanicBehaviour::Reboot::delay] seconds, see
+    /// [pm::PowerManagement::reset].
+    Reboot { delay: u32 },
+}
+
+impl PanicBehaviour {
+    /// Packs this into a single value, so that it can be kept in an atomic.
+    const fn encode(self) -> u64 {
+        match self {
+            PanicBehaviour::Halt => 0,
+            PanicBehaviour::Idle => 1,
+            PanicBehaviour::Reboot { delay } => 2 | (delay as u64) << 32,
+        }
+    }
+
+    /// Unpacks a value that was packed by [PanicBehaviour::encode].
+    const fn decode(value: u64) -> PanicBehaviour {
+        match value as u32 {
+            1 => PanicBehaviour::Idle,
+            2 => PanicBehaviour::Reboot {
+                delay: (value >> 32) as u32,
+            },
+            _ => PanicBehaviour::Halt,
+        }
+    }
+}
+
+/// Snapshots the [PanicBehaviour] from the [params], so that the panic handler doesn't have to
+/// lock them. Until this is called, panics use [PanicBehaviour::Halt].
+///
+/// This must be called after [params::initialize].
+pub fn initialize() {
+    BEHAVIOUR.store(params::instance().panic.encode(), Ordering::Relaxed);
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    // The other cores may be running threads that depend on whatever went wrong.
+    ipi::stop_others();
+
+    // The panic may have happened while this core was printing, which would never finish.
+    unsafe { console::force_unlock() };
+    println!("\n{}", info);
+
+    match PanicBehaviour::decode(BEHAVIOUR.load(Ordering::Relaxed)) {
+        PanicBehaviour::Halt => cpu::halt(),
+        PanicBehaviour::Idle => loop {
+            unsafe { asm!("wfi") }
+        },
+        PanicBehaviour::Reboot { delay } => {
+            println!("[angeldust::panic] rebooting in {} seconds", delay);
+
+            // This doesn't depend on the timer interrupt, which may not be running.
+            timer::delay(Duration::from_secs(delay.into()));
+
+            // Unlike pm::reboot(), this doesn't sync the filesystems, as their locks may be held
+            // by whatever panicked, and their state can't be trusted anymore.
+            pm::PowerManagement::new().reset(0)
+        }
+    }
+}
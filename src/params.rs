@@ -0,0 +1,212 @@
+use crate::{
+    console, fs,
+    io::{emmc, framebuffer, mailbox, power::watchdog, thermal},
+    mutex::Mutex,
+    panic, println,
+    sync::Lazy,
+};
+use core::num::ParseIntError;
+
+static BOOT_PARAMS: Mutex<Option<BootParams>> = Mutex::new(None);
+
+/// The [BootParams::defaults], which are only parsed the first time that they are needed.
+static DEFAULTS: Lazy<BootParams> = Lazy::new(BootParams::parse_defaults);
+
+/// Every subsystem that accepts parameters on the kernel command line.
+/// A subsystem's [Parameter]s are ignored (and reported as unknown) until it is added here.
+const SUBSYSTEMS: &[&[Parameter]] = &[
+    console::PARAMETERS,
+    emmc::PARAMETERS,
+    framebuffer::PARAMETERS,
+    fs::PARAMETERS,
+    mailbox::PARAMETERS,
+    panic::PARAMETERS,
+    thermal::PARAMETERS,
+    watchdog::PARAMETERS,
+];
+
+/// The typed values of every [Parameter], parsed from the kernel command line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BootParams {
+    pub console: console::ConsoleParams,
+    pub emmc: emmc::EmmcParams,
+    pub framebuffer: framebuffer::FramebufferParams,
+    pub fs: fs::FsParams,
+    pub mailbox: mailbox::MailboxParams,
+    pub panic: panic::PanicBehaviour,
+    pub thermal: thermal::ThermalConfig,
+    pub watchdog: watchdog::WatchdogParams,
+}
+
+/// A `key=value` parameter that a subsystem accepts on the kernel command line.
+pub struct Parameter {
+    pub key: &'static str,
+
+    /// The value used when the key is not on the command line.
+    pub default: &'static str,
+
+    pub description: &'static str,
+
+    /// Parses [value] and stores it in the [BootParams].
+    /// Keys without a value (e.g. `quiet`) are passed an empty string.
+    pub apply: fn(&mut BootParams, value: &str) -> Result<(), ParameterError>,
+}
+
+/// Represents an error that can occur while parsing the value of a [Parameter].
+#[derive(Debug)]
+#[allow(dead_code)]
+pub enum ParameterError {
+    /// Occurs when a number could not be parsed.
+    InvalidNumber(ParseIntError),
+
+    /// Occurs when a number is parsed, but isn't one that the subsystem can use.
+    OutOfRange { minimum: u32, maximum: u32 },
+
+    /// Occurs when the value isn't one of the values that the subsystem accepts.
+    UnknownValue { expected: &'static str },
+}
+
+/// Parses [command_line] into the global [BootParams].
+///
+/// Unknown keys, and values that can't be parsed, are reported and otherwise ignored, as the
+/// firmware adds a lot of parameters that are only meant for Linux.
+pub fn initialize(command_line: &str) {
+    println!("[angeldust::params] command line: {}", command_line);
+
+    let mut has_invalid_values = false;
+    let params = BootParams::parse(command_line, |key, error| match error {
+        Some(error) => {
+            has_invalid_values = true;
+            println!(
+                "[angeldust::params] warning: ignoring invalid value for '{}': {:?}",
+                key, error
+            )
+        }
+        None => println!("[angeldust::params] warning: unknown parameter '{}'", key),
+    });
+
+    // Unknown keys are expected (the firmware adds its own), but an invalid value for one of
+    // ours is probably a typo, so show what would have been accepted.
+    if has_invalid_values {
+        print_help();
+    }
+
+    *BOOT_PARAMS.lock() = Some(params);
+}
+
+/// Retrieves the global [BootParams].
+///
+/// If [initialize] hasn't been called yet (e.g. while panicking early in boot), the declared
+/// defaults are returned instead.
+pub fn instance() -> BootParams {
+    let params = *BOOT_PARAMS.lock();
+    params.unwrap_or_else(BootParams::defaults)
+}
+
+/// Prints every known [Parameter], along with its default value.
+pub fn print_help() {
+    for parameter in SUBSYSTEMS.iter().flat_map(|it| it.iter()) {
+        println!(
+            "[angeldust::params] {}={}: {}",
+            parameter.key, parameter.default, parameter.description
+        );
+    }
+}
+
+impl BootParams {
+    /// Returns the [BootParams] with every [Parameter] set to its declared default.
+    pub fn defaults() -> BootParams {
+        *DEFAULTS
+    }
+
+    fn parse_defaults() -> BootParams {
+        let mut params = BootParams::default();
+
+        for parameter in SUBSYSTEMS.iter().flat_map(|it| it.iter()) {
+            if let Err(error) = (parameter.apply)(&mut params, parameter.default) {
+                panic!(
+                    "the default value for '{}' is invalid: {:?}",
+                    parameter.key, error
+                );
+            }
+        }
+
+        params
+    }
+
+    /// Parses every argument in [command_line] on top of the [BootParams::defaults].
+    /// If a key is repeated, the last value wins.
+    ///
+    /// [on_error] is called for each argument that was ignored, with the [ParameterError] if the
+    /// key is known.
+    pub fn parse(
+        command_line: &str,
+        mut on_error: impl FnMut(&str, Option<ParameterError>),
+    ) -> BootParams {
+        let mut params = BootParams::defaults();
+
+        for (key, value) in arguments(command_line) {
+            let parameter = SUBSYSTEMS
+                .iter()
+                .flat_map(|it| it.iter())
+                .find(|it| it.key == key);
+
+            match parameter {
+                Some(parameter) => {
+                    if let Err(error) = (parameter.apply)(&mut params, value) {
+                        on_error(key, Some(error));
+                    }
+                }
+
+                None => on_error(key, None),
+            }
+        }
+
+        params
+    }
+}
+
+/// Parses a number for a [Parameter], making sure that it is between [minimum] and [maximum].
+pub fn parse_u32(value: &str, minimum: u32, maximum: u32) -> Result<u32, ParameterError> {
+    let value = value
+        .parse::<u32>()
+        .map_err(ParameterError::InvalidNumber)?;
+
+    if value < minimum || value > maximum {
+        return Err(ParameterError::OutOfRange { minimum, maximum });
+    }
+
+    Ok(value)
+}
+
+/// Splits [command_line] into `key=value` pairs, separated by whitespace.
+/// Values may be wrapped in double quotes to include whitespace (e.g. `key="a b"`).
+fn arguments(command_line: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut remaining = command_line;
+
+    core::iter::from_fn(move || {
+        remaining = remaining.trim_start();
+        if remaining.is_empty() {
+            return None;
+        }
+
+        // Whitespace only ends the argument if it isn't within quotes.
+        let mut quoted = false;
+        let end = remaining
+            .char_indices()
+            .find(|(_, it)| {
+                if *it == '"' {
+                    quoted = !quoted;
+                }
+
+                it.is_whitespace() && !quoted
+            })
+            .map_or(remaining.len(), |(index, _)| index);
+
+        let (argument, rest) = remaining.split_at(end);
+        remaining = rest;
+
+        let (key, value) = argument.split_once('=').unwrap_or((argument, ""));
+        Some((key, value.trim_matches('"')))
+    })
+}
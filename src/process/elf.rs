@@ -0,0 +1,296 @@
+use crate::{fs::vfs::VfsError, memory::PagingError};
+use bitflags::bitflags;
+
+/// The size of the ELF64 file header.
+pub const HEADER_SIZE: usize = 64;
+
+/// The size of an ELF64 program header.
+pub const PROGRAM_HEADER_SIZE: usize = 56;
+
+const MAGIC: [u8; 4] = *b"\x7fELF";
+const CLASS_64: u8 = 2;
+const DATA_LITTLE_ENDIAN: u8 = 1;
+const VERSION_CURRENT: u32 = 1;
+const ABI_SYSTEM_V: u8 = 0;
+const ABI_LINUX: u8 = 3;
+const TYPE_EXECUTABLE: u16 = 2;
+const MACHINE_AARCH64: u16 = 183;
+
+/// The amount of program headers that means that the real amount is elsewhere, which is never
+/// needed for executables.
+const PN_XNUM: u16 = 0xFFFF;
+
+const SEGMENT_LOAD: u32 = 1;
+const SEGMENT_INTERPRETER: u32 = 3;
+
+/// Represents an error that can occur while loading an ELF file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+    /// Occurs when the file couldn't be opened or read.
+    Io(VfsError),
+
+    /// Occurs when the file ends before the structure at the offset.
+    Truncated(u64),
+
+    /// Occurs when the file doesn't start with `\x7fELF`.
+    InvalidMagic([u8; 4]),
+
+    /// Occurs when the file is a 32-bit ELF file.
+    UnsupportedClass(u8),
+
+    /// Occurs when the file is big-endian.
+    UnsupportedEndianness(u8),
+
+    /// Occurs when the file's ELF version isn't 1.
+    UnsupportedVersion(u32),
+
+    /// Occurs when the file was built for an ABI other than System V or Linux.
+    UnsupportedAbi(u8),
+
+    /// Occurs when the file isn't a statically linked executable, e.g. a shared library or a
+    /// position-independent executable.
+    UnsupportedType(u16),
+
+    /// Occurs when the file was built for an architecture other than AArch64.
+    UnsupportedMachine(u16),
+
+    /// Occurs when the file header's size isn't [HEADER_SIZE].
+    InvalidHeaderSize(u16),
+
+    /// Occurs when the size of a program header isn't [PROGRAM_HEADER_SIZE].
+    InvalidProgramHeaderSize(u16),
+
+    /// Occurs when there are more program headers than the loader can hold.
+    TooManyProgramHeaders(u16),
+
+    /// Occurs when the file needs a dynamic linker.
+    DynamicallyLinked,
+
+    /// Occurs when there are no segments to load.
+    NoLoadableSegments,
+
+    /// Occurs when a segment has more bytes in the file than in memory.
+    SegmentTooLarge { index: usize },
+
+    /// Occurs when a segment's alignment isn't a power of two, or its address and offset aren't
+    /// congruent modulo it.
+    MisalignedSegment { index: usize },
+
+    /// Occurs when a segment isn't within user space, or overlaps the stack.
+    SegmentOutOfRange {
+        index: usize,
+        address: u64,
+        size: u64,
+    },
+
+    /// Occurs when a segment overlaps the one before it, or comes before it.
+    OverlappingSegments { index: usize },
+
+    /// Occurs when a segment is both writable and executable, or shares a page with a segment
+    /// that makes the page both, as no page is ever mapped like that.
+    WritableAndExecutable { index: usize },
+
+    /// Occurs when the entry point isn't in an executable segment.
+    InvalidEntryPoint(u64),
+
+    /// Occurs when there are too many arguments or environment variables, or they don't fit on
+    /// the stack.
+    ArgumentsTooLarge,
+
+    /// Occurs when the address space couldn't be set up.
+    Paging(PagingError),
+}
+
+bitflags! {
+    /// The permissions of a segment.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SegmentFlags: u32 {
+        const Execute = 1 << 0;
+        const Write = 1 << 1;
+        const Read = 1 << 2;
+    }
+}
+
+/// The parts of the ELF64 file header that the loader uses.
+/// https://refspecs.linuxfoundation.org/elf/gabi4+/ch4.eheader.html
+#[derive(Debug, Clone, Copy)]
+pub struct Header {
+    pub entry: u64,
+    pub program_header_offset: u64,
+    pub program_header_count: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    Load,
+
+    /// The segment names a dynamic linker, which isn't supported.
+    Interpreter,
+
+    /// Anything else, e.g. `PT_NOTE` or `PT_GNU_STACK`, which is ignored.
+    Other(u32),
+}
+
+/// An ELF64 program header, which describes a segment.
+/// https://refspecs.linuxfoundation.org/elf/gabi4+/ch5.pheader.html
+#[derive(Debug, Clone, Copy)]
+pub struct ProgramHeader {
+    pub kind: SegmentKind,
+    pub flags: SegmentFlags,
+    pub offset: u64,
+    pub virtual_address: u64,
+    pub file_size: u64,
+    pub memory_size: u64,
+    pub alignment: u64,
+}
+
+impl Header {
+    /// Parses and validates the file header in [bytes].
+    pub fn parse(bytes: &[u8; HEADER_SIZE]) -> Result<Header, ElfError> {
+        let magic = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if magic != MAGIC {
+            return Err(ElfError::InvalidMagic(magic));
+        }
+
+        if bytes[4] != CLASS_64 {
+            return Err(ElfError::UnsupportedClass(bytes[4]));
+        }
+
+        if bytes[5] != DATA_LITTLE_ENDIAN {
+            return Err(ElfError::UnsupportedEndianness(bytes[5]));
+        }
+
+        if bytes[6] as u32 != VERSION_CURRENT {
+            return Err(ElfError::UnsupportedVersion(bytes[6] as u32));
+        }
+
+        if bytes[7] != ABI_SYSTEM_V && bytes[7] != ABI_LINUX {
+            return Err(ElfError::UnsupportedAbi(bytes[7]));
+        }
+
+        let kind = read_u16(bytes, 16);
+        if kind != TYPE_EXECUTABLE {
+            return Err(ElfError::UnsupportedType(kind));
+        }
+
+        let machine = read_u16(bytes, 18);
+        if machine != MACHINE_AARCH64 {
+            return Err(ElfError::UnsupportedMachine(machine));
+        }
+
+        let version = read_u32(bytes, 20);
+        if version != VERSION_CURRENT {
+            return Err(ElfError::UnsupportedVersion(version));
+        }
+
+        let header_size = read_u16(bytes, 52);
+        if header_size as usize != HEADER_SIZE {
+            return Err(ElfError::InvalidHeaderSize(header_size));
+        }
+
+        let program_header_size = read_u16(bytes, 54);
+        let program_header_count = read_u16(bytes, 56);
+        if program_header_count > 0 && program_header_size as usize != PROGRAM_HEADER_SIZE {
+            return Err(ElfError::InvalidProgramHeaderSize(program_header_size));
+        }
+
+        if program_header_count == PN_XNUM {
+            return Err(ElfError::TooManyProgramHeaders(program_header_count));
+        }
+
+        Ok(Header {
+            entry: read_u64(bytes, 24),
+            program_header_offset: read_u64(bytes, 32),
+            program_header_count,
+        })
+    }
+}
+
+impl ProgramHeader {
+    pub fn parse(bytes: &[u8; PROGRAM_HEADER_SIZE]) -> ProgramHeader {
+        let kind = match read_u32(bytes, 0) {
+            SEGMENT_LOAD => SegmentKind::Load,
+            SEGMENT_INTERPRETER => SegmentKind::Interpreter,
+            kind => SegmentKind::Other(kind),
+        };
+
+        ProgramHeader {
+            kind,
+            flags: SegmentFlags::from_bits_truncate(read_u32(bytes, 4)),
+            offset: read_u64(bytes, 8),
+            virtual_address: read_u64(bytes, 16),
+            file_size: read_u64(bytes, 32),
+            memory_size: read_u64(bytes, 40),
+            alignment: read_u64(bytes, 48),
+        }
+    }
+
+    /// Makes sure that the loadable segment at [index] can be loaded between [start] and [limit],
+    /// in a file of [file_size] bytes.
+    pub fn validate(
+        &self,
+        index: usize,
+        start: u64,
+        limit: u64,
+        file_size: u64,
+    ) -> Result<(), ElfError> {
+        if self.file_size > self.memory_size {
+            return Err(ElfError::SegmentTooLarge { index });
+        }
+
+        if self.alignment > 1
+            && (!self.alignment.is_power_of_two()
+                || self.virtual_address % self.alignment != self.offset % self.alignment)
+        {
+            return Err(ElfError::MisalignedSegment { index });
+        }
+
+        let out_of_range = ElfError::SegmentOutOfRange {
+            index,
+            address: self.virtual_address,
+            size: self.memory_size,
+        };
+
+        if self.virtual_address < start || self.end().is_none_or(|it| it > limit) {
+            return Err(out_of_range);
+        }
+
+        if self
+            .offset
+            .checked_add(self.file_size)
+            .is_none_or(|it| it > file_size)
+        {
+            return Err(ElfError::Truncated(self.offset));
+        }
+
+        Ok(())
+    }
+
+    /// The address past the end of the segment in memory.
+    pub fn end(&self) -> Option<u64> {
+        self.virtual_address.checked_add(self.memory_size)
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.virtual_address && self.end().is_some_and(|it| address < it)
+    }
+}
+
+/// Reads a little-endian u16 from [bytes] at [offset].
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Reads a little-endian u32 from [bytes] at [offset].
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+/// Reads a little-endian u64 from [bytes] at [offset].
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(value)
+}
@@ -0,0 +1,257 @@
+use super::{region::Backing, syscall::Errno, Process};
+use crate::{
+    arch::aarch64::{
+        cache,
+        exception::{Abort, ExceptionSyndromeRegister, FaultKind, TrapFrame},
+    },
+    fs::vfs::file,
+    memory::{
+        self,
+        paging::{USER_END, USER_START},
+        Frame, PageFlags, FRAME_SIZE,
+    },
+    println,
+};
+
+/// The signals that end a process, numbered like Linux's, which is what its parent sees in the
+/// status from `wait4`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Signal {
+    /// `SIGILL`: an instruction that isn't allowed at EL0, or is undefined.
+    IllegalInstruction = 4,
+
+    /// `SIGBUS`: a misaligned access, or a page that couldn't be loaded.
+    Bus = 7,
+
+    /// `SIGSEGV`: an access to memory that isn't mapped for it.
+    SegmentationFault = 11,
+}
+
+/// What a page is used for when it faults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+/// Handles a data or instruction abort from EL0, by mapping the page if it is part of one of the
+/// process's regions, or copying it if it is copy-on-write. Otherwise the process is killed.
+pub fn handle_abort(frame: &mut TrapFrame, abort: Abort) {
+    let access = if abort.is_instruction {
+        Access::Execute
+    } else if abort.is_write {
+        Access::Write
+    } else {
+        Access::Read
+    };
+
+    let result = match abort.kind {
+        FaultKind::Translation | FaultKind::AccessFlag | FaultKind::Permission => {
+            super::with_current(|process| process.handle_fault(abort.address, access))
+                .unwrap_or(Err(Errno::Fault))
+        }
+        _ => Err(Errno::Fault),
+    };
+
+    let Err(errno) = result else {
+        return;
+    };
+
+    let signal = match (abort.kind, errno) {
+        (FaultKind::Alignment, _) => Signal::Bus,
+        (_, Errno::Fault) => Signal::SegmentationFault,
+        _ => Signal::Bus,
+    };
+
+    println!(
+        "[angeldust::process] {:?} fault at {:#x}: {:?} of {:#x} ({:?}), killing the process",
+        abort.kind, frame.elr, access, abort.address, errno
+    );
+    super::kill(signal)
+}
+
+/// Handles any other synchronous exception from EL0 that isn't a system call, e.g. an undefined
+/// instruction, by killing the process.
+pub fn handle_exception(frame: &mut TrapFrame, syndrome: ExceptionSyndromeRegister) {
+    println!(
+        "[angeldust::process] unhandled {:?} at {:#x} (esr: {:#x}), killing the process",
+        syndrome.class, frame.elr, syndrome.value
+    );
+    super::kill(Signal::IllegalInstruction)
+}
+
+impl Process {
+    /// Makes the page at [address] usable for [access], by mapping it from the region that it is
+    /// in, or giving the process its own copy if it is copy-on-write. Fails with [Errno::Fault]
+    /// if the process may not use it like that.
+    pub fn handle_fault(&mut self, address: u64, access: Access) -> Result<(), Errno> {
+        let page = address & !(FRAME_SIZE - 1);
+        if !(USER_START..USER_END).contains(&page) {
+            return Err(Errno::Fault);
+        }
+
+        match self.address_space.translate(page) {
+            Some((physical, flags)) if access == Access::Write => {
+                if flags.contains(PageFlags::CopyOnWrite) {
+                    self.copy_on_write(page, physical, flags)
+                } else if allows(flags, access) {
+                    // Another core may have used the translation from before it was changed.
+                    Ok(())
+                } else {
+                    Err(Errno::Fault)
+                }
+            }
+            Some((_, flags)) if allows(flags, access) => Ok(()),
+            Some(_) => Err(Errno::Fault),
+            None => self.populate(page, access),
+        }
+    }
+
+    /// Returns the bytes from [address] up to the end of its page, after faulting the page in for
+    /// [access] like the process itself would. This is how the kernel reaches user memory.
+    pub fn user_bytes(&mut self, address: u64, access: Access) -> Result<&mut [u8], Errno> {
+        let usable = self
+            .address_space
+            .translate(address)
+            .is_some_and(|(_, flags)| allows(flags, access));
+
+        if !usable {
+            self.handle_fault(address, access)?;
+        }
+
+        self.address_space
+            .bytes_mut(address)
+            .map_err(|_| Errno::Fault)
+    }
+
+    /// Maps a new frame at [page] with the contents of the regions that it is in, which get the
+    /// permissions of all of them, or grows the stack down to it. A page is never mapped both
+    /// writable and executable, which the loader and `mmap` already refuse.
+    fn populate(&mut self, page: u64, access: Access) -> Result<(), Errno> {
+        let flags = self
+            .regions
+            .overlapping(page)
+            .map(|it| it.flags)
+            .reduce(|flags, it| flags | it);
+
+        let flags = match flags {
+            Some(flags) => flags,
+            None => self.regions.grow_stack(page).ok_or(Errno::Fault)?.flags,
+        };
+
+        if !allows(flags, access) || flags.contains(PageFlags::Write | PageFlags::Execute) {
+            return Err(Errno::Fault);
+        }
+
+        let frame = memory::allocate().map_err(|_| Errno::OutOfMemory)?;
+        frame.zero();
+
+        let result = self.read_page(page, frame).and_then(|_| {
+            self.address_space
+                .map(page, frame, flags)
+                .map_err(|_| Errno::OutOfMemory)
+        });
+
+        if let Err(errno) = result {
+            let _ = memory::free(frame);
+            return Err(errno);
+        }
+
+        if flags.contains(PageFlags::Execute) {
+            synchronize_instructions(frame);
+        }
+
+        Ok(())
+    }
+
+    /// Reads the parts of the file-backed regions that are in the page at [page] into [frame].
+    fn read_page(&self, page: u64, frame: Frame) -> Result<(), Errno> {
+        // Safety: The frame was just allocated, and is mapped in the upper half.
+        let bytes = unsafe { core::slice::from_raw_parts_mut(frame.as_ptr(), FRAME_SIZE as usize) };
+
+        for region in self.regions.overlapping(page) {
+            let Backing::File { offset, size } = region.backing else {
+                continue;
+            };
+
+            let start = region.start.max(page);
+            let end = (region.start + size).min(page + FRAME_SIZE);
+            if start >= end {
+                continue;
+            }
+
+            let fd = self.file.ok_or(Errno::BadFileDescriptor)?;
+            let mut position = offset + (start - region.start);
+            let mut buffer = &mut bytes[(start - page) as usize..(end - page) as usize];
+            while !buffer.is_empty() {
+                let length = file::read_at(fd, position, buffer).map_err(|_| Errno::Io)?;
+                if length == 0 {
+                    // The file was truncated since the program was loaded.
+                    return Err(Errno::Io);
+                }
+
+                position += length as u64;
+                buffer = &mut buffer[length..];
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Makes the copy-on-write page at [page] writable, copying it first if another address space
+    /// still shares its frame.
+    fn copy_on_write(&mut self, page: u64, physical: u64, flags: PageFlags) -> Result<(), Errno> {
+        let frame = Frame::from_address(physical).map_err(|_| Errno::Fault)?;
+        let flags = (flags - PageFlags::CopyOnWrite) | PageFlags::Write;
+
+        // The last owner can just have it, as the others have already made their copies.
+        if !memory::is_shared(frame) {
+            return self
+                .address_space
+                .protect(page, flags)
+                .map_err(|_| Errno::Fault);
+        }
+
+        let copy = memory::allocate().map_err(|_| Errno::OutOfMemory)?;
+
+        // Safety: Both frames are mapped in the upper half, and the copy was just allocated.
+        unsafe {
+            core::ptr::copy_nonoverlapping(frame.as_ptr(), copy.as_ptr(), FRAME_SIZE as usize)
+        };
+
+        let _ = self.address_space.unmap(page);
+        let result = self.address_space.map(page, copy, flags);
+
+        // This only drops the process's share of the frame.
+        let _ = memory::free(frame);
+        if result.is_err() {
+            let _ = memory::free(copy);
+            return Err(Errno::OutOfMemory);
+        }
+
+        if flags.contains(PageFlags::Execute) {
+            synchronize_instructions(copy);
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a user page with [flags] can be used for [access].
+fn allows(flags: PageFlags, access: Access) -> bool {
+    flags.contains(PageFlags::User)
+        && match access {
+            Access::Read => true,
+            Access::Write => flags.contains(PageFlags::Write),
+            Access::Execute => flags.contains(PageFlags::Execute),
+        }
+}
+
+/// Makes sure that instruction fetches see the code that was written to [frame] through the data
+/// cache.
+fn synchronize_instructions(frame: Frame) {
+    cache::clean_and_invalidate(frame.as_ptr() as usize, FRAME_SIZE as usize);
+    cache::invalidate_instructions();
+}
@@ -0,0 +1,400 @@
+use super::{
+    elf::{
+        ElfError, Header, ProgramHeader, SegmentFlags, SegmentKind, HEADER_SIZE,
+        PROGRAM_HEADER_SIZE,
+    },
+    region::{Backing, Region, Regions},
+};
+use crate::{
+    fs::vfs::file::{self, Fd, OpenFlags},
+    io::rng,
+    memory::{
+        self,
+        paging::{USER_END, USER_START},
+        AddressSpace, PageFlags, PagingError, FRAME_SIZE,
+    },
+};
+
+/// The most program headers that a file may have.
+const MAX_PROGRAM_HEADERS: usize = 16;
+
+/// The most arguments, and separately the most environment variables, that a program may be
+/// given.
+const MAX_ARGUMENTS: usize = 32;
+
+/// The address past the top of the stack of a new program.
+pub const STACK_TOP: u64 = USER_END;
+
+/// The size of the stack that a new program starts with, which its arguments have to fit in.
+pub const STACK_SIZE: u64 = 64 * 1024;
+
+/// How far the stack may grow, nothing else is mapped in this region.
+pub const STACK_LIMIT: u64 = 8 * 1024 * 1024;
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/auxvec.h
+const AT_NULL: u64 = 0;
+const AT_PHDR: u64 = 3;
+const AT_PHENT: u64 = 4;
+const AT_PHNUM: u64 = 5;
+const AT_PAGESZ: u64 = 6;
+const AT_ENTRY: u64 = 9;
+const AT_UID: u64 = 11;
+const AT_EUID: u64 = 12;
+const AT_GID: u64 = 13;
+const AT_EGID: u64 = 14;
+const AT_PLATFORM: u64 = 15;
+const AT_SECURE: u64 = 23;
+const AT_RANDOM: u64 = 25;
+const AT_EXECFN: u64 = 31;
+
+/// A program that is ready to run.
+pub struct Image {
+    pub address_space: AddressSpace,
+
+    /// The parts of the address space that are mapped when they are first used: the segments,
+    /// which are read from [Image::file], and the stack.
+    pub regions: Regions,
+
+    /// The program's file, which stays open while it runs.
+    pub file: Fd,
+
+    /// Where the program starts running.
+    pub entry: u64,
+
+    /// The initial stack pointer, which points at `argc`.
+    pub stack_pointer: u64,
+
+    /// The first page after the highest segment, where the heap can start.
+    pub end: u64,
+}
+
+/// Loads the statically linked ELF64 executable at [path] into a fresh address space, and sets
+/// up its stack with [arguments], [environment] and the auxiliary vector, like Linux does.
+///
+/// Only the stack's top pages are mapped straight away. The segments are read from the file when
+/// the program first uses each of their pages, so the file stays open until [super::run] is done
+/// with the program.
+pub fn load(path: &str, arguments: &[&str], environment: &[&str]) -> Result<Image, ElfError> {
+    let fd = file::open(path, OpenFlags::Read).map_err(ElfError::Io)?;
+    load_from(fd, path, arguments, environment).inspect_err(|_| {
+        let _ = file::close(fd);
+    })
+}
+
+fn load_from(
+    fd: Fd,
+    path: &str,
+    arguments: &[&str],
+    environment: &[&str],
+) -> Result<Image, ElfError> {
+    let file_size = file::stat(fd).map_err(ElfError::Io)?.size;
+
+    let mut header_bytes = [0; HEADER_SIZE];
+    read_exact(fd, 0, &mut header_bytes)?;
+    let header = Header::parse(&header_bytes)?;
+
+    let count = header.program_header_count as usize;
+    if count > MAX_PROGRAM_HEADERS {
+        return Err(ElfError::TooManyProgramHeaders(header.program_header_count));
+    }
+
+    let mut segments = [None; MAX_PROGRAM_HEADERS];
+    let mut previous_end = 0;
+
+    // The last page of the segments so far, and the permissions of all of them that are in it.
+    let mut previous_page: Option<(u64, SegmentFlags)> = None;
+    for (index, slot) in segments.iter_mut().enumerate().take(count) {
+        let offset = header.program_header_offset + (index * PROGRAM_HEADER_SIZE) as u64;
+        let mut bytes = [0; PROGRAM_HEADER_SIZE];
+        read_exact(fd, offset, &mut bytes)?;
+
+        let segment = ProgramHeader::parse(&bytes);
+        match segment.kind {
+            SegmentKind::Load if segment.memory_size > 0 => {}
+            SegmentKind::Interpreter => return Err(ElfError::DynamicallyLinked),
+            _ => continue,
+        }
+
+        segment.validate(index, USER_START, STACK_TOP - STACK_LIMIT, file_size)?;
+
+        // Loadable segments have to be sorted by their address.
+        if segment.virtual_address < previous_end {
+            return Err(ElfError::OverlappingSegments { index });
+        }
+
+        let first_page = segment.virtual_address & !(FRAME_SIZE - 1);
+        let last_page = (segment.virtual_address + segment.memory_size - 1) & !(FRAME_SIZE - 1);
+
+        let mut flags = segment.flags;
+        if let Some((page, previous_flags)) = previous_page {
+            if page == first_page {
+                flags |= previous_flags;
+            }
+        }
+
+        if flags.contains(SegmentFlags::Write | SegmentFlags::Execute) {
+            return Err(ElfError::WritableAndExecutable { index });
+        }
+
+        previous_page = Some((
+            last_page,
+            if last_page == first_page {
+                flags
+            } else {
+                segment.flags
+            },
+        ));
+        previous_end = segment.virtual_address + segment.memory_size;
+        *slot = Some(segment);
+    }
+
+    let mut loadable = segments.iter().flatten();
+    if loadable.clone().next().is_none() {
+        return Err(ElfError::NoLoadableSegments);
+    }
+
+    if !loadable
+        .clone()
+        .any(|it| it.flags.contains(SegmentFlags::Execute) && it.contains(header.entry))
+    {
+        return Err(ElfError::InvalidEntryPoint(header.entry));
+    }
+
+    let mut regions = Regions::new();
+    for segment in loadable.clone() {
+        regions
+            .add(segment_region(segment))
+            .ok_or(ElfError::TooManyProgramHeaders(header.program_header_count))?;
+    }
+
+    // Nothing else is mapped below the stack, so it can grow down to its limit.
+    regions
+        .add(Region {
+            start: STACK_TOP - STACK_SIZE,
+            end: STACK_TOP,
+            flags: PageFlags::User | PageFlags::Write,
+            backing: Backing::Stack {
+                limit: STACK_TOP - STACK_LIMIT,
+            },
+        })
+        .ok_or(ElfError::TooManyProgramHeaders(header.program_header_count))?;
+
+    let mut address_space = AddressSpace::new().map_err(ElfError::Paging)?;
+
+    // The program headers are usually in the first segment, which lets the C library find its
+    // thread-local storage template.
+    let program_headers = loadable
+        .find(|it| {
+            header.program_header_offset >= it.offset
+                && header.program_header_offset - it.offset < it.file_size
+        })
+        .map(|it| it.virtual_address + (header.program_header_offset - it.offset))
+        .unwrap_or(0);
+
+    let mut stack = Stack::new(&mut address_space);
+    let auxiliary = [
+        (AT_PHDR, program_headers),
+        (AT_PHENT, PROGRAM_HEADER_SIZE as u64),
+        (AT_PHNUM, header.program_header_count as u64),
+        (AT_PAGESZ, FRAME_SIZE),
+        (AT_ENTRY, header.entry),
+        (AT_UID, 0),
+        (AT_EUID, 0),
+        (AT_GID, 0),
+        (AT_EGID, 0),
+        (AT_SECURE, 0),
+    ];
+
+    let stack_pointer = stack.populate(path, arguments, environment, &auxiliary)?;
+
+    Ok(Image {
+        address_space,
+        regions,
+        file: fd,
+        entry: header.entry,
+        stack_pointer,
+        end: previous_end.next_multiple_of(FRAME_SIZE),
+    })
+}
+
+/// The region that [segment] is loaded into, whose pages come from the file.
+fn segment_region(segment: &ProgramHeader) -> Region {
+    let mut flags = PageFlags::User;
+    if segment.flags.contains(SegmentFlags::Write) {
+        flags |= PageFlags::Write;
+    }
+
+    if segment.flags.contains(SegmentFlags::Execute) {
+        flags |= PageFlags::Execute;
+    }
+
+    Region {
+        start: segment.virtual_address,
+        end: segment.virtual_address + segment.memory_size,
+        flags,
+        backing: Backing::File {
+            offset: segment.offset,
+            size: segment.file_size,
+        },
+    }
+}
+
+/// Reads exactly [buffer]'s length from [fd] at [offset].
+fn read_exact(fd: Fd, offset: u64, buffer: &mut [u8]) -> Result<(), ElfError> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match file::read_at(fd, offset + read as u64, &mut buffer[read..]).map_err(ElfError::Io)? {
+            0 => return Err(ElfError::Truncated(offset)),
+            length => read += length,
+        }
+    }
+
+    Ok(())
+}
+
+/// The initial stack of a program, which is filled from the top down. Only the pages that it
+/// fills are mapped, the rest of the stack is mapped when the program first uses it.
+struct Stack<'a> {
+    address_space: &'a mut AddressSpace,
+    pointer: u64,
+
+    /// The lowest page that is mapped.
+    bottom: u64,
+}
+
+impl<'a> Stack<'a> {
+    fn new(address_space: &'a mut AddressSpace) -> Stack<'a> {
+        Stack {
+            address_space,
+            pointer: STACK_TOP,
+            bottom: STACK_TOP,
+        }
+    }
+
+    /// Lays out the stack that the AArch64 Linux ABI expects, and returns the stack pointer:
+    ///
+    /// ```text
+    /// sp -> argc
+    ///       argv[0..argc], null
+    ///       envp[..], null
+    ///       auxv pairs, AT_NULL
+    ///       the strings and random bytes that the vectors point to
+    /// ```
+    fn populate(
+        &mut self,
+        path: &str,
+        arguments: &[&str],
+        environment: &[&str],
+        auxiliary: &[(u64, u64)],
+    ) -> Result<u64, ElfError> {
+        if arguments.len() > MAX_ARGUMENTS || environment.len() > MAX_ARGUMENTS {
+            return Err(ElfError::ArgumentsTooLarge);
+        }
+
+        let execute_name = self.push_string(path)?;
+        let platform = self.push_string("aarch64")?;
+
+        let mut random = [0; 16];
+        rng::fill_bytes(&mut random);
+        let random = self.push(&random)?;
+
+        let mut argument_pointers = [0; MAX_ARGUMENTS];
+        for (pointer, argument) in argument_pointers.iter_mut().zip(arguments) {
+            *pointer = self.push_string(argument)?;
+        }
+
+        let mut environment_pointers = [0; MAX_ARGUMENTS];
+        for (pointer, variable) in environment_pointers.iter_mut().zip(environment) {
+            *pointer = self.push_string(variable)?;
+        }
+
+        let extra = [
+            (AT_PLATFORM, platform),
+            (AT_RANDOM, random),
+            (AT_EXECFN, execute_name),
+            (AT_NULL, 0),
+        ];
+
+        // argc, both vectors with their null terminators, and the auxiliary vector.
+        let words = 1
+            + (arguments.len() + 1)
+            + (environment.len() + 1)
+            + 2 * (auxiliary.len() + extra.len());
+
+        self.reserve(words as u64 * 8)?;
+        self.pointer &= !0xF;
+        let stack_pointer = self.pointer;
+
+        let mut address = stack_pointer;
+        let mut write = |value: u64| -> Result<(), ElfError> {
+            self.address_space
+                .write(address, &value.to_le_bytes())
+                .map_err(ElfError::Paging)?;
+
+            address += 8;
+            Ok(())
+        };
+
+        write(arguments.len() as u64)?;
+        for pointer in &argument_pointers[..arguments.len()] {
+            write(*pointer)?;
+        }
+
+        write(0)?;
+        for pointer in &environment_pointers[..environment.len()] {
+            write(*pointer)?;
+        }
+
+        write(0)?;
+        for (key, value) in auxiliary.iter().chain(&extra) {
+            write(*key)?;
+            write(*value)?;
+        }
+
+        Ok(stack_pointer)
+    }
+
+    /// Pushes [string] with a null terminator, returning its address.
+    fn push_string(&mut self, string: &str) -> Result<u64, ElfError> {
+        self.push(&[0])?;
+        self.push(string.as_bytes())
+    }
+
+    /// Pushes [data], returning its address.
+    fn push(&mut self, data: &[u8]) -> Result<u64, ElfError> {
+        self.reserve(data.len() as u64)?;
+        self.address_space
+            .write(self.pointer, data)
+            .map_err(ElfError::Paging)?;
+
+        Ok(self.pointer)
+    }
+
+    /// Moves the stack pointer down by [length] bytes, keeping a page free for the program, and
+    /// maps the pages that it moves into.
+    fn reserve(&mut self, length: u64) -> Result<(), ElfError> {
+        let bottom = STACK_TOP - STACK_SIZE + FRAME_SIZE;
+        self.pointer = self
+            .pointer
+            .checked_sub(length)
+            .filter(|it| *it >= bottom)
+            .ok_or(ElfError::ArgumentsTooLarge)?;
+
+        // The pointer is aligned down to 16 bytes afterwards, which never leaves its page.
+        while self.bottom > self.pointer {
+            let frame =
+                memory::allocate().map_err(|it| ElfError::Paging(PagingError::Memory(it)))?;
+
+            frame.zero();
+            self.bottom -= FRAME_SIZE;
+            self.address_space
+                .map(self.bottom, frame, PageFlags::User | PageFlags::Write)
+                .map_err(|error| {
+                    let _ = memory::free(frame);
+                    ElfError::Paging(error)
+                })?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+pub mod elf;
+pub mod fault;
+pub mod loader;
+pub mod region;
+pub mod syscall;
+pub mod user_memory;
+
+pub use elf::ElfError;
+pub use fault::Signal;
+pub use loader::{load, Image};
+
+use crate::{
+    arch::aarch64::{
+        daif::DaifRegister,
+        exception::TrapFrame,
+        user::{self, KernelContext},
+    },
+    fs::vfs::file::{self, Fd},
+    memory::{paging, AddressSpace},
+    println,
+    scheduler::{self, Priority, MAX_THREADS},
+    sync::{self, Condvar},
+};
+use core::cell::UnsafeCell;
+use region::Regions;
+use syscall::Errno;
+
+/// The first program that is run, from the root filesystem.
+pub const INIT_PATH: &str = "/init";
+
+/// Where anonymous mappings made with `mmap` start, which leaves plenty of room for the program
+/// below it. A program that is linked above this gets them after its highest segment instead.
+pub const MMAP_BASE: u64 = 0x40_0000_0000;
+
+/// The most processes that can be running at once, each of which takes up a thread.
+pub const MAX_PROCESSES: usize = 8;
+
+/// The program status that a process starts with: EL0, with every exception unmasked.
+const SPSR_EL0: u64 = 0;
+
+/// Identifies a process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pid(pub u32);
+
+/// A program that is running at EL0, on a thread of its own.
+pub struct Process {
+    pub pid: Pid,
+
+    /// The process that forked this one, which collects its exit status with `wait4`.
+    pub parent: Option<Pid>,
+
+    pub address_space: AddressSpace,
+
+    /// The parts of the address space that are mapped when they are first used.
+    pub regions: Regions,
+
+    /// The program's file, which the file-backed regions are read from.
+    pub file: Option<Fd>,
+
+    /// Where the next anonymous mapping goes.
+    pub mmap_next: u64,
+
+    /// The scheduler slot of the thread that runs the process.
+    thread: usize,
+
+    /// What a forked process starts running with, until its thread picks it up.
+    start: Option<Start>,
+}
+
+/// The state that a forked process starts with, copied from its parent.
+#[derive(Clone, Copy)]
+struct Start {
+    frame: TrapFrame,
+    thread_pointer: u64,
+}
+
+/// A process that has exited, but whose parent hasn't collected its status yet.
+#[derive(Clone, Copy)]
+struct Exited {
+    pid: Pid,
+    parent: Pid,
+
+    /// The status as `wait4` reports it: the exit status in bits 8 to 15, or the signal that
+    /// killed the process in the low bits.
+    status: u32,
+}
+
+struct ProcessTable {
+    processes: [Option<Process>; MAX_PROCESSES],
+    exited: [Option<Exited>; MAX_PROCESSES],
+    next_pid: u32,
+}
+
+/// Every process, which is a sleeping lock as pages of a program are read from its file while
+/// it is held.
+static PROCESSES: sync::Mutex<ProcessTable> = sync::Mutex::new(ProcessTable {
+    processes: [const { None }; MAX_PROCESSES],
+    exited: [None; MAX_PROCESSES],
+    next_pid: 1,
+});
+
+/// Where each thread entered its process, which [exit] returns to, indexed by scheduler slot.
+struct Contexts([UnsafeCell<KernelContext>; MAX_THREADS]);
+
+static CONTEXTS: Contexts =
+    Contexts([const { UnsafeCell::new(KernelContext::new()) }; MAX_THREADS]);
+
+/// Notified whenever a process exits, which lets `wait4` wait for the next one.
+static EXITED: Condvar = Condvar::new();
+
+/// Runs [image] at EL0 on the calling thread until it exits, and returns its exit status, or
+/// 128 plus the signal that killed it, like a shell does. Processes that it forks keep running.
+pub fn run(image: Image) -> Result<i32, Errno> {
+    let thread = scheduler::current_slot();
+    image.address_space.activate();
+
+    PROCESSES.lock().insert(Process {
+        pid: Pid(0),
+        parent: None,
+        address_space: image.address_space,
+        regions: image.regions,
+        file: Some(image.file),
+        mmap_next: MMAP_BASE.max(image.end),
+        thread,
+        start: None,
+    })?;
+
+    let frame = TrapFrame {
+        registers: [0; 31],
+        sp_el0: image.stack_pointer,
+        elr: image.entry,
+        spsr: SPSR_EL0,
+    };
+
+    // The process exits from a system call, which runs with IRQs masked.
+    let daif = DaifRegister::read();
+
+    // Safety: The frame returns to the program's entry point, which is in one of its regions, and
+    // the context is this thread's own static slot.
+    let status = unsafe { user::enter(&frame, context(thread)) } as u32;
+
+    daif.write();
+    Ok(match status & 0x7F {
+        0 => ((status >> 8) & 0xFF) as i32,
+        signal => 128 + signal as i32,
+    })
+}
+
+/// Creates a copy of the current process, which starts on a thread of its own as if it returned
+/// from the system call that [frame] is from with 0. Returns the new process's [Pid].
+pub fn fork(frame: &TrapFrame) -> Result<Pid, Errno> {
+    let mut processes = PROCESSES.lock();
+    let parent = processes
+        .current(scheduler::current_slot())
+        .ok_or(Errno::NotPermitted)?;
+
+    let address_space = parent
+        .address_space
+        .fork()
+        .map_err(|_| Errno::OutOfMemory)?;
+
+    let file = parent
+        .file
+        .map(file::duplicate)
+        .transpose()
+        .map_err(|_| Errno::Again)?;
+
+    let mut start = Start {
+        frame: *frame,
+        thread_pointer: user::thread_pointer(),
+    };
+    start.frame.registers[0] = 0;
+
+    let (parent_pid, regions, mmap_next) = (parent.pid, parent.regions, parent.mmap_next);
+
+    // The child's thread waits for the table to be unlocked before it looks for its process.
+    let thread = match scheduler::spawn("process", Priority::Normal, start_forked) {
+        Ok(handle) => handle.slot(),
+        Err(_) => {
+            if let Some(fd) = file {
+                let _ = file::close(fd);
+            }
+
+            return Err(Errno::Again);
+        }
+    };
+
+    // If there is no room for it, its thread doesn't find it and just ends.
+    processes.insert(Process {
+        pid: Pid(0),
+        parent: Some(parent_pid),
+        address_space,
+        regions,
+        file,
+        mmap_next,
+        thread,
+        start: Some(start),
+    })
+}
+
+/// Waits for a child of the current process to exit, either [pid] or any of them, and returns its
+/// [Pid] and its status, see [Exited::status]. With [block] unset, it returns [None] straight
+/// away if none of them has exited yet.
+pub fn wait(pid: Option<Pid>, block: bool) -> Result<Option<(Pid, u32)>, Errno> {
+    let thread = scheduler::current_slot();
+    let mut result = Ok(None);
+    let _processes = EXITED.wait_while(PROCESSES.lock(), |processes| {
+        result = processes.collect(thread, pid);
+        block && matches!(result, Ok(None))
+    });
+
+    result
+}
+
+/// Ends the current process with [status], returning from [run] or ending its thread, and frees
+/// its memory.
+pub fn exit(status: i32) -> ! {
+    end((status as u32 & 0xFF) << 8)
+}
+
+/// Ends the current process because of [signal], like [exit].
+pub fn kill(signal: Signal) -> ! {
+    end(signal as u32)
+}
+
+/// Calls [f] with the current process, or returns [None] if there isn't one.
+pub fn with_current<R>(f: impl FnOnce(&mut Process) -> R) -> Option<R> {
+    PROCESSES.lock().current(scheduler::current_slot()).map(f)
+}
+
+/// Runs a forked process on the thread that [fork] spawned for it.
+fn start_forked() {
+    let thread = scheduler::current_slot();
+    let start = with_current(|process| {
+        process.address_space.activate();
+        process.start.take()
+    });
+
+    // The process couldn't be added, so there is nothing to run.
+    let Some(Some(start)) = start else {
+        return;
+    };
+
+    user::set_thread_pointer(start.thread_pointer);
+
+    // Safety: The frame returns to where the parent made the system call, which is mapped the
+    // same way in the copy of its address space, and the context is this thread's own static slot.
+    unsafe { user::enter(&start.frame, context(thread)) };
+}
+
+/// Removes the current process, leaving its status for its parent, and returns to where its
+/// thread entered it.
+fn end(status: u32) -> ! {
+    // The address space can't be torn down while it is still in use.
+    paging::switch_to_kernel();
+
+    let thread = scheduler::current_slot();
+    {
+        let mut processes = PROCESSES.lock();
+        if let Some(process) = processes.remove(thread) {
+            processes.orphan_children(process.pid);
+
+            let parent = process.parent.filter(|it| processes.get(*it).is_some());
+            if let Some(parent) = parent {
+                let exited = Exited {
+                    pid: process.pid,
+                    parent,
+                    status,
+                };
+
+                match processes.exited.iter_mut().find(|it| it.is_none()) {
+                    Some(slot) => *slot = Some(exited),
+                    None => println!(
+                        "[angeldust::process] no room for the status of process {}",
+                        process.pid.0
+                    ),
+                }
+            }
+        }
+    }
+
+    EXITED.notify_all();
+
+    // Safety: A process is only running while the [user::enter] call of its thread hasn't
+    // returned, and the guard of [PROCESSES] was dropped above, as this never returns.
+    unsafe { user::leave(context(thread), status as u64) }
+}
+
+/// The context that the thread in [thread] entered its process from, which only that thread may
+/// use, see [Contexts].
+fn context(thread: usize) -> *mut KernelContext {
+    CONTEXTS.0[thread].get()
+}
+
+impl ProcessTable {
+    /// Adds [process] with a new [Pid], which is returned.
+    fn insert(&mut self, mut process: Process) -> Result<Pid, Errno> {
+        let slot = self
+            .processes
+            .iter_mut()
+            .find(|it| it.is_none())
+            .ok_or(Errno::Again)?;
+
+        let pid = Pid(self.next_pid);
+        self.next_pid += 1;
+        process.pid = pid;
+        *slot = Some(process);
+        Ok(pid)
+    }
+
+    /// The process that the thread in [thread] runs.
+    fn current(&mut self, thread: usize) -> Option<&mut Process> {
+        self.processes
+            .iter_mut()
+            .flatten()
+            .find(|it| it.thread == thread)
+    }
+
+    fn get(&self, pid: Pid) -> Option<&Process> {
+        self.processes.iter().flatten().find(|it| it.pid == pid)
+    }
+
+    fn remove(&mut self, thread: usize) -> Option<Process> {
+        self.processes
+            .iter_mut()
+            .find(|it| it.as_ref().is_some_and(|it| it.thread == thread))?
+            .take()
+    }
+
+    /// Takes the status of a child of the process on [thread] that has exited, either [pid] or
+    /// any of them, see [wait]. Returns [None] if none of them has exited yet.
+    fn collect(&mut self, thread: usize, pid: Option<Pid>) -> Result<Option<(Pid, u32)>, Errno> {
+        let parent = self.current(thread).ok_or(Errno::NotPermitted)?.pid;
+        let matches = |child: Pid, child_parent: Option<Pid>| {
+            child_parent == Some(parent) && pid.is_none_or(|it| it == child)
+        };
+
+        if let Some(slot) = self
+            .exited
+            .iter_mut()
+            .find(|it| it.is_some_and(|it| matches(it.pid, Some(it.parent))))
+        {
+            let exited = slot.take().unwrap();
+            return Ok(Some((exited.pid, exited.status)));
+        }
+
+        if !self
+            .processes
+            .iter()
+            .flatten()
+            .any(|it| matches(it.pid, it.parent))
+        {
+            return Err(Errno::NoChild);
+        }
+
+        Ok(None)
+    }
+
+    /// Forgets that the children of [parent] have one, as nothing will wait for them anymore.
+    fn orphan_children(&mut self, parent: Pid) {
+        for process in self.processes.iter_mut().flatten() {
+            if process.parent == Some(parent) {
+                process.parent = None;
+            }
+        }
+
+        for slot in self.exited.iter_mut() {
+            if slot.is_some_and(|it| it.parent == parent) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// # Safety
+/// - A slot is only ever used by the thread in that scheduler slot: [run] and [start_forked] save
+///   into it when the thread enters its process, and [end] restores from it on the same thread
+///   when the process leaves. The scheduler doesn't touch it while switching threads, and a slot
+///   is only reused once its thread has exited, so no two cores use the same slot at once.
+unsafe impl Sync for Contexts {}
+
+impl Drop for Process {
+    /// Closes the program's file. The address space frees itself.
+    fn drop(&mut self) {
+        if let Some(fd) = self.file {
+            let _ = file::close(fd);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::memory::{PageFlags, FRAME_SIZE};
+
+/// The most regions that a process can have. Anonymous mappings next to each other are merged,
+/// so this is mostly taken up by the segments of the program.
+pub const MAX_REGIONS: usize = 32;
+
+/// A part of a process's address space that pages are mapped into when they are first used,
+/// rather than when the region is created.
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    /// The first address of the region, which doesn't have to be aligned to a page, e.g. for the
+    /// segments of a program.
+    pub start: u64,
+
+    /// The first address past the end of the region.
+    pub end: u64,
+
+    pub flags: PageFlags,
+    pub backing: Backing,
+}
+
+/// Where the contents of the pages of a [Region] come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backing {
+    /// Zeroed pages, e.g. from `mmap`.
+    Anonymous,
+
+    /// The first [size] bytes come from the process's file at [offset], and the rest is zeroed,
+    /// like the `.bss` at the end of a segment.
+    File { offset: u64, size: u64 },
+
+    /// Zeroed pages, and the region grows down a page at a time when the page below it is used,
+    /// down to [limit].
+    Stack { limit: u64 },
+}
+
+/// The regions of a process, which don't overlap each other, except that regions of a program's
+/// segments may share a page at their edges.
+#[derive(Debug, Clone, Copy)]
+pub struct Regions {
+    entries: [Option<Region>; MAX_REGIONS],
+}
+
+impl Region {
+    /// Whether any of the region is in the page at [page].
+    pub fn overlaps(&self, page: u64) -> bool {
+        self.start < page + FRAME_SIZE && self.end > page
+    }
+}
+
+impl Regions {
+    pub const fn new() -> Regions {
+        Regions {
+            entries: [None; MAX_REGIONS],
+        }
+    }
+
+    /// Adds [region], or extends the anonymous region that ends where it starts. Returns [None]
+    /// if there are already [MAX_REGIONS].
+    pub fn add(&mut self, region: Region) -> Option<()> {
+        if region.backing == Backing::Anonymous {
+            let previous = self.entries.iter_mut().flatten().find(|it| {
+                it.backing == Backing::Anonymous
+                    && it.flags == region.flags
+                    && it.end == region.start
+            });
+
+            if let Some(previous) = previous {
+                previous.end = region.end;
+                return Some(());
+            }
+        }
+
+        let slot = self.entries.iter_mut().find(|it| it.is_none())?;
+        *slot = Some(region);
+        Some(())
+    }
+
+    /// The regions that are at least partly in the page at [page].
+    pub fn overlapping(&self, page: u64) -> impl Iterator<Item = &Region> + Clone {
+        self.entries
+            .iter()
+            .flatten()
+            .filter(move |it| it.overlaps(page))
+    }
+
+    /// Grows the stack that [page] is below, if it may grow that far, and returns it.
+    pub fn grow_stack(&mut self, page: u64) -> Option<&Region> {
+        let region = self
+            .entries
+            .iter_mut()
+            .flatten()
+            .find(|it| match it.backing {
+                Backing::Stack { limit } => (limit..it.start).contains(&page),
+                _ => false,
+            })?;
+
+        region.start = page;
+        Some(region)
+    }
+}
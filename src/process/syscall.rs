@@ -0,0 +1,343 @@
+use super::{
+    loader::{STACK_LIMIT, STACK_TOP},
+    region::{Backing, Region},
+    user_memory::{copy_from_user, copy_to_user},
+    Pid,
+};
+use crate::{
+    arch::aarch64::exception::TrapFrame,
+    console,
+    memory::{PageFlags, FRAME_SIZE},
+    println, scheduler,
+};
+use core::time::Duration;
+
+/// The system calls, which use the numbers and arguments of Linux on AArch64, so that programs
+/// built with a Linux toolchain work.
+///
+/// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/unistd.h
+const SYSCALLS: [Syscall; 11] = [
+    Syscall::new(63, read),
+    Syscall::new(64, write),
+    Syscall::new(93, exit),
+    Syscall::new(94, exit), // exit_group
+    Syscall::new(101, nanosleep),
+    Syscall::new(124, sched_yield),
+    Syscall::new(172, getpid),
+    Syscall::new(173, getppid),
+    Syscall::with_frame(220, clone),
+    Syscall::new(222, mmap),
+    Syscall::new(260, wait4),
+];
+
+/// How often [read] checks the console for input while it waits.
+const CONSOLE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The size of the chunks that [write] copies out of the process at a time.
+const WRITE_CHUNK_SIZE: usize = 256;
+
+const STDIN: u64 = 0;
+const STDOUT: u64 = 1;
+const STDERR: u64 = 2;
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/mman-common.h
+const PROT_WRITE: u64 = 0x2;
+const PROT_EXEC: u64 = 0x4;
+const MAP_FIXED: u64 = 0x10;
+const MAP_ANONYMOUS: u64 = 0x20;
+
+/// The signal that a child sends its parent when it exits, which is the only thing that `clone`
+/// may be asked for, as `fork` does.
+const SIGCHLD: u64 = 17;
+
+/// Makes `wait4` return straight away if no child has exited yet.
+const WNOHANG: u64 = 1;
+
+/// Represents the reason that a system call failed, which is returned to the process negated.
+///
+/// https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/errno-base.h
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i64)]
+pub enum Errno {
+    /// `EPERM`: the operation isn't permitted.
+    NotPermitted = 1,
+
+    /// `EIO`: an I/O error occurred.
+    Io = 5,
+
+    /// `EBADF`: the file descriptor isn't open, or can't be used like that.
+    BadFileDescriptor = 9,
+
+    /// `ECHILD`: there is no child process to wait for.
+    NoChild = 10,
+
+    /// `EAGAIN`: the operation would block.
+    Again = 11,
+
+    /// `ENOMEM`: there isn't enough memory.
+    OutOfMemory = 12,
+
+    /// `EACCES`: the access isn't allowed, e.g. memory that is both writable and executable.
+    AccessDenied = 13,
+
+    /// `EFAULT`: an address that was passed in isn't mapped in the process.
+    Fault = 14,
+
+    /// `ENODEV`: the operation isn't supported by the device.
+    NoDevice = 19,
+
+    /// `EINVAL`: an argument is invalid.
+    InvalidArgument = 22,
+
+    /// `ENOSYS`: there is no system call with that number.
+    NotImplemented = 38,
+}
+
+/// How a system call is handled: most only need their arguments, but some need all of the
+/// caller's registers, e.g. to copy them.
+enum Handler {
+    Arguments(fn(&[u64; 6]) -> Result<u64, Errno>),
+    Frame(fn(&TrapFrame, &[u64; 6]) -> Result<u64, Errno>),
+}
+
+/// An entry in [SYSCALLS].
+struct Syscall {
+    number: u64,
+    handler: Handler,
+}
+
+impl Syscall {
+    const fn new(number: u64, handler: fn(&[u64; 6]) -> Result<u64, Errno>) -> Syscall {
+        Syscall {
+            number,
+            handler: Handler::Arguments(handler),
+        }
+    }
+
+    const fn with_frame(
+        number: u64,
+        handler: fn(&TrapFrame, &[u64; 6]) -> Result<u64, Errno>,
+    ) -> Syscall {
+        Syscall {
+            number,
+            handler: Handler::Frame(handler),
+        }
+    }
+}
+
+/// Handles an `svc` from EL0: the number is in x8, the arguments are in x0 to x5, and the result
+/// is returned in x0, or `-errno` if it failed.
+///
+/// IRQs are masked while this runs, unless a handler unmasks them to wait.
+pub fn handle(frame: &mut TrapFrame) {
+    let number = frame.registers[8];
+    let mut arguments = [0; 6];
+    arguments.copy_from_slice(&frame.registers[..6]);
+
+    let result = match SYSCALLS.iter().find(|it| it.number == number) {
+        Some(syscall) => match syscall.handler {
+            Handler::Arguments(handler) => handler(&arguments),
+            Handler::Frame(handler) => handler(frame, &arguments),
+        },
+        None => {
+            println!(
+                "[angeldust::syscall] unknown system call {} at {:#x}",
+                number, frame.elr
+            );
+            Err(Errno::NotImplemented)
+        }
+    };
+
+    frame.registers[0] = match result {
+        Ok(value) => value,
+        Err(errno) => (-(errno as i64)) as u64,
+    };
+}
+
+/// `read(fd, buffer, count)`: waits for at least one byte from the console, then returns as many
+/// as have arrived, up to [count]. Carriage returns are read as newlines, and everything is echoed.
+fn read(arguments: &[u64; 6]) -> Result<u64, Errno> {
+    let [fd, buffer, count, ..] = *arguments;
+    if fd != STDIN {
+        return Err(Errno::BadFileDescriptor);
+    }
+
+    if count == 0 {
+        return Ok(0);
+    }
+
+    let first = loop {
+        match console::read_byte() {
+            Some(byte) => break byte,
+            None => scheduler::sleep(CONSOLE_POLL_INTERVAL),
+        }
+    };
+    let mut bytes = [0u8; WRITE_CHUNK_SIZE];
+    let mut length = 0;
+    let mut next = Some(first);
+    while let Some(byte) = next {
+        bytes[length] = if byte == b'\r' { b'\n' } else { byte };
+        length += 1;
+        if length as u64 == count || length == bytes.len() || bytes[length - 1] == b'\n' {
+            break;
+        }
+
+        next = console::read_byte();
+    }
+
+    console::write_bytes(&bytes[..length]);
+    copy_to_user(buffer, &bytes[..length])?;
+    Ok(length as u64)
+}
+
+/// `write(fd, buffer, count)`: writes to the console, for standard output and standard error.
+fn write(arguments: &[u64; 6]) -> Result<u64, Errno> {
+    let [fd, buffer, count, ..] = *arguments;
+    if fd != STDOUT && fd != STDERR {
+        return Err(Errno::BadFileDescriptor);
+    }
+
+    let mut chunk = [0u8; WRITE_CHUNK_SIZE];
+    let mut written = 0;
+    while written < count {
+        let length = (count - written).min(WRITE_CHUNK_SIZE as u64) as usize;
+        copy_from_user(buffer.wrapping_add(written), &mut chunk[..length])?;
+        console::write_bytes(&chunk[..length]);
+        written += length as u64;
+    }
+
+    Ok(count)
+}
+
+/// `exit(status)`: ends the process, and never returns to it.
+fn exit(arguments: &[u64; 6]) -> Result<u64, Errno> {
+    super::exit(arguments[0] as i32)
+}
+
+/// `nanosleep(duration, remaining)`: waits for [duration], a `struct timespec`. It is never
+/// interrupted, so [remaining] is left as it is.
+fn nanosleep(arguments: &[u64; 6]) -> Result<u64, Errno> {
+    let mut timespec = [0u8; 16];
+    copy_from_user(arguments[0], &mut timespec)?;
+
+    let mut seconds = [0u8; 8];
+    let mut nanoseconds = [0u8; 8];
+    seconds.copy_from_slice(&timespec[..8]);
+    nanoseconds.copy_from_slice(&timespec[8..]);
+
+    let seconds = i64::from_le_bytes(seconds);
+    let nanoseconds = i64::from_le_bytes(nanoseconds);
+    if seconds < 0 || !(0..1_000_000_000).contains(&nanoseconds) {
+        return Err(Errno::InvalidArgument);
+    }
+
+    scheduler::sleep(Duration::new(seconds as u64, nanoseconds as u32));
+    Ok(0)
+}
+
+/// `sched_yield()`: lets the other kernel threads run first.
+fn sched_yield(_arguments: &[u64; 6]) -> Result<u64, Errno> {
+    scheduler::yield_now();
+    Ok(0)
+}
+
+/// `getpid()`
+fn getpid(_arguments: &[u64; 6]) -> Result<u64, Errno> {
+    super::with_current(|process| process.pid.0 as u64).ok_or(Errno::NotPermitted)
+}
+
+/// `getppid()`: returns 0 for a process that has no parent, like Linux does for the first one.
+fn getppid(_arguments: &[u64; 6]) -> Result<u64, Errno> {
+    super::with_current(|process| process.parent.map_or(0, |it| it.0 as u64))
+        .ok_or(Errno::NotPermitted)
+}
+
+/// `clone(flags, stack, parent_tid, tls, child_tid)`: only forks the process, which is what C
+/// libraries do for `fork` with [flags] set to [SIGCHLD] and no [stack]. Threads aren't supported.
+fn clone(frame: &TrapFrame, arguments: &[u64; 6]) -> Result<u64, Errno> {
+    let [flags, stack, ..] = *arguments;
+    if flags != SIGCHLD || stack != 0 {
+        return Err(Errno::InvalidArgument);
+    }
+
+    super::fork(frame).map(|it| it.0 as u64)
+}
+
+/// `wait4(pid, status, options, usage)`: waits for the child [pid] to exit, or any child if it is
+/// -1, and writes its status to [status] unless that is null. Process groups and [usage] aren't
+/// supported, and the only option is [WNOHANG].
+fn wait4(arguments: &[u64; 6]) -> Result<u64, Errno> {
+    let [pid, status, options, ..] = *arguments;
+    let pid = match pid as i32 {
+        -1 => None,
+        pid if pid > 0 => Some(Pid(pid as u32)),
+        _ => return Err(Errno::InvalidArgument),
+    };
+
+    if options & !WNOHANG != 0 {
+        return Err(Errno::InvalidArgument);
+    }
+
+    let Some((child, child_status)) = super::wait(pid, options & WNOHANG == 0)? else {
+        return Ok(0);
+    };
+
+    if status != 0 {
+        copy_to_user(status, &child_status.to_le_bytes())?;
+    }
+
+    Ok(child.0 as u64)
+}
+
+/// `mmap(address, length, protection, flags, fd, offset)`: reserves zeroed pages anywhere in the
+/// process, which are mapped when they are first used. Only private anonymous mappings are
+/// supported, and [address] is only a hint, which is ignored.
+fn mmap(arguments: &[u64; 6]) -> Result<u64, Errno> {
+    let [_, length, protection, flags, ..] = *arguments;
+    if flags & MAP_ANONYMOUS == 0 {
+        return Err(Errno::NoDevice);
+    }
+
+    if flags & MAP_FIXED != 0 || length == 0 {
+        return Err(Errno::InvalidArgument);
+    }
+
+    let length = length
+        .checked_next_multiple_of(FRAME_SIZE)
+        .ok_or(Errno::OutOfMemory)?;
+
+    let mut page_flags = PageFlags::User;
+    if protection & PROT_WRITE != 0 {
+        page_flags |= PageFlags::Write;
+    }
+
+    if protection & PROT_EXEC != 0 {
+        page_flags |= PageFlags::Execute;
+    }
+
+    if page_flags.contains(PageFlags::Write | PageFlags::Execute) {
+        return Err(Errno::AccessDenied);
+    }
+
+    super::with_current(|process| {
+        let start = process.mmap_next;
+        let end = start
+            .checked_add(length)
+            .filter(|it| *it <= STACK_TOP - STACK_LIMIT)
+            .ok_or(Errno::OutOfMemory)?;
+
+        process
+            .regions
+            .add(Region {
+                start,
+                end,
+                flags: page_flags,
+                backing: Backing::Anonymous,
+            })
+            .ok_or(Errno::OutOfMemory)?;
+
+        process.mmap_next = end;
+        Ok(start)
+    })
+    .ok_or(Errno::NotPermitted)?
+}
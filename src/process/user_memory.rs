@@ -0,0 +1,59 @@
+use super::{fault::Access, syscall::Errno};
+use crate::memory::paging::{USER_END, USER_START};
+
+/// Copies [buffer.len()] bytes from the current process's memory at [address] into [buffer].
+///
+/// Pages that the process hasn't used yet are faulted in like the process would, and if any of
+/// it can't be read from EL0 this fails with [Errno::Fault], rather than faulting the kernel.
+pub fn copy_from_user(address: u64, buffer: &mut [u8]) -> Result<(), Errno> {
+    check_range(address, buffer.len())?;
+
+    super::with_current(|process| {
+        let mut address = address;
+        let mut buffer = buffer;
+        while !buffer.is_empty() {
+            let bytes = process.user_bytes(address, Access::Read)?;
+            let length = bytes.len().min(buffer.len());
+            buffer[..length].copy_from_slice(&bytes[..length]);
+
+            address += length as u64;
+            buffer = &mut buffer[length..];
+        }
+
+        Ok(())
+    })
+    .unwrap_or(Err(Errno::Fault))
+}
+
+/// Copies [data] into the current process's memory at [address], which must be writable from EL0.
+/// Copy-on-write pages are copied first.
+pub fn copy_to_user(address: u64, data: &[u8]) -> Result<(), Errno> {
+    check_range(address, data.len())?;
+
+    super::with_current(|process| {
+        let mut address = address;
+        let mut data = data;
+        while !data.is_empty() {
+            let bytes = process.user_bytes(address, Access::Write)?;
+            let length = bytes.len().min(data.len());
+            bytes[..length].copy_from_slice(&data[..length]);
+
+            address += length as u64;
+            data = &data[length..];
+        }
+
+        Ok(())
+    })
+    .unwrap_or(Err(Errno::Fault))
+}
+
+/// Makes sure that [length] bytes at [address] are within user space, without overflowing.
+fn check_range(address: u64, length: usize) -> Result<(), Errno> {
+    let end = address.checked_add(length as u64).ok_or(Errno::Fault)?;
+
+    if address < USER_START || end > USER_END {
+        return Err(Errno::Fault);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,779 @@
+pub mod run_queue;
+pub mod thread;
+
+pub use thread::{Affinity, Priority, ThreadId, ThreadState};
+
+use self::thread::{Stack, Thread, STACK_SIZE};
+use crate::{
+    arch::aarch64::{
+        context::{self, Context},
+        daif, mmu,
+    },
+    cpu::{
+        ipi::{self, Message},
+        percpu::{self, MAX_CORES},
+    },
+    memory::{paging, FRAME_SIZE},
+    mutex::Mutex,
+    println,
+    timer::{self, TICK_FREQUENCY},
+};
+use core::{
+    arch::asm,
+    hint,
+    mem::ManuallyDrop,
+    ptr::addr_of_mut,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+/// The maximum amount of threads, including the one that the kernel booted on and the idle
+/// threads.
+pub const MAX_THREADS: usize = 16;
+
+/// The amount of ticks that a thread may run for before another thread of the same priority gets
+/// a turn.
+const TIME_SLICE: u64 = 2;
+
+/// The slot of the thread that the kernel booted on, which keeps the stack from `boot.S`.
+const BOOT_SLOT: usize = 0;
+
+const IDLE_NAMES: [&str; MAX_CORES] = ["idle0", "idle1", "idle2", "idle3"];
+
+/// Represents an error that can occur when spawning a thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchedulerError {
+    /// Occurs when there are already [MAX_THREADS] threads.
+    TooManyThreads,
+}
+
+/// Allows waiting for a thread to finish with [JoinHandle::join]. Dropping it detaches the thread
+/// instead, so that it is forgotten once it finishes.
+#[derive(Debug)]
+pub struct JoinHandle {
+    id: ThreadId,
+    slot: usize,
+}
+
+/// A thread that stopped with [block], which [unblock] wakes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Waiter {
+    slot: usize,
+
+    /// Makes sure that a thread that took over the slot isn't woken by mistake.
+    id: ThreadId,
+}
+
+/// What [threads] reports about each thread.
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadInfo {
+    pub id: ThreadId,
+    pub name: &'static str,
+    pub priority: Priority,
+    pub state: ThreadState,
+
+    /// The core that the thread is running on, or last ran on.
+    pub core: usize,
+
+    /// The amount of ticks that the thread has been running for.
+    pub ticks: u64,
+}
+
+/// What [cpus] reports about each core.
+#[derive(Debug, Clone, Copy)]
+pub struct CpuInfo {
+    pub online: bool,
+
+    /// The name of the thread that the core is running.
+    pub current: &'static str,
+
+    /// The amount of threads that are waiting to run on the core.
+    pub queued: usize,
+    pub idle_ticks: u64,
+    pub busy_ticks: u64,
+}
+
+struct Scheduler {
+    threads: [Option<Thread>; MAX_THREADS],
+    next_id: u32,
+
+    /// The kernel's own address space, which new threads start in.
+    kernel_table: u64,
+}
+
+/// The threads, which are shared by every core. The run queues are in each core's
+/// [percpu::PerCpu], and are only locked while this is locked.
+static SCHEDULER: Mutex<Scheduler> = Mutex::new(Scheduler::new());
+
+/// Whether the thread in each slot is on a core, which includes the time it takes to save its
+/// registers after it was switched out. A thread can't be switched to while this is set.
+static ON_CPU: [AtomicBool; MAX_THREADS] = [const { AtomicBool::new(false) }; MAX_THREADS];
+
+/// The stacks of every thread but the one that the kernel booted on.
+static mut STACKS: [Stack; MAX_THREADS - 1] = [const {
+    Stack {
+        guard: [0; FRAME_SIZE as usize],
+        bytes: [0; STACK_SIZE],
+    }
+}; MAX_THREADS - 1];
+
+/// Turns the code that is running into the "init" thread, and starts scheduling threads on this
+/// core on every timer tick.
+///
+/// The MMU must be enabled, and [timer::initialize] must have been called, before this.
+pub fn initialize() {
+    daif::without_irqs(|| {
+        let cpu = percpu::this();
+        let mut scheduler = SCHEDULER.lock();
+        let id = scheduler.next_id();
+        scheduler.kernel_table = mmu::current_table();
+        scheduler.threads[BOOT_SLOT] = Some(Thread {
+            id,
+            name: "init",
+            priority: Priority::Normal,
+            state: ThreadState::Running,
+            affinity: Affinity::all(),
+            core: cpu.core,
+            context: Context::empty(),
+            entry: None,
+            joiner: None,
+            detached: true,
+            ticks: 0,
+        });
+
+        ON_CPU[BOOT_SLOT].store(true, Ordering::Relaxed);
+        cpu.current.store(BOOT_SLOT, Ordering::Relaxed);
+        cpu.slice.store(TIME_SLICE, Ordering::Relaxed);
+    });
+
+    for slot in BOOT_SLOT + 1..MAX_THREADS {
+        // Safety: Only the address is taken, and no thread uses a guard page.
+        let guard = unsafe { addr_of_mut!(STACKS[slot - 1].guard) };
+        paging::add_guard_page(guard as u64).expect("paging::add_guard_page() failed");
+    }
+
+    // There must always be something to run, which starts when nothing else is ready.
+    add_core(percpu::this().core).expect("scheduler::add_core() failed");
+    percpu::this().online.store(true, Ordering::Release);
+
+    println!(
+        "[angeldust::scheduler] started, {} threads of {} KiB stack at most",
+        MAX_THREADS,
+        STACK_SIZE / 1024
+    );
+}
+
+/// Creates the idle thread of [core], and returns the top of its stack. For cores other than
+/// the one that called [initialize], the core starts on this stack, and calls [start_core].
+pub fn add_core(core: usize) -> Result<u64, SchedulerError> {
+    daif::without_irqs(|| {
+        let mut scheduler = SCHEDULER.lock();
+        let slot = scheduler.create(
+            IDLE_NAMES[core],
+            Priority::Idle,
+            Affinity::core(core),
+            idle_entry,
+        )?;
+        scheduler.thread_mut(slot).detached = true;
+
+        percpu::get(core).idle_thread.store(slot, Ordering::Relaxed);
+        Ok(stack_top(slot))
+    })
+}
+
+/// Turns the code that is running on a newly started core into its idle thread, and starts
+/// scheduling threads on it.
+pub fn start_core() -> ! {
+    let cpu = percpu::this();
+
+    {
+        let mut scheduler = SCHEDULER.lock();
+        let slot = cpu.idle_thread();
+        scheduler.thread_mut(slot).state = ThreadState::Running;
+
+        ON_CPU[slot].store(true, Ordering::Relaxed);
+        cpu.current.store(slot, Ordering::Relaxed);
+    }
+
+    cpu.online.store(true, Ordering::Release);
+    daif::unmask_irqs();
+    idle()
+}
+
+/// Starts a thread called [name] that runs [entry] on any core.
+///
+/// If it has a higher priority than the calling thread, it runs straight away.
+pub fn spawn(
+    name: &'static str,
+    priority: Priority,
+    entry: fn(),
+) -> Result<JoinHandle, SchedulerError> {
+    spawn_on(name, priority, Affinity::all(), entry)
+}
+
+/// Starts a thread called [name] that runs [entry] on the cores in [affinity].
+pub fn spawn_on(
+    name: &'static str,
+    priority: Priority,
+    affinity: Affinity,
+    entry: fn(),
+) -> Result<JoinHandle, SchedulerError> {
+    assert!(
+        !affinity.is_empty(),
+        "a thread must be able to run somewhere"
+    );
+
+    let (handle, preempt) = daif::without_irqs(|| {
+        let mut scheduler = SCHEDULER.lock();
+        let slot = scheduler.create(name, priority, affinity, entry)?;
+        let id = scheduler.thread(slot).id;
+        scheduler.wake(slot);
+
+        let cpu = percpu::this();
+        let preempt = cpu.need_reschedule.load(Ordering::Relaxed);
+        Ok((JoinHandle { id, slot }, preempt))
+    })?;
+
+    if preempt {
+        yield_now();
+    }
+
+    Ok(handle)
+}
+
+/// Lets other threads of the same or a higher priority run before the calling thread continues.
+pub fn yield_now() {
+    daif::without_irqs(schedule);
+}
+
+/// Stops the calling thread for at least [duration], rounded up to a whole amount of ticks.
+pub fn sleep(duration: Duration) {
+    let tick_duration = 1_000_000_000 / TICK_FREQUENCY as u128;
+    let ticks = u64::try_from(duration.as_nanos().div_ceil(tick_duration))
+        .unwrap_or(u64::MAX)
+        .max(1);
+
+    daif::without_irqs(|| {
+        {
+            let mut scheduler = SCHEDULER.lock();
+            let current = percpu::this().current();
+            scheduler.thread_mut(current).state =
+                ThreadState::Sleeping(timer::ticks().saturating_add(ticks));
+        }
+
+        schedule();
+    });
+}
+
+/// Whether threads are being scheduled on the current core, so that the calling thread can
+/// [block]. This isn't the case before [initialize], in the idle thread, or in an interrupt
+/// handler, which have to spin instead.
+pub fn can_block() -> bool {
+    let cpu = percpu::this();
+    cpu.is_online() && cpu.current() != cpu.idle_thread() && !percpu::in_interrupt()
+}
+
+/// The slot of the calling thread, which identifies it until it exits, e.g. to keep per-thread
+/// state in an array of [MAX_THREADS] entries.
+pub fn current_slot() -> usize {
+    percpu::this().current()
+}
+
+/// Marks the calling thread as blocked, and returns what wakes it. The thread keeps running until
+/// it calls [block], but [unblock] may be called before that, in which case [block] returns
+/// straight away. IRQs must stay masked until [block] is called, see [can_block].
+pub fn prepare_to_block() -> Waiter {
+    let mut scheduler = SCHEDULER.lock();
+    let slot = percpu::this().current();
+    let thread = scheduler.thread_mut(slot);
+    thread.state = ThreadState::Blocked;
+
+    Waiter {
+        slot,
+        id: thread.id,
+    }
+}
+
+/// Stops the calling thread until it is woken with [unblock], after [prepare_to_block]. IRQs must
+/// be masked.
+pub fn block() {
+    schedule();
+}
+
+/// Wakes the thread that [waiter] came from, if it is still blocked. Returns whether it was.
+pub fn unblock(waiter: Waiter) -> bool {
+    daif::without_irqs(|| {
+        let mut scheduler = SCHEDULER.lock();
+        let blocked = scheduler.threads[waiter.slot]
+            .as_ref()
+            .is_some_and(|it| it.id == waiter.id && it.state == ThreadState::Blocked);
+
+        if blocked {
+            scheduler.wake(waiter.slot);
+        }
+
+        blocked
+    })
+}
+
+/// Changes the cores that the calling thread may run on, moving it to one of them if needed.
+pub fn set_affinity(affinity: Affinity) {
+    assert!(
+        !affinity.is_empty(),
+        "a thread must be able to run somewhere"
+    );
+
+    daif::without_irqs(|| {
+        let cpu = percpu::this();
+        SCHEDULER.lock().thread_mut(cpu.current()).affinity = affinity;
+
+        if !affinity.allows(cpu.core) {
+            schedule();
+        }
+    });
+}
+
+/// Finishes the calling thread, waking the thread that is joining it.
+pub fn exit() -> ! {
+    daif::mask_irqs();
+
+    {
+        let mut scheduler = SCHEDULER.lock();
+        let current = percpu::this().current();
+        let joiner = {
+            let thread = scheduler.thread_mut(current);
+            thread.state = ThreadState::Finished;
+            thread.joiner.take()
+        };
+
+        if let Some(joiner) = joiner {
+            scheduler.wake(joiner);
+        }
+    }
+
+    schedule();
+    unreachable!("a finished thread was scheduled");
+}
+
+/// Switches threads if something asked for it while an interrupt was handled. This is called at
+/// the end of every IRQ, with IRQs masked.
+pub fn preempt() {
+    if percpu::this()
+        .need_reschedule
+        .swap(false, Ordering::Relaxed)
+    {
+        schedule();
+    }
+}
+
+/// Called by the timer on every tick of every core, with IRQs masked. Wakes the threads whose
+/// sleep is over, and asks for a reschedule if the running thread's time slice is used up, or an
+/// idle core has something to do.
+pub fn tick() {
+    let cpu = percpu::this();
+    if !cpu.is_online() {
+        return;
+    }
+
+    let mut scheduler = SCHEDULER.lock();
+
+    // Core 0 counts the ticks, so it wakes the threads that were sleeping on any core.
+    if cpu.core == 0 {
+        let now = timer::ticks();
+        for slot in 0..MAX_THREADS {
+            let due = matches!(
+                scheduler.threads[slot].as_ref().map(|it| it.state),
+                Some(ThreadState::Sleeping(until)) if until <= now
+            );
+
+            if due {
+                scheduler.wake(slot);
+            }
+        }
+    }
+
+    let current = cpu.current();
+    if current == cpu.idle_thread() {
+        cpu.idle_ticks.fetch_add(1, Ordering::Relaxed);
+
+        // Threads that were queued here or on a busy core can run now.
+        if !cpu.run_queue.lock().is_empty() || scheduler.can_steal(cpu.core) {
+            cpu.need_reschedule.store(true, Ordering::Relaxed);
+        }
+
+        return;
+    }
+
+    cpu.busy_ticks.fetch_add(1, Ordering::Relaxed);
+    let thread = scheduler.thread_mut(current);
+    thread.ticks += 1;
+
+    let slice = cpu.slice.load(Ordering::Relaxed).saturating_sub(1);
+    cpu.slice.store(slice, Ordering::Relaxed);
+
+    let priority = thread.priority;
+    let waiting = cpu.run_queue.lock().highest_priority();
+    if slice == 0 && waiting.is_some_and(|it| it >= priority) {
+        cpu.need_reschedule.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Returns what each thread is doing, for the shell. Threads that finished without being joined
+/// are left out.
+pub fn threads() -> [Option<ThreadInfo>; MAX_THREADS] {
+    daif::without_irqs(|| {
+        SCHEDULER.lock().threads.each_ref().map(|thread| {
+            thread
+                .as_ref()
+                .filter(|it| !it.is_reapable())
+                .map(|it| ThreadInfo {
+                    id: it.id,
+                    name: it.name,
+                    priority: it.priority,
+                    state: it.state,
+                    core: it.core,
+                    ticks: it.ticks,
+                })
+        })
+    })
+}
+
+/// Returns what each core is doing, for the shell.
+pub fn cpus() -> [CpuInfo; MAX_CORES] {
+    daif::without_irqs(|| {
+        let scheduler = SCHEDULER.lock();
+        percpu::all().each_ref().map(|cpu| {
+            let online = cpu.is_online();
+            CpuInfo {
+                online,
+                current: if online {
+                    scheduler.thread(cpu.current()).name
+                } else {
+                    "-"
+                },
+                queued: cpu.run_queue.lock().len(),
+                idle_ticks: cpu.idle_ticks.load(Ordering::Relaxed),
+                busy_ticks: cpu.busy_ticks.load(Ordering::Relaxed),
+            }
+        })
+    })
+}
+
+impl JoinHandle {
+    /// The id of the thread, as [threads] reports it.
+    pub fn id(&self) -> ThreadId {
+        self.id
+    }
+
+    /// The slot of the thread, see [current_slot].
+    pub fn slot(&self) -> usize {
+        self.slot
+    }
+
+    /// Waits for the thread to finish.
+    pub fn join(self) {
+        let handle = ManuallyDrop::new(self);
+
+        daif::without_irqs(|| {
+            let finished = {
+                let mut scheduler = SCHEDULER.lock();
+                let current = percpu::this().current();
+                assert_ne!(current, handle.slot, "a thread can't join itself");
+
+                let target = scheduler.thread_mut(handle.slot);
+                if target.state == ThreadState::Finished {
+                    true
+                } else {
+                    target.joiner = Some(current);
+                    scheduler.thread_mut(current).state = ThreadState::Joining(handle.id);
+                    false
+                }
+            };
+
+            if !finished {
+                schedule();
+            }
+
+            // The thread may still be saving its registers on another core.
+            while ON_CPU[handle.slot].load(Ordering::Acquire) {
+                hint::spin_loop();
+            }
+
+            SCHEDULER.lock().threads[handle.slot] = None;
+        });
+    }
+}
+
+impl Drop for JoinHandle {
+    fn drop(&mut self) {
+        daif::without_irqs(|| {
+            if let Some(thread) = SCHEDULER.lock().threads[self.slot].as_mut() {
+                thread.detached = true;
+            }
+        });
+    }
+}
+
+impl Scheduler {
+    const fn new() -> Scheduler {
+        Scheduler {
+            threads: [const { None }; MAX_THREADS],
+            next_id: 0,
+            kernel_table: 0,
+        }
+    }
+
+    fn next_id(&mut self) -> ThreadId {
+        let id = ThreadId(self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn thread(&self, slot: usize) -> &Thread {
+        self.threads[slot]
+            .as_ref()
+            .expect("scheduler::initialize() was called")
+    }
+
+    fn thread_mut(&mut self, slot: usize) -> &mut Thread {
+        self.threads[slot]
+            .as_mut()
+            .expect("scheduler::initialize() was called")
+    }
+
+    /// Creates a thread that will run [entry] once it is woken, and returns its slot.
+    fn create(
+        &mut self,
+        name: &'static str,
+        priority: Priority,
+        affinity: Affinity,
+        entry: fn(),
+    ) -> Result<usize, SchedulerError> {
+        // The boot thread's slot is never reused, as it has no stack in [STACKS].
+        let slot = (BOOT_SLOT + 1..MAX_THREADS)
+            .find(|it| self.is_free(*it))
+            .ok_or(SchedulerError::TooManyThreads)?;
+
+        let id = self.next_id();
+        let context = Context::new(thread_start, slot, stack_top(slot), self.kernel_table);
+        self.threads[slot] = Some(Thread {
+            id,
+            name,
+            priority,
+            state: ThreadState::Ready,
+            affinity,
+            core: (0..MAX_CORES).find(|it| affinity.allows(*it)).unwrap_or(0),
+            context,
+            entry: Some(entry),
+            joiner: None,
+            detached: false,
+            ticks: 0,
+        });
+
+        Ok(slot)
+    }
+
+    /// Whether [slot] can be given to a new thread.
+    fn is_free(&self, slot: usize) -> bool {
+        self.threads[slot]
+            .as_ref()
+            .is_none_or(|it| it.is_reapable() && !ON_CPU[slot].load(Ordering::Acquire))
+    }
+
+    /// Makes the thread in [slot] ready to run, and queues it on the core that should run it. That
+    /// core is asked to reschedule if the thread should run before the one it is running.
+    fn wake(&mut self, slot: usize) {
+        let core = self.place(slot);
+        let thread = self.thread_mut(slot);
+        thread.state = ThreadState::Ready;
+        thread.core = core;
+
+        let priority = thread.priority;
+        let cpu = percpu::get(core);
+        cpu.run_queue.lock().push(slot, priority);
+
+        let current = cpu.current();
+        if current == cpu.idle_thread() || priority > self.thread(current).priority {
+            kick(core);
+        }
+    }
+
+    /// Chooses the core that should run the thread in [slot]: an idle core if there is one,
+    /// preferably the one that it last ran on, or else the core that it last ran on.
+    fn place(&self, slot: usize) -> usize {
+        let thread = self.thread(slot);
+        let allowed = |core: usize| thread.affinity.allows(core) && percpu::get(core).is_online();
+        let idle = |core: usize| {
+            let cpu = percpu::get(core);
+            cpu.current() == cpu.idle_thread() && cpu.run_queue.lock().is_empty()
+        };
+
+        if allowed(thread.core) && idle(thread.core) {
+            return thread.core;
+        }
+
+        (0..MAX_CORES)
+            .find(|it| allowed(*it) && idle(*it))
+            .or_else(|| allowed(thread.core).then_some(thread.core))
+            .or_else(|| (0..MAX_CORES).find(|it| allowed(*it)))
+            .unwrap_or(thread.core)
+    }
+
+    /// Takes a thread that [core] may run from the run queue of another core, starting with the
+    /// one that has the most threads waiting.
+    fn steal(&self, core: usize) -> Option<usize> {
+        let mut victims = [0; MAX_CORES];
+        let mut lengths = [0; MAX_CORES];
+        for (index, cpu) in percpu::all().iter().enumerate() {
+            victims[index] = index;
+            lengths[index] = cpu.run_queue.lock().len();
+        }
+
+        victims.sort_unstable_by_key(|it| core::cmp::Reverse(lengths[*it]));
+        victims
+            .into_iter()
+            .filter(|it| *it != core && lengths[*it] > 0)
+            .find_map(|victim| {
+                percpu::get(victim)
+                    .run_queue
+                    .lock()
+                    .steal(|slot| self.thread(slot).affinity.allows(core))
+            })
+    }
+
+    /// Whether [steal] would find something for [core].
+    fn can_steal(&self, core: usize) -> bool {
+        percpu::all()
+            .iter()
+            .filter(|it| it.core != core)
+            .any(|cpu| {
+                cpu.run_queue
+                    .lock()
+                    .iter()
+                    .any(|slot| self.thread(slot).affinity.allows(core))
+            })
+    }
+}
+
+/// Switches the current core to the thread that should run next: the first one in its run queue,
+/// one stolen from another core, or its idle thread. This may be the calling thread, if it is
+/// still ready to run. IRQs must be masked.
+fn schedule() {
+    let cpu = percpu::this();
+
+    let switch = {
+        let mut scheduler = SCHEDULER.lock();
+        let current = cpu.current();
+        let idle = cpu.idle_thread();
+
+        if current == idle {
+            scheduler.thread_mut(current).state = ThreadState::Ready;
+        } else if scheduler.thread(current).state == ThreadState::Running {
+            let thread = scheduler.thread(current);
+            if thread.affinity.allows(cpu.core) {
+                let priority = thread.priority;
+                scheduler.thread_mut(current).state = ThreadState::Ready;
+                cpu.run_queue.lock().push(current, priority);
+            } else {
+                scheduler.wake(current);
+            }
+        }
+
+        let queued = cpu.run_queue.lock().pop();
+        let next = queued.or_else(|| scheduler.steal(cpu.core)).unwrap_or(idle);
+
+        let thread = scheduler.thread_mut(next);
+        thread.state = ThreadState::Running;
+        thread.core = cpu.core;
+
+        cpu.current.store(next, Ordering::Relaxed);
+        cpu.slice.store(TIME_SLICE, Ordering::Relaxed);
+        cpu.need_reschedule.store(false, Ordering::Relaxed);
+
+        (next != current).then(|| {
+            (
+                current,
+                next,
+                &mut scheduler.thread_mut(current).context as *mut Context,
+                &scheduler.thread(next).context as *const Context,
+            )
+        })
+    };
+
+    if let Some((current, next, old, new)) = switch {
+        // The thread may have just been switched out on another core, which has to finish saving
+        // its registers first.
+        while ON_CPU[next].load(Ordering::Acquire) {
+            hint::spin_loop();
+        }
+
+        ON_CPU[next].store(true, Ordering::Relaxed);
+        cpu.previous.store(current, Ordering::Relaxed);
+
+        // Safety: IRQs are masked, and nothing else can switch to either thread until [ON_CPU]
+        // says that they are off their cores.
+        unsafe { context::switch(old, new) };
+
+        finish_switch();
+    }
+}
+
+/// Marks the thread that the current core switched away from as off the core. This is the first
+/// thing that a thread does once it is switched to, which may be on a different core than the
+/// one that switched away from it.
+fn finish_switch() {
+    let previous = percpu::this().previous.load(Ordering::Relaxed);
+    ON_CPU[previous].store(false, Ordering::Release);
+
+    // The thread's address space may not have been used on this core before.
+    paging::note_switch();
+}
+
+/// Asks [core] to reschedule, which it does once it handles the next interrupt. Other cores are
+/// interrupted straight away, rather than waiting for their next tick.
+fn kick(core: usize) {
+    let cpu = percpu::this();
+    if core == cpu.core {
+        cpu.need_reschedule.store(true, Ordering::Relaxed);
+    } else if let Err(error) = ipi::send(core, Message::Reschedule) {
+        println!(
+            "[angeldust::scheduler] failed to interrupt core {}: {:?}",
+            core, error
+        );
+    }
+}
+
+/// The top of the stack of the thread in [slot].
+fn stack_top(slot: usize) -> u64 {
+    // Safety: Only the address is taken, the stack is only used by the thread in [slot].
+    let stack = unsafe { addr_of_mut!(STACKS[slot - 1].bytes) };
+    stack as u64 + STACK_SIZE as u64
+}
+
+/// The first thing that every spawned thread runs.
+extern "C" fn thread_start(slot: usize) -> ! {
+    finish_switch();
+
+    let entry = SCHEDULER
+        .lock()
+        .thread(slot)
+        .entry
+        .expect("spawned threads have an entry point");
+
+    daif::unmask_irqs();
+    entry();
+    exit();
+}
+
+/// The entry point of the idle thread of cores that are started by [initialize].
+fn idle_entry() {
+    idle()
+}
+
+/// Runs whenever a core has nothing else to do, waiting for the next interrupt.
+fn idle() -> ! {
+    loop {
+        // Safety: `wfi` only waits for an interrupt.
+        unsafe { asm!("wfi") }
+    }
+}
@@ -0,0 +1,114 @@
+use super::{
+    thread::{Priority, PRIORITIES},
+    MAX_THREADS,
+};
+
+/// The threads that are ready to run on a core, with a queue for each [Priority].
+pub struct RunQueue {
+    queues: [Fifo; PRIORITIES],
+}
+
+/// The slots of the threads of one priority, in the order that they will run in.
+struct Fifo {
+    slots: [usize; MAX_THREADS],
+    head: usize,
+    length: usize,
+}
+
+impl RunQueue {
+    pub const fn new() -> RunQueue {
+        RunQueue {
+            queues: [const { Fifo::new() }; PRIORITIES],
+        }
+    }
+
+    /// Adds [slot] to the back of the queue for [priority].
+    pub fn push(&mut self, slot: usize, priority: Priority) {
+        self.queues[priority.index()].push(slot);
+    }
+
+    /// Removes the thread that should run next, from the front of the highest priority queue that
+    /// isn't empty.
+    pub fn pop(&mut self) -> Option<usize> {
+        self.queues.iter_mut().rev().find_map(Fifo::pop)
+    }
+
+    /// Removes the thread that should run next out of the ones that [allowed] accepts, e.g. so
+    /// that another core can run it.
+    pub fn steal(&mut self, mut allowed: impl FnMut(usize) -> bool) -> Option<usize> {
+        self.queues
+            .iter_mut()
+            .rev()
+            .find_map(|it| it.remove_first(&mut allowed))
+    }
+
+    /// The priority of the thread that would run next.
+    pub fn highest_priority(&self) -> Option<Priority> {
+        Priority::ALL
+            .into_iter()
+            .rev()
+            .find(|it| self.queues[it.index()].length > 0)
+    }
+
+    /// The slots of every thread that is waiting to run.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.queues.iter().flat_map(|queue| {
+            (0..queue.length).map(|it| queue.slots[(queue.head + it) % MAX_THREADS])
+        })
+    }
+
+    /// The amount of threads that are waiting to run.
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(|it| it.length).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Fifo {
+    const fn new() -> Fifo {
+        Fifo {
+            slots: [0; MAX_THREADS],
+            head: 0,
+            length: 0,
+        }
+    }
+
+    /// Adds [slot] to the back of the queue. Every thread is in at most one queue, so this never
+    /// overflows.
+    fn push(&mut self, slot: usize) {
+        assert!(self.length < MAX_THREADS, "run queue overflow");
+
+        self.slots[(self.head + self.length) % MAX_THREADS] = slot;
+        self.length += 1;
+    }
+
+    /// Removes the slot at the front of the queue.
+    fn pop(&mut self) -> Option<usize> {
+        if self.length == 0 {
+            return None;
+        }
+
+        let slot = self.slots[self.head];
+        self.head = (self.head + 1) % MAX_THREADS;
+        self.length -= 1;
+        Some(slot)
+    }
+
+    /// Removes the first slot that [matches] accepts, keeping the others in order.
+    fn remove_first(&mut self, matches: &mut impl FnMut(usize) -> bool) -> Option<usize> {
+        let index =
+            (0..self.length).find(|it| matches(self.slots[(self.head + it) % MAX_THREADS]))?;
+        let slot = self.slots[(self.head + index) % MAX_THREADS];
+
+        for it in index..self.length - 1 {
+            self.slots[(self.head + it) % MAX_THREADS] =
+                self.slots[(self.head + it + 1) % MAX_THREADS];
+        }
+
+        self.length -= 1;
+        Some(slot)
+    }
+}
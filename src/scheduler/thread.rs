@@ -0,0 +1,143 @@
+use crate::{arch::aarch64::context::Context, memory::FRAME_SIZE};
+use bitflags::bitflags;
+
+/// The size of each kernel thread's stack.
+pub const STACK_SIZE: usize = 64 * 1024;
+
+/// Identifies a thread, and is never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadId(pub u32);
+
+/// How urgently a thread wants to run. A thread only runs when no thread of a higher priority is
+/// ready, and threads of the same priority take turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    /// Only runs when there is nothing else to do.
+    Idle,
+    Low,
+    Normal,
+    High,
+}
+
+/// The amount of [Priority] levels, each of which has its own run queue.
+pub const PRIORITIES: usize = Priority::ALL.len();
+
+bitflags! {
+    /// The cores that a thread may run on.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Affinity: u8 {
+        const Core0 = 1 << 0;
+        const Core1 = 1 << 1;
+        const Core2 = 1 << 2;
+        const Core3 = 1 << 3;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    /// The thread is waiting in a run queue.
+    Ready,
+
+    /// The thread is the one that is running.
+    Running,
+
+    /// The thread is waiting until the tick.
+    Sleeping(u64),
+
+    /// The thread is waiting for another thread to finish.
+    Joining(ThreadId),
+
+    /// The thread is waiting to be woken by something else, e.g. a [crate::sync::WaitQueue].
+    Blocked,
+
+    /// The thread has returned from its entry point, and is waiting to be joined.
+    Finished,
+}
+
+/// A kernel thread, which has its own stack and registers.
+pub struct Thread {
+    pub id: ThreadId,
+    pub name: &'static str,
+    pub priority: Priority,
+    pub state: ThreadState,
+    pub affinity: Affinity,
+
+    /// The core that the thread is running on, or last ran on.
+    pub core: usize,
+    pub context: Context,
+
+    /// What the thread runs, or [None] for the thread that the kernel booted on.
+    pub entry: Option<fn()>,
+
+    /// The slot of the thread that is waiting to join this one.
+    pub joiner: Option<usize>,
+
+    /// Whether nothing will join this thread, so its slot is freed once it finishes.
+    pub detached: bool,
+
+    /// The amount of ticks that the thread has been running for.
+    pub ticks: u64,
+}
+
+/// A kernel thread's stack, above a page that is unmapped once the scheduler starts, so that an
+/// overflow faults rather than running into the stack below it.
+#[repr(C, align(4096))]
+pub struct Stack {
+    pub guard: [u8; FRAME_SIZE as usize],
+    pub bytes: [u8; STACK_SIZE],
+}
+
+impl Affinity {
+    /// Only [core].
+    pub const fn core(core: usize) -> Affinity {
+        Affinity::from_bits_truncate(1 << core)
+    }
+
+    pub const fn allows(self, core: usize) -> bool {
+        self.contains(Affinity::core(core))
+    }
+}
+
+impl Thread {
+    /// Whether the thread's slot can be given to a new thread.
+    pub fn is_reapable(&self) -> bool {
+        self.state == ThreadState::Finished && self.detached
+    }
+}
+
+impl Priority {
+    /// Every priority, from the lowest to the highest.
+    pub const ALL: [Priority; 4] = [
+        Priority::Idle,
+        Priority::Low,
+        Priority::Normal,
+        Priority::High,
+    ];
+
+    /// The index of the priority's run queue.
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Priority::Idle => "idle",
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        }
+    }
+}
+
+impl ThreadState {
+    pub const fn name(self) -> &'static str {
+        match self {
+            ThreadState::Ready => "ready",
+            ThreadState::Running => "running",
+            ThreadState::Sleeping(_) => "sleeping",
+            ThreadState::Joining(_) => "joining",
+            ThreadState::Blocked => "blocked",
+            ThreadState::Finished => "finished",
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{
+    console,
+    cpu::percpu,
+    fs,
+    io::{mailbox, power::pm},
+    memory, print, println,
+    scheduler::{self, Affinity, ThreadState},
+    timer,
+};
+use core::time::Duration;
+
+/// The longest line that can be typed.
+const MAX_LINE_LENGTH: usize = 128;
+
+/// How often the console is checked for input.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7F;
+
+/// A command that can be typed into the shell.
+struct Command {
+    name: &'static str,
+
+    /// What is expected after the name, e.g. `<path>`, for [help].
+    arguments: &'static str,
+
+    description: &'static str,
+
+    /// Runs the command with everything that was typed after its name.
+    run: fn(&str),
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        arguments: "",
+        description: "lists the commands",
+        run: |_| help(),
+    },
+    Command {
+        name: "threads",
+        arguments: "",
+        description: "lists the kernel threads and what they are doing",
+        run: |_| threads(),
+    },
+    Command {
+        name: "cpus",
+        arguments: "",
+        description: "lists the cores and how busy they have been",
+        run: |_| cpus(),
+    },
+    Command {
+        name: "pin",
+        arguments: "<core>",
+        description: "moves the shell to a core, and keeps it there",
+        run: pin,
+    },
+    Command {
+        name: "memory",
+        arguments: "",
+        description: "shows how much memory is free, and what is reserved",
+        run: |_| memory::report(),
+    },
+    Command {
+        name: "uptime",
+        arguments: "",
+        description: "shows how long the system has been running",
+        run: |_| uptime(),
+    },
+    Command {
+        name: "trace",
+        arguments: "",
+        description: "turns decoding every mailbox transaction on or off",
+        run: |_| trace(),
+    },
+    Command {
+        name: "df",
+        arguments: "",
+        description: "shows the mounted sd card partition, and how much space is free on it",
+        run: |_| fs::report(),
+    },
+    Command {
+        name: "ls",
+        arguments: "<path>",
+        description: "lists the entries of a directory",
+        run: fs::list,
+    },
+    Command {
+        name: "cat",
+        arguments: "<path>",
+        description: "prints a file",
+        run: fs::print,
+    },
+    Command {
+        name: "tail",
+        arguments: "<path>",
+        description: "prints the last lines of a file",
+        run: fs::print_tail,
+    },
+    Command {
+        name: "stat",
+        arguments: "<path>",
+        description: "shows what kind of entry a path is, and how large it is",
+        run: fs::print_metadata,
+    },
+    Command {
+        name: "append",
+        arguments: "<path> <text>",
+        description: "appends a line to a file, creating it if needed",
+        run: |arguments| {
+            let (path, text) = arguments.split_once(' ').unwrap_or((arguments, ""));
+            fs::append(path, text.trim_start())
+        },
+    },
+    Command {
+        name: "rm",
+        arguments: "<path>",
+        description: "removes a file or an empty directory",
+        run: fs::remove,
+    },
+    Command {
+        name: "sync",
+        arguments: "",
+        description: "writes pending changes back to the mounted filesystems",
+        run: |_| fs::sync(),
+    },
+    Command {
+        name: "reboot",
+        arguments: "",
+        description: "syncs the filesystems and reboots the board",
+        run: |_| pm::reboot(),
+    },
+    Command {
+        name: "halt",
+        arguments: "",
+        description: "syncs the filesystems and powers the board off",
+        run: |_| pm::halt(),
+    },
+];
+
+/// A very small shell on the console, for looking around the kernel while it runs. This is meant
+/// to be run in its own thread.
+pub fn run() {
+    println!("[angeldust::shell] type 'help' for a list of commands");
+
+    let mut line = [0u8; MAX_LINE_LENGTH];
+    loop {
+        print!("> ");
+        let length = read_line(&mut line);
+
+        // Everything that can be typed into the line is ASCII.
+        let line = core::str::from_utf8(&line[..length]).unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (name, arguments) = line.split_once(' ').unwrap_or((line, ""));
+        match COMMANDS.iter().find(|it| it.name == name) {
+            Some(command) => (command.run)(arguments.trim()),
+            None => println!(
+                "unknown command '{}', type 'help' for a list of commands",
+                name
+            ),
+        }
+    }
+}
+
+/// Reads a line into [line], echoing it as it is typed, and returns its length.
+fn read_line(line: &mut [u8]) -> usize {
+    let mut length = 0;
+    loop {
+        let Some(byte) = console::read_byte() else {
+            scheduler::sleep(POLL_INTERVAL);
+            continue;
+        };
+
+        match byte {
+            b'\r' | b'\n' => {
+                println!();
+                return length;
+            }
+
+            BACKSPACE | DELETE if length > 0 => {
+                length -= 1;
+                print!("\x08 \x08");
+            }
+
+            b' '..=b'~' if length < line.len() => {
+                line[length] = byte;
+                length += 1;
+                print!("{}", byte as char);
+            }
+
+            _ => {}
+        }
+    }
+}
+
+fn help() {
+    for command in COMMANDS {
+        println!(
+            "{:<8} {:<14} {}",
+            command.name, command.arguments, command.description
+        );
+    }
+}
+
+fn threads() {
+    println!(
+        "{:>4}  {:<16} {:<8} {:<10} {:>4} {:>8}",
+        "id", "name", "priority", "state", "core", "ticks"
+    );
+
+    for thread in scheduler::threads().iter().flatten() {
+        print!(
+            "{:>4}  {:<16} {:<8} {:<10} {:>4} {:>8}",
+            thread.id.0,
+            thread.name,
+            thread.priority.name(),
+            thread.state.name(),
+            thread.core,
+            thread.ticks
+        );
+
+        match thread.state {
+            ThreadState::Sleeping(until) => {
+                let ticks = until.saturating_sub(timer::ticks());
+                println!(
+                    "  (for another {} ms)",
+                    ticks * 1000 / timer::TICK_FREQUENCY
+                );
+            }
+            ThreadState::Joining(id) => println!("  (until thread {} finishes)", id.0),
+            _ => println!(),
+        }
+    }
+}
+
+fn cpus() {
+    println!(
+        "{:>4}  {:<7} {:<16} {:>6} {:>5}",
+        "core", "state", "running", "queued", "busy"
+    );
+
+    for (core, cpu) in scheduler::cpus().iter().enumerate() {
+        let ticks = cpu.idle_ticks + cpu.busy_ticks;
+        let busy = (cpu.busy_ticks * 100).checked_div(ticks).unwrap_or(0);
+
+        println!(
+            "{:>4}  {:<7} {:<16} {:>6} {:>4}%",
+            core,
+            if cpu.online { "online" } else { "offline" },
+            cpu.current,
+            cpu.queued,
+            busy
+        );
+    }
+}
+
+fn pin(arguments: &str) {
+    let cpus = scheduler::cpus();
+    let Some(core) = arguments
+        .parse::<usize>()
+        .ok()
+        .filter(|it| cpus.get(*it).is_some_and(|cpu| cpu.online))
+    else {
+        println!("'{}' is not an online core", arguments);
+        return;
+    };
+
+    scheduler::set_affinity(Affinity::core(core));
+    println!("the shell is running on core {}", percpu::this().core);
+}
+
+fn uptime() {
+    let uptime = timer::uptime();
+    println!(
+        "up {}.{:03}s, {} ticks",
+        uptime.as_secs(),
+        uptime.subsec_millis(),
+        timer::ticks()
+    );
+}
+
+fn trace() {
+    let enabled = !mailbox::is_tracing();
+    mailbox::set_tracing(enabled);
+    println!("mailbox tracing is {}", if enabled { "on" } else { "off" });
+}
@@ -0,0 +1,43 @@
+use super::{MutexGuard, WaitQueue};
+
+/// Lets threads sleep until another thread changes the data behind a [super::Mutex], and tells
+/// them about it with [Condvar::notify_all].
+pub struct Condvar {
+    waiters: WaitQueue,
+}
+
+impl Condvar {
+    pub const fn new() -> Condvar {
+        Condvar {
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Unlocks [guard]'s mutex and sleeps until this is notified, then locks the mutex again.
+    ///
+    /// This may return without being notified, so the caller has to check the data again, see
+    /// [Condvar::wait_while].
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        let mutex = guard.mutex();
+        self.waiters.wait_after(|| drop(guard));
+        mutex.lock()
+    }
+
+    /// Waits until [condition] returns false for the data behind [guard]'s mutex.
+    pub fn wait_while<'a, T>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) -> MutexGuard<'a, T> {
+        while condition(&mut guard) {
+            guard = self.wait(guard);
+        }
+
+        guard
+    }
+
+    /// Wakes every thread that is waiting.
+    pub fn notify_all(&self) {
+        self.waiters.wake_all();
+    }
+}
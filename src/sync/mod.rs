@@ -0,0 +1,12 @@
+pub mod condvar;
+pub mod mutex;
+pub mod once;
+pub mod rwlock;
+pub mod semaphore;
+pub mod wait_queue;
+
+pub use condvar::Condvar;
+pub use mutex::{Mutex, MutexGuard};
+pub use once::{Lazy, Once};
+pub use rwlock::RwLock;
+pub use wait_queue::WaitQueue;
@@ -0,0 +1,127 @@
+use super::WaitQueue;
+#[cfg(feature = "lockdep")]
+use crate::lockdep::{self, Held, LockKind};
+#[cfg(feature = "lockdep")]
+use core::panic::Location;
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// A mutex that puts the calling thread to sleep until it can be locked, rather than spinning
+/// like [crate::mutex::Mutex]. This is for data that is held for a long time, e.g. across a
+/// transfer to the SD card.
+///
+/// It must not be locked from an interrupt handler, which can't sleep.
+pub struct Mutex<T> {
+    is_locked: AtomicBool,
+    waiters: WaitQueue,
+    data: UnsafeCell<T>,
+}
+
+/// Gives access to the data of a [Mutex], and unlocks it when it is dropped.
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+
+    #[cfg(feature = "lockdep")]
+    held: Held,
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(data: T) -> Mutex<T> {
+        Mutex {
+            is_locked: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Locks this [Mutex], sleeping until the thread that holds it unlocks it.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        let held = lockdep::acquire(
+            self.address(),
+            LockKind::Sleeping,
+            Location::caller(),
+            false,
+        );
+
+        while !self.lock_once() {
+            self.waiters
+                .wait_until(|| !self.is_locked.load(Ordering::Relaxed));
+        }
+
+        MutexGuard {
+            mutex: self,
+            #[cfg(feature = "lockdep")]
+            held,
+        }
+    }
+
+    /// Locks this [Mutex] if nothing else holds it.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if !self.lock_once() {
+            return None;
+        }
+
+        Some(MutexGuard {
+            mutex: self,
+            #[cfg(feature = "lockdep")]
+            held: lockdep::acquire(self.address(), LockKind::Sleeping, Location::caller(), true),
+        })
+    }
+
+    fn lock_once(&self) -> bool {
+        self.is_locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    #[cfg(feature = "lockdep")]
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+impl<'a, T> MutexGuard<'a, T> {
+    /// The [Mutex] that this guard locks, e.g. so that a [super::condvar::Condvar] can lock it
+    /// again.
+    pub(super) fn mutex(&self) -> &'a Mutex<T> {
+        self.mutex
+    }
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::release(self.mutex.address(), self.held);
+
+        self.mutex.is_locked.store(false, Ordering::Release);
+        self.mutex.waiters.wake_one();
+    }
+}
+
+/// # Safety
+/// - The [Mutex] only gives access to the data of [T] to the thread that holds it.
+unsafe impl<T: Send> Send for Mutex<T> {}
+
+/// # Safety
+/// - The [Mutex] only gives access to the data of [T] to the thread that holds it.
+unsafe impl<T: Send> Sync for Mutex<T> {}
@@ -0,0 +1,95 @@
+use super::WaitQueue;
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ops::Deref,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+
+/// A value that is set once, e.g. by a driver's `initialize()`, and can then be read from
+/// anywhere without locking.
+pub struct Once<T> {
+    state: AtomicU8,
+    waiters: WaitQueue,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A value that is created the first time that it is used, by [Lazy::new]'s function.
+pub struct Lazy<T, F = fn() -> T> {
+    once: Once<T>,
+    initialize: F,
+}
+
+impl<T> Once<T> {
+    pub const fn new() -> Once<T> {
+        Once {
+            state: AtomicU8::new(INCOMPLETE),
+            waiters: WaitQueue::new(),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value, setting it to what [f] returns first if it isn't set yet. If another
+    /// thread is already running its own function, this waits for it instead.
+    pub fn call_once(&self, f: impl FnOnce() -> T) -> &T {
+        if self
+            .state
+            .compare_exchange(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Acquire)
+            .is_ok()
+        {
+            // Safety: Only the thread that moved the state to [RUNNING] writes the value.
+            unsafe { (*self.value.get()).write(f()) };
+            self.state.store(COMPLETE, Ordering::Release);
+            self.waiters.wake_all();
+        } else {
+            self.waiters
+                .wait_until(|| self.state.load(Ordering::Acquire) == COMPLETE);
+        }
+
+        // Safety: The state is [COMPLETE], so the value has been written.
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+
+    /// Sets the value, unless it was already set. Returns whether it was set by this call.
+    pub fn set(&self, value: T) -> bool {
+        let mut value = Some(value);
+        self.call_once(|| value.take().expect("the value is only taken once"));
+        value.is_none()
+    }
+
+    /// Returns the value, or [None] if it hasn't been set yet.
+    pub fn get(&self) -> Option<&T> {
+        // Safety: The state is [COMPLETE], so the value has been written.
+        (self.state.load(Ordering::Acquire) == COMPLETE)
+            .then(|| unsafe { (*self.value.get()).assume_init_ref() })
+    }
+}
+
+impl<T, F: Fn() -> T> Lazy<T, F> {
+    pub const fn new(initialize: F) -> Lazy<T, F> {
+        Lazy {
+            once: Once::new(),
+            initialize,
+        }
+    }
+}
+
+impl<T, F: Fn() -> T> Deref for Lazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.once.call_once(&self.initialize)
+    }
+}
+
+/// # Safety
+/// - The value of [T] is only written once, before any thread can read it.
+unsafe impl<T: Send> Send for Once<T> {}
+
+/// # Safety
+/// - The value of [T] is only written once, before any thread can read it.
+unsafe impl<T: Send + Sync> Sync for Once<T> {}
@@ -0,0 +1,163 @@
+use super::WaitQueue;
+#[cfg(feature = "lockdep")]
+use crate::lockdep::{self, Held, LockKind};
+#[cfg(feature = "lockdep")]
+use core::panic::Location;
+use core::{
+    cell::UnsafeCell,
+    ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The bit of [RwLock::state] that is set while a writer holds the lock. The other bits count
+/// the readers.
+const WRITER: usize = 1 << (usize::BITS - 1);
+
+/// A lock that many threads can read through at once, or a single thread can write through.
+/// Threads sleep until they can lock it, like with [super::Mutex].
+///
+/// Readers are let in whenever there is no writer, so a steady stream of readers can keep a
+/// writer waiting.
+pub struct RwLock<T> {
+    state: AtomicUsize,
+    waiters: WaitQueue,
+    data: UnsafeCell<T>,
+}
+
+/// Gives shared access to the data of a [RwLock].
+pub struct ReadGuard<'a, T> {
+    lock: &'a RwLock<T>,
+
+    #[cfg(feature = "lockdep")]
+    held: Held,
+}
+
+/// Gives exclusive access to the data of a [RwLock].
+pub struct WriteGuard<'a, T> {
+    lock: &'a RwLock<T>,
+
+    #[cfg(feature = "lockdep")]
+    held: Held,
+}
+
+impl<T> RwLock<T> {
+    pub const fn new(data: T) -> RwLock<T> {
+        RwLock {
+            state: AtomicUsize::new(0),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Locks this [RwLock] for reading, sleeping while a writer holds it.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub fn read(&self) -> ReadGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        let held = lockdep::acquire(self.address(), LockKind::Read, Location::caller(), false);
+
+        while !self.read_once() {
+            self.waiters
+                .wait_until(|| self.state.load(Ordering::Relaxed) & WRITER == 0);
+        }
+
+        ReadGuard {
+            lock: self,
+            #[cfg(feature = "lockdep")]
+            held,
+        }
+    }
+
+    /// Locks this [RwLock] for writing, sleeping while anything else holds it.
+    #[cfg_attr(feature = "lockdep", track_caller)]
+    pub fn write(&self) -> WriteGuard<'_, T> {
+        #[cfg(feature = "lockdep")]
+        let held = lockdep::acquire(
+            self.address(),
+            LockKind::Sleeping,
+            Location::caller(),
+            false,
+        );
+
+        while !self.write_once() {
+            self.waiters
+                .wait_until(|| self.state.load(Ordering::Relaxed) == 0);
+        }
+
+        WriteGuard {
+            lock: self,
+            #[cfg(feature = "lockdep")]
+            held,
+        }
+    }
+
+    fn read_once(&self) -> bool {
+        self.state
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |it| {
+                (it & WRITER == 0).then_some(it + 1)
+            })
+            .is_ok()
+    }
+
+    fn write_once(&self) -> bool {
+        self.state
+            .compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    #[cfg(feature = "lockdep")]
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+impl<T> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for ReadGuard<'_, T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::release(self.lock.address(), self.held);
+
+        // Only writers wait while there are readers, and they need every reader to be gone.
+        if self.lock.state.fetch_sub(1, Ordering::Release) == 1 {
+            self.lock.waiters.wake_all();
+        }
+    }
+}
+
+impl<T> Deref for WriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for WriteGuard<'_, T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        lockdep::release(self.lock.address(), self.held);
+
+        self.lock.state.store(0, Ordering::Release);
+        self.lock.waiters.wake_all();
+    }
+}
+
+/// # Safety
+/// - The [RwLock] only gives shared access to the data of [T] while no thread writes to it.
+unsafe impl<T: Send> Send for RwLock<T> {}
+
+/// # Safety
+/// - The [RwLock] only gives shared access to the data of [T] while no thread writes to it.
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
@@ -0,0 +1,44 @@
+use super::WaitQueue;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts how many of something are available, and puts threads to sleep until one is.
+pub struct Semaphore {
+    permits: AtomicUsize,
+    waiters: WaitQueue,
+}
+
+impl Semaphore {
+    /// Creates a [Semaphore] with [permits] available.
+    pub const fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            permits: AtomicUsize::new(permits),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    /// Takes a permit, sleeping until one is available.
+    pub fn acquire(&self) {
+        while !self.try_acquire() {
+            self.waiters
+                .wait_until(|| self.permits.load(Ordering::Relaxed) > 0);
+        }
+    }
+
+    /// Takes a permit if one is available.
+    pub fn try_acquire(&self) -> bool {
+        self.permits
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |it| it.checked_sub(1))
+            .is_ok()
+    }
+
+    /// Gives a permit back, waking a thread that is waiting for one.
+    pub fn release(&self) {
+        self.permits.fetch_add(1, Ordering::Release);
+        self.waiters.wake_one();
+    }
+
+    /// The amount of permits that are available.
+    pub fn available(&self) -> usize {
+        self.permits.load(Ordering::Relaxed)
+    }
+}
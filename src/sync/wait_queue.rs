@@ -0,0 +1,133 @@
+use crate::{
+    arch::aarch64::daif,
+    mutex,
+    scheduler::{self, Waiter, MAX_THREADS},
+};
+use core::hint;
+
+/// The threads that are waiting for something, e.g. a [super::Mutex] to be unlocked, which are
+/// woken in the order that they started waiting.
+///
+/// Code that can't block (see [scheduler::can_block]) spins instead, e.g. while the kernel is
+/// still booting.
+pub struct WaitQueue {
+    waiters: mutex::Mutex<Waiters>,
+}
+
+/// Every thread waits on at most one queue, so [MAX_THREADS] entries are always enough.
+struct Waiters {
+    entries: [Option<Waiter>; MAX_THREADS],
+    length: usize,
+}
+
+impl WaitQueue {
+    pub const fn new() -> WaitQueue {
+        WaitQueue {
+            waiters: mutex::Mutex::new(Waiters {
+                entries: [None; MAX_THREADS],
+                length: 0,
+            }),
+        }
+    }
+
+    /// Waits until [condition] returns true, checking it again whenever the thread is woken.
+    ///
+    /// The condition is checked with the queue locked and IRQs masked, so whatever makes it true
+    /// must call [WaitQueue::wake_one] or [WaitQueue::wake_all] afterwards for nothing to be
+    /// missed.
+    pub fn wait_until(&self, mut condition: impl FnMut() -> bool) {
+        loop {
+            let done = daif::without_irqs(|| {
+                let mut waiters = self.waiters.lock();
+                if condition() {
+                    return true;
+                }
+
+                if !scheduler::can_block() {
+                    drop(waiters);
+                    hint::spin_loop();
+                    return false;
+                }
+
+                waiters.push(scheduler::prepare_to_block());
+                drop(waiters);
+
+                scheduler::block();
+                false
+            });
+
+            if done {
+                return;
+            }
+        }
+    }
+
+    /// Waits until the thread is woken, after calling [release] once it is in the queue. This
+    /// may return without being woken, so the caller has to check whatever it waited for.
+    pub fn wait_after(&self, release: impl FnOnce()) {
+        daif::without_irqs(|| {
+            let mut waiters = self.waiters.lock();
+            if !scheduler::can_block() {
+                drop(waiters);
+                release();
+                return;
+            }
+
+            waiters.push(scheduler::prepare_to_block());
+            drop(waiters);
+
+            release();
+            scheduler::block();
+        });
+    }
+
+    /// Wakes the thread that has been waiting the longest. Returns whether there was one.
+    pub fn wake_one(&self) -> bool {
+        daif::without_irqs(|| {
+            let mut waiters = self.waiters.lock();
+            while let Some(waiter) = waiters.pop() {
+                if scheduler::unblock(waiter) {
+                    return true;
+                }
+            }
+
+            false
+        })
+    }
+
+    /// Wakes every thread that is waiting, and returns how many there were.
+    pub fn wake_all(&self) -> usize {
+        daif::without_irqs(|| {
+            let mut waiters = self.waiters.lock();
+            let mut count = 0;
+            while let Some(waiter) = waiters.pop() {
+                if scheduler::unblock(waiter) {
+                    count += 1;
+                }
+            }
+
+            count
+        })
+    }
+}
+
+impl Waiters {
+    fn push(&mut self, waiter: Waiter) {
+        assert!(self.length < MAX_THREADS, "wait queue overflow");
+
+        self.entries[self.length] = Some(waiter);
+        self.length += 1;
+    }
+
+    fn pop(&mut self) -> Option<Waiter> {
+        if self.length == 0 {
+            return None;
+        }
+
+        let waiter = self.entries[0].take();
+        self.entries.copy_within(1..self.length, 0);
+        self.length -= 1;
+        self.entries[self.length] = None;
+        waiter
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    arch::aarch64::{daif, generic_timer::GenericTimer},
+    cpu::percpu,
+    io::interrupts::{self, Interrupt},
+    mutex::Mutex,
+    scheduler,
+};
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// How many times per second the timer interrupt fires.
+pub const TICK_FREQUENCY: u64 = 100;
+
+/// The maximum amount of callbacks that can be registered with [every].
+const MAX_CALLBACKS: usize = 8;
+
+/// Represents an error that can occur when registering a timer callback.
+#[derive(Debug)]
+pub enum TimerError {
+    /// Occurs when [MAX_CALLBACKS] callbacks have already been registered.
+    TooManyCallbacks,
+}
+
+/// A callback that is called every [PeriodicCallback::period] ticks.
+#[derive(Clone, Copy)]
+struct PeriodicCallback {
+    period: u64,
+    next_tick: u64,
+    callback: fn(),
+}
+
+/// The amount of ticks since [initialize] was called.
+///
+/// Every core has its own timer interrupt, but only core 0's counts ticks, so a plain load and
+/// store is enough.
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// The amount of counter ticks between each timer interrupt.
+static INTERVAL: AtomicU64 = AtomicU64::new(0);
+
+static CALLBACKS: Mutex<[Option<PeriodicCallback>; MAX_CALLBACKS]> =
+    Mutex::new([None; MAX_CALLBACKS]);
+
+/// Starts the periodic timer interrupt.
+/// [interrupts::initialize] must be called before this.
+pub fn initialize() {
+    let interval = GenericTimer::frequency() / TICK_FREQUENCY;
+    INTERVAL.store(interval, Ordering::Relaxed);
+
+    interrupts::register(Interrupt::VirtualTimer, tick);
+    GenericTimer::arm(interval);
+}
+
+/// Starts the timer interrupt on a core other than the one that called [initialize], which only
+/// drives the scheduler on that core. [interrupts::initialize_core] must be called before this.
+pub fn initialize_core() {
+    GenericTimer::arm(INTERVAL.load(Ordering::Relaxed));
+}
+
+/// Returns the amount of timer interrupts that have fired since [initialize].
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// Returns the time since the system counter started (usually when the board was powered on).
+/// This does not depend on the timer interrupt, so it can be used before [initialize].
+pub fn uptime() -> Duration {
+    let frequency = GenericTimer::frequency();
+    let counter = GenericTimer::counter();
+
+    let seconds = counter / frequency;
+    let nanoseconds = ((counter % frequency) * 1_000_000_000) / frequency;
+    Duration::new(seconds, nanoseconds as u32)
+}
+
+/// Spins until [duration] has passed.
+/// This does not depend on the timer interrupt, so it can be used with IRQs masked.
+pub fn delay(duration: Duration) {
+    let deadline = uptime() + duration;
+    while uptime() < deadline {
+        core::hint::spin_loop();
+    }
+}
+
+/// Registers [callback] to be called every [period], starting one [period] from now.
+///
+/// The callback is called from core 0's timer interrupt handler with IRQs masked. The period is
+/// rounded up to a whole amount of ticks.
+pub fn every(period: Duration, callback: fn()) -> Result<(), TimerError> {
+    let tick_duration = 1_000_000_000 / TICK_FREQUENCY as u128;
+    let period = (period.as_nanos().div_ceil(tick_duration) as u64).max(1);
+
+    daif::without_irqs(|| {
+        let mut callbacks = CALLBACKS.lock();
+        let slot = callbacks
+            .iter_mut()
+            .find(|it| it.is_none())
+            .ok_or(TimerError::TooManyCallbacks)?;
+
+        *slot = Some(PeriodicCallback {
+            period,
+            next_tick: ticks() + period,
+            callback,
+        });
+
+        Ok(())
+    })
+}
+
+/// Called by the interrupt handler whenever the timer fires, on every core.
+fn tick() {
+    // Re-arming the timer also clears the interrupt.
+    GenericTimer::arm(INTERVAL.load(Ordering::Relaxed));
+
+    if percpu::this().core == 0 {
+        count_tick();
+    }
+
+    scheduler::tick();
+}
+
+/// Advances [TICKS], and runs the callbacks that are due.
+fn count_tick() {
+    let now = TICKS.load(Ordering::Relaxed) + 1;
+    TICKS.store(now, Ordering::Relaxed);
+
+    // Collect the callbacks that are due first, as a callback may want to register another.
+    let mut due: [Option<fn()>; MAX_CALLBACKS] = [None; MAX_CALLBACKS];
+    for (index, entry) in CALLBACKS.lock().iter_mut().enumerate() {
+        if let Some(callback) = entry {
+            if callback.next_tick <= now {
+                callback.next_tick = now + callback.period;
+                due[index] = Some(callback.callback);
+            }
+        }
+    }
+
+    for callback in due.into_iter().flatten() {
+        callback();
+    }
+}